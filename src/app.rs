@@ -1,7 +1,15 @@
-use crate::core::config::Config;
-use crate::core::store::SecretsStore;
+use crate::core::config::{Config, KeyAction, key_name};
+use crate::core::generator::{self, Charset};
+use crate::core::init::replace_files;
+use crate::core::store::{
+    MAX_EXPIRATION_DAYS, Secret, SecretsStore, expires_at_from_days, parse_expiration_date,
+};
+use crate::core::usages::{self, MatchMode};
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
 use std::collections::HashMap;
 use std::path::Path;
+use std::time::{Duration, Instant};
 use token_analyzer::{AnalysisReport, TokenSecurityAnalyzer};
 use zeroize::Zeroize;
 
@@ -22,22 +30,127 @@ pub enum Modal {
     AddSecret,
     /// Delete confirmation
     DeleteConfirm,
+    /// Confirmation before the add form replaces an existing secret (keeps the form)
+    OverwriteConfirm,
     /// Help with hotkey list
     Help,
     /// Command input (vim-style :command)
     Command,
+    /// Rename form for the selected secret
+    Rename,
+    /// Filter input for the secrets list (opened with '/')
+    Search,
+    /// Full metadata of the selected secret (opened with Enter)
+    Detail,
 }
 
 /// Available commands for the command modal
 pub const COMMANDS: &[(&str, &str)] = &[
-    ("env", "Generate .env file with secrets in plain text"),
+    (
+        "env",
+        "Generate .env file with secrets in plain text (:env mask hides values)",
+    ),
+    (
+        "env-example",
+        "Generate .env.example with names and empty values",
+    ),
     ("bash", "Export secrets to ~/.bashrc"),
     ("zsh", "Export secrets to ~/.zshrc"),
     ("fish", "Export secrets to ~/.config/fish/config.fish"),
-    ("json", "Export secrets as JSON file"),
+    (
+        "json",
+        "Export secrets as JSON file (:json mask hides values)",
+    ),
+    (
+        "get-file",
+        "Write the selected secret to a file (:get-file [PATH])",
+    ),
+    ("prune", "Remove every expired secret"),
     ("clear", "Clear all shell exports from profile files"),
 ];
 
+/// Pause after which the type-ahead jump ends
+pub const JUMP_TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// Order of the secrets list
+#[derive(Debug, PartialEq, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortMode {
+    /// Alphabetical
+    #[default]
+    Name,
+    /// Soonest to expire first (expired ones on top, permanent ones last)
+    ExpirationAsc,
+    /// Most recently written first (secrets without timestamps last)
+    UpdatedDesc,
+}
+
+impl SortMode {
+    /// The mode after this one when cycling with the sort key
+    pub fn next(self) -> Self {
+        match self {
+            SortMode::Name => SortMode::ExpirationAsc,
+            SortMode::ExpirationAsc => SortMode::UpdatedDesc,
+            SortMode::UpdatedDesc => SortMode::Name,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortMode::Name => "name",
+            SortMode::ExpirationAsc => "expiration",
+            SortMode::UpdatedDesc => "last update",
+        }
+    }
+
+    /// Sorts `secrets` in this order; ties are broken by name
+    pub fn sort(self, secrets: &mut [&Secret]) {
+        secrets.sort_by(|a, b| {
+            let order = match self {
+                SortMode::Name => std::cmp::Ordering::Equal,
+                // Past timestamps sort first, so expired secrets group on top
+                SortMode::ExpirationAsc => match (a.expires_at, b.expires_at) {
+                    (Some(a), Some(b)) => a.cmp(&b),
+                    (Some(_), None) => std::cmp::Ordering::Less,
+                    (None, Some(_)) => std::cmp::Ordering::Greater,
+                    (None, None) => std::cmp::Ordering::Equal,
+                },
+                SortMode::UpdatedDesc => b.updated_at.cmp(&a.updated_at),
+            };
+            order.then_with(|| a.name.cmp(&b.name))
+        });
+    }
+}
+
+/// What the TUI remembers between sessions, in `ui_state.json` next to the
+/// secrets: the selected name and the list view, never a value
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UiState {
+    /// Name rather than index, since the list changes between sessions
+    pub selected: Option<String>,
+    pub sort_mode: SortMode,
+    pub search_query: String,
+    pub tag_filter: Option<String>,
+}
+
+impl UiState {
+    const FILE_NAME: &'static str = "ui_state.json";
+
+    /// The state saved in `locker_dir`; a missing or unreadable file is the default
+    pub fn load(locker_dir: &Path) -> Self {
+        std::fs::read_to_string(locker_dir.join(Self::FILE_NAME))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, locker_dir: &Path) -> anyhow::Result<()> {
+        let content = serde_json::to_vec_pretty(self)?;
+        replace_files(locker_dir, &[(Self::FILE_NAME, &content)])
+    }
+}
+
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum Field {
     Name,
@@ -51,33 +164,57 @@ pub struct App {
     pub mode: Mode,
     pub modal: Modal,
     pub passphrase: Vec<u8>,
+    /// The passphrase being typed creates a new locker (shows its strength)
+    pub creating_locker: bool,
     pub error_message: Option<String>,
     pub secrets_store: Option<SecretsStore>,
     // Fields for AddSecret modal
     pub new_secret_name: String,
     pub new_secret_value: String,
+    /// Whether the add form shows the value as `*` (only rendering changes)
+    pub mask_new_value: bool,
     pub new_secret_expiration: String, // Number of days (empty = permanent)
     pub current_field: Field,
     // Navigation in the secrets list
     pub selected_index: usize,
     // Display decrypted token
     pub revealed_secret: Option<String>,
+    // When the value was revealed, to mask it again after `reveal.hide_after_secs`
+    pub revealed_at: Option<Instant>,
+    // Decrypted seed of the selected TOTP secret while its live code is shown
+    pub totp_seed: Option<String>,
     // Analysis report for the selected token
     pub token_analysis: Option<AnalysisReport>,
     // Reason why analysis was skipped (if any)
     pub analysis_skipped_reason: Option<String>,
+    // First usage shown in the usages panel
+    pub usages_scroll: usize,
+    // Number of usages the panel showed at the last render (set by ui.rs)
+    pub usages_rows: Cell<usize>,
     // Temporary status message
     pub status_message: Option<String>,
     // Agent mode: if true, secrets are decrypted via agent
     pub agent_mode: bool,
     // Secrets from agent (name -> value), used when agent_mode is true
     pub agent_secrets: Option<HashMap<String, String>>,
+    // New name typed in the rename modal
+    pub rename_input: String,
     // Command input for command modal
     pub command_input: String,
     // Selected command suggestion index
     pub command_suggestion_index: usize,
     // User configuration for analyzer settings
     pub config: Config,
+    // Type-ahead jump buffer (Some while jumping, started with 'g')
+    pub jump_query: Option<String>,
+    // Time of the last jump keystroke, used to end the jump after a pause
+    pub jump_last_input: Option<Instant>,
+    // Case-insensitive name filter for the secrets list (empty = show all)
+    pub search_query: String,
+    // Order of the secrets list
+    pub sort_mode: SortMode,
+    /// Only secrets carrying this tag are listed
+    pub tag_filter: Option<String>,
 }
 
 impl App {
@@ -88,6 +225,7 @@ impl App {
         Self {
             should_quit: false,
             initialized: false,
+            creating_locker: false,
             mode: Mode::Normal,
             modal: Modal::None,
             passphrase: Vec::new(),
@@ -95,18 +233,29 @@ impl App {
             secrets_store: None,
             new_secret_name: String::new(),
             new_secret_value: String::new(),
+            mask_new_value: false,
             new_secret_expiration: String::new(),
             current_field: Field::Name,
             selected_index: 0,
             revealed_secret: None,
+            revealed_at: None,
+            totp_seed: None,
             token_analysis: None,
             analysis_skipped_reason: None,
+            usages_scroll: 0,
+            usages_rows: Cell::new(0),
             status_message: None,
             agent_mode: false,
             agent_secrets: None,
+            rename_input: String::new(),
             command_input: String::new(),
             command_suggestion_index: 0,
             config,
+            jump_query: None,
+            jump_last_input: None,
+            search_query: String::new(),
+            sort_mode: SortMode::default(),
+            tag_filter: None,
         }
     }
 
@@ -121,16 +270,55 @@ impl App {
 
     pub fn open_add_modal(&mut self) {
         self.modal = Modal::AddSecret;
+        self.error_message = None;
         self.new_secret_name.clear();
         self.new_secret_value.clear();
-        self.new_secret_expiration.clear();
+        self.mask_new_value = self.config.reveal.mask_input_by_default;
+        // Pre-filled with the configured default; clearing it adds a permanent token
+        self.new_secret_expiration = self
+            .config
+            .store
+            .default_expiration_days
+            .filter(|days| *days > 0)
+            .map(|days| days.to_string())
+            .unwrap_or_default();
         self.current_field = Field::Name;
     }
 
+    /// Fills the add form's value with a random secret
+    pub fn generate_secret_value(&mut self) {
+        match generator::generate_secret(generator::DEFAULT_LENGTH, Charset::default()) {
+            Ok(value) => {
+                self.new_secret_value.zeroize();
+                self.new_secret_value = value;
+                self.current_field = Field::Value;
+            }
+            Err(e) => self.set_error(e.to_string()),
+        }
+    }
+
     pub fn open_delete_modal(&mut self) {
         self.modal = Modal::DeleteConfirm;
     }
 
+    /// Opens the rename modal, prefilled with the selected secret's name
+    pub fn open_rename_modal(&mut self) {
+        if let Some(name) = self.get_selected_secret_name() {
+            self.modal = Modal::Rename;
+            self.error_message = None;
+            self.rename_input = name;
+        }
+    }
+
+    /// Opens the detail view of the selected secret, its value masked
+    pub fn open_detail_modal(&mut self) {
+        if self.get_selected_secret_name().is_some() {
+            self.hide_revealed();
+            self.hide_totp();
+            self.modal = Modal::Detail;
+        }
+    }
+
     pub fn open_help_modal(&mut self) {
         self.modal = Modal::Help;
     }
@@ -141,9 +329,14 @@ impl App {
         self.command_suggestion_index = 0;
     }
 
-    /// Get filtered command suggestions based on current input
+    /// Get filtered command suggestions based on the command word of the input
     pub fn get_command_suggestions(&self) -> Vec<(&'static str, &'static str)> {
-        let input = self.command_input.to_lowercase();
+        let input = self
+            .command_input
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
         COMMANDS
             .iter()
             .filter(|(cmd, _)| cmd.starts_with(&input))
@@ -159,9 +352,97 @@ impl App {
             .map(|(cmd, _)| *cmd)
     }
 
+    /// Get the argument typed after the command word (e.g. the path in `:get-file PATH`)
+    pub fn get_command_argument(&self) -> Option<&str> {
+        self.command_input
+            .trim_start()
+            .split_once(char::is_whitespace)
+            .map(|(_, arg)| arg.trim())
+            .filter(|arg| !arg.is_empty())
+    }
+
+    /// Stops showing the live TOTP code, wiping the seed
+    pub fn hide_totp(&mut self) {
+        if let Some(ref mut seed) = self.totp_seed {
+            seed.zeroize();
+        }
+        self.totp_seed = None;
+    }
+
+    /// Whether a secret called `name` is in the locker
+    pub fn name_exists(&self, name: &str) -> bool {
+        match self.agent_secrets {
+            Some(ref secrets) => secrets.contains_key(name),
+            None => self
+                .secrets_store
+                .as_ref()
+                .is_some_and(|store| store.contains(name)),
+        }
+    }
+
+    /// On submitting the add form, asks before replacing an existing secret.
+    /// Returns true when the add must wait for that confirmation.
+    pub fn hold_for_overwrite(&mut self) -> bool {
+        if self.modal == Modal::AddSecret && self.name_exists(&self.new_secret_name) {
+            self.modal = Modal::OverwriteConfirm;
+            return true;
+        }
+        false
+    }
+
     pub fn close_modal(&mut self) {
         self.modal = Modal::None;
+        self.hide_revealed();
+    }
+
+    /// Shows a decrypted value until it is hidden or times out
+    pub fn reveal(&mut self, value: String) {
+        self.hide_revealed();
+        self.revealed_secret = Some(value);
+        self.revealed_at = Some(Instant::now());
+    }
+
+    /// Why the selected secret cannot be revealed: it is protected (copy only)
+    pub fn reveal_refusal(&self) -> Option<String> {
+        let name = self.get_selected_secret_name()?;
+        let secret = self.secrets_store.as_ref()?.get_secret(&name)?;
+        if !secret.protected {
+            return None;
+        }
+        let copy_key = self
+            .config
+            .keybindings
+            .copy
+            .first()
+            .map_or_else(|| "y".to_string(), |&key| key_name(key));
+        Some(format!("'{}' is protected; use {} to copy", name, copy_key))
+    }
+
+    /// Wipes the revealed value, if any
+    pub fn hide_revealed(&mut self) {
+        if let Some(ref mut revealed) = self.revealed_secret {
+            revealed.zeroize();
+        }
         self.revealed_secret = None;
+        self.revealed_at = None;
+    }
+
+    /// Whether the revealed value has been shown for `reveal.hide_after_secs`
+    pub fn reveal_expired(&self, now: Instant) -> bool {
+        let timeout = self.config.reveal.hide_after_secs;
+        match self.revealed_at {
+            Some(at) if timeout > 0 && self.revealed_secret.is_some() => {
+                now.duration_since(at) >= Duration::from_secs(timeout)
+            }
+            _ => false,
+        }
+    }
+
+    /// Masks the revealed value once it has timed out
+    pub fn expire_reveal(&mut self, now: Instant) {
+        if self.reveal_expired(now) {
+            self.hide_revealed();
+        }
     }
 
     pub fn set_error(&mut self, msg: String) {
@@ -175,53 +456,172 @@ impl App {
 
     /// Returns the name of the currently selected secret
     pub fn get_selected_secret_name(&self) -> Option<String> {
-        // Agent mode: use agent_secrets
-        if let Some(ref secrets) = self.agent_secrets {
-            let mut names: Vec<_> = secrets.keys().collect();
-            names.sort();
-            if self.selected_index < names.len() {
-                return Some(names[self.selected_index].clone());
+        self.filtered_secret_names()
+            .into_iter()
+            .nth(self.selected_index)
+    }
+
+    /// Number of secrets shown in the list (matching the filters, if any)
+    pub fn secrets_count(&self) -> usize {
+        if self.search_query.is_empty() && self.tag_filter.is_none() {
+            // Agent mode
+            if let Some(ref secrets) = self.agent_secrets {
+                return secrets.len();
             }
+            // Normal mode
+            return self.secrets_store.as_ref().map(|s| s.len()).unwrap_or(0);
         }
-        // Normal mode: use store
-        if let Some(ref store) = self.secrets_store {
-            let secrets = store.list_secrets();
-            if self.selected_index < secrets.len() {
-                return Some(secrets[self.selected_index].name.clone());
-            }
+        self.filtered_secret_names().len()
+    }
+
+    /// Names shown in the list (sorted): all of them, or those carrying the
+    /// tag filter and containing the search query, ignoring case
+    pub fn filtered_secret_names(&self) -> Vec<String> {
+        let mut names = self.get_secret_names();
+        if let Some(ref tag) = self.tag_filter
+            && let Some(ref store) = self.secrets_store
+        {
+            names.retain(|name| store.get_secret(name).is_some_and(|s| s.has_tag(tag)));
         }
-        None
+        if self.search_query.is_empty() {
+            return names;
+        }
+        let query = self.search_query.to_lowercase();
+        names
+            .into_iter()
+            .filter(|name| name.to_lowercase().contains(&query))
+            .collect()
     }
 
-    /// Number of secrets in the store
-    pub fn secrets_count(&self) -> usize {
-        // Agent mode
-        if let Some(ref secrets) = self.agent_secrets {
-            return secrets.len();
+    /// Selects `name` in the list, if it is shown
+    pub fn select_secret(&mut self, name: &str) {
+        if let Some(index) = self
+            .filtered_secret_names()
+            .iter()
+            .position(|shown| shown == name)
+        {
+            self.selected_index = index;
+        }
+    }
+
+    /// Opens the search input; typing filters the list by name
+    pub fn open_search_modal(&mut self) {
+        self.modal = Modal::Search;
+    }
+
+    /// Clears the search filter, keeping the selected secret selected
+    pub fn clear_search(&mut self) {
+        let selected = self.get_selected_secret_name();
+        self.search_query.clear();
+        self.selected_index = 0;
+        if let Some(name) = selected {
+            self.select_secret(&name);
         }
-        // Normal mode
-        self.secrets_store
+    }
+
+    /// Clears the search and the tag filter, keeping the selected secret selected
+    pub fn clear_filters(&mut self) {
+        self.tag_filter = None;
+        self.clear_search();
+    }
+
+    /// The list view and selection, to save for the next session
+    pub fn ui_state(&self) -> UiState {
+        UiState {
+            selected: self.get_selected_secret_name(),
+            sort_mode: self.sort_mode,
+            search_query: self.search_query.clone(),
+            tag_filter: self.tag_filter.clone(),
+        }
+    }
+
+    /// Restores a saved view, then selects the saved name when it is still
+    /// listed, or the first secret. A tag no secret carries any more is dropped.
+    pub fn restore_ui_state(&mut self, state: UiState) {
+        self.sort_mode = state.sort_mode;
+        self.search_query = state.search_query;
+        self.tag_filter = state.tag_filter.filter(|tag| {
+            self.secrets_store
+                .as_ref()
+                .is_some_and(|store| store.all_tags().contains(tag))
+        });
+        self.selected_index = 0;
+        if let Some(name) = state.selected {
+            self.select_secret(&name);
+        }
+    }
+
+    /// Filters the list by the next tag in use, then none again after the last.
+    /// Keeps the selected secret selected when it is still listed.
+    pub fn cycle_tag_filter(&mut self) {
+        let tags = self
+            .secrets_store
             .as_ref()
-            .map(|s| s.list_secrets().len())
-            .unwrap_or(0)
+            .map(|store| store.all_tags())
+            .unwrap_or_default();
+        if tags.is_empty() {
+            self.set_status("No tags to filter by".to_string());
+            return;
+        }
+
+        let selected = self.get_selected_secret_name();
+        self.tag_filter = match self.tag_filter {
+            None => tags.first().cloned(),
+            Some(ref current) => tags
+                .iter()
+                .skip_while(|tag| *tag != current)
+                .nth(1)
+                .cloned(),
+        };
+        self.selected_index = 0;
+        self.hide_revealed();
+        if let Some(name) = selected {
+            self.select_secret(&name);
+        }
+        match self.tag_filter {
+            Some(ref tag) => self.set_status(format!("Showing tag #{}", tag)),
+            None => self.set_status("Showing all tags".to_string()),
+        }
+    }
+
+    /// Re-applies the filter after the query changed: selects the first match
+    fn update_search(&mut self) {
+        self.selected_index = 0;
+        self.hide_revealed();
     }
 
     /// Returns list of secret names (sorted)
-    #[allow(dead_code)]
     pub fn get_secret_names(&self) -> Vec<String> {
         if let Some(ref secrets) = self.agent_secrets {
+            // The agent only provides values, so it is always sorted by name
             let mut names: Vec<_> = secrets.keys().cloned().collect();
             names.sort();
             return names;
         }
-        if let Some(ref store) = self.secrets_store {
-            return store
-                .list_secrets()
-                .iter()
-                .map(|s| s.name.clone())
-                .collect();
+        self.sorted_secrets()
+            .iter()
+            .map(|s| s.name.clone())
+            .collect()
+    }
+
+    /// Secrets of the store in the current sort order
+    pub fn sorted_secrets(&self) -> Vec<&Secret> {
+        let Some(ref store) = self.secrets_store else {
+            return Vec::new();
+        };
+        let mut secrets = store.list_secrets();
+        self.sort_mode.sort(&mut secrets);
+        secrets
+    }
+
+    /// Switches to the next sort order, keeping the selected secret selected
+    pub fn cycle_sort_mode(&mut self) {
+        let selected = self.get_selected_secret_name();
+        self.sort_mode = self.sort_mode.next();
+        if let Some(name) = selected {
+            self.select_secret(&name);
         }
-        Vec::new()
+        self.set_status(format!("Sorted by {}", self.sort_mode.label()));
     }
 
     /// Gets decrypted value from agent_secrets cache
@@ -235,7 +635,7 @@ impl App {
     pub fn move_selection_up(&mut self) {
         if self.selected_index > 0 {
             self.selected_index -= 1;
-            self.revealed_secret = None;
+            self.hide_revealed();
         }
     }
 
@@ -243,7 +643,90 @@ impl App {
         let count = self.secrets_count();
         if count > 0 && self.selected_index < count - 1 {
             self.selected_index += 1;
-            self.revealed_secret = None;
+            self.hide_revealed();
+        }
+    }
+
+    /// Number of files listed in the usages panel
+    pub fn usages_count(&self) -> usize {
+        self.token_analysis
+            .as_ref()
+            .map_or(0, |report| report.files.len())
+    }
+
+    /// Scrolls the usages panel one page down, keeping the last page full
+    pub fn scroll_usages_down(&mut self) {
+        let page = self.usages_rows.get().max(1);
+        let last = self.usages_count().saturating_sub(page);
+        self.usages_scroll = (self.usages_scroll + page).min(last);
+    }
+
+    /// Scrolls the usages panel one page up
+    pub fn scroll_usages_up(&mut self) {
+        let page = self.usages_rows.get().max(1);
+        self.usages_scroll = self.usages_scroll.saturating_sub(page);
+    }
+
+    /// Starts a type-ahead jump: following letters select a secret by prefix
+    pub fn start_jump(&mut self) {
+        self.jump_query = Some(String::new());
+        self.jump_last_input = Some(Instant::now());
+    }
+
+    /// Ends the type-ahead jump
+    pub fn end_jump(&mut self) {
+        self.jump_query = None;
+        self.jump_last_input = None;
+    }
+
+    /// Returns true while a type-ahead jump is in progress
+    pub fn is_jumping(&self) -> bool {
+        self.jump_query.is_some()
+    }
+
+    /// Ends the jump if no key was typed within `JUMP_TIMEOUT`
+    pub fn expire_jump(&mut self, now: Instant) {
+        if let Some(last) = self.jump_last_input
+            && now.duration_since(last) > JUMP_TIMEOUT
+        {
+            self.end_jump();
+        }
+    }
+
+    /// Moves the selection to the first secret starting with the jump query
+    fn jump_to_prefix(&mut self) {
+        let Some(ref query) = self.jump_query else {
+            return;
+        };
+        let query = query.to_lowercase();
+        if let Some(index) = self
+            .filtered_secret_names()
+            .iter()
+            .position(|name| name.to_lowercase().starts_with(&query))
+            && index != self.selected_index
+        {
+            self.selected_index = index;
+            self.hide_revealed();
+        }
+    }
+
+    fn handle_jump_key(&mut self, key_code: crossterm::event::KeyCode) {
+        match key_code {
+            crossterm::event::KeyCode::Char(c) => {
+                if let Some(ref mut query) = self.jump_query {
+                    query.push(c);
+                }
+                self.jump_last_input = Some(Instant::now());
+                self.jump_to_prefix();
+            }
+            crossterm::event::KeyCode::Backspace => {
+                if let Some(ref mut query) = self.jump_query {
+                    query.pop();
+                }
+                self.jump_last_input = Some(Instant::now());
+                self.jump_to_prefix();
+            }
+            _ => self.end_jump(),
         }
     }
 
@@ -251,14 +734,16 @@ impl App {
         // If a modal is open, handle its events
         match self.modal {
             Modal::AddSecret => {
+                // Any edit dismisses the previous validation error
+                self.error_message = None;
                 match key_code {
                     crossterm::event::KeyCode::Char(c) => {
                         match self.current_field {
                             Field::Name => self.new_secret_name.push(c),
                             Field::Value => self.new_secret_value.push(c),
                             Field::Expiration => {
-                                // Only accept digits for expiration
-                                if c.is_ascii_digit() {
+                                // A day count or a date (YYYY-MM-DD or RFC 3339)
+                                if c.is_ascii_digit() || "-:+.TZ".contains(c) {
                                     self.new_secret_expiration.push(c);
                                 }
                             }
@@ -275,6 +760,8 @@ impl App {
                             self.new_secret_expiration.pop();
                         }
                     },
+                    crossterm::event::KeyCode::F(2) => self.generate_secret_value(),
+                    crossterm::event::KeyCode::F(3) => self.mask_new_value = !self.mask_new_value,
                     crossterm::event::KeyCode::Tab => {
                         self.current_field = match self.current_field {
                             Field::Name => Field::Value,
@@ -305,6 +792,18 @@ impl App {
                 }
                 return;
             }
+            Modal::OverwriteConfirm => {
+                match key_code {
+                    crossterm::event::KeyCode::Char('y') | crossterm::event::KeyCode::Enter => {} // Handled in main.rs
+                    crossterm::event::KeyCode::Char('n') | crossterm::event::KeyCode::Esc => {
+                        // Back to the form, with everything typed so far
+                        self.modal = Modal::AddSecret;
+                        self.current_field = Field::Name;
+                    }
+                    _ => {}
+                }
+                return;
+            }
             Modal::Help => {
                 match key_code {
                     crossterm::event::KeyCode::Esc
@@ -316,6 +815,15 @@ impl App {
                 }
                 return;
             }
+            Modal::Detail => {
+                match key_code {
+                    crossterm::event::KeyCode::Esc
+                    | crossterm::event::KeyCode::Enter
+                    | crossterm::event::KeyCode::Char('q') => self.close_modal(),
+                    _ => {} // 'e' is handled in main.rs (decrypt)
+                }
+                return;
+            }
             Modal::Command => {
                 match key_code {
                     crossterm::event::KeyCode::Char(c) => {
@@ -348,9 +856,49 @@ impl App {
                 }
                 return;
             }
+            Modal::Rename => {
+                self.error_message = None;
+                match key_code {
+                    crossterm::event::KeyCode::Char(c) => self.rename_input.push(c),
+                    crossterm::event::KeyCode::Backspace => {
+                        self.rename_input.pop();
+                    }
+                    crossterm::event::KeyCode::Enter => {} // Handled in main.rs
+                    crossterm::event::KeyCode::Esc => self.close_modal(),
+                    _ => {}
+                }
+                return;
+            }
+            Modal::Search => {
+                match key_code {
+                    crossterm::event::KeyCode::Char(c) => {
+                        self.search_query.push(c);
+                        self.update_search();
+                    }
+                    crossterm::event::KeyCode::Backspace if !self.search_query.is_empty() => {
+                        self.search_query.pop();
+                        self.update_search();
+                    }
+                    // Keep the filter and go back to the list
+                    crossterm::event::KeyCode::Enter => self.modal = Modal::None,
+                    crossterm::event::KeyCode::Esc => {
+                        self.clear_search();
+                        self.modal = Modal::None;
+                    }
+                    crossterm::event::KeyCode::Up => self.move_selection_up(),
+                    crossterm::event::KeyCode::Down => self.move_selection_down(),
+                    _ => {}
+                }
+                return;
+            }
             Modal::None => {}
         }
 
+        if self.mode == Mode::Normal && self.is_jumping() {
+            self.handle_jump_key(key_code);
+            return;
+        }
+
         // Handle main modes
         match self.mode {
             Mode::InitPassphrase => match key_code {
@@ -366,31 +914,63 @@ impl App {
                 crossterm::event::KeyCode::Esc => self.quit(),
                 _ => {}
             },
-            Mode::Normal => match key_code {
-                crossterm::event::KeyCode::Char('q') => self.quit(),
-                crossterm::event::KeyCode::Char('a') => self.open_add_modal(),
-                crossterm::event::KeyCode::Char('d') => {
+            Mode::Normal => match self.config.keybindings.action(key_code) {
+                Some(KeyAction::Quit) => self.quit(),
+                Some(KeyAction::Add) => self.open_add_modal(),
+                Some(KeyAction::Delete) => {
                     if self.secrets_count() > 0 {
                         self.open_delete_modal();
                     }
                 }
-                crossterm::event::KeyCode::Char('h') => self.open_help_modal(),
-                crossterm::event::KeyCode::Char('e') => {} // Handled in main.rs (decrypt)
-                crossterm::event::KeyCode::Char(':') => self.open_command_modal(),
-                crossterm::event::KeyCode::Char('y') => {} // Handled in main.rs (copy)
-                crossterm::event::KeyCode::Up => self.move_selection_up(),
-                crossterm::event::KeyCode::Down => self.move_selection_down(),
-                _ => {}
+                Some(KeyAction::Help) => self.open_help_modal(),
+                Some(KeyAction::Reveal) => {} // Handled in main.rs (decrypt)
+                Some(KeyAction::Copy) => {}   // Handled in main.rs (copy)
+                Some(KeyAction::Command) => self.open_command_modal(),
+                Some(KeyAction::Sort) => self.cycle_sort_mode(),
+                Some(KeyAction::TagFilter) => self.cycle_tag_filter(),
+                Some(KeyAction::MoveUp) => self.move_selection_up(),
+                Some(KeyAction::MoveDown) => self.move_selection_down(),
+                // Keys that cannot be rebound (see config::RESERVED_KEYS)
+                None => match key_code {
+                    crossterm::event::KeyCode::Char('r') if self.secrets_count() > 0 => {
+                        self.open_rename_modal();
+                    }
+                    crossterm::event::KeyCode::Char('g') => self.start_jump(),
+                    crossterm::event::KeyCode::Char('/') => self.open_search_modal(),
+                    crossterm::event::KeyCode::Esc
+                        if !self.search_query.is_empty() || self.tag_filter.is_some() =>
+                    {
+                        self.clear_filters();
+                    }
+                    crossterm::event::KeyCode::Char('o') => {} // Handled in main.rs (TOTP code)
+                    crossterm::event::KeyCode::Enter => self.open_detail_modal(),
+                    crossterm::event::KeyCode::PageDown => self.scroll_usages_down(),
+                    crossterm::event::KeyCode::PageUp => self.scroll_usages_up(),
+                    _ => {}
+                },
             },
         }
     }
 
-    /// Parse the number of expiration days from input
-    pub fn get_expiration_days(&self) -> Option<u32> {
+    /// Parse the expiration input: a number of days, or a date when it contains `-`
+    /// Returns the expiration timestamp, or an error message if the value is invalid
+    pub fn get_expiration(&self) -> Result<Option<i64>, String> {
         if self.new_secret_expiration.is_empty() {
-            None
-        } else {
-            self.new_secret_expiration.parse().ok()
+            return Ok(None);
+        }
+        if self.new_secret_expiration.contains('-') {
+            return parse_expiration_date(&self.new_secret_expiration)
+                .map(Some)
+                .map_err(|e| e.to_string());
+        }
+        match self.new_secret_expiration.parse::<u32>() {
+            Ok(days) if days <= MAX_EXPIRATION_DAYS => expires_at_from_days(days)
+                .map(Some)
+                .map_err(|e| e.to_string()),
+            _ => Err(format!(
+                "Expiration must be at most {} days",
+                MAX_EXPIRATION_DAYS
+            )),
         }
     }
 
@@ -414,12 +994,26 @@ impl App {
 
         // Clear skip reason when analysis runs
         self.analysis_skipped_reason = None;
+        self.usages_scroll = 0;
 
         if let Some(name) = self.get_selected_secret_name() {
-            let analyzer_config = self.config.analyzer.to_analyzer_config();
-            let analyzer = TokenSecurityAnalyzer::new(analyzer_config);
+            let settings = &self.config.analyzer;
+            let locker_dir = Config::get_locker_dir().ok();
+            // token-analyzer matches whole words; other modes are searched by usages.rs
+            if settings.match_mode != MatchMode::Word {
+                match usages::scan_usages(&name, work_dir, settings, locker_dir.as_deref()) {
+                    Ok(report) => self.token_analysis = Some(report),
+                    Err(e) => {
+                        self.token_analysis = None;
+                        self.analysis_skipped_reason = Some(e.to_string());
+                    }
+                }
+                return;
+            }
+            let analyzer = TokenSecurityAnalyzer::new(settings.to_analyzer_config());
             match analyzer.analyze(&name, work_dir) {
-                Ok(report) => {
+                Ok(mut report) => {
+                    usages::filter_report(&mut report, settings, locker_dir.as_deref());
                     self.token_analysis = Some(report);
                 }
                 Err(_) => {
@@ -440,15 +1034,39 @@ impl App {
     pub fn clear_status(&mut self) {
         self.status_message = None;
     }
+
+    /// Wipes the passphrase buffer and all transient input, closing any modal.
+    /// The loaded store, agent state, mode and configuration are kept, so the
+    /// caller decides whether the session relocks.
+    pub fn reset_session(&mut self) {
+        self.passphrase.zeroize();
+        self.passphrase.clear();
+        self.new_secret_name.clear();
+        self.new_secret_value.zeroize();
+        self.new_secret_value.clear();
+        self.new_secret_expiration.clear();
+        self.current_field = Field::Name;
+        self.hide_revealed();
+        self.hide_totp();
+        self.rename_input.clear();
+        self.command_input.clear();
+        self.command_suggestion_index = 0;
+        self.end_jump();
+        self.error_message = None;
+        self.status_message = None;
+        self.modal = Modal::None;
+    }
+}
+
+impl Default for App {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Drop for App {
     fn drop(&mut self) {
-        self.passphrase.zeroize();
-        self.new_secret_value.zeroize();
-        if let Some(ref mut revealed) = self.revealed_secret {
-            revealed.zeroize();
-        }
+        self.reset_session();
         if let Some(ref mut store) = self.secrets_store {
             store.secrets.clear();
         }
@@ -515,6 +1133,7 @@ mod tests {
         app.new_secret_value = "data".to_string();
         app.new_secret_expiration = "30".to_string();
         app.current_field = Field::Value;
+        app.config.store.default_expiration_days = None;
 
         app.open_add_modal();
 
@@ -525,6 +1144,44 @@ mod tests {
         assert_eq!(app.current_field, Field::Name); // Reset to first field
     }
 
+    #[test]
+    fn test_add_modal_mask_toggle_keeps_value() {
+        let mut app = App::new();
+        app.config.reveal.mask_input_by_default = true;
+        app.open_add_modal();
+        assert!(app.mask_new_value);
+
+        app.current_field = Field::Value;
+        for c in "abc".chars() {
+            app.handle_key(KeyCode::Char(c));
+        }
+        app.handle_key(KeyCode::F(3));
+        assert!(!app.mask_new_value);
+        app.handle_key(KeyCode::Backspace);
+        assert_eq!(app.new_secret_value, "ab");
+        app.handle_key(KeyCode::F(3));
+        assert!(app.mask_new_value);
+        assert_eq!(app.new_secret_value, "ab");
+
+        // Each opening starts from the configured default again
+        app.config.reveal.mask_input_by_default = false;
+        app.open_add_modal();
+        assert!(!app.mask_new_value);
+    }
+
+    #[test]
+    fn test_open_add_modal_prefills_default_expiration() {
+        let mut app = App::new();
+        app.config.store.default_expiration_days = Some(90);
+        app.open_add_modal();
+        assert_eq!(app.new_secret_expiration, "90");
+        assert!(app.get_expiration().unwrap().is_some());
+
+        app.config.store.default_expiration_days = Some(0);
+        app.open_add_modal();
+        assert!(app.new_secret_expiration.is_empty());
+    }
+
     #[test]
     fn test_open_delete_modal() {
         let mut app = App::new();
@@ -701,16 +1358,55 @@ mod tests {
     }
 
     #[test]
-    fn test_handle_key_add_modal_expiration_only_digits() {
+    fn test_handle_key_add_modal_expiration_days_or_date() {
         let mut app = App::new();
         app.modal = Modal::AddSecret;
         app.current_field = Field::Expiration;
 
-        app.handle_key(KeyCode::Char('3'));
-        app.handle_key(KeyCode::Char('0'));
-        app.handle_key(KeyCode::Char('a')); // Should be ignored
-
+        for c in "30a".chars() {
+            app.handle_key(KeyCode::Char(c)); // 'a' should be ignored
+        }
         assert_eq!(app.new_secret_expiration, "30");
+
+        app.new_secret_expiration.clear();
+        for c in "2099-12-31T18:00:00Z".chars() {
+            app.handle_key(KeyCode::Char(c));
+        }
+        assert_eq!(app.new_secret_expiration, "2099-12-31T18:00:00Z");
+    }
+
+    #[test]
+    fn test_get_expiration_bounds() {
+        let mut app = App::new();
+
+        assert_eq!(app.get_expiration(), Ok(None));
+
+        app.new_secret_expiration = "30".to_string();
+        assert!(app.get_expiration().unwrap().is_some());
+
+        app.new_secret_expiration = MAX_EXPIRATION_DAYS.to_string();
+        assert!(app.get_expiration().is_ok());
+
+        app.new_secret_expiration = "36501".to_string();
+        assert!(app.get_expiration().is_err());
+
+        // Overflows u32: must not silently become "permanent"
+        app.new_secret_expiration = "99999999999".to_string();
+        assert!(app.get_expiration().is_err());
+    }
+
+    #[test]
+    fn test_get_expiration_date() {
+        let mut app = App::new();
+
+        app.new_secret_expiration = "2099-12-31".to_string();
+        assert_eq!(app.get_expiration(), Ok(Some(4102358400)));
+
+        app.new_secret_expiration = "2000-01-01".to_string();
+        assert!(app.get_expiration().unwrap_err().contains("in the past"));
+
+        app.new_secret_expiration = "2099-13-01".to_string();
+        assert!(app.get_expiration().is_err());
     }
 
     // ========================
@@ -775,6 +1471,597 @@ mod tests {
     // Key handling tests (Normal mode - no modal)
     // ========================
 
+    // ========================
+    // Type-ahead jump tests
+    // ========================
+
+    /// App with agent-provided secret names (no store needed)
+    fn app_with_names(names: &[&str]) -> App {
+        let mut app = App::new();
+        app.agent_secrets = Some(
+            names
+                .iter()
+                .map(|n| (n.to_string(), String::new()))
+                .collect(),
+        );
+        app
+    }
+
+    #[test]
+    fn test_jump_selects_first_prefix_match() {
+        let mut app = app_with_names(&["ALPHA", "DB_PASS", "DB_URL", "MIDDLE"]);
+
+        app.handle_key(KeyCode::Char('g'));
+        assert!(app.is_jumping());
+
+        app.handle_key(KeyCode::Char('d'));
+        assert_eq!(app.get_selected_secret_name(), Some("DB_PASS".to_string()));
+
+        app.handle_key(KeyCode::Char('b'));
+        app.handle_key(KeyCode::Char('_'));
+        app.handle_key(KeyCode::Char('u'));
+        assert_eq!(app.get_selected_secret_name(), Some("DB_URL".to_string()));
+    }
+
+    #[test]
+    fn test_jump_does_not_trigger_actions() {
+        let mut app = app_with_names(&["ALPHA", "BETA"]);
+
+        app.handle_key(KeyCode::Char('g'));
+        app.handle_key(KeyCode::Char('q'));
+        app.handle_key(KeyCode::Char('a'));
+
+        assert!(!app.should_quit);
+        assert_eq!(app.modal, Modal::None);
+        assert_eq!(app.selected_index, 0); // "qa" matches nothing, selection kept
+    }
+
+    #[test]
+    fn test_jump_ends_on_escape_and_after_pause() {
+        let mut app = app_with_names(&["ALPHA"]);
+
+        app.handle_key(KeyCode::Char('g'));
+        app.handle_key(KeyCode::Esc);
+        assert!(!app.is_jumping());
+
+        app.handle_key(KeyCode::Char('g'));
+        app.expire_jump(Instant::now());
+        assert!(app.is_jumping());
+
+        app.expire_jump(Instant::now() + JUMP_TIMEOUT * 2);
+        assert!(!app.is_jumping());
+    }
+
+    #[test]
+    fn test_revealed_value_expires_after_timeout() {
+        let mut app = App::new();
+        app.config.reveal.hide_after_secs = 10;
+        app.reveal("sk-123".to_string());
+        let revealed_at = app.revealed_at.unwrap();
+
+        assert!(!app.reveal_expired(revealed_at + Duration::from_secs(9)));
+        assert!(app.reveal_expired(revealed_at + Duration::from_secs(10)));
+
+        app.expire_reveal(revealed_at + Duration::from_secs(11));
+        assert!(app.revealed_secret.is_none());
+        assert!(!app.reveal_expired(revealed_at + Duration::from_secs(11)));
+    }
+
+    #[test]
+    fn test_reveal_timeout_zero_never_expires() {
+        let mut app = App::new();
+        app.config.reveal.hide_after_secs = 0;
+        app.reveal("sk-123".to_string());
+
+        app.expire_reveal(Instant::now() + Duration::from_secs(3600));
+
+        assert_eq!(app.revealed_secret.as_deref(), Some("sk-123"));
+    }
+
+    #[test]
+    fn test_protected_secret_refuses_reveal_only() {
+        let mut store = SecretsStore::new();
+        for (name, protected) in [("OPEN", false), ("ROOT_PASSWORD", true)] {
+            let secret = Secret {
+                name: name.to_string(),
+                protected,
+                ..Default::default()
+            };
+            store.secrets.insert(name.to_string(), secret);
+        }
+        let mut app = App::new();
+        app.secrets_store = Some(store);
+
+        app.selected_index = 0; // OPEN
+        assert!(app.reveal_refusal().is_none());
+
+        app.selected_index = 1; // ROOT_PASSWORD
+        assert_eq!(
+            app.reveal_refusal().as_deref(),
+            Some("'ROOT_PASSWORD' is protected; use y to copy")
+        );
+
+        app.config.keybindings.copy = vec![KeyCode::Char('c')];
+        assert!(app.reveal_refusal().unwrap().ends_with("use c to copy"));
+    }
+
+    // ========================
+    // Search filter tests
+    // ========================
+
+    #[test]
+    fn test_filtered_names_empty_query_lists_all() {
+        let app = app_with_names(&["DB_URL", "API_KEY"]);
+
+        assert_eq!(app.filtered_secret_names(), vec!["API_KEY", "DB_URL"]);
+        assert_eq!(app.secrets_count(), 2);
+    }
+
+    #[test]
+    fn test_filtered_names_no_match() {
+        let mut app = app_with_names(&["API_KEY", "DB_URL"]);
+        app.search_query = "stripe".to_string();
+
+        assert!(app.filtered_secret_names().is_empty());
+        assert_eq!(app.secrets_count(), 0);
+        assert_eq!(app.get_selected_secret_name(), None);
+    }
+
+    #[test]
+    fn test_filtered_names_ignore_case() {
+        let mut app = app_with_names(&["API_KEY", "DB_PASS", "DB_URL", "db_backup"]);
+        app.search_query = "Db".to_string();
+
+        assert_eq!(
+            app.filtered_secret_names(),
+            vec!["DB_PASS", "DB_URL", "db_backup"]
+        );
+        app.selected_index = 1;
+        assert_eq!(app.get_selected_secret_name(), Some("DB_URL".to_string()));
+    }
+
+    #[test]
+    fn test_search_typing_filters_and_escape_restores() {
+        let mut app = app_with_names(&["ALPHA", "BETA", "GAMMA"]);
+
+        app.handle_key(KeyCode::Char('/'));
+        assert_eq!(app.modal, Modal::Search);
+        app.handle_key(KeyCode::Char('m'));
+        app.handle_key(KeyCode::Char('m'));
+        assert_eq!(app.secrets_count(), 1);
+        assert_eq!(app.get_selected_secret_name(), Some("GAMMA".to_string()));
+
+        // Enter keeps the filter; typed letters no longer go to the query
+        app.handle_key(KeyCode::Enter);
+        assert_eq!(app.modal, Modal::None);
+        assert_eq!(app.search_query, "mm");
+
+        // Esc clears it and keeps the selected secret selected
+        app.handle_key(KeyCode::Esc);
+        assert!(app.search_query.is_empty());
+        assert_eq!(app.secrets_count(), 3);
+        assert_eq!(app.get_selected_secret_name(), Some("GAMMA".to_string()));
+    }
+
+    // ========================
+    // Sort tests
+    // ========================
+
+    fn secret(name: &str, expires_at: Option<i64>, updated_at: i64) -> Secret {
+        Secret {
+            name: name.to_string(),
+            expires_at,
+            updated_at,
+            ..Default::default()
+        }
+    }
+
+    fn sorted_names(mode: SortMode, secrets: &[Secret]) -> Vec<&str> {
+        let mut refs: Vec<&Secret> = secrets.iter().collect();
+        mode.sort(&mut refs);
+        refs.iter().map(|s| s.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_sort_by_expiration_groups_expired_first() {
+        let now = crate::core::store::now_timestamp();
+        let secrets = [
+            secret("PERMANENT", None, 0),
+            secret("NEXT_YEAR", Some(now + 365 * 86400), 0),
+            secret("EXPIRED_LONG_AGO", Some(now - 30 * 86400), 0),
+            secret("TOMORROW", Some(now + 86400), 0),
+            secret("EXPIRED_TODAY", Some(now - 60), 0),
+            secret("ALSO_PERMANENT", None, 0),
+        ];
+
+        assert_eq!(
+            sorted_names(SortMode::ExpirationAsc, &secrets),
+            vec![
+                "EXPIRED_LONG_AGO",
+                "EXPIRED_TODAY",
+                "TOMORROW",
+                "NEXT_YEAR",
+                "ALSO_PERMANENT",
+                "PERMANENT",
+            ]
+        );
+        assert_eq!(
+            sorted_names(SortMode::Name, &secrets)[..2],
+            ["ALSO_PERMANENT", "EXPIRED_LONG_AGO"]
+        );
+    }
+
+    #[test]
+    fn test_sort_by_update_falls_back_to_name() {
+        let secrets = [
+            secret("OLD", None, 100),
+            secret("B_LEGACY", None, 0),
+            secret("RECENT", None, 300),
+            secret("A_LEGACY", None, 0),
+        ];
+
+        assert_eq!(
+            sorted_names(SortMode::UpdatedDesc, &secrets),
+            vec!["RECENT", "OLD", "A_LEGACY", "B_LEGACY"]
+        );
+    }
+
+    #[test]
+    fn test_sort_key_cycles_and_keeps_selection() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let key = [0x42u8; 32];
+        let mut store = SecretsStore::new();
+        store
+            .add_secret_at(
+                "ALPHA".to_string(),
+                "a".to_string(),
+                Some(4102358400),
+                dir.path(),
+                &key,
+            )
+            .unwrap();
+        store
+            .add_secret_at(
+                "BETA".to_string(),
+                "b".to_string(),
+                Some(1),
+                dir.path(),
+                &key,
+            )
+            .unwrap();
+        let mut app = App::new();
+        app.secrets_store = Some(store);
+        app.selected_index = 0; // ALPHA
+
+        app.handle_key(KeyCode::Char('s'));
+
+        assert_eq!(app.sort_mode, SortMode::ExpirationAsc);
+        assert_eq!(app.get_secret_names(), vec!["BETA", "ALPHA"]);
+        assert_eq!(app.get_selected_secret_name(), Some("ALPHA".to_string()));
+    }
+
+    #[test]
+    fn test_add_existing_name_waits_for_overwrite_confirmation() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let key = [0x42u8; 32];
+        let mut store = SecretsStore::new();
+        store
+            .add_secret(
+                "API_KEY".to_string(),
+                "old".to_string(),
+                None,
+                dir.path(),
+                &key,
+            )
+            .unwrap();
+        let mut app = App::new();
+        app.secrets_store = Some(store);
+        assert!(app.name_exists("API_KEY"));
+        assert!(!app.name_exists("api_key") && !app.name_exists("NEW"));
+
+        // A new name goes straight through
+        app.open_add_modal();
+        app.new_secret_name = "NEW".to_string();
+        assert!(!app.hold_for_overwrite());
+        assert_eq!(app.modal, Modal::AddSecret);
+
+        // An existing one is held; 'n' goes back to the form, keeping the input
+        app.new_secret_name = "API_KEY".to_string();
+        app.new_secret_value = "new".to_string();
+        assert!(app.hold_for_overwrite());
+        assert_eq!(app.modal, Modal::OverwriteConfirm);
+        // Once confirming, main.rs may write
+        assert!(!app.hold_for_overwrite());
+        app.handle_key(KeyCode::Char('n'));
+        assert_eq!(app.modal, Modal::AddSecret);
+        assert_eq!(app.new_secret_name, "API_KEY");
+        assert_eq!(app.new_secret_value, "new");
+        assert_eq!(app.current_field, Field::Name);
+
+        // 'y' is left to main.rs, which writes
+        assert!(app.hold_for_overwrite());
+        app.handle_key(KeyCode::Char('y'));
+        assert_eq!(app.modal, Modal::OverwriteConfirm);
+    }
+
+    #[test]
+    fn test_agent_mode_name_exists() {
+        let app = app_with_names(&["DB_URL"]);
+        assert!(app.name_exists("DB_URL"));
+        assert!(!app.name_exists("API_KEY"));
+    }
+
+    #[test]
+    fn test_restore_ui_state_selects_saved_name_or_first() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let key = [0x42u8; 32];
+        let mut store = SecretsStore::new();
+        for name in ["ALPHA", "BETA", "GAMMA"] {
+            store
+                .add_secret(name.to_string(), "v".to_string(), None, dir.path(), &key)
+                .unwrap();
+        }
+        let mut app = App::new();
+        app.secrets_store = Some(store);
+        app.selected_index = 2;
+        app.sort_mode = SortMode::ExpirationAsc;
+
+        let state = app.ui_state();
+        assert_eq!(state.selected.as_deref(), Some("GAMMA"));
+        state.save(dir.path()).unwrap();
+        let saved = std::fs::read_to_string(dir.path().join("ui_state.json")).unwrap();
+        assert!(saved.contains("\"expiration_asc\"") && !saved.contains("\"v\""));
+
+        app.selected_index = 0;
+        app.sort_mode = SortMode::Name;
+        app.restore_ui_state(UiState::load(dir.path()));
+        assert_eq!(app.sort_mode, SortMode::ExpirationAsc);
+        assert_eq!(app.get_selected_secret_name(), Some("GAMMA".to_string()));
+
+        // A deleted secret or a tag no longer in use falls back to the first one
+        app.restore_ui_state(UiState {
+            selected: Some("DELETED".to_string()),
+            tag_filter: Some("gone".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(app.selected_index, 0);
+        assert_eq!(app.tag_filter, None);
+
+        // The search is restored too; a name it hides is not selected
+        app.restore_ui_state(UiState {
+            selected: Some("BETA".to_string()),
+            search_query: "mm".to_string(),
+            ..Default::default()
+        });
+        assert_eq!(app.filtered_secret_names(), vec!["GAMMA"]);
+        assert_eq!(app.get_selected_secret_name(), Some("GAMMA".to_string()));
+    }
+
+    #[test]
+    fn test_ui_state_load_defaults_on_missing_or_corrupt_file() {
+        let dir = tempfile::TempDir::new().unwrap();
+        assert_eq!(UiState::load(dir.path()), UiState::default());
+        std::fs::write(dir.path().join("ui_state.json"), "{not json").unwrap();
+        assert_eq!(UiState::load(dir.path()), UiState::default());
+    }
+
+    #[test]
+    fn test_tag_filter_cycles_through_tags() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let key = [0x42u8; 32];
+        let mut store = SecretsStore::new();
+        for (name, tag) in [("API", "backend"), ("DB", "Backend"), ("WEB", "frontend")] {
+            store
+                .add_secret(name.to_string(), "v".to_string(), None, dir.path(), &key)
+                .unwrap();
+            store
+                .update_secret_with(name, None, dir.path(), &key, |secret| {
+                    secret.tags = vec![tag.to_string()]
+                })
+                .unwrap();
+        }
+        store
+            .add_secret("PLAIN".to_string(), "v".to_string(), None, dir.path(), &key)
+            .unwrap();
+        let mut app = App::new();
+        app.secrets_store = Some(store);
+
+        app.handle_key(KeyCode::Char('t'));
+        assert_eq!(app.tag_filter.as_deref(), Some("backend"));
+        assert_eq!(app.filtered_secret_names(), vec!["API", "DB"]);
+        assert_eq!(app.secrets_count(), 2);
+
+        app.handle_key(KeyCode::Char('t'));
+        assert_eq!(app.filtered_secret_names(), vec!["WEB"]);
+
+        app.handle_key(KeyCode::Char('t'));
+        assert_eq!(app.tag_filter, None);
+        assert_eq!(app.secrets_count(), 4);
+
+        app.handle_key(KeyCode::Char('t'));
+        app.handle_key(KeyCode::Esc);
+        assert_eq!(app.tag_filter, None);
+    }
+
+    // ========================
+    // Keybinding tests
+    // ========================
+
+    #[test]
+    fn test_custom_binding_moves_selection() {
+        let mut app = app_with_names(&["ALPHA", "BETA", "GAMMA"]);
+        app.config.keybindings.move_down = vec![KeyCode::Down, KeyCode::Char('j')];
+        app.config.keybindings.move_up = vec![KeyCode::Up, KeyCode::Char('k')];
+
+        app.handle_key(KeyCode::Char('j'));
+        app.handle_key(KeyCode::Char('j'));
+        assert_eq!(app.selected_index, 2);
+
+        app.handle_key(KeyCode::Char('k'));
+        app.handle_key(KeyCode::Up);
+        assert_eq!(app.selected_index, 0);
+    }
+
+    #[test]
+    fn test_rebound_key_replaces_default() {
+        let mut app = app_with_names(&["ALPHA"]);
+        app.config.keybindings.quit = vec![KeyCode::Char('x')];
+
+        app.handle_key(KeyCode::Char('q'));
+        assert!(!app.should_quit);
+
+        app.handle_key(KeyCode::Char('x'));
+        assert!(app.should_quit);
+    }
+
+    #[test]
+    fn test_add_modal_generates_value() {
+        let mut app = App::new();
+        app.open_add_modal();
+
+        app.handle_key(KeyCode::F(2));
+
+        assert_eq!(app.new_secret_value.len(), generator::DEFAULT_LENGTH);
+        assert_eq!(app.current_field, Field::Value);
+    }
+
+    #[test]
+    fn test_rename_modal_prefills_selected_name() {
+        let mut app = app_with_names(&["ALPHA", "BETA"]);
+        app.selected_index = 1;
+
+        app.handle_key(KeyCode::Char('r'));
+        assert_eq!(app.modal, Modal::Rename);
+        assert_eq!(app.rename_input, "BETA");
+
+        app.handle_key(KeyCode::Backspace);
+        app.handle_key(KeyCode::Char('X'));
+        assert_eq!(app.rename_input, "BETX");
+
+        app.handle_key(KeyCode::Esc);
+        assert_eq!(app.modal, Modal::None);
+    }
+
+    #[test]
+    fn test_detail_modal_never_keeps_a_revealed_value() {
+        let mut app = app_with_names(&["ALPHA", "BETA"]);
+        app.revealed_secret = Some("revealed in the list".to_string());
+
+        app.handle_key(KeyCode::Enter);
+        assert_eq!(app.modal, Modal::Detail);
+        assert!(app.revealed_secret.is_none());
+
+        app.revealed_secret = Some("revealed in the modal".to_string());
+        app.handle_key(KeyCode::Esc);
+        assert_eq!(app.modal, Modal::None);
+        assert!(app.revealed_secret.is_none());
+    }
+
+    #[test]
+    fn test_detail_modal_ignored_without_secrets() {
+        let mut app = App::new();
+
+        app.handle_key(KeyCode::Enter);
+
+        assert_eq!(app.modal, Modal::None);
+    }
+
+    fn report_with_files(count: usize) -> AnalysisReport {
+        let file = |i: usize| token_analyzer::FileAnalysis {
+            path: format!("src/file{}.rs", i).into(),
+            call_count: 1,
+            has_exposure: false,
+            risk_level: token_analyzer::RiskLevel::Low,
+            risk_score: 1,
+            exposures: Vec::new(),
+            exposure_lines: Vec::new(),
+            occurrence_lines: vec![1],
+        };
+        AnalysisReport {
+            token_name: "API_KEY".to_string(),
+            search_dir: ".".into(),
+            total_calls: count,
+            exposure_count: 0,
+            total_risk_score: count,
+            critical_files: 0,
+            files: (0..count).map(file).collect(),
+            duration: Duration::ZERO,
+            files_scanned: count,
+            truncated: false,
+            errors: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_usages_scroll_by_page_within_bounds() {
+        let mut app = App::new();
+        app.mode = Mode::Normal;
+        app.token_analysis = Some(report_with_files(25));
+        app.usages_rows.set(10);
+
+        app.handle_key(KeyCode::PageDown);
+        assert_eq!(app.usages_scroll, 10);
+        app.handle_key(KeyCode::PageDown);
+        // The last page stays full: files 15 to 24
+        assert_eq!(app.usages_scroll, 15);
+        app.handle_key(KeyCode::PageDown);
+        assert_eq!(app.usages_scroll, 15);
+
+        app.handle_key(KeyCode::PageUp);
+        assert_eq!(app.usages_scroll, 5);
+        app.handle_key(KeyCode::PageUp);
+        assert_eq!(app.usages_scroll, 0);
+    }
+
+    #[test]
+    fn test_rename_ignored_without_secrets() {
+        let mut app = App::new();
+
+        app.handle_key(KeyCode::Char('r'));
+
+        assert_eq!(app.modal, Modal::None);
+    }
+
+    #[test]
+    fn test_reset_session_clears_transient_state() {
+        let mut app = App::default();
+        app.passphrase = b"hunter2".to_vec();
+        app.open_add_modal();
+        app.new_secret_name = "API_KEY".to_string();
+        app.new_secret_value = "sk-123".to_string();
+        app.current_field = Field::Value;
+        app.revealed_secret = Some("sk-123".to_string());
+        app.totp_seed = Some("GEZDGNBV".to_string());
+        app.set_status("✓ Saved".to_string());
+
+        app.reset_session();
+
+        assert!(app.totp_seed.is_none());
+
+        assert!(app.passphrase.is_empty());
+        assert!(app.new_secret_name.is_empty());
+        assert!(app.new_secret_value.is_empty());
+        assert_eq!(app.current_field, Field::Name);
+        assert!(app.revealed_secret.is_none());
+        assert!(app.status_message.is_none());
+        assert_eq!(app.modal, Modal::None);
+    }
+
+    #[test]
+    fn test_command_argument_keeps_suggestions() {
+        let mut app = App::new();
+        app.open_command_modal();
+        for c in "get-file  out/token.txt ".chars() {
+            app.handle_key(KeyCode::Char(c));
+        }
+
+        assert_eq!(app.get_selected_command(), Some("get-file"));
+        assert_eq!(app.get_command_argument(), Some("out/token.txt"));
+
+        app.command_input = "get-file".to_string();
+        assert_eq!(app.get_command_argument(), None);
+    }
+
     #[test]
     fn test_handle_key_normal_mode_no_effect() {
         let mut app = App::new();
@@ -1,28 +1,74 @@
 //! Agent daemon for lazy-locker.
 //!
 //! The agent stores the derived key in memory and responds to requests
-//! from SDKs (Python, JS) via a Unix socket.
+//! from SDKs (Python, JS) via a Unix socket, or a named pipe on Windows.
 //!
 //! Architecture:
-//! - Socket: ~/.lazy-locker/agent.sock
+//! - Socket: ~/.lazy-locker/agent.sock (`\\.\pipe\lazy-locker-<user>` on Windows)
 //! - Protocol: Simple JSON over lines
-//! - TTL: 8h by default, configurable
+//! - TTL: 8h by default, set with `--ttl` or `LAZY_LOCKER_TTL_HOURS` (0 = no expiry).
+//!   With `--idle-ttl` (or `LAZY_LOCKER_IDLE_TTL=1`) it counts from the last
+//!   secret access instead of startup.
+//! - Peers: on Linux only clients with the agent's uid are served (`SO_PEERCRED`);
+//!   `--allow-pid` (or `LAZY_LOCKER_AGENT_ALLOW_PIDS`) further restricts them to
+//!   the listed processes and their descendants.
+//! - Audit: with `--audit` (or `LAZY_LOCKER_AGENT_AUDIT=1`) every secret read is
+//!   appended to `agent.log` with the client's uid/pid; names only, never values.
+//!   Errors of the agent itself (reloads, undecryptable secrets, clients) are
+//!   always appended there, since the daemon runs without a terminal.
 
-use std::collections::HashMap;
-use std::io::{BufRead, BufReader, Write};
-use std::os::unix::net::{UnixListener, UnixStream};
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
 
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use zeroize::Zeroize;
+use zeroize::{Zeroize, Zeroizing};
 
-use crate::core::store::SecretsStore;
+use crate::core::config::{self, Config};
+use crate::core::lock::StoreLock;
+use crate::core::store::{SecretsStore, now_timestamp};
+use crate::core::transport::{AgentTransport, PeerCred, Transport};
 
 /// Default session duration (8 hours)
-const DEFAULT_TTL_HOURS: u64 = 8;
+pub const DEFAULT_TTL_HOURS: u64 = 8;
+
+/// Environment variable overriding the session duration, in hours
+pub const TTL_ENV_VAR: &str = "LAZY_LOCKER_TTL_HOURS";
+
+/// Environment variable enabling the idle (sliding) TTL when set to 1
+pub const IDLE_TTL_ENV_VAR: &str = "LAZY_LOCKER_IDLE_TTL";
+
+/// Environment variable enabling the audit log when set to 1
+pub const AUDIT_ENV_VAR: &str = "LAZY_LOCKER_AGENT_AUDIT";
+
+/// Environment variable making the agent refuse `shutdown` and `reload`
+/// when set to 1
+pub const READONLY_ENV_VAR: &str = "LAZY_LOCKER_AGENT_READONLY";
+
+/// Environment variable restricting clients to these processes and their
+/// descendants (comma-separated pids)
+pub const ALLOW_PIDS_ENV_VAR: &str = "LAZY_LOCKER_AGENT_ALLOW_PIDS";
+
+/// Audit log, next to secrets.json
+const AUDIT_LOG_FILE: &str = "agent.log";
+
+/// Size above which the audit log moves to `agent.log.1`, replacing the previous one
+const AUDIT_LOG_MAX_BYTES: u64 = 1024 * 1024;
+
+/// Attempts made to reach an agent that may still be starting
+const CONNECT_ATTEMPTS: u32 = 5;
+
+/// First retry delay, doubled after each failed attempt (~450ms in total)
+const CONNECT_BACKOFF: Duration = Duration::from_millis(30);
+
+/// How often the agent checks whether secrets.json changed on disk
+const STORE_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How long a value decrypted for `GetSecret` stays cached
+const CACHE_TTL: Duration = Duration::from_secs(30);
 
 /// Request sent to the agent
 #[derive(Debug, Deserialize)]
@@ -32,9 +78,12 @@ pub enum AgentRequest {
     #[serde(rename = "ping")]
     Ping,
 
-    /// Request all decrypted secrets
+    /// Request all decrypted secrets, optionally resolving an environment variant
     #[serde(rename = "get_secrets")]
-    GetSecrets,
+    GetSecrets {
+        #[serde(default)]
+        env: Option<String>,
+    },
 
     /// Request a specific secret
     #[serde(rename = "get_secret")]
@@ -44,6 +93,10 @@ pub enum AgentRequest {
     #[serde(rename = "list")]
     List,
 
+    /// Reload secrets from disk
+    #[serde(rename = "reload")]
+    Reload,
+
     /// Stop the agent
     #[serde(rename = "shutdown")]
     Shutdown,
@@ -60,45 +113,333 @@ pub enum AgentResponse {
     Error { message: String },
 }
 
+/// Which clients the agent serves, on top of the endpoint's owner-only permissions
+#[derive(Debug, Default)]
+struct PeerPolicy {
+    /// Uid every client must have (None = not checked)
+    uid: Option<u32>,
+    /// Processes allowed to connect, with their descendants (empty = any process)
+    allowed_pids: Vec<u32>,
+}
+
+impl PeerPolicy {
+    /// Policy of a running agent: same uid where the OS reports it
+    fn for_current_user(allowed_pids: Vec<u32>) -> Self {
+        #[cfg(target_os = "linux")]
+        // SAFETY: getuid has no preconditions and cannot fail
+        let uid = Some(unsafe { libc::getuid() });
+        #[cfg(not(target_os = "linux"))]
+        let uid = None;
+        Self { uid, allowed_pids }
+    }
+
+    /// Rejects clients outside the policy. Credentials the OS could not
+    /// provide fail the check whenever something is enforced.
+    fn check(&self, peer: Option<PeerCred>) -> Result<(), String> {
+        if self.uid.is_none() && self.allowed_pids.is_empty() {
+            return Ok(());
+        }
+        let peer = peer.ok_or("Client credentials unavailable")?;
+        if let Some(uid) = self.uid
+            && peer.uid != Some(uid)
+        {
+            return Err(format!(
+                "Client uid {} does not match the agent's",
+                peer.uid.map_or("unknown".to_string(), |u| u.to_string())
+            ));
+        }
+        if !self.allowed_pids.is_empty()
+            && !process_lineage(peer.pid).any(|pid| self.allowed_pids.contains(&pid))
+        {
+            return Err(format!("Client process {} is not allowed", peer.pid));
+        }
+        Ok(())
+    }
+}
+
+/// `pid` followed by its ancestors, as far as the OS tells (only `pid` itself
+/// where parents cannot be read)
+fn process_lineage(pid: u32) -> impl Iterator<Item = u32> {
+    std::iter::successors(Some(pid), |&pid| parent_pid(pid).filter(|&ppid| ppid != 0)).take(64)
+}
+
+#[cfg(target_os = "linux")]
+fn parent_pid(pid: u32) -> Option<u32> {
+    // Fields after the parenthesized command name: state, then ppid
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    let (_, fields) = stat.rsplit_once(')')?;
+    fields.split_whitespace().nth(1)?.parse().ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn parent_pid(_pid: u32) -> Option<u32> {
+    None
+}
+
+/// Parses a comma-separated list of pids
+pub fn parse_pid_list(list: &str) -> Result<Vec<u32>> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|pid| !pid.is_empty())
+        .map(|pid| {
+            pid.parse()
+                .map_err(|_| anyhow::anyhow!("Invalid pid '{}' in allowed pids", pid))
+        })
+        .collect()
+}
+
 /// Agent state in memory
 struct AgentState {
     /// Decryption key (zeroized on shutdown)
     key: Vec<u8>,
     /// Secrets store
     store: SecretsStore,
+    /// Path of secrets.json, watched for changes
+    store_path: PathBuf,
+    /// Modification time of secrets.json when the store was last loaded
+    store_mtime: Option<SystemTime>,
     /// Startup timestamp
     started_at: Instant,
-    /// TTL in hours
+    /// Last successful secret access (GetSecrets/GetSecret)
+    last_access: Instant,
+    /// TTL in hours (0 = never expires)
     ttl_hours: u64,
+    /// Whether the TTL counts from the last access rather than startup
+    sliding: bool,
+    /// Values decrypted for `GetSecret`, with when they were decrypted
+    cache: HashMap<String, (Zeroizing<String>, Instant)>,
+    /// Whether secret reads are appended to the audit log
+    audit: bool,
+    /// Clients allowed to make requests
+    peer_policy: PeerPolicy,
+    /// Whether `Shutdown` and `Reload` are refused, leaving the agent to be
+    /// stopped by PID or by its TTL
+    readonly: bool,
     /// Shutdown flag
     should_stop: bool,
 }
 
+impl AgentState {
+    fn new(
+        key: Vec<u8>,
+        store: SecretsStore,
+        store_path: PathBuf,
+        ttl_hours: u64,
+        sliding: bool,
+        now: Instant,
+    ) -> Self {
+        Self {
+            key,
+            store,
+            store_mtime: modified_time(&store_path),
+            store_path,
+            started_at: now,
+            last_access: now,
+            ttl_hours,
+            sliding,
+            cache: HashMap::new(),
+            audit: false,
+            peer_policy: PeerPolicy::default(),
+            readonly: false,
+            should_stop: false,
+        }
+    }
+
+    /// Time the TTL is measured from
+    fn ttl_start(&self) -> Instant {
+        if self.sliding {
+            self.last_access
+        } else {
+            self.started_at
+        }
+    }
+
+    /// Whether the session has outlived its TTL at `now`
+    fn is_expired(&self, now: Instant) -> bool {
+        self.ttl_hours > 0
+            && now.saturating_duration_since(self.ttl_start())
+                > Duration::from_secs(self.ttl_hours * 3600)
+    }
+
+    /// Reloads the store from disk while holding the locker lock, so a write
+    /// in progress is never read half-done. On failure the previous store is kept.
+    fn reload(&mut self) -> Result<usize> {
+        let locker_dir = self
+            .store_path
+            .parent()
+            .ok_or_else(|| anyhow::anyhow!("Invalid store path"))?;
+        let _lock = StoreLock::acquire(locker_dir)?;
+        let mtime = modified_time(&self.store_path);
+        let store = SecretsStore::load_from_path(&self.store_path, &self.key)?;
+        self.store = store;
+        self.store_mtime = mtime;
+        self.cache.clear();
+        Ok(self.store.list_secrets().len())
+    }
+
+    /// Reloads the store if secrets.json changed since it was loaded
+    fn reload_if_changed(&mut self) -> Result<bool> {
+        if modified_time(&self.store_path) == self.store_mtime {
+            return Ok(false);
+        }
+        self.reload().map(|_| true)
+    }
+
+    /// Decrypts the secret `name` alone, serving it from the cache while fresh.
+    /// Returns None when there is no such secret.
+    fn decrypt_cached(&mut self, name: &str, now: Instant) -> Result<Option<Zeroizing<String>>> {
+        self.cache
+            .retain(|_, (_, cached_at)| now.saturating_duration_since(*cached_at) < CACHE_TTL);
+        if let Some((value, _)) = self.cache.get(name) {
+            return Ok(Some(value.clone()));
+        }
+
+        let Some(secret) = self.store.get_secret(name) else {
+            return Ok(None);
+        };
+        let value = Zeroizing::new(secret.decrypt_for(None, &self.key)?);
+        self.cache.insert(name.to_string(), (value.clone(), now));
+        Ok(Some(value))
+    }
+
+    /// Appends a secret read to the audit log when auditing is on.
+    /// A failed write is reported but never fails the request.
+    fn record_access(&self, mut entry: serde_json::Value, peer: Option<PeerCred>) {
+        if !self.audit {
+            return;
+        }
+        entry["ts"] = now_timestamp().into();
+        entry["pid"] = peer.map(|p| p.pid).into();
+        entry["uid"] = peer.and_then(|p| p.uid).into();
+        let path = self.store_path.with_file_name(AUDIT_LOG_FILE);
+        if let Err(e) = append_audit_line(&path, &entry.to_string(), AUDIT_LOG_MAX_BYTES) {
+            eprintln!("Audit log error: {}", e);
+        }
+    }
+
+    /// Reports an error of the agent itself. The daemon has no terminal, so
+    /// it is appended to the audit log too, whether or not auditing is on.
+    fn log_error(&self, message: &str) {
+        eprintln!("{}", message);
+        let entry = serde_json::json!({
+            "action": "error",
+            "message": message,
+            "ts": now_timestamp(),
+        });
+        let path = self.store_path.with_file_name(AUDIT_LOG_FILE);
+        append_audit_line(&path, &entry.to_string(), AUDIT_LOG_MAX_BYTES).ok();
+    }
+
+    /// Seconds left before expiry at `now`, or None when the session never expires
+    fn ttl_remaining_secs(&self, now: Instant) -> Option<u64> {
+        (self.ttl_hours > 0).then(|| {
+            (self.ttl_hours * 3600)
+                .saturating_sub(now.saturating_duration_since(self.ttl_start()).as_secs())
+        })
+    }
+}
+
 impl Drop for AgentState {
     fn drop(&mut self) {
-        // Clean up key in memory
+        // Clean up key and cached values in memory
         self.key.zeroize();
+        self.cache.clear();
     }
 }
 
-/// Gets the agent socket path
-pub fn get_socket_path() -> Result<PathBuf> {
-    let base_dirs = directories::BaseDirs::new()
-        .ok_or_else(|| anyhow::anyhow!("Unable to determine user directories"))?;
+/// Appends `line` to the log at `path` (owner-only), first moving a log
+/// larger than `max_bytes` to `<path>.1`
+fn append_audit_line(path: &Path, line: &str, max_bytes: u64) -> std::io::Result<()> {
+    if std::fs::metadata(path).is_ok_and(|m| m.len() + line.len() as u64 > max_bytes) {
+        let mut rotated = path.as_os_str().to_owned();
+        rotated.push(".1");
+        std::fs::rename(path, rotated)?;
+    }
 
+    let mut options = std::fs::OpenOptions::new();
+    options.create(true).append(true);
     #[cfg(unix)]
-    let sub_dir = ".lazy-locker";
-    #[cfg(not(unix))]
-    let sub_dir = "lazy-locker";
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    writeln!(file, "{}", line)
+}
 
-    let locker_dir = base_dirs.config_dir().join(sub_dir);
-    Ok(locker_dir.join("agent.sock"))
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Resolves the session duration in hours: the `--ttl` flag takes priority
+/// over `LAZY_LOCKER_TTL_HOURS`, which takes priority over the 8h default.
+/// 0 means the session never expires.
+pub fn resolve_ttl_hours(flag: Option<u64>) -> Result<u64> {
+    if let Some(hours) = flag {
+        return Ok(hours);
+    }
+    match std::env::var(TTL_ENV_VAR) {
+        Ok(value) => value.trim().parse().map_err(|_| {
+            anyhow::anyhow!(
+                "Invalid {} '{}': expected a number of hours",
+                TTL_ENV_VAR,
+                value
+            )
+        }),
+        Err(_) => Ok(DEFAULT_TTL_HOURS),
+    }
+}
+
+/// Gets the agent socket path (the pipe name on Windows)
+pub fn get_socket_path() -> Result<PathBuf> {
+    Ok(Transport::endpoint(&Config::get_locker_dir()?))
 }
 
 /// Gets the agent PID file path
 pub fn get_pid_path() -> Result<PathBuf> {
-    let socket_path = get_socket_path()?;
-    Ok(socket_path.with_extension("pid"))
+    Ok(Config::get_locker_dir()?.join("agent.pid"))
+}
+
+/// PID of the agent, as written to `agent.pid` when it was started
+pub fn read_agent_pid() -> Option<u32> {
+    read_pid(&get_pid_path().ok()?)
+}
+
+fn read_pid(pid_path: &Path) -> Option<u32> {
+    std::fs::read_to_string(pid_path).ok()?.trim().parse().ok()
+}
+
+/// Whether a process with this PID exists. Assumed on platforms where the
+/// endpoint cannot outlive the agent anyway (a named pipe vanishes with it).
+fn process_alive(pid: u32) -> bool {
+    #[cfg(unix)]
+    {
+        let Ok(pid) = libc::pid_t::try_from(pid) else {
+            return false;
+        };
+        // SAFETY: signal 0 only checks that the process exists
+        let exists = unsafe { libc::kill(pid, 0) } == 0;
+        // EPERM: it exists, owned by another user
+        exists || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = pid;
+        true
+    }
+}
+
+/// Removes the socket and PID file of an agent that died without cleaning up,
+/// i.e. whose recorded PID no longer exists. Returns true if they were stale.
+pub fn remove_stale_agent_files(socket_path: &Path, pid_path: &Path) -> bool {
+    match read_pid(pid_path) {
+        Some(pid) if !process_alive(pid) => {
+            Transport::cleanup(socket_path);
+            std::fs::remove_file(pid_path).ok();
+            true
+        }
+        _ => false,
+    }
 }
 
 /// Checks if the agent is running
@@ -106,11 +447,16 @@ pub fn is_agent_running() -> bool {
     let Ok(socket_path) = get_socket_path() else {
         return false;
     };
-    if !socket_path.exists() {
+    if let Ok(pid_path) = get_pid_path()
+        && remove_stale_agent_files(&socket_path, &pid_path)
+    {
+        return false;
+    }
+    if !Transport::exists(&socket_path) {
         return false;
     }
     // Try connecting to verify
-    let Ok(mut stream) = UnixStream::connect(&socket_path) else {
+    let Ok(mut stream) = Transport::connect(&socket_path) else {
         return false;
     };
     let request = r#"{"action":"ping"}"#;
@@ -119,7 +465,7 @@ pub fn is_agent_running() -> bool {
         .is_ok()
     {
         stream.flush().ok();
-        let mut reader = BufReader::new(&stream);
+        let mut reader = BufReader::new(stream);
         let mut response = String::new();
         if reader.read_line(&mut response).is_ok() {
             return response.contains("\"status\":\"ok\"");
@@ -128,16 +474,18 @@ pub fn is_agent_running() -> bool {
     false
 }
 
-/// Starts the agent in daemon mode (fork)
-pub fn start_daemon(key: Vec<u8>, store: SecretsStore) -> Result<()> {
+/// Starts the agent in daemon mode (fork).
+/// Without `ttl_hours`, the agent resolves its TTL from the environment.
+pub fn start_daemon(key: Vec<u8>, store: SecretsStore, ttl_hours: Option<u64>) -> Result<()> {
     use std::process::Command;
 
     let socket_path = get_socket_path()?;
     let pid_path = get_pid_path()?;
 
     // Remove old socket if it exists
-    if socket_path.exists() {
-        std::fs::remove_file(&socket_path)?;
+    remove_stale_agent_files(&socket_path, &pid_path);
+    if Transport::exists(&socket_path) {
+        Transport::cleanup(&socket_path);
     }
 
     // Serialize key and store path for subprocess
@@ -145,12 +493,31 @@ pub fn start_daemon(key: Vec<u8>, store: SecretsStore) -> Result<()> {
     let store_path = store.get_path().to_string_lossy().to_string();
 
     // Launch daemon in background
-    let child = Command::new(std::env::current_exe()?)
+    let mut command = Command::new(std::env::current_exe()?);
+    if let Some(home) = config::home_override()? {
+        command.arg("--home").arg(home);
+    }
+    let profile = config::active_profile()?;
+    if profile != config::DEFAULT_PROFILE {
+        command.arg("--profile").arg(profile);
+    }
+    command
         .arg("agent")
         .arg("--key")
         .arg(&key_hex)
         .arg("--store")
-        .arg(&store_path)
+        .arg(&store_path);
+    if let Some(hours) = ttl_hours {
+        command.arg("--ttl").arg(hours.to_string());
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        // Outlive the console the TUI ran in
+        const DETACHED_PROCESS: u32 = 0x0000_0008;
+        command.creation_flags(DETACHED_PROCESS);
+    }
+    let child = command
         .stdin(std::process::Stdio::null())
         .stdout(std::process::Stdio::null())
         .stderr(std::process::Stdio::null())
@@ -161,7 +528,7 @@ pub fn start_daemon(key: Vec<u8>, store: SecretsStore) -> Result<()> {
 
     // Wait for socket to be ready
     for _ in 0..50 {
-        if socket_path.exists() {
+        if Transport::exists(&socket_path) {
             return Ok(());
         }
         std::thread::sleep(Duration::from_millis(100));
@@ -170,33 +537,48 @@ pub fn start_daemon(key: Vec<u8>, store: SecretsStore) -> Result<()> {
     Err(anyhow::anyhow!("Agent did not start in time"))
 }
 
-/// Agent mode entry point (called by the daemon)
-pub fn run_agent(key_hex: &str, store_path: &str) -> Result<()> {
+/// Agent mode entry point (called by the daemon).
+/// `idle_ttl` makes the TTL count inactivity; it is also enabled by `LAZY_LOCKER_IDLE_TTL=1`.
+/// `audit` turns on the audit log; it is also enabled by `LAZY_LOCKER_AGENT_AUDIT=1`.
+/// `allowed_pids` restricts clients to these processes and their descendants,
+/// adding to `LAZY_LOCKER_AGENT_ALLOW_PIDS`. A `readonly` agent refuses
+/// `shutdown` and `reload` requests.
+pub fn run_agent(
+    key_hex: &str,
+    store_path: &str,
+    ttl_hours: Option<u64>,
+    idle_ttl: bool,
+    audit: bool,
+    readonly: bool,
+    mut allowed_pids: Vec<u32>,
+) -> Result<()> {
+    let ttl_hours = resolve_ttl_hours(ttl_hours)?;
+    let sliding = idle_ttl || std::env::var(IDLE_TTL_ENV_VAR).is_ok_and(|v| v.trim() == "1");
+    let audit = audit || std::env::var(AUDIT_ENV_VAR).is_ok_and(|v| v.trim() == "1");
+    let readonly = readonly || std::env::var(READONLY_ENV_VAR).is_ok_and(|v| v.trim() == "1");
+    if let Ok(list) = std::env::var(ALLOW_PIDS_ENV_VAR) {
+        allowed_pids.extend(parse_pid_list(&list)?);
+    }
     let key = hex::decode(key_hex)?;
     let store = SecretsStore::load_from_path(&PathBuf::from(store_path), &key)?;
 
     let socket_path = get_socket_path()?;
 
-    // Create Unix socket
-    let listener = UnixListener::bind(&socket_path)?;
-
-    // Set non-blocking to allow periodic shutdown checks
-    listener.set_nonblocking(true)?;
-
-    // Restrictive permissions on socket
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs::PermissionsExt;
-        std::fs::set_permissions(&socket_path, std::fs::Permissions::from_mode(0o600))?;
-    }
+    // Non-blocking listener, only reachable by the current user
+    let mut listener = Transport::bind(&socket_path)?;
 
-    let state = Arc::new(Mutex::new(AgentState {
+    let mut state = AgentState::new(
         key,
         store,
-        started_at: Instant::now(),
-        ttl_hours: DEFAULT_TTL_HOURS,
-        should_stop: false,
-    }));
+        PathBuf::from(store_path),
+        ttl_hours,
+        sliding,
+        Instant::now(),
+    );
+    state.audit = audit;
+    state.readonly = readonly;
+    state.peer_policy = PeerPolicy::for_current_user(allowed_pids);
+    let state = Arc::new(Mutex::new(state));
 
     // TTL check thread
     let state_ttl = Arc::clone(&state);
@@ -204,7 +586,7 @@ pub fn run_agent(key_hex: &str, store_path: &str) -> Result<()> {
         loop {
             std::thread::sleep(Duration::from_secs(60));
             let mut s = state_ttl.lock().unwrap();
-            if s.started_at.elapsed() > Duration::from_secs(s.ttl_hours * 3600) {
+            if s.is_expired(Instant::now()) {
                 s.should_stop = true;
                 break;
             }
@@ -214,6 +596,21 @@ pub fn run_agent(key_hex: &str, store_path: &str) -> Result<()> {
         }
     });
 
+    // Store watcher thread: picks up secrets added by the CLI or the TUI
+    let state_watch = Arc::clone(&state);
+    std::thread::spawn(move || {
+        loop {
+            std::thread::sleep(STORE_POLL_INTERVAL);
+            let mut s = state_watch.lock().unwrap();
+            if s.should_stop {
+                break;
+            }
+            if let Err(e) = s.reload_if_changed() {
+                s.log_error(&format!("Reload error (keeping previous secrets): {}", e));
+            }
+        }
+    });
+
     // Main loop with non-blocking accept
     loop {
         // Check if we should stop first
@@ -221,27 +618,34 @@ pub fn run_agent(key_hex: &str, store_path: &str) -> Result<()> {
             break;
         }
 
-        match listener.accept() {
-            Ok((stream, _)) => {
+        match Transport::accept(&mut listener) {
+            Ok(Some(stream)) => {
+                let peer = Transport::peer_cred(&stream);
                 let state_clone = Arc::clone(&state);
                 std::thread::spawn(move || {
-                    if let Err(e) = handle_client(stream, state_clone) {
-                        eprintln!("Client error: {}", e);
+                    if let Err(e) = handle_client(stream, peer, Arc::clone(&state_clone)) {
+                        state_clone
+                            .lock()
+                            .unwrap()
+                            .log_error(&format!("Client error: {}", e));
                     }
                 });
             }
-            Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+            Ok(None) => {
                 // No connection pending, sleep briefly then check again
                 std::thread::sleep(Duration::from_millis(50));
             }
             Err(e) => {
-                eprintln!("Connection error: {}", e);
+                state
+                    .lock()
+                    .unwrap()
+                    .log_error(&format!("Connection error: {}", e));
             }
         }
     }
 
     // Cleanup
-    std::fs::remove_file(&socket_path).ok();
+    Transport::cleanup(&socket_path);
     if let Ok(pid_path) = get_pid_path() {
         std::fs::remove_file(&pid_path).ok();
     }
@@ -249,72 +653,130 @@ pub fn run_agent(key_hex: &str, store_path: &str) -> Result<()> {
     Ok(())
 }
 
-/// Handles a client connection
-fn handle_client(stream: UnixStream, state: Arc<Mutex<AgentState>>) -> Result<()> {
-    let mut reader = BufReader::new(&stream);
-    let mut writer = &stream;
+/// Handles a client connection; `peer` identifies the client when the OS reports it
+fn handle_client(
+    stream: impl Read + Write,
+    peer: Option<PeerCred>,
+    state: Arc<Mutex<AgentState>>,
+) -> Result<()> {
+    let mut reader = BufReader::new(stream);
 
     let mut line = String::new();
     reader.read_line(&mut line)?;
 
-    let response = match serde_json::from_str::<AgentRequest>(&line) {
-        Ok(request) => process_request(request, &state),
-        Err(e) => AgentResponse::Error {
+    let response_json = match serde_json::from_str::<AgentRequest>(&line) {
+        Ok(request) => process_request(request, &state, peer)?,
+        Err(e) => Zeroizing::new(serde_json::to_string(&AgentResponse::Error {
             message: format!("Invalid request: {}", e),
-        },
+        })?),
     };
 
-    let response_json = serde_json::to_string(&response)?;
-    writeln!(writer, "{}", response_json)?;
+    let writer = reader.get_mut();
+    writeln!(writer, "{}", response_json.as_str())?;
     writer.flush()?;
 
     Ok(())
 }
 
-/// Processes a request
-fn process_request(request: AgentRequest, state: &Arc<Mutex<AgentState>>) -> AgentResponse {
+/// Serializes an `ok` response carrying plaintext into a buffer that is wiped on drop.
+/// The buffer is sized for the worst-case JSON escaping up front so serialization
+/// never reallocates and leaves unwiped copies of the plaintext behind.
+fn plaintext_response(data: impl Serialize, plaintext_len: usize) -> Result<Zeroizing<String>> {
+    #[derive(Serialize)]
+    struct OkResponse<T> {
+        status: &'static str,
+        data: T,
+    }
+
+    let mut buf = Zeroizing::new(Vec::with_capacity(plaintext_len * 6 + 64));
+    serde_json::to_writer(&mut *buf, &OkResponse { status: "ok", data })?;
+    let json = String::from_utf8(std::mem::take(&mut *buf))?;
+    Ok(Zeroizing::new(json))
+}
+
+/// Processes a request and returns the serialized response line
+fn process_request(
+    request: AgentRequest,
+    state: &Arc<Mutex<AgentState>>,
+    peer: Option<PeerCred>,
+) -> Result<Zeroizing<String>> {
+    process_request_at(request, state, peer, Instant::now())
+}
+
+/// Processes a request as if received at `now`
+fn process_request_at(
+    request: AgentRequest,
+    state: &Arc<Mutex<AgentState>>,
+    peer: Option<PeerCred>,
+    now: Instant,
+) -> Result<Zeroizing<String>> {
     let mut s = state.lock().unwrap();
 
+    if let Err(message) = s.peer_policy.check(peer) {
+        s.record_access(
+            serde_json::json!({ "action": "rejected", "reason": message }),
+            peer,
+        );
+        return respond(AgentResponse::Error { message });
+    }
+
     // Check TTL
-    if s.started_at.elapsed() > Duration::from_secs(s.ttl_hours * 3600) {
+    if s.is_expired(now) {
         s.should_stop = true;
-        return AgentResponse::Error {
+        return respond(AgentResponse::Error {
             message: "Session expired".to_string(),
-        };
+        });
     }
 
     match request {
-        AgentRequest::Ping => AgentResponse::Ok {
+        AgentRequest::Ping => respond(AgentResponse::Ok {
             data: serde_json::json!({
-                "uptime_secs": s.started_at.elapsed().as_secs(),
-                "ttl_remaining_secs": (s.ttl_hours * 3600).saturating_sub(s.started_at.elapsed().as_secs()),
+                "uptime_secs": now.saturating_duration_since(s.started_at).as_secs(),
+                "ttl_remaining_secs": s.ttl_remaining_secs(now),
+                "idle_ttl": s.sliding,
+                "audit": s.audit,
+                "readonly": s.readonly,
             }),
-        },
+        }),
 
-        AgentRequest::GetSecrets => match s.store.decrypt_all(&s.key) {
-            Ok(secrets) => AgentResponse::Ok {
-                data: serde_json::to_value(secrets).unwrap_or_default(),
-            },
-            Err(e) => AgentResponse::Error {
-                message: format!("Decryption error: {}", e),
-            },
-        },
+        AgentRequest::GetSecrets { env } => {
+            // One bad entry should not block every other secret
+            let (mut secrets, failures) = s.store.decrypt_all_for_reporting(env.as_deref(), &s.key);
+            for (name, error) in &failures {
+                s.log_error(&format!(
+                    "Decryption error for '{}' (skipped): {}",
+                    name, error
+                ));
+            }
+            s.last_access = now;
+            // The count only: listing every name would make huge lines
+            s.record_access(
+                serde_json::json!({ "action": "get_secrets", "count": secrets.len(), "env": env }),
+                peer,
+            );
+            let len = secrets.iter().map(|(k, v)| k.len() + v.len() + 6).sum();
+            let response = plaintext_response(&secrets, len);
+            for value in secrets.values_mut() {
+                value.zeroize();
+            }
+            response
+        }
 
-        AgentRequest::GetSecret { name } => match s.store.decrypt_all(&s.key) {
-            Ok(secrets) => {
-                if let Some(value) = secrets.get(&name) {
-                    AgentResponse::Ok {
-                        data: serde_json::json!({ "value": value }),
-                    }
-                } else {
-                    AgentResponse::Error {
-                        message: format!("Secret '{}' not found", name),
-                    }
-                }
+        AgentRequest::GetSecret { name } => match s.decrypt_cached(&name, now) {
+            Ok(Some(value)) => {
+                s.last_access = now;
+                s.record_access(
+                    serde_json::json!({ "action": "get_secret", "name": name }),
+                    peer,
+                );
+                plaintext_response(HashMap::from([("value", &*value)]), value.len() + 16)
             }
-            Err(e) => AgentResponse::Error {
+            Ok(None) => respond(AgentResponse::Error {
+                message: format!("Secret '{}' not found", name),
+            }),
+            Err(e) => respond(AgentResponse::Error {
                 message: format!("Decryption error: {}", e),
-            },
+            }),
         },
 
         AgentRequest::List => {
@@ -324,36 +786,103 @@ fn process_request(request: AgentRequest, state: &Arc<Mutex<AgentState>>) -> Age
                 .iter()
                 .map(|s| s.name.clone())
                 .collect();
-            AgentResponse::Ok {
+            respond(AgentResponse::Ok {
                 data: serde_json::to_value(names).unwrap_or_default(),
-            }
+            })
         }
 
+        AgentRequest::Reload | AgentRequest::Shutdown if s.readonly => {
+            s.record_access(
+                serde_json::json!({ "action": "rejected", "reason": "readonly" }),
+                peer,
+            );
+            respond(AgentResponse::Error {
+                message: "Agent is read-only: stop it by its PID or let its TTL expire".to_string(),
+            })
+        }
+
+        AgentRequest::Reload => match s.reload() {
+            Ok(count) => respond(AgentResponse::Ok {
+                data: serde_json::json!({ "secrets": count }),
+            }),
+            Err(e) => respond(AgentResponse::Error {
+                message: format!("Reload error (keeping previous secrets): {}", e),
+            }),
+        },
+
         AgentRequest::Shutdown => {
             s.should_stop = true;
-            AgentResponse::Ok {
+            respond(AgentResponse::Ok {
                 data: serde_json::json!({ "message": "Agent stopped" }),
-            }
+            })
         }
     }
 }
 
+/// Serializes a response that carries no plaintext
+fn respond(response: AgentResponse) -> Result<Zeroizing<String>> {
+    Ok(Zeroizing::new(serde_json::to_string(&response)?))
+}
+
 /// Client for communicating with the agent
 pub struct AgentClient;
 
 impl AgentClient {
     /// Retrieves all secrets from the agent
-    pub fn get_secrets() -> Result<HashMap<String, String>> {
+    /// `env` selects environment-specific variants, falling back to default values.
+    #[allow(dead_code)]
+    pub fn get_secrets(env: Option<&str>) -> Result<BTreeMap<String, String>> {
+        Self::get_secrets_at(&get_socket_path()?, env)
+    }
+
+    /// Retrieves all secrets, retrying with backoff while the agent finishes starting
+    pub fn get_secrets_with_retry(env: Option<&str>) -> Result<BTreeMap<String, String>> {
         let socket_path = get_socket_path()?;
-        let mut stream = UnixStream::connect(&socket_path)
+        Self::get_secrets_at_with_retry(&socket_path, env, CONNECT_ATTEMPTS, CONNECT_BACKOFF)
+    }
+
+    /// Retrieves all secrets when an agent socket exists; `None` is how `run`
+    /// decides to fall back to the passphrase
+    pub fn try_get_secrets(env: Option<&str>) -> Option<BTreeMap<String, String>> {
+        match get_socket_path() {
+            Ok(socket_path) if Transport::exists(&socket_path) => {
+                Self::get_secrets_with_retry(env).ok()
+            }
+            _ => None,
+        }
+    }
+
+    fn get_secrets_at_with_retry(
+        socket_path: &Path,
+        env: Option<&str>,
+        attempts: u32,
+        backoff: Duration,
+    ) -> Result<BTreeMap<String, String>> {
+        let mut delay = backoff;
+        let mut attempt = 1;
+        loop {
+            match Self::get_secrets_at(socket_path, env) {
+                Ok(secrets) => return Ok(secrets),
+                Err(e) if attempt >= attempts => return Err(e),
+                Err(_) => {
+                    std::thread::sleep(delay);
+                    delay *= 2;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    fn get_secrets_at(socket_path: &Path, env: Option<&str>) -> Result<BTreeMap<String, String>> {
+        let mut stream = Transport::connect(socket_path)
             .map_err(|_| anyhow::anyhow!("Agent not started. Run lazy-locker first."))?;
 
-        let request = r#"{"action":"get_secrets"}"#;
+        let request = serde_json::json!({"action": "get_secrets", "env": env});
         writeln!(stream, "{}", request)?;
         stream.flush()?;
 
-        let mut reader = BufReader::new(&stream);
-        let mut response = String::new();
+        let mut reader = BufReader::new(stream);
+        let mut response = Zeroizing::new(String::new());
         reader.read_line(&mut response)?;
 
         let resp: AgentResponse = serde_json::from_str(&response)?;
@@ -367,15 +896,15 @@ impl AgentClient {
     #[allow(dead_code)]
     pub fn get_secret(name: &str) -> Result<String> {
         let socket_path = get_socket_path()?;
-        let mut stream = UnixStream::connect(&socket_path)
+        let mut stream = Transport::connect(&socket_path)
             .map_err(|_| anyhow::anyhow!("Agent not started. Run lazy-locker first."))?;
 
         let request = serde_json::json!({"action": "get_secret", "name": name});
         writeln!(stream, "{}", request)?;
         stream.flush()?;
 
-        let mut reader = BufReader::new(&stream);
-        let mut response = String::new();
+        let mut reader = BufReader::new(stream);
+        let mut response = Zeroizing::new(String::new());
         reader.read_line(&mut response)?;
 
         let resp: AgentResponse = serde_json::from_str(&response)?;
@@ -385,17 +914,35 @@ impl AgentClient {
         }
     }
 
+    /// Asks the agent to stop
+    pub fn shutdown() -> Result<()> {
+        let socket_path = get_socket_path()?;
+        let mut stream =
+            Transport::connect(&socket_path).map_err(|_| anyhow::anyhow!("Agent not started"))?;
+
+        writeln!(stream, r#"{{"action":"shutdown"}}"#)?;
+        stream.flush()?;
+
+        let mut reader = BufReader::new(stream);
+        let mut response = String::new();
+        reader.read_line(&mut response)?;
+        match serde_json::from_str(&response)? {
+            AgentResponse::Ok { .. } => Ok(()),
+            AgentResponse::Error { message } => Err(anyhow::anyhow!("{}", message)),
+        }
+    }
+
     /// Checks agent status
     pub fn status() -> Result<serde_json::Value> {
         let socket_path = get_socket_path()?;
         let mut stream =
-            UnixStream::connect(&socket_path).map_err(|_| anyhow::anyhow!("Agent not started"))?;
+            Transport::connect(&socket_path).map_err(|_| anyhow::anyhow!("Agent not started"))?;
 
         let request = r#"{"action":"ping"}"#;
         writeln!(stream, "{}", request)?;
         stream.flush()?;
 
-        let mut reader = BufReader::new(&stream);
+        let mut reader = BufReader::new(stream);
         let mut response = String::new();
         reader.read_line(&mut response)?;
 
@@ -406,3 +953,518 @@ impl AgentClient {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const HOUR: Duration = Duration::from_secs(3600);
+
+    fn test_state() -> Arc<Mutex<AgentState>> {
+        test_state_started(Instant::now(), DEFAULT_TTL_HOURS, false)
+    }
+
+    fn test_state_started(
+        started_at: Instant,
+        ttl_hours: u64,
+        sliding: bool,
+    ) -> Arc<Mutex<AgentState>> {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let key = vec![0x42u8; 32];
+        let mut store = SecretsStore::new();
+        store
+            .add_secret(
+                "API_KEY".to_string(),
+                "sk-\"quoted\"".to_string(),
+                None,
+                temp_dir.path(),
+                &key,
+            )
+            .expect("Failed to add secret");
+
+        let store_path = temp_dir.path().join("secrets.json");
+        Arc::new(Mutex::new(AgentState::new(
+            key, store, store_path, ttl_hours, sliding, started_at,
+        )))
+    }
+
+    fn add_on_disk(dir: &Path, name: &str, value: &str) {
+        let key = [0x42u8; 32];
+        let mut store = SecretsStore::load(dir, &key).unwrap();
+        store
+            .add_secret(name.to_string(), value.to_string(), None, dir, &key)
+            .unwrap();
+    }
+
+    fn agent_on(dir: &Path) -> Arc<Mutex<AgentState>> {
+        add_on_disk(dir, "API_KEY", "sk-123");
+        let key = vec![0x42u8; 32];
+        let store_path = dir.join("secrets.json");
+        let store = SecretsStore::load_from_path(&store_path, &key).unwrap();
+        Arc::new(Mutex::new(AgentState::new(
+            key,
+            store,
+            store_path,
+            DEFAULT_TTL_HOURS,
+            false,
+            Instant::now(),
+        )))
+    }
+
+    #[test]
+    fn test_reload_request_picks_up_new_secret() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let state = agent_on(temp_dir.path());
+        add_on_disk(temp_dir.path(), "NEW_KEY", "sk-new");
+
+        let request = AgentRequest::GetSecret {
+            name: "NEW_KEY".to_string(),
+        };
+        let stale = process_request(request, &state, None).unwrap();
+        assert!(stale.contains("not found"));
+
+        let reloaded = process_request(AgentRequest::Reload, &state, None).unwrap();
+        assert!(reloaded.contains(r#""secrets":2"#));
+
+        let request = AgentRequest::GetSecret {
+            name: "NEW_KEY".to_string(),
+        };
+        let fresh = process_request(request, &state, None).unwrap();
+        assert!(fresh.contains("sk-new"));
+    }
+
+    #[test]
+    fn test_get_secret_only_decrypts_requested_entry() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        add_on_disk(temp_dir.path(), "BROKEN", "sk-broken");
+        let state = agent_on(temp_dir.path());
+        state
+            .lock()
+            .unwrap()
+            .store
+            .secrets
+            .get_mut("BROKEN")
+            .unwrap()
+            .encrypted_value = vec![0x02, 0xff];
+
+        let request = AgentRequest::GetSecret {
+            name: "API_KEY".to_string(),
+        };
+        let response = process_request(request, &state, None).unwrap();
+        assert!(response.contains("sk-123"));
+
+        let request = AgentRequest::GetSecret {
+            name: "BROKEN".to_string(),
+        };
+        let response = process_request(request, &state, None).unwrap();
+        assert!(response.contains("Decryption error"));
+    }
+
+    #[test]
+    fn test_get_secrets_skips_entries_that_fail_to_decrypt() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        add_on_disk(temp_dir.path(), "BROKEN", "sk-broken");
+        let state = agent_on(temp_dir.path());
+        state
+            .lock()
+            .unwrap()
+            .store
+            .secrets
+            .get_mut("BROKEN")
+            .unwrap()
+            .encrypted_value = vec![0x02, 0xff];
+
+        let all = process_request(AgentRequest::GetSecrets { env: None }, &state, None).unwrap();
+        let all: AgentResponse = serde_json::from_str(&all).unwrap();
+        assert!(matches!(
+            all,
+            AgentResponse::Ok { data } if data["API_KEY"] == "sk-123" && data.get("BROKEN").is_none()
+        ));
+    }
+
+    #[test]
+    fn test_get_secret_cache_expires() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let state = agent_on(temp_dir.path());
+        let start = Instant::now();
+        let get = |now| {
+            let request = AgentRequest::GetSecret {
+                name: "API_KEY".to_string(),
+            };
+            process_request_at(request, &state, None, now).unwrap()
+        };
+
+        assert!(get(start).contains("sk-123"));
+        // Served from the cache without decrypting again
+        state
+            .lock()
+            .unwrap()
+            .store
+            .secrets
+            .get_mut("API_KEY")
+            .unwrap()
+            .encrypted_value = vec![0x02, 0xff];
+        assert!(get(start + CACHE_TTL / 2).contains("sk-123"));
+        assert!(get(start + CACHE_TTL).contains("Decryption error"));
+    }
+
+    #[test]
+    fn test_readonly_agent_refuses_shutdown_and_keeps_serving() {
+        let state = test_state();
+        state.lock().unwrap().readonly = true;
+
+        for request in [AgentRequest::Shutdown, AgentRequest::Reload] {
+            let response = process_request(request, &state, None).unwrap();
+            assert!(response.contains(r#""status":"error""#));
+            assert!(response.contains("read-only"));
+        }
+        assert!(!state.lock().unwrap().should_stop);
+
+        let secrets =
+            process_request(AgentRequest::GetSecrets { env: None }, &state, None).unwrap();
+        assert!(secrets.contains(r#""status":"ok""#));
+        assert!(secrets.contains("API_KEY"));
+    }
+
+    #[test]
+    fn test_reload_if_changed_follows_mtime() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let state = agent_on(temp_dir.path());
+        let mut s = state.lock().unwrap();
+        assert!(!s.reload_if_changed().unwrap());
+
+        add_on_disk(temp_dir.path(), "NEW_KEY", "sk-new");
+        // Filesystem timestamps can be coarse: make the change visible
+        std::fs::File::options()
+            .write(true)
+            .open(temp_dir.path().join("secrets.json"))
+            .unwrap()
+            .set_modified(SystemTime::now() + Duration::from_secs(10))
+            .unwrap();
+
+        assert!(s.reload_if_changed().unwrap());
+        assert_eq!(s.store.list_secrets().len(), 2);
+    }
+
+    #[test]
+    fn test_failed_reload_keeps_previous_store() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let state = agent_on(temp_dir.path());
+        std::fs::write(temp_dir.path().join("secrets.json"), b"half-written").unwrap();
+
+        let response = process_request(AgentRequest::Reload, &state, None).unwrap();
+        assert!(response.contains("keeping previous secrets"));
+
+        let still_served = process_request(get_secret(), &state, None).unwrap();
+        assert!(still_served.contains("sk-123"));
+    }
+
+    fn get_secret() -> AgentRequest {
+        AgentRequest::GetSecret {
+            name: "API_KEY".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_process_request_expires_after_ttl() {
+        let start = Instant::now();
+        let state = test_state_started(start, 1, false);
+
+        let response =
+            process_request_at(AgentRequest::Ping, &state, None, start + 2 * HOUR).unwrap();
+
+        assert!(response.contains("Session expired"));
+        assert!(state.lock().unwrap().should_stop);
+    }
+
+    #[test]
+    fn test_zero_ttl_never_expires() {
+        let start = Instant::now();
+        let state = test_state_started(start, 0, false);
+
+        let response =
+            process_request_at(AgentRequest::Ping, &state, None, start + 2 * HOUR).unwrap();
+        let response: AgentResponse = serde_json::from_str(&response).unwrap();
+
+        assert!(matches!(
+            response,
+            AgentResponse::Ok { data } if data["ttl_remaining_secs"].is_null()
+        ));
+        assert!(!state.lock().unwrap().should_stop);
+    }
+
+    #[test]
+    fn test_fixed_ttl_ignores_accesses() {
+        let start = Instant::now();
+        let state = test_state_started(start, 1, false);
+
+        let response = process_request_at(get_secret(), &state, None, start + HOUR / 2).unwrap();
+        assert!(response.contains(r#""status":"ok""#));
+
+        let response =
+            process_request_at(get_secret(), &state, None, start + HOUR + HOUR / 4).unwrap();
+        assert!(response.contains("Session expired"));
+    }
+
+    #[test]
+    fn test_idle_ttl_slides_with_each_access() {
+        let start = Instant::now();
+        let state = test_state_started(start, 1, true);
+
+        // Accesses every 45 minutes keep the session alive past the hour
+        for step in 1..=4 {
+            let now = start + step * HOUR * 3 / 4;
+            let response = process_request_at(get_secret(), &state, None, now).unwrap();
+            assert!(response.contains(r#""status":"ok""#), "access {}", step);
+        }
+
+        let last_access = start + 3 * HOUR;
+        let response =
+            process_request_at(AgentRequest::Ping, &state, None, last_access + HOUR / 2).unwrap();
+        let response: AgentResponse = serde_json::from_str(&response).unwrap();
+        assert!(matches!(
+            response,
+            AgentResponse::Ok { data }
+                if data["ttl_remaining_secs"] == 1800 && data["idle_ttl"] == true
+        ));
+
+        // Pings are not activity: the session still ends an hour after the last access
+        let response = process_request_at(
+            AgentRequest::Ping,
+            &state,
+            None,
+            last_access + HOUR + HOUR / 4,
+        )
+        .unwrap();
+        assert!(response.contains("Session expired"));
+    }
+
+    #[test]
+    fn test_resolve_ttl_flag_takes_priority() {
+        assert_eq!(resolve_ttl_hours(Some(0)).unwrap(), 0);
+        assert_eq!(resolve_ttl_hours(Some(24)).unwrap(), 24);
+    }
+
+    #[test]
+    fn test_process_request_serializes_plaintext_responses() {
+        let state = test_state();
+
+        let all = process_request(AgentRequest::GetSecrets { env: None }, &state, None).unwrap();
+        let all: AgentResponse = serde_json::from_str(&all).unwrap();
+        assert!(matches!(
+            all,
+            AgentResponse::Ok { data } if data["API_KEY"] == "sk-\"quoted\""
+        ));
+
+        let one = process_request(
+            AgentRequest::GetSecret {
+                name: "API_KEY".to_string(),
+            },
+            &state,
+            None,
+        )
+        .unwrap();
+        let one: AgentResponse = serde_json::from_str(&one).unwrap();
+        assert!(matches!(
+            one,
+            AgentResponse::Ok { data } if data["value"] == "sk-\"quoted\""
+        ));
+
+        let missing = process_request(
+            AgentRequest::GetSecret {
+                name: "MISSING".to_string(),
+            },
+            &state,
+            None,
+        )
+        .unwrap();
+        assert!(missing.contains(r#""status":"error""#));
+    }
+
+    #[test]
+    fn test_audit_log_records_reads_without_values() {
+        let dir = TempDir::new().unwrap();
+        let state = agent_on(dir.path());
+        let peer = Some(PeerCred {
+            pid: 4242,
+            uid: Some(1000),
+        });
+
+        // Nothing is logged until auditing is turned on
+        process_request(get_secret(), &state, peer).unwrap();
+        let log_path = dir.path().join(AUDIT_LOG_FILE);
+        assert!(!log_path.exists());
+
+        state.lock().unwrap().audit = true;
+        process_request(get_secret(), &state, peer).unwrap();
+        process_request(AgentRequest::GetSecrets { env: None }, &state, None).unwrap();
+
+        let log = std::fs::read_to_string(&log_path).unwrap();
+        assert!(!log.contains("sk-123"));
+        let entries: Vec<serde_json::Value> = log
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0]["action"], "get_secret");
+        assert_eq!(entries[0]["name"], "API_KEY");
+        assert_eq!(entries[0]["pid"], 4242);
+        assert_eq!(entries[0]["uid"], 1000);
+        assert_eq!(entries[1]["action"], "get_secrets");
+        assert_eq!(entries[1]["count"], 1);
+        assert!(entries[1]["pid"].is_null());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&log_path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    #[test]
+    fn test_errors_are_logged_without_audit() {
+        let dir = TempDir::new().unwrap();
+        let state = agent_on(dir.path());
+
+        state.lock().unwrap().log_error("Client error: broken pipe");
+
+        let log = std::fs::read_to_string(dir.path().join(AUDIT_LOG_FILE)).unwrap();
+        let entry: serde_json::Value = serde_json::from_str(log.trim()).unwrap();
+        assert_eq!(entry["action"], "error");
+        assert_eq!(entry["message"], "Client error: broken pipe");
+    }
+
+    #[test]
+    fn test_peer_policy_rejects_other_uid() {
+        let dir = TempDir::new().unwrap();
+        let state = agent_on(dir.path());
+        state.lock().unwrap().peer_policy = PeerPolicy {
+            uid: Some(1000),
+            allowed_pids: Vec::new(),
+        };
+        let peer = |uid| Some(PeerCred { pid: 1, uid });
+
+        let response = process_request(get_secret(), &state, peer(Some(1001))).unwrap();
+        assert!(response.contains("Client uid 1001 does not match"));
+        assert!(!response.contains("sk-123"));
+
+        // Fail closed when the OS reported nothing
+        let response = process_request(get_secret(), &state, None).unwrap();
+        assert!(response.contains("Client credentials unavailable"));
+
+        let response = process_request(get_secret(), &state, peer(Some(1000))).unwrap();
+        assert!(response.contains("sk-123"));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_peer_policy_allows_descendants_of_listed_pids() {
+        let me = std::process::id();
+        let parent = std::os::unix::process::parent_id();
+        let policy = |allowed_pids| PeerPolicy {
+            uid: None,
+            allowed_pids,
+        };
+        let peer = Some(PeerCred { pid: me, uid: None });
+
+        assert!(policy(vec![me]).check(peer).is_ok());
+        assert!(policy(vec![parent]).check(peer).is_ok());
+        let err = policy(vec![u32::MAX]).check(peer).unwrap_err();
+        assert!(err.contains("is not allowed"));
+    }
+
+    #[test]
+    fn test_parse_pid_list() {
+        assert_eq!(parse_pid_list("12, 34,").unwrap(), vec![12, 34]);
+        assert!(parse_pid_list("12,abc").is_err());
+    }
+
+    #[test]
+    fn test_audit_log_rotates_past_size_cap() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(AUDIT_LOG_FILE);
+
+        append_audit_line(&path, "first", 10).unwrap();
+        append_audit_line(&path, "second", 10).unwrap();
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "second\n");
+        let rotated = dir.path().join(format!("{}.1", AUDIT_LOG_FILE));
+        assert_eq!(std::fs::read_to_string(rotated).unwrap(), "first\n");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_get_secrets_retries_until_agent_accepts() {
+        use std::os::unix::net::UnixListener;
+
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let socket_path = temp_dir.path().join("agent.sock");
+
+        // Agent that only starts listening after the first attempts have failed
+        let server_path = socket_path.clone();
+        let server = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            let listener = UnixListener::bind(&server_path).unwrap();
+            let (stream, _) = listener.accept().unwrap();
+            let mut request = String::new();
+            BufReader::new(&stream).read_line(&mut request).unwrap();
+            assert!(request.contains("get_secrets"));
+            writeln!(
+                &stream,
+                r#"{{"status":"ok","data":{{"API_KEY":"sk-123"}}}}"#
+            )
+            .unwrap();
+        });
+
+        let secrets = AgentClient::get_secrets_at_with_retry(
+            &socket_path,
+            None,
+            8,
+            Duration::from_millis(20),
+        )
+        .expect("Agent fetch should succeed after retrying");
+        server.join().unwrap();
+
+        assert_eq!(secrets.get("API_KEY").map(String::as_str), Some("sk-123"));
+    }
+
+    #[test]
+    fn test_get_secrets_gives_up_after_attempts() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let socket_path = temp_dir.path().join("agent.sock");
+
+        let started = Instant::now();
+        let result = AgentClient::get_secrets_at_with_retry(
+            &socket_path,
+            None,
+            3,
+            Duration::from_millis(10),
+        );
+
+        assert!(result.is_err());
+        assert!(started.elapsed() >= Duration::from_millis(30)); // 10ms + 20ms of backoff
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_stale_agent_files_are_removed_only_for_dead_pids() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let socket_path = Transport::endpoint(temp_dir.path());
+        let pid_path = temp_dir.path().join("agent.pid");
+        // A crashed agent leaves its socket file behind
+        drop(Transport::bind(&socket_path).unwrap());
+
+        std::fs::write(&pid_path, std::process::id().to_string()).unwrap();
+        assert!(!remove_stale_agent_files(&socket_path, &pid_path));
+        assert!(socket_path.exists() && pid_path.exists());
+
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        child.wait().unwrap();
+        std::fs::write(&pid_path, child.id().to_string()).unwrap();
+        assert!(remove_stale_agent_files(&socket_path, &pid_path));
+        assert!(!socket_path.exists());
+        assert!(!pid_path.exists());
+    }
+}
@@ -0,0 +1,280 @@
+//! Encrypted, portable locker backups.
+//!
+//! A backup carries its own salt and is sealed with a key derived from the
+//! backup passphrase, independently of the locker's `salt` and `hash`. It can
+//! be restored on another machine or into a locker with another passphrase.
+//!
+//! Layout: `LLBK` magic, format version byte, 16-byte Argon2 salt, then the
+//! store JSON (every secret with its variants and metadata, values sealed under
+//! the backup key) encrypted by `crypto::encrypt_with` with the header as AAD.
+
+use crate::core::crypto::{CipherAlgo, decrypt_with_aad, encrypt_with};
+use crate::core::store::{ConflictStrategy, ImportSummary, SecretsStore};
+use anyhow::{Context, Result};
+use argon2::{Algorithm, Argon2, Params, Version};
+use rand::Rng;
+use std::path::Path;
+use zeroize::{Zeroize, Zeroizing};
+
+const MAGIC: &[u8; 4] = b"LLBK";
+
+/// Current backup format version
+pub const BACKUP_VERSION: u8 = 1;
+
+const SALT_LEN: usize = 16;
+
+/// Argon2id costs of format version 1, fixed so that a backup never depends
+/// on the library's defaults: memory in KiB, passes, lanes
+const KDF_COSTS: (u32, u32, u32) = (19 * 1024, 2, 1);
+const HEADER_LEN: usize = MAGIC.len() + 1 + SALT_LEN;
+
+/// Secrets read back from a backup, still sealed under the backup key
+pub struct Backup {
+    store: SecretsStore,
+    key: Zeroizing<[u8; 32]>,
+}
+
+impl Backup {
+    /// Decrypts a backup written by `create` with the same passphrase
+    pub fn open(data: &[u8], passphrase: &str) -> Result<Self> {
+        if data.len() < HEADER_LEN || &data[..MAGIC.len()] != MAGIC {
+            anyhow::bail!("Not a lazy-locker backup");
+        }
+        let version = data[MAGIC.len()];
+        if version != BACKUP_VERSION {
+            anyhow::bail!(
+                "Unsupported backup version {} (this build reads version {})",
+                version,
+                BACKUP_VERSION
+            );
+        }
+        let (header, blob) = data.split_at(HEADER_LEN);
+        let key = derive_key(passphrase, &header[MAGIC.len() + 1..])?;
+
+        let mut json = decrypt_with_aad(blob, key.as_slice(), header)
+            .map_err(|_| anyhow::anyhow!("Wrong backup passphrase, or the backup is corrupted"))?;
+        let store = serde_json::from_slice::<SecretsStore>(&json);
+        json.zeroize();
+        let mut store = store.context("Backup contents are malformed")?;
+        store.bind_values(key.as_slice())?;
+
+        Ok(Self { store, key })
+    }
+
+    /// Number of tokens in the backup
+    pub fn len(&self) -> usize {
+        self.store.len()
+    }
+
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.store.is_empty()
+    }
+
+    /// Merges the backup into `store`, resealing every value under `key`
+    pub fn restore_into(
+        &self,
+        store: &mut SecretsStore,
+        strategy: ConflictStrategy,
+        locker_dir: &Path,
+        key: &[u8],
+    ) -> Result<ImportSummary> {
+        store.merge(&self.store, self.key.as_slice(), strategy, locker_dir, key)
+    }
+}
+
+/// Seals every secret of `store` (encrypted under `key`) into a backup
+/// protected by `passphrase`
+pub fn create(store: &SecretsStore, key: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    let salt: [u8; SALT_LEN] = rand::rng().random();
+    let backup_key = derive_key(passphrase, &salt)?;
+
+    let mut sealed = store.clone();
+    sealed.reencrypt(key, backup_key.as_slice())?;
+    let mut json = serde_json::to_vec(&sealed)?;
+
+    let mut data = Vec::with_capacity(HEADER_LEN + json.len() + 32);
+    data.extend_from_slice(MAGIC);
+    data.push(BACKUP_VERSION);
+    data.extend_from_slice(&salt);
+    let blob = encrypt_with(
+        CipherAlgo::default(),
+        &json,
+        backup_key.as_slice(),
+        Some(&data),
+    );
+    json.zeroize();
+    data.extend(blob?);
+    Ok(data)
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Zeroizing<[u8; 32]>> {
+    let (m_cost, t_cost, p_cost) = KDF_COSTS;
+    let params = Params::new(m_cost, t_cost, p_cost, None)
+        .map_err(|e| anyhow::anyhow!("Key derivation error: {}", e))?;
+    let mut key = Zeroizing::new([0u8; 32]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, key.as_mut_slice())
+        .map_err(|e| anyhow::anyhow!("Key derivation error: {}", e))?;
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::store::PlainSecret;
+    use tempfile::TempDir;
+
+    fn sample_store(dir: &Path, key: &[u8]) -> SecretsStore {
+        let mut store = SecretsStore::new();
+        let mut api_key = PlainSecret::new("API_KEY".to_string(), "sk-123".to_string());
+        api_key.expires_at = Some(4102358400);
+        api_key.tags = vec!["prod".to_string()];
+        api_key.description = Some("Payment provider".to_string());
+        store
+            .add_many(
+                &[
+                    api_key,
+                    PlainSecret::new("DB_URL".to_string(), "postgres://dev".to_string()),
+                ],
+                dir,
+                key,
+            )
+            .unwrap();
+        store
+            .add_variant("DB_URL", "prod", "postgres://prod", dir, key)
+            .unwrap();
+        store
+    }
+
+    #[test]
+    fn test_backup_wipe_restore_round_trip() {
+        let source = TempDir::new().unwrap();
+        let source_key = [0x42u8; 32];
+        let store = sample_store(source.path(), &source_key);
+
+        let data = create(&store, &source_key, "backup pass").unwrap();
+        assert!(!data.windows(6).any(|w| w == b"sk-123"));
+        drop(store);
+        drop(source);
+
+        // A fresh locker with a different key, as on another machine
+        let target = TempDir::new().unwrap();
+        let target_key = [0x17u8; 32];
+        let mut restored = SecretsStore::new();
+        let backup = Backup::open(&data, "backup pass").unwrap();
+        assert_eq!(backup.len(), 2);
+        let summary = backup
+            .restore_into(
+                &mut restored,
+                ConflictStrategy::Skip,
+                target.path(),
+                &target_key,
+            )
+            .unwrap();
+        assert_eq!(summary.imported, vec!["API_KEY", "DB_URL"]);
+
+        let reloaded = SecretsStore::load(target.path(), &target_key).unwrap();
+        let api_key = reloaded.get_secret("API_KEY").unwrap();
+        assert_eq!(api_key.decrypt_for(None, &target_key).unwrap(), "sk-123");
+        assert_eq!(api_key.expires_at, Some(4102358400));
+        assert_eq!(api_key.tags, vec!["prod"]);
+        assert_eq!(api_key.description.as_deref(), Some("Payment provider"));
+        let db_url = reloaded.get_secret("DB_URL").unwrap();
+        assert_eq!(
+            db_url.decrypt_for(Some("prod"), &target_key).unwrap(),
+            "postgres://prod"
+        );
+    }
+
+    #[test]
+    fn test_restore_respects_conflict_strategy() {
+        let dir = TempDir::new().unwrap();
+        let key = [0x42u8; 32];
+        let mut store = sample_store(dir.path(), &key);
+        let data = create(&store, &key, "pw").unwrap();
+        store
+            .update_secret("API_KEY", "sk-new", dir.path(), &key)
+            .unwrap();
+        let backup = Backup::open(&data, "pw").unwrap();
+
+        let summary = backup
+            .restore_into(&mut store, ConflictStrategy::Skip, dir.path(), &key)
+            .unwrap();
+        assert_eq!(summary.skipped.len(), 2);
+        assert_eq!(store.decrypt_secret("API_KEY", &key).unwrap(), "sk-new");
+
+        assert!(
+            backup
+                .restore_into(&mut store, ConflictStrategy::Error, dir.path(), &key)
+                .is_err()
+        );
+
+        backup
+            .restore_into(&mut store, ConflictStrategy::Overwrite, dir.path(), &key)
+            .unwrap();
+        assert_eq!(store.decrypt_secret("API_KEY", &key).unwrap(), "sk-123");
+    }
+
+    #[test]
+    fn test_restore_rejects_invalid_env_names_unless_allowed() {
+        let source = TempDir::new().unwrap();
+        let key = [0x42u8; 32];
+        let mut store = SecretsStore::new();
+        store.allow_any_names();
+        store
+            .add_secret_at(
+                "db.url".to_string(),
+                "v".to_string(),
+                None,
+                source.path(),
+                &key,
+            )
+            .unwrap();
+        let backup = Backup::open(&create(&store, &key, "pw").unwrap(), "pw").unwrap();
+
+        let target = TempDir::new().unwrap();
+        let mut restored = SecretsStore::load(target.path(), &key).unwrap();
+        let err = backup
+            .restore_into(&mut restored, ConflictStrategy::Skip, target.path(), &key)
+            .unwrap_err();
+        assert!(err.to_string().contains("Invalid token name 'db.url'"));
+        assert!(!target.path().join("secrets.json").exists());
+
+        restored.allow_any_names();
+        backup
+            .restore_into(&mut restored, ConflictStrategy::Skip, target.path(), &key)
+            .unwrap();
+        assert_eq!(restored.decrypt_secret("db.url", &key).unwrap(), "v");
+    }
+
+    #[test]
+    fn test_backup_key_derivation_is_pinned() {
+        let key = derive_key("correct horse", b"saltsaltsaltsalt").unwrap();
+
+        assert_eq!(
+            hex::encode(key.as_slice()),
+            "01dc1e04bc1c7e59cd5f61d55bc8b532dbbb7ebae7e0a8bb87fad8486b94ec8b"
+        );
+    }
+
+    #[test]
+    fn test_open_rejects_wrong_passphrase_and_tampering() {
+        let dir = TempDir::new().unwrap();
+        let key = [0x42u8; 32];
+        let data = create(&sample_store(dir.path(), &key), &key, "pw").unwrap();
+
+        let err = Backup::open(&data, "not pw").err().unwrap();
+        assert!(err.to_string().contains("Wrong backup passphrase"));
+
+        let mut tampered = data.clone();
+        tampered[MAGIC.len() + 1] ^= 1; // salt is authenticated as AAD
+        assert!(Backup::open(&tampered, "pw").is_err());
+
+        assert!(Backup::open(b"{\"API_KEY\": \"x\"}", "pw").is_err());
+        let mut future = data;
+        future[MAGIC.len()] = BACKUP_VERSION + 1;
+        let err = Backup::open(&future, "pw").err().unwrap();
+        assert!(err.to_string().contains("Unsupported backup version"));
+    }
+}
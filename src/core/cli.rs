@@ -2,32 +2,336 @@
 //!
 //! Provides non-interactive commands for automation:
 //! - `init --passphrase <PASS>` - Initialize a new locker
-//! - `token add/get/list/remove` - Manage tokens
+//! - `token add/get/list/remove/which/exists` - Manage tokens
 //! - `import` - Import from .env files
+//! - `shell-env` - Print `export` lines for `eval`
 
 use anyhow::{Context, Result};
-use std::collections::HashMap;
-use std::io::{self, BufRead, Read};
-use std::path::PathBuf;
-
-use crate::core::crypto::decrypt;
-use crate::core::init::Locker;
-use crate::core::store::SecretsStore;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, IsTerminal, Read};
+use std::path::{Path, PathBuf};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::core::analyze;
+use crate::core::backup::{self, Backup};
+use crate::core::config::{self, Config};
+use crate::core::executor;
+use crate::core::format;
+use crate::core::generator::{self, Charset};
+use crate::core::hooks::{self, ChangeAction};
+use crate::core::init::{
+    self, IncorrectPassphrase, KdfParams, Locker, Strength, estimate_strength, write_error,
+};
+use crate::core::lock::StoreLock;
+use crate::core::recovery::{self, IncorrectRecoveryKey};
+use crate::core::store::{
+    ConflictStrategy, ImportSummary, PlainSecret, Secret, SecretKind, SecretsStore,
+    StoreKeyMismatch, expires_at_from_days, format_timestamp, is_valid_env_name, normalize_tags,
+    now_timestamp, parse_expiration_date,
+};
+use crate::core::totp;
+use crate::core::yaml;
 
 /// Environment variable for passphrase (more secure than CLI argument)
-const PASSPHRASE_ENV_VAR: &str = "LAZY_LOCKER_PASSPHRASE";
+pub const PASSPHRASE_ENV_VAR: &str = "LAZY_LOCKER_PASSPHRASE";
+
+// ============================================================================
+// EXIT CODES
+// ============================================================================
+
+/// Failures that scripts can tell apart by exit code.
+/// Success exits 0; failures not listed here exit 1.
+#[derive(Debug)]
+pub enum CliError {
+    /// The token does not exist (exit 1)
+    NotFound(String),
+    /// The token exists but has expired (exit 2)
+    Expired(String),
+    /// Missing or incorrect passphrase (exit 3)
+    Auth(String),
+    /// Invalid command line (exit 4)
+    Usage(String),
+}
+
+impl CliError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::NotFound(_) => 1,
+            Self::Expired(_) => 2,
+            Self::Auth(_) => 3,
+            Self::Usage(_) => 4,
+        }
+    }
+
+    /// Stable name of the failure in `--json` errors
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::NotFound(_) => "not_found",
+            Self::Expired(_) => "expired",
+            Self::Auth(_) => "auth",
+            Self::Usage(_) => "usage",
+        }
+    }
+
+    fn token_not_found(name: &str) -> Self {
+        Self::NotFound(format!("Token '{}' not found", name))
+    }
+}
+
+impl std::fmt::Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotFound(msg) | Self::Expired(msg) | Self::Auth(msg) | Self::Usage(msg) => {
+                f.write_str(msg)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+/// Exit code for a failed command
+pub fn exit_code(err: &anyhow::Error) -> i32 {
+    if let Some(e) = err.downcast_ref::<CliError>() {
+        e.exit_code()
+    } else if err.is::<IncorrectPassphrase>()
+        || err.is::<IncorrectRecoveryKey>()
+        || err.is::<StoreKeyMismatch>()
+    {
+        3
+    } else {
+        1
+    }
+}
+
+/// Stable code of a failed command: a `CliError` code, or "error"
+pub fn error_code(err: &anyhow::Error) -> &'static str {
+    if let Some(e) = err.downcast_ref::<CliError>() {
+        e.code()
+    } else if err.is::<IncorrectPassphrase>()
+        || err.is::<IncorrectRecoveryKey>()
+        || err.is::<StoreKeyMismatch>()
+    {
+        "auth"
+    } else {
+        "error"
+    }
+}
+
+/// `{"error": {"code": ..., "message": ...}}`, printed instead of the
+/// usual error text when a command runs with `--json`
+pub fn error_json(err: &anyhow::Error) -> serde_json::Value {
+    serde_json::json!({
+        "error": {
+            "code": error_code(err),
+            "message": format!("{:#}", err),
+        }
+    })
+}
+
+/// Where `--passphrase-stdin` or `--passphrase-fd` read the passphrase from.
+/// Both keep it out of the process table and of child environments.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PassphraseInput {
+    #[default]
+    None,
+    /// The first line of standard input
+    Stdin,
+    /// The first line read from an inherited file descriptor (Unix only)
+    Fd(i32),
+}
 
-/// Gets passphrase from argument or environment variable
-/// Priority: argument > environment variable
-pub fn get_passphrase(arg_passphrase: Option<&str>) -> Result<String> {
+/// Gets passphrase from argument, `input`, environment variable or an interactive prompt
+/// Priority: argument > stdin > file descriptor > environment variable > prompt (only
+/// when stdin is a terminal)
+pub fn get_passphrase(arg_passphrase: Option<&str>, input: PassphraseInput) -> Result<String> {
     if let Some(pass) = arg_passphrase {
         return Ok(pass.to_string());
     }
 
-    std::env::var(PASSPHRASE_ENV_VAR).context(format!(
+    match input {
+        PassphraseInput::None => {}
+        PassphraseInput::Stdin => return read_passphrase_line(io::stdin().lock(), "stdin"),
+        PassphraseInput::Fd(fd) => return read_passphrase_fd(fd),
+    }
+
+    if let Ok(pass) = std::env::var(PASSPHRASE_ENV_VAR) {
+        return Ok(pass);
+    }
+
+    if io::stdin().is_terminal() {
+        // The prompt goes to the terminal, so stdout stays clean for pipes
+        return Ok(rpassword::prompt_password("Passphrase: ")?);
+    }
+
+    Err(CliError::Auth(format!(
         "Passphrase required. Use --passphrase <PASS> or set {} environment variable",
         PASSPHRASE_ENV_VAR
     ))
+    .into())
+}
+
+/// Parses an `--expires` value: a number of days, or `never`/`0` for permanent.
+/// Anything else is an error rather than silently meaning "permanent".
+pub fn parse_expires(value: &str) -> Result<Option<u32>> {
+    match value.trim() {
+        "never" | "0" => Ok(None),
+        days => match days.parse::<u32>() {
+            Ok(days) => Ok(Some(days)),
+            Err(_) => anyhow::bail!(
+                "Invalid --expires value '{}': use a number of days, or 'never'",
+                value
+            ),
+        },
+    }
+}
+
+/// Expiration requested with `--expires <DAYS>` or `--expires-at <DATE>`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expiration {
+    Days(u32),
+    At(i64),
+    /// `--expires never` or `--permanent`, overriding `store.default_expiration_days`
+    Never,
+}
+
+impl Expiration {
+    /// Reads the two flags, at most one of which may be given
+    pub fn from_flags(days: Option<&str>, at: Option<&str>) -> Result<Option<Self>> {
+        match (days, at) {
+            (Some(_), Some(_)) => anyhow::bail!("Use either --expires or --expires-at, not both"),
+            (Some(days), None) => Ok(Some(parse_expires(days)?.map_or(Self::Never, Self::Days))),
+            (None, Some(at)) => Ok(Some(Self::At(parse_expiration_date(at)?))),
+            (None, None) => Ok(None),
+        }
+    }
+
+    /// The expiration as a Unix timestamp, None when permanent
+    pub fn timestamp(self) -> Result<Option<i64>> {
+        match self {
+            Self::Days(days) => expires_at_from_days(days).map(Some),
+            Self::At(timestamp) => Ok(Some(timestamp)),
+            Self::Never => Ok(None),
+        }
+    }
+
+    /// The expiration of a new token: `expires` when given, otherwise the
+    /// locker's `store.default_expiration_days` (0 or unset means permanent)
+    pub fn or_default(expires: Option<Self>, locker_dir: &Path) -> Option<Self> {
+        expires.or_else(|| {
+            Config::load_or_default(locker_dir)
+                .store
+                .default_expiration_days
+                .filter(|days| *days > 0)
+                .map(Self::Days)
+        })
+    }
+
+    /// "in 30 days", "on 2025-12-31 00:00 UTC" or "never"
+    fn describe(self) -> String {
+        match self {
+            Self::Days(days) => format!("in {} days", days),
+            Self::At(timestamp) => format!("on {}", format_timestamp(timestamp)),
+            Self::Never => "never".to_string(),
+        }
+    }
+}
+
+/// Reads a passphrase from the first line of `reader`, without its line ending
+fn read_passphrase_line(mut reader: impl BufRead, source: &str) -> Result<String> {
+    let mut line = Zeroizing::new(String::new());
+    reader
+        .read_line(&mut line)
+        .with_context(|| format!("Failed to read the passphrase from {}", source))?;
+    let pass = line.trim_end_matches(['\n', '\r']);
+    if pass.is_empty() {
+        anyhow::bail!(CliError::Auth(format!(
+            "No passphrase provided on {}",
+            source
+        )));
+    }
+    Ok(pass.to_string())
+}
+
+#[cfg(unix)]
+fn read_passphrase_fd(fd: i32) -> Result<String> {
+    use std::os::fd::FromRawFd;
+
+    // SAFETY: fcntl only queries the descriptor; it fails if it is not open
+    if unsafe { libc::fcntl(fd, libc::F_GETFD) } == -1 {
+        anyhow::bail!(CliError::Usage(format!(
+            "--passphrase-fd {}: not an open file descriptor",
+            fd
+        )));
+    }
+    // SAFETY: the descriptor is open. It belongs to the caller, so it is not
+    // closed afterwards.
+    let file = std::mem::ManuallyDrop::new(unsafe { std::fs::File::from_raw_fd(fd) });
+    read_passphrase_line(
+        io::BufReader::new(&*file),
+        &format!("file descriptor {}", fd),
+    )
+}
+
+#[cfg(not(unix))]
+fn read_passphrase_fd(_fd: i32) -> Result<String> {
+    anyhow::bail!(CliError::Usage(
+        "--passphrase-fd is only supported on Unix; use --passphrase-stdin".into()
+    ))
+}
+
+/// Gets the passphrase for a new locker.
+/// `--passphrase`, `--passphrase-stdin`, `--passphrase-fd` or the environment
+/// variable are used as-is; otherwise, on a terminal, the passphrase is typed
+/// twice so a typo can't lock the user out.
+pub fn get_new_passphrase(arg_passphrase: Option<&str>, input: PassphraseInput) -> Result<String> {
+    if arg_passphrase.is_some()
+        || input != PassphraseInput::None
+        || std::env::var_os(PASSPHRASE_ENV_VAR).is_some()
+        || !io::stdin().is_terminal()
+    {
+        return get_passphrase(arg_passphrase, input);
+    }
+
+    prompt_new_passphrase()
+}
+
+/// Gets the replacement passphrase when changing it.
+/// Unlike `get_new_passphrase`, the environment variable is never used: it
+/// holds the current passphrase.
+pub fn get_changed_passphrase(arg_passphrase: Option<&str>) -> Result<String> {
+    if let Some(pass) = arg_passphrase {
+        return Ok(pass.to_string());
+    }
+
+    if io::stdin().is_terminal() {
+        return prompt_new_passphrase();
+    }
+
+    anyhow::bail!("New passphrase required. Use --new <PASS>")
+}
+
+/// Prompts for a new passphrase twice on the terminal
+fn prompt_new_passphrase() -> Result<String> {
+    let first = rpassword::prompt_password("New passphrase: ")?;
+    let second = rpassword::prompt_password("Confirm passphrase: ")?;
+    confirm_passphrase(first, second)
+}
+
+/// Returns the passphrase if both entries match, wiping the copies otherwise
+fn confirm_passphrase(mut first: String, mut second: String) -> Result<String> {
+    let matches = first == second;
+    second.zeroize();
+
+    if !matches {
+        first.zeroize();
+        anyhow::bail!("Passphrases do not match");
+    }
+    if first.is_empty() {
+        anyhow::bail!("Passphrase cannot be empty");
+    }
+    Ok(first)
 }
 
 /// Output format for list/get commands
@@ -36,6 +340,9 @@ pub enum OutputFormat {
     Human,
     Json,
     Env,
+    /// Metadata rows without values, for spreadsheets (`export`, `token list`).
+    /// Commands printing a single value treat it like `Human`.
+    Csv,
 }
 
 impl OutputFormat {
@@ -48,14 +355,51 @@ impl OutputFormat {
             OutputFormat::Human
         }
     }
+
+    /// Parses `export --format`: env (or yaml, with `--yaml` output), json,
+    /// csv, or table for the human metadata table
+    pub fn parse_export(value: &str) -> Result<Self> {
+        match value {
+            "env" | "yaml" => Ok(Self::Env),
+            "json" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv),
+            "table" => Ok(Self::Human),
+            _ => anyhow::bail!(CliError::Usage(format!(
+                "Unknown export format '{}': use env, json, yaml, csv or table",
+                value
+            ))),
+        }
+    }
 }
 
 // ============================================================================
 // INIT COMMAND
 // ============================================================================
 
-/// Initialize a new locker with the given passphrase
-pub fn cmd_init(passphrase: &str, force: bool) -> Result<()> {
+/// Initialize a new locker with the given passphrase, and a recovery key if
+/// `recovery` is set.
+///
+/// A weak passphrase needs a confirmation on the terminal, or `allow_weak`
+/// when running non-interactively.
+pub fn cmd_init(
+    passphrase: &str,
+    force: bool,
+    yes: bool,
+    kdf: KdfParams,
+    allow_weak: bool,
+    recovery: bool,
+) -> Result<()> {
+    kdf.validate().map_err(|e| CliError::Usage(e.to_string()))?;
+
+    if estimate_strength(passphrase) == Strength::Weak {
+        if allow_weak {
+            eprintln!("⚠️  Using a weak passphrase (--allow-weak)");
+        } else if !weak_passphrase_confirmed()? {
+            println!("Aborted, locker not created");
+            return Ok(());
+        }
+    }
+
     let locker_dir = get_locker_dir()?;
     let salt_path = locker_dir.join("salt");
 
@@ -67,62 +411,1013 @@ pub fn cmd_init(passphrase: &str, force: bool) -> Result<()> {
     }
 
     if force && salt_path.exists() {
-        // Remove existing locker files
-        std::fs::remove_file(locker_dir.join("salt")).ok();
-        std::fs::remove_file(locker_dir.join("hash")).ok();
-        std::fs::remove_file(locker_dir.join("secrets.json")).ok();
+        let interactive = io::stdin().is_terminal();
+        if !overwrite_confirmed(&locker_dir, yes || !interactive, prompt_overwrite)? {
+            println!("Aborted, existing locker kept");
+            return Ok(());
+        }
+        // Set the existing locker aside rather than deleting it
+        let _lock = StoreLock::acquire(&locker_dir)?;
+        if let Some(stamp) = init::backup_locker_files(&locker_dir)? {
+            println!(
+                "📦 Previous locker moved to {:?}",
+                locker_dir.join(init::BACKUP_DIR).join(&stamp)
+            );
+            println!(
+                "   Bring it back with: lazy-locker restore-backup {}",
+                stamp
+            );
+        }
     }
 
     // Initialize with passphrase
-    let _locker = Locker::init_or_load_with_passphrase(passphrase)?;
+    let locker = Locker::create(passphrase, &kdf)?;
 
     println!("✅ Locker initialized at {:?}", locker_dir);
+    if kdf != KdfParams::default() {
+        println!("   Key derivation: {}", kdf);
+    }
+    if recovery {
+        print_recovery_key(&locker.enable_recovery()?);
+    }
+    Ok(())
+}
+
+/// Whether `init --force` may replace the locker in `locker_dir`: always
+/// with `skip`, otherwise only if `ask` returns the locker path or "yes"
+fn overwrite_confirmed(
+    locker_dir: &Path,
+    skip: bool,
+    ask: impl FnOnce(&Path) -> Result<String>,
+) -> Result<bool> {
+    if skip {
+        return Ok(true);
+    }
+    let answer = ask(locker_dir)?;
+    let answer = answer.trim();
+    Ok(answer == "yes" || Path::new(answer) == locker_dir)
+}
+
+fn prompt_overwrite(locker_dir: &Path) -> Result<String> {
+    eprintln!(
+        "⚠️  This replaces the locker at {} (its files are kept under {}/).",
+        locker_dir.display(),
+        init::BACKUP_DIR
+    );
+    eprint!("Type the locker path or \"yes\" to continue: ");
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(answer)
+}
+
+/// Restores the locker files that `init --force` set aside under `timestamp`,
+/// or lists the available backups when no timestamp is given
+pub fn cmd_restore_backup(timestamp: Option<&str>) -> Result<()> {
+    let locker_dir = get_locker_dir()?;
+    let Some(timestamp) = timestamp else {
+        let stamps = init::list_backups(&locker_dir);
+        if stamps.is_empty() {
+            println!(
+                "No locker backups in {:?}",
+                locker_dir.join(init::BACKUP_DIR)
+            );
+        }
+        for stamp in stamps {
+            let when = stamp.parse().map(format_timestamp).unwrap_or_default();
+            println!("{}  {}", stamp, when);
+        }
+        return Ok(());
+    };
+
+    if timestamp.is_empty() || !timestamp.bytes().all(|b| b.is_ascii_digit()) {
+        anyhow::bail!(CliError::Usage(format!(
+            "Invalid backup timestamp '{}' (run restore-backup without one to list them)",
+            timestamp
+        )));
+    }
+    let replaced = init::restore_locker_files(&locker_dir, timestamp)?;
+    println!("✅ Locker restored from backup {}", timestamp);
+    if let Some(stamp) = replaced {
+        println!("   The replaced locker was kept as backup {}", stamp);
+    }
+    Ok(())
+}
+
+/// Asks on the terminal whether to keep a weak passphrase
+fn weak_passphrase_confirmed() -> Result<bool> {
+    if !io::stdin().is_terminal() {
+        anyhow::bail!(CliError::Usage(
+            "Passphrase is weak: use at least 12 characters mixing letters, digits and symbols, \
+             or pass --allow-weak"
+                .into()
+        ));
+    }
+    eprint!("⚠️  This passphrase is weak and easy to guess. Use it anyway? [y/N] ");
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+// ============================================================================
+// ROTATE COMMAND
+// ============================================================================
+
+/// Rotate the master passphrase, re-encrypting every secret under the new key.
+///
+/// With `dry_run`, only verifies the old passphrase and that every secret
+/// currently decrypts, then prints what would be re-encrypted.
+pub fn cmd_rotate(old_passphrase: &str, new_passphrase: &str, dry_run: bool) -> Result<()> {
+    let locker = Locker::load_existing(old_passphrase)?;
+    let locker_dir = locker.base_dir().clone();
+    let old_key = locker
+        .get_key()
+        .context("Failed to get encryption key")?
+        .to_vec();
+
+    let store = SecretsStore::load(&locker_dir, &old_key)?;
+    let results = store.verify_all(&old_key);
+    let failed: Vec<_> = results
+        .iter()
+        .filter(|(_, ok)| !ok)
+        .map(|(name, _)| name.as_str())
+        .collect();
+
+    if !failed.is_empty() {
+        anyhow::bail!(
+            "{} secret(s) cannot be decrypted with the current key: {}. Rotation aborted.",
+            failed.len(),
+            failed.join(", ")
+        );
+    }
+
+    if dry_run {
+        println!("🔍 Dry run: no changes written");
+        println!("   Old passphrase verified");
+        println!(
+            "   {} secret(s) would be re-encrypted, all decrypt successfully",
+            results.len()
+        );
+        println!("   Salt and hash would be regenerated");
+        return Ok(());
+    }
+
+    let count = locker.change_passphrase(old_passphrase, new_passphrase)?;
+
+    println!("✅ Passphrase rotated, {} secret(s) re-encrypted", count);
+    Ok(())
+}
+
+// ============================================================================
+// CHECK COMMAND
+// ============================================================================
+
+/// Files of the locker that must stay private to their owner
+const PRIVATE_FILES: [&str; 5] = [
+    "salt",
+    "hash",
+    "secrets.json",
+    recovery::RECOVERY_FILE,
+    "agent.log",
+];
+
+/// Check the locker's integrity without revealing any value: the key files
+/// parse, every secret decrypts, metadata is consistent and files are private.
+/// Fails if any secret does not decrypt; other problems are warnings.
+pub fn cmd_check(passphrase: &str) -> Result<()> {
+    let locker = Locker::load_existing(passphrase)?;
+    let locker_dir = locker.base_dir().clone();
+    let key = locker.get_key().context("Failed to get encryption key")?;
+
+    println!("🔍 Checking locker at {:?}", locker_dir);
+    println!(
+        "✅ salt and hash: OK ({})",
+        Locker::kdf_params(&locker_dir)?
+    );
+
+    let mut warnings = 0;
+    for name in PRIVATE_FILES {
+        if let Some(mode) = loose_permissions(&locker_dir.join(name)) {
+            println!(
+                "⚠️  {} is accessible to other users (mode {:o}); run: chmod 600 {:?}",
+                name,
+                mode,
+                locker_dir.join(name)
+            );
+            warnings += 1;
+        }
+    }
+
+    let store = SecretsStore::load(&locker_dir, key)?;
+    let (failed, metadata_warnings) = check_secrets(&store, key);
+    let total = store.secrets.len();
+
+    println!(
+        "{} secret(s): {} OK, {} failed, {} warning(s)",
+        total,
+        total - failed,
+        failed,
+        warnings + metadata_warnings
+    );
+    if failed > 0 {
+        anyhow::bail!("{} secret(s) failed to decrypt", failed);
+    }
+    Ok(())
+}
+
+/// Prints whether each secret decrypts and any metadata problem.
+/// Returns the number of secrets that failed and of metadata warnings.
+fn check_secrets(store: &SecretsStore, key: &[u8]) -> (usize, usize) {
+    let results = store.verify_all(key);
+    for (name, ok) in &results {
+        println!("  {}  {}", if *ok { "OK  " } else { "FAIL" }, name);
+    }
+
+    let issues = store.metadata_issues();
+    for (name, problem) in &issues {
+        println!("⚠️  {}: {}", name, problem);
+    }
+
+    (results.iter().filter(|(_, ok)| !ok).count(), issues.len())
+}
+
+/// The file's mode if group or others have any access to it
+#[cfg(unix)]
+fn loose_permissions(path: &std::path::Path) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = std::fs::metadata(path).ok()?.permissions().mode() & 0o777;
+    (mode & 0o077 != 0).then_some(mode)
+}
+
+/// File modes don't apply outside Unix
+#[cfg(not(unix))]
+fn loose_permissions(_path: &std::path::Path) -> Option<u32> {
+    None
+}
+
+// ============================================================================
+// RECOVERY COMMANDS
+// ============================================================================
+
+/// Create a recovery key, replacing any previous one
+pub fn cmd_recovery_enable(passphrase: &str) -> Result<()> {
+    let locker = Locker::load_existing(passphrase)?;
+    let replaced = recovery::is_enabled(locker.base_dir());
+
+    let recovery_key = locker.enable_recovery()?;
+
+    if replaced {
+        println!("✅ Recovery key replaced; the previous one no longer works");
+    } else {
+        println!("✅ Recovery enabled");
+    }
+    print_recovery_key(&recovery_key);
+    Ok(())
+}
+
+/// Remove the recovery key
+pub fn cmd_recovery_disable(passphrase: &str) -> Result<()> {
+    let locker = Locker::load_existing(passphrase)?;
+
+    if locker.disable_recovery()? {
+        println!("✅ Recovery disabled, the recovery key no longer works");
+    } else {
+        println!("Recovery was not enabled");
+    }
     Ok(())
 }
 
+/// Show whether the locker has a recovery key
+pub fn cmd_recovery_status() -> Result<()> {
+    if recovery::is_enabled(&get_locker_dir()?) {
+        println!("Recovery: enabled");
+    } else {
+        println!("Recovery: disabled");
+    }
+    Ok(())
+}
+
+/// Set a new passphrase with the recovery key, when the passphrase is lost
+pub fn cmd_recovery_unlock(recovery_key: &str, new_passphrase: &str) -> Result<()> {
+    let count = Locker::recover(recovery_key, new_passphrase)?;
+
+    println!(
+        "✅ Passphrase reset with the recovery key, {} secret(s) re-encrypted",
+        count
+    );
+    println!("   The recovery key stays valid");
+    Ok(())
+}
+
+/// Gets the recovery key from `--key` or a prompt on the terminal
+pub fn get_recovery_key(arg_key: Option<&str>) -> Result<String> {
+    if let Some(key) = arg_key {
+        return Ok(key.to_string());
+    }
+
+    if io::stdin().is_terminal() {
+        return Ok(rpassword::prompt_password("Recovery key: ")?);
+    }
+
+    Err(CliError::Usage("Recovery key required. Use --key <RECOVERY>".into()).into())
+}
+
+/// Shows a new recovery key; it is never displayed again
+fn print_recovery_key(recovery_key: &str) {
+    println!();
+    println!("🔑 Recovery key (shown only once, keep it offline):");
+    println!();
+    println!("   {}", recovery_key);
+    println!();
+    println!("   It unlocks the locker with `lazy-locker recovery unlock` if the");
+    println!("   passphrase is forgotten. Anyone holding it can read your secrets.");
+}
+
 // ============================================================================
 // TOKEN COMMANDS
 // ============================================================================
 
-/// Add a new token
+/// Add a new token, or an environment-specific variant of an existing one
 pub fn cmd_token_add(
     name: &str,
     value: Option<&str>,
     stdin: bool,
-    expires_days: Option<u32>,
+    options: TokenAddOptions,
     passphrase: &str,
 ) -> Result<()> {
-    let secret_value = if stdin {
+    let TokenAddOptions {
+        expires,
+        env,
+        totp,
+        note,
+        tags,
+        allow_any_name,
+        no_analyze,
+        allow_large,
+        protected,
+    } = options;
+    if env.is_some() && expires.is_some() {
+        anyhow::bail!("--expires applies to the whole token; set it without --env");
+    }
+    if env.is_some() && totp {
+        anyhow::bail!("--totp applies to the whole token; set it without --env");
+    }
+    if env.is_some() && (note.is_some() || !tags.is_empty()) {
+        anyhow::bail!("--note and --tag apply to the whole token; set them without --env");
+    }
+    if env.is_some() && protected {
+        anyhow::bail!("--protected applies to the whole token; set it without --env");
+    }
+
+    let mut secret_value = if stdin {
         read_value_from_stdin()?
     } else if let Some(v) = value {
         v.to_string()
     } else {
         anyhow::bail!("Value required. Provide as argument or use --stdin");
     };
+    if totp {
+        totp::validate_seed(&secret_value)?;
+    }
+
+    let locker = Locker::init_or_load_with_passphrase(passphrase)?;
+    let key = locker.get_key().context("Failed to get encryption key")?;
+    let locker_dir = locker.base_dir().clone();
+
+    let mut store = SecretsStore::load(&locker_dir, key)?;
+    if allow_large {
+        store.allow_large_values();
+    }
+    if !no_analyze && !totp {
+        warn_about_values([(name, secret_value.as_str())], &locker_dir);
+    }
+
+    if let Some(env) = env {
+        store.add_variant(name, env, &secret_value, &locker_dir, key)?;
+        println!("✅ Token '{}' added for environment '{}'", name, env);
+        hooks::on_change(&locker_dir, ChangeAction::Add, &[name]);
+        return Ok(());
+    }
+
+    let expires = Expiration::or_default(expires, &locker_dir);
+    let secret = Secret {
+        name: name.to_string(),
+        expires_at: expires.map(Expiration::timestamp).transpose()?.flatten(),
+        tags: tags.to_vec(),
+        description: note.and_then(note_value),
+        kind: if totp {
+            SecretKind::Totp
+        } else {
+            SecretKind::Opaque
+        },
+        protected,
+        ..Default::default()
+    };
+    if allow_any_name {
+        store.allow_any_names();
+    }
+    let result = store.add_secret_with(secret, &secret_value, &locker_dir, key);
+    secret_value.zeroize();
+    result?;
+
+    println!("✅ Token '{}' added", name);
+    if totp {
+        println!(
+            "   TOTP seed: get codes with lazy-locker token code {}",
+            name
+        );
+    }
+    if let Some(expires) = expires.filter(|expires| *expires != Expiration::Never) {
+        println!("   Expires {}", expires.describe());
+    }
+    if protected {
+        println!("   Protected: copy it with token get --copy");
+    }
+    hooks::on_change(&locker_dir, ChangeAction::Add, &[name]);
+
+    Ok(())
+}
+
+/// One token read by `token add --json-stdin`; the value is zeroized on drop
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TokenAddRequest {
+    pub name: String,
+    pub value: String,
+    /// Days (0 or "never": permanent) or a date, as `--expires` or `--expires-at` take
+    #[serde(default)]
+    pub expires: Option<serde_json::Value>,
+    #[serde(default)]
+    pub note: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl Drop for TokenAddRequest {
+    fn drop(&mut self) {
+        self.value.zeroize();
+    }
+}
+
+impl TokenAddRequest {
+    /// Parses a single JSON object; arrays are rejected since `import` handles batches
+    pub fn parse(content: &str) -> Result<Self> {
+        let json: serde_json::Value =
+            serde_json::from_str(content).context("Invalid JSON on stdin")?;
+        if !json.is_object() {
+            anyhow::bail!(CliError::Usage(
+                "--json-stdin expects one JSON object; use import --format json for several tokens"
+                    .into()
+            ));
+        }
+        serde_json::from_value(json).context("Invalid token JSON")
+    }
+
+    /// The `expires` field as an expiration, None when absent
+    pub fn expiration(&self) -> Result<Option<Expiration>> {
+        match &self.expires {
+            None | Some(serde_json::Value::Null) => Ok(None),
+            Some(serde_json::Value::Number(days)) => {
+                Expiration::from_flags(Some(&days.to_string()), None)
+            }
+            Some(serde_json::Value::String(date)) if date.contains('-') => {
+                Expiration::from_flags(None, Some(date))
+            }
+            Some(serde_json::Value::String(days)) => Expiration::from_flags(Some(days), None),
+            Some(_) => anyhow::bail!("\"expires\" must be a number of days, \"never\" or a date"),
+        }
+    }
+}
+
+/// Add one token described by a JSON object on stdin, so a script can set the
+/// value and its metadata without shell-quoting. `--expires`, `--note` and
+/// `--tag` are taken from the object instead of the flags.
+pub fn cmd_token_add_from_json(options: TokenAddOptions, passphrase: &str) -> Result<()> {
+    if options.expires.is_some() || options.note.is_some() || !options.tags.is_empty() {
+        anyhow::bail!(CliError::Usage(
+            "With --json-stdin, set expires, note and tags in the JSON object".into()
+        ));
+    }
+    let mut content = String::new();
+    io::stdin().read_to_string(&mut content)?;
+    let request = TokenAddRequest::parse(&content);
+    content.zeroize();
+    let request = request?;
+
+    let options = TokenAddOptions {
+        expires: request.expiration()?,
+        note: request.note.as_deref(),
+        tags: &request.tags,
+        ..options
+    };
+    cmd_token_add(
+        &request.name,
+        Some(&request.value),
+        false,
+        options,
+        passphrase,
+    )
+}
+
+/// What `cmd_token_add` stores besides the value
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokenAddOptions<'a> {
+    pub expires: Option<Expiration>,
+    /// Environment whose variant is added, instead of the default value
+    pub env: Option<&'a str>,
+    /// The value is a TOTP seed
+    pub totp: bool,
+    /// Human-readable note describing the token
+    pub note: Option<&'a str>,
+    /// Tags grouping the token, e.g. by service
+    pub tags: &'a [String],
+    /// Accept a name that is not a valid environment variable name
+    pub allow_any_name: bool,
+    /// Skip the advisory checks of the value (`analyze::value_warning`)
+    pub no_analyze: bool,
+    /// Accept a value above `store.max_secret_bytes`
+    pub allow_large: bool,
+    /// Copy only: never displayed (see `Secret::protected`)
+    pub protected: bool,
+}
+
+/// Prints `analyze::value_warning` for each (name, value) on stderr, unless
+/// the analyzer is disabled in the config
+fn warn_about_values<'a>(entries: impl IntoIterator<Item = (&'a str, &'a str)>, locker_dir: &Path) {
+    if !Config::load_or_default(locker_dir).analyzer.enabled {
+        return;
+    }
+    for (name, value) in entries {
+        if let Some(warning) = analyze::value_warning(name, value) {
+            eprintln!("⚠️  {}", warning);
+        }
+    }
+}
+
+/// A `--note` value as stored: an empty note clears it
+fn note_value(note: &str) -> Option<String> {
+    let note = note.trim();
+    (!note.is_empty()).then(|| note.to_string())
+}
+
+/// Generate a random value and store it as a new token.
+/// The value is printed once on stdout, or copied to the clipboard with `copy`.
+pub fn cmd_token_gen(
+    name: &str,
+    length: usize,
+    charset: Charset,
+    expires: Option<Expiration>,
+    copy: bool,
+    passphrase: &str,
+) -> Result<()> {
+    let locker = Locker::init_or_load_with_passphrase(passphrase)?;
+    let key = locker.get_key().context("Failed to get encryption key")?;
+    let locker_dir = locker.base_dir().clone();
+
+    let expires_at = Expiration::or_default(expires, &locker_dir)
+        .map(Expiration::timestamp)
+        .transpose()?
+        .flatten();
+    let mut value = generator::generate_secret(length, charset)?;
+
+    let mut store = SecretsStore::load(&locker_dir, key)?;
+    if store.contains(name) {
+        value.zeroize();
+        anyhow::bail!(
+            "Token '{}' already exists. Remove it first to generate a new value.",
+            name
+        );
+    }
+    store.add_secret_at(
+        name.to_string(),
+        value.clone(),
+        expires_at,
+        &locker_dir,
+        key,
+    )?;
+
+    // Status goes to stderr so stdout carries only the value
+    eprintln!("✅ Token '{}' generated ({} characters)", name, length);
+    hooks::on_change(&locker_dir, ChangeAction::Add, &[name]);
+    if copy {
+        let copied = copy_with_auto_clear(&value, &locker_dir);
+        value.zeroize();
+        match copied? {
+            Some(secs) => eprintln!("   Copied to clipboard (clears in {}s)", secs),
+            None => eprintln!("   Copied to clipboard"),
+        }
+    } else {
+        println!("{}", value);
+        value.zeroize();
+    }
+
+    Ok(())
+}
+
+/// Copies `value` to the clipboard and, per `[clipboard] clear_after_secs`,
+/// has a detached helper clear it later. Returns that delay when scheduled.
+fn copy_with_auto_clear(value: &str, locker_dir: &Path) -> Result<Option<u64>> {
+    executor::copy_to_clipboard(value).context("Cannot copy to the clipboard")?;
+
+    let secs = Config::load_or_default(locker_dir)
+        .clipboard
+        .clear_after_secs;
+    if secs == 0 {
+        return Ok(None);
+    }
+    match executor::schedule_detached_clipboard_clear(value, std::time::Duration::from_secs(secs)) {
+        Ok(()) => Ok(Some(secs)),
+        Err(e) => {
+            eprintln!("⚠️  The clipboard will not be cleared automatically: {}", e);
+            Ok(None)
+        }
+    }
+}
+
+/// Print the current one-time code of a token stored as a TOTP seed
+pub fn cmd_token_code(name: &str, format: OutputFormat, passphrase: &str) -> Result<()> {
+    let locker = Locker::init_or_load_with_passphrase(passphrase)?;
+    let key = locker.get_key().context("Failed to get encryption key")?;
+    let locker_dir = locker.base_dir().clone();
+
+    let store = SecretsStore::load(&locker_dir, key)?;
+    let secret = store
+        .get_secret(name)
+        .ok_or_else(|| CliError::token_not_found(name))?;
+    if secret.kind != SecretKind::Totp {
+        anyhow::bail!(
+            "Token '{}' is not a TOTP seed. Add it with lazy-locker token add {} <SEED> --totp",
+            name,
+            name
+        );
+    }
+
+    let mut seed = secret.decrypt_for(None, key)?;
+    let now = std::time::SystemTime::now();
+    let code = totp::generate_totp(&seed, now);
+    seed.zeroize();
+    let code = code?;
+    let remaining = totp::seconds_remaining(now);
+
+    match format {
+        OutputFormat::Json => {
+            let obj = serde_json::json!({
+                "name": name,
+                "code": code,
+                "seconds_remaining": remaining,
+            });
+            println!("{}", serde_json::to_string_pretty(&obj)?);
+        }
+        OutputFormat::Human | OutputFormat::Env | OutputFormat::Csv => {
+            println!("{} ({}s remaining)", code, remaining);
+        }
+    }
+
+    Ok(())
+}
+
+/// Change the value of an existing token, keeping its expiration and metadata
+pub fn cmd_token_update(
+    name: &str,
+    value: Option<&str>,
+    stdin: bool,
+    note: Option<&str>,
+    tags: Option<&[String]>,
+    protected: Option<bool>,
+    passphrase: &str,
+) -> Result<()> {
+    let mut secret_value = if stdin {
+        Some(read_value_from_stdin()?)
+    } else if let Some(v) = value {
+        Some(v.to_string())
+    } else if note.is_some() || tags.is_some() || protected.is_some() {
+        None
+    } else {
+        anyhow::bail!(
+            "Value required. Provide as argument, use --stdin, --note, --tag or --protected"
+        );
+    };
+
+    let locker = Locker::init_or_load_with_passphrase(passphrase)?;
+    let key = locker.get_key().context("Failed to get encryption key")?;
+    let locker_dir = locker.base_dir().clone();
+
+    let mut store = SecretsStore::load(&locker_dir, key)?;
+    let result =
+        store.update_secret_with(name, secret_value.as_deref(), &locker_dir, key, |secret| {
+            if let Some(note) = note {
+                secret.description = note_value(note);
+            }
+            if let Some(tags) = tags {
+                secret.tags = tags.to_vec();
+            }
+            if let Some(protected) = protected {
+                secret.protected = protected;
+            }
+        });
+    if let Some(ref mut secret_value) = secret_value {
+        secret_value.zeroize();
+    }
+    result?;
+
+    println!("✅ Token '{}' updated", name);
+    hooks::on_change(&locker_dir, ChangeAction::Update, &[name]);
+    Ok(())
+}
+
+/// Edit a token's value in `$EDITOR` (or `$VISUAL`, falling back to `vi`)
+/// The store is left untouched when the editor fails or the value is unchanged.
+pub fn cmd_token_edit(name: &str, passphrase: &str) -> Result<()> {
+    let locker = Locker::init_or_load_with_passphrase(passphrase)?;
+    let key = locker.get_key().context("Failed to get encryption key")?;
+    let locker_dir = locker.base_dir().clone();
+
+    let mut store = SecretsStore::load(&locker_dir, key)?;
+    let mut current = store
+        .get_secret(name)
+        .ok_or_else(|| CliError::token_not_found(name))?
+        .decrypt_for(None, key)?;
+
+    let edited = edit_in_editor(&current, &editor_command());
+    current.zeroize();
+
+    match edited? {
+        Some(mut value) => {
+            let result = store.update_secret(name, &value, &locker_dir, key);
+            value.zeroize();
+            result?;
+            println!("✅ Token '{}' updated", name);
+            hooks::on_change(&locker_dir, ChangeAction::Update, &[name]);
+        }
+        None => println!("Token '{}' unchanged", name),
+    }
+    Ok(())
+}
+
+/// Editor to launch for `token edit`
+fn editor_command() -> String {
+    ["EDITOR", "VISUAL"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| if cfg!(windows) { "notepad" } else { "vi" }.to_string())
+}
+
+/// Plaintext temp file that is overwritten and removed when dropped
+struct EditFile {
+    path: PathBuf,
+}
+
+impl EditFile {
+    fn create(value: &str) -> Result<Self> {
+        use std::io::Write;
+
+        let suffix: u64 = rand::random();
+        let path = std::env::temp_dir().join(format!(
+            "lazy-locker-edit-{}-{:016x}",
+            std::process::id(),
+            suffix
+        ));
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options
+            .open(&path)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+        let edit_file = Self { path };
+        file.write_all(value.as_bytes())?;
+        Ok(edit_file)
+    }
+}
+
+impl Drop for EditFile {
+    fn drop(&mut self) {
+        use std::io::Write;
+
+        // Editors may have replaced the file, so overwrite whatever is there now
+        if let Ok(len) = std::fs::metadata(&self.path).map(|m| m.len())
+            && let Ok(mut file) = std::fs::OpenOptions::new().write(true).open(&self.path)
+        {
+            file.write_all(&vec![0u8; len as usize]).ok();
+            file.sync_all().ok();
+        }
+        std::fs::remove_file(&self.path).ok();
+    }
+}
+
+/// Opens `value` in `editor` and returns the new value, or None if unchanged
+/// `editor` may carry arguments (e.g. `code --wait`).
+fn edit_in_editor(value: &str, editor: &str) -> Result<Option<String>> {
+    let file = EditFile::create(value)?;
+
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().context("No editor configured")?;
+    let status = std::process::Command::new(program)
+        .args(parts)
+        .arg(&file.path)
+        .status()
+        .with_context(|| format!("Failed to launch editor '{}'", program))?;
+    if !status.success() {
+        anyhow::bail!("Editor exited with {}; token left unchanged", status);
+    }
+
+    let mut edited = std::fs::read_to_string(&file.path)
+        .with_context(|| format!("Failed to read {}", file.path.display()))?;
+    // Editors append a final newline the original value did not have
+    if !value.ends_with('\n') && edited.ends_with('\n') {
+        edited.pop();
+        if edited.ends_with('\r') {
+            edited.pop();
+        }
+    }
+
+    if edited == value {
+        edited.zeroize();
+        return Ok(None);
+    }
+    Ok(Some(edited))
+}
+
+/// Add a batch of tokens from a JSON object or array read on stdin
+/// Accepts the same formats as `import --format json`, including per-token metadata.
+/// Each token goes through the name, size and value checks of `cmd_token_add`.
+pub fn cmd_token_add_json(options: TokenAddOptions, passphrase: &str) -> Result<()> {
+    let TokenAddOptions {
+        expires,
+        allow_any_name,
+        no_analyze,
+        allow_large,
+        ..
+    } = options;
+    if options.env.is_some()
+        || options.totp
+        || options.note.is_some()
+        || !options.tags.is_empty()
+        || options.protected
+    {
+        anyhow::bail!(CliError::Usage(
+            "--stdin-json only sets names, values and --expires; \
+             use token add per token for --env, --totp, --note, --tag or --protected"
+                .into()
+        ));
+    }
+
+    let mut content = String::new();
+    io::stdin().read_to_string(&mut content)?;
+
+    let mut secrets = parse_json_format(&content)?;
+    content.zeroize();
+    if secrets.is_empty() {
+        println!("⚠️  No tokens found in input");
+        return Ok(());
+    }
+
+    let locker = Locker::init_or_load_with_passphrase(passphrase)?;
+    let key = locker.get_key().context("Failed to get encryption key")?;
+    let locker_dir = locker.base_dir().clone();
+
+    apply_expiration(&mut secrets, expires, &locker_dir)?;
+
+    let mut store = SecretsStore::load(&locker_dir, key)?;
+    if allow_any_name {
+        store.allow_any_names();
+    }
+    if allow_large {
+        store.allow_large_values();
+    }
+    if !no_analyze {
+        warn_about_values(
+            secrets.iter().map(|s| (s.name.as_str(), s.value.as_str())),
+            &locker_dir,
+        );
+    }
+    // Every entry is checked like `token add` before anything is saved
+    let count = store.add_many(&secrets, &locker_dir, key)?;
+
+    println!("✅ Added {} tokens", count);
+    let names: Vec<&str> = secrets.iter().map(|s| s.name.as_str()).collect();
+    hooks::on_change(&locker_dir, ChangeAction::Add, &names);
+    Ok(())
+}
+
+/// Get a token value, resolving the variant for `env` if one exists.
+/// A protected token is only copied, unless `force` prints it anyway.
+pub fn cmd_token_get(
+    name: &str,
+    format: OutputFormat,
+    env: Option<&str>,
+    copy: bool,
+    force: bool,
+    passphrase: &str,
+) -> Result<()> {
+    if copy && format != OutputFormat::Human {
+        anyhow::bail!(CliError::Usage(
+            "--copy cannot be combined with --json or --env".into()
+        ));
+    }
+
+    let locker = Locker::init_or_load_with_passphrase(passphrase)?;
+    let key = locker.get_key().context("Failed to get encryption key")?;
+    let locker_dir = locker.base_dir().clone();
+
+    let store = SecretsStore::load(&locker_dir, key)?;
+    let secret = store
+        .get_secret(name)
+        .ok_or_else(|| CliError::token_not_found(name))?;
+
+    if secret.is_expired() {
+        anyhow::bail!(CliError::Expired(format!("Token '{}' has expired", name)));
+    }
+    if secret.protected && !copy && !force {
+        anyhow::bail!(
+            "Token '{}' is protected; use --copy, or --force to print it",
+            name
+        );
+    }
+
+    let value_str = Zeroizing::new(secret.decrypt_for(env, key)?);
+
+    // The value only goes to the clipboard, cleared like copies from the TUI
+    if copy {
+        let copied = copy_with_auto_clear(&value_str, &locker_dir);
+        drop(value_str);
+        match copied? {
+            Some(secs) => println!("✓ copied '{}' to clipboard (clears in {}s)", name, secs),
+            None => println!("✓ copied '{}' to clipboard", name),
+        }
+        return Ok(());
+    }
+
+    match format {
+        OutputFormat::Human | OutputFormat::Csv => println!("{}", *value_str),
+        OutputFormat::Json => {
+            let mut obj = serde_json::json!({
+                "name": name,
+                "value": *value_str,
+                "expires_at": secret.expires_at,
+                "created_at": secret.created_at,
+                "updated_at": secret.updated_at,
+                "variants": secret.variant_names(),
+            });
+            let output = serde_json::to_string_pretty(&obj).map(Zeroizing::new);
+            if let Some(serde_json::Value::String(value)) = obj.get_mut("value") {
+                value.zeroize();
+            }
+            println!("{}", *output?);
+        }
+        OutputFormat::Env => {
+            let output = Zeroizing::new(format::to_env(&[(name, &value_str)]));
+            print!("{}", *output);
+        }
+    }
+
+    Ok(())
+}
 
+/// Check that a token is present and not expired, for scripts.
+/// Returns the exit code (0 present, 1 absent, 2 expired); prints only with `verbose`.
+pub fn cmd_token_exists(name: &str, verbose: bool, passphrase: &str) -> Result<i32> {
     let locker = Locker::init_or_load_with_passphrase(passphrase)?;
     let key = locker.get_key().context("Failed to get encryption key")?;
     let locker_dir = locker.base_dir().clone();
 
-    let mut store = SecretsStore::load(&locker_dir, key)?;
-    store.add_secret(
-        name.to_string(),
-        secret_value,
-        expires_days,
-        &locker_dir,
-        key,
-    )?;
-
-    println!("✅ Token '{}' added", name);
-    if let Some(days) = expires_days {
-        println!("   Expires in {} days", days);
+    let store = SecretsStore::load(&locker_dir, key)?;
+    let code = match token_status(&store, name) {
+        Ok(()) => 0,
+        Err(e) => {
+            if verbose {
+                println!("{}", e);
+            }
+            return Ok(e.exit_code());
+        }
+    };
+    if verbose {
+        println!("Token '{}' exists", name);
     }
+    Ok(code)
+}
 
-    Ok(())
+/// Whether `name` is present and usable
+fn token_status(store: &SecretsStore, name: &str) -> std::result::Result<(), CliError> {
+    match store.get_secret(name) {
+        None => Err(CliError::token_not_found(name)),
+        Some(secret) if secret.is_expired() => {
+            Err(CliError::Expired(format!("Token '{}' has expired", name)))
+        }
+        Some(_) => Ok(()),
+    }
 }
 
-/// Get a token value
-pub fn cmd_token_get(name: &str, format: OutputFormat, passphrase: &str) -> Result<()> {
+/// Explain where a token's value would come from, without printing the value
+pub fn cmd_token_which(
+    name: &str,
+    format: OutputFormat,
+    env: Option<&str>,
+    passphrase: &str,
+) -> Result<()> {
     let locker = Locker::init_or_load_with_passphrase(passphrase)?;
     let key = locker.get_key().context("Failed to get encryption key")?;
     let locker_dir = locker.base_dir().clone();
@@ -130,39 +1425,133 @@ pub fn cmd_token_get(name: &str, format: OutputFormat, passphrase: &str) -> Resu
     let store = SecretsStore::load(&locker_dir, key)?;
     let secret = store
         .get_secret(name)
-        .context(format!("Token '{}' not found", name))?;
-
-    if secret.is_expired() {
-        anyhow::bail!("Token '{}' has expired", name);
-    }
+        .ok_or_else(|| CliError::token_not_found(name))?;
 
-    let value = decrypt(&secret.encrypted_value, key)?;
-    let value_str = String::from_utf8(value)?;
+    // The same choice as `run`: the agent whenever it answers
+    let agent_answers = match crate::core::agent::AgentClient::try_get_secrets(env) {
+        Some(mut secrets) => {
+            for value in secrets.values_mut() {
+                value.zeroize();
+            }
+            true
+        }
+        None => false,
+    };
+    let source = if agent_answers { "agent" } else { "passphrase" };
+    let resolved = secret.resolved_variant(env);
 
     match format {
-        OutputFormat::Human => println!("{}", value_str),
         OutputFormat::Json => {
             let obj = serde_json::json!({
                 "name": name,
-                "value": value_str,
+                "locker": locker_dir.display().to_string(),
+                "source": source,
+                "expired": secret.is_expired(),
                 "expires_at": secret.expires_at,
+                "days_remaining": secret.days_until_expiration(),
+                "variants": secret.variant_names(),
+                "requested_variant": env,
+                "resolved_variant": resolved,
             });
             println!("{}", serde_json::to_string_pretty(&obj)?);
         }
-        OutputFormat::Env => println!("{}={}", name, value_str),
+        OutputFormat::Human | OutputFormat::Env | OutputFormat::Csv => {
+            println!("{}", name);
+            println!("   Locker:     {}", locker_dir.display());
+            if agent_answers {
+                println!("   Source:     agent (no passphrase needed)");
+            } else {
+                println!("   Source:     passphrase (agent not started)");
+            }
+            println!("   Expiration: {}", secret.expiration_display());
+            let variants = secret.variant_names();
+            if !variants.is_empty() {
+                println!("   Variants:   {}", variants.join(", "));
+            }
+            match (env, resolved) {
+                (_, Some(variant)) => println!("   Resolves:   variant '{}'", variant),
+                (Some(env), None) => {
+                    println!("   Resolves:   default value (no '{}' variant)", env)
+                }
+                (None, None) => println!("   Resolves:   default value"),
+            }
+        }
     }
 
     Ok(())
 }
 
+/// How `cmd_token_list` prints the tokens
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokenListOptions<'a> {
+    /// In env format, resolve values to this environment's variant where one exists
+    pub env: Option<&'a str>,
+    /// Do not warn about tokens expiring soon
+    pub quiet: bool,
+    /// Never color the table
+    pub no_color: bool,
+    /// Only the names, one per line
+    pub names_only: bool,
+    /// Only the number of tokens
+    pub count: bool,
+    /// In env format, also print the values of protected tokens
+    pub force: bool,
+}
+
 /// List all tokens
-pub fn cmd_token_list(format: OutputFormat, passphrase: &str) -> Result<()> {
+/// `--names-only` and `--count` print no header and decrypt no value.
+pub fn cmd_token_list(
+    format: OutputFormat,
+    filter: &SecretFilter,
+    options: TokenListOptions,
+    passphrase: &str,
+) -> Result<()> {
+    let TokenListOptions {
+        env,
+        quiet,
+        no_color,
+        names_only,
+        count,
+        force,
+    } = options;
+    if names_only && count {
+        anyhow::bail!(CliError::Usage(
+            "--names-only and --count cannot be combined".into()
+        ));
+    }
+    if (names_only || count) && format != OutputFormat::Human {
+        anyhow::bail!(CliError::Usage(
+            "--names-only and --count cannot be combined with --json or --env".into()
+        ));
+    }
+
     let locker = Locker::init_or_load_with_passphrase(passphrase)?;
     let key = locker.get_key().context("Failed to get encryption key")?;
     let locker_dir = locker.base_dir().clone();
 
     let store = SecretsStore::load(&locker_dir, key)?;
-    let secrets = store.list_secrets();
+    let secrets = filter.select(&store)?;
+    if count {
+        println!("{}", secrets.len());
+        return Ok(());
+    }
+    if names_only {
+        for secret in &secrets {
+            println!("{}", secret.name);
+        }
+        return Ok(());
+    }
+    let warn_days = Config::load_or_default(&locker_dir)
+        .store
+        .warn_expiring_days;
+
+    // Only next to the table: scripts reading --json or --env get no extra output
+    if format == OutputFormat::Human
+        && !quiet
+        && let Some(summary) = store.expiring_summary(warn_days)
+    {
+        eprintln!("⚠️  {}", summary);
+    }
 
     match format {
         OutputFormat::Human => {
@@ -170,24 +1559,9 @@ pub fn cmd_token_list(format: OutputFormat, passphrase: &str) -> Result<()> {
                 println!("No tokens found.");
                 return Ok(());
             }
-
-            println!("{:<30} {:<20} STATUS", "NAME", "EXPIRES");
-            println!("{:-<60}", "");
-
-            for secret in secrets {
-                let status = if secret.is_expired() {
-                    "⚠️ EXPIRED"
-                } else {
-                    "✓"
-                };
-                println!(
-                    "{:<30} {:<20} {}",
-                    secret.name,
-                    secret.expiration_display(),
-                    status
-                );
-            }
+            print!("{}", token_table(&secrets, warn_days, use_color(no_color)));
         }
+        OutputFormat::Csv => print!("{}", format::to_csv(&secrets)),
         OutputFormat::Json => {
             let list: Vec<_> = secrets
                 .iter()
@@ -197,6 +1571,12 @@ pub fn cmd_token_list(format: OutputFormat, passphrase: &str) -> Result<()> {
                         "expires_at": s.expires_at,
                         "is_expired": s.is_expired(),
                         "days_remaining": s.days_until_expiration(),
+                        "created_at": s.created_at,
+                        "updated_at": s.updated_at,
+                        "variants": s.variant_names(),
+                        "tags": s.tags,
+                        "description": s.description,
+                        "protected": s.protected,
                     })
                 })
                 .collect();
@@ -204,33 +1584,283 @@ pub fn cmd_token_list(format: OutputFormat, passphrase: &str) -> Result<()> {
         }
         OutputFormat::Env => {
             // For env format, we need to decrypt and output all values
-            for secret in secrets {
-                if !secret.is_expired() {
-                    let value = decrypt(&secret.encrypted_value, key)?;
-                    let value_str = String::from_utf8(value)?;
-                    println!("{}={}", secret.name, value_str);
-                }
-            }
+            let active: Vec<&Secret> = secrets.into_iter().filter(|s| !s.is_expired()).collect();
+            let active = without_protected(active, force);
+            print_env_values(&active, env, Some(key), None, |entries| {
+                Ok(format::to_env(entries))
+            })?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Leaves out protected tokens, whose values are only printed with `force`,
+/// and names them on stderr
+fn without_protected(secrets: Vec<&Secret>, force: bool) -> Vec<&Secret> {
+    if force {
+        return secrets;
+    }
+    let (protected, shown): (Vec<&Secret>, Vec<&Secret>) =
+        secrets.into_iter().partition(|s| s.protected);
+    if !protected.is_empty() {
+        let names: Vec<&str> = protected.iter().map(|s| s.name.as_str()).collect();
+        eprintln!(
+            "⚠️  Left out protected token(s): {} (use --force to print them)",
+            names.join(", ")
+        );
+    }
+    shown
+}
+
+/// Characters of a note shown in the `token list` table
+const NOTE_COLUMN_WIDTH: usize = 36;
+
+/// Widest NAME column of the `token list` table; longer names end with `…`
+const NAME_COLUMN_MAX_WIDTH: usize = 48;
+
+/// ANSI colors of `token list` rows, like the TUI's red and yellow
+const ANSI_RED: &str = "\x1b[31m";
+const ANSI_YELLOW: &str = "\x1b[33m";
+const ANSI_RESET: &str = "\x1b[0m";
+
+/// Whether CLI output may be colored: stdout is a terminal and neither
+/// `--no-color` nor a non-empty `NO_COLOR` turns colors off
+pub fn use_color(no_color: bool) -> bool {
+    !no_color
+        && std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+        && io::stdout().is_terminal()
+}
+
+/// The human `token list` table, its NAME column as wide as the longest name.
+/// With `color`, expired rows are red and rows expiring within `warn_days` yellow.
+fn token_table(secrets: &[&Secret], warn_days: u32, color: bool) -> String {
+    let name_width = secrets
+        .iter()
+        .map(|s| s.name.chars().count())
+        .max()
+        .unwrap_or(0)
+        .clamp("NAME".len(), NAME_COLUMN_MAX_WIDTH);
+    let with_tags = secrets.iter().any(|s| !s.tags.is_empty());
+    let with_notes = secrets.iter().any(|s| s.description.is_some());
+
+    let mut header = format!("{:<name_width$} {:<20} {:<12}", "NAME", "EXPIRES", "STATUS");
+    let mut width = name_width + 34;
+    if with_tags {
+        header.push_str(&format!(" {:<24}", "TAGS"));
+        width += 25;
+    }
+    if with_notes {
+        header.push_str(" NOTE");
+        width += NOTE_COLUMN_WIDTH + 1;
+    }
+    let mut table = format!("{}\n{:-<width$}\n", header.trim_end(), "");
+
+    let soon = now_timestamp() + i64::from(warn_days) * 86400;
+    for secret in secrets {
+        let expired = secret.is_expired();
+        let status = if expired { "⚠️ EXPIRED" } else { "✓" };
+        let mut line = format!(
+            "{:<name_width$} {:<20} {:<12}",
+            format::truncate_chars(&secret.name, name_width),
+            secret.expiration_display(),
+            status
+        );
+        if with_tags {
+            line.push_str(&format!(" {:<24}", secret.tags.join(",")));
+        }
+        if with_notes {
+            let note = secret.description.as_deref().unwrap_or("");
+            line.push(' ');
+            line.push_str(&format::truncate_chars(note, NOTE_COLUMN_WIDTH));
+        }
+        let line = line.trim_end();
+
+        let expiring = warn_days > 0 && secret.expires_at.is_some_and(|at| at <= soon);
+        let row_color = match (expired, expiring) {
+            (true, _) => Some(ANSI_RED),
+            (false, true) => Some(ANSI_YELLOW),
+            (false, false) => None,
+        };
+        match row_color.filter(|_| color) {
+            Some(ansi) => table.push_str(&format!("{}{}{}", ansi, line, ANSI_RESET)),
+            None => table.push_str(line),
+        }
+        table.push('\n');
+    }
+    table
+}
+
+/// Remove tokens by name and/or `--glob` pattern, saving the store once.
+/// Missing names are reported without stopping the others, unless `strict`.
+/// Removing more than one token asks for confirmation unless `yes`.
+pub fn cmd_token_remove(
+    names: &[String],
+    glob: Option<&str>,
+    yes: bool,
+    strict: bool,
+    passphrase: &str,
+) -> Result<()> {
+    let locker = Locker::init_or_load_with_passphrase(passphrase)?;
+    let key = locker.get_key().context("Failed to get encryption key")?;
+    let locker_dir = locker.base_dir().clone();
+
+    let mut store = SecretsStore::load(&locker_dir, key)?;
+
+    let (mut targets, missing): (Vec<String>, Vec<String>) =
+        names.iter().cloned().partition(|name| store.contains(name));
+    if let Some(pattern) = glob {
+        let mut matched: Vec<String> = store
+            .secrets
+            .keys()
+            .filter(|name| glob_match(pattern, name) && !targets.contains(name))
+            .cloned()
+            .collect();
+        if matched.is_empty() {
+            eprintln!("⚠️  No tokens match '{}'", pattern);
+        }
+        matched.sort();
+        targets.extend(matched);
+    }
+
+    if let Some(name) = missing.first()
+        && (strict || targets.is_empty())
+    {
+        anyhow::bail!(CliError::token_not_found(name));
+    }
+    if targets.is_empty() {
+        return Ok(());
+    }
+    for name in &missing {
+        eprintln!("⚠️  Token '{}' not found", name);
+    }
+
+    if targets.len() > 1 {
+        println!("Tokens to remove:");
+        for name in &targets {
+            println!("  - {}", name);
+        }
+    }
+    if !removal_confirmed(targets.len(), yes, || {
+        if !io::stdin().is_terminal() {
+            anyhow::bail!(CliError::Usage(format!(
+                "Refusing to remove {} tokens without confirmation; pass --yes",
+                targets.len()
+            )));
         }
+        print!("Remove {} tokens? [y/N] ", targets.len());
+        io::Write::flush(&mut io::stdout())?;
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer)?;
+        Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+    })? {
+        println!("Aborted, nothing removed");
+        return Ok(());
+    }
+
+    let removed = store.delete_many(&targets, &locker_dir, key)?;
+    for name in &removed {
+        println!("✅ Token '{}' removed", name);
     }
+    let names: Vec<&str> = removed.iter().map(String::as_str).collect();
+    hooks::on_change(&locker_dir, ChangeAction::Remove, &names);
 
     Ok(())
 }
 
-/// Remove a token
-pub fn cmd_token_remove(name: &str, passphrase: &str) -> Result<()> {
+/// Removes every expired token, or only those expired more than
+/// `older_than_days` days ago. Permanent tokens are never removed.
+pub fn cmd_token_prune(older_than_days: i64, dry_run: bool, passphrase: &str) -> Result<()> {
     let locker = Locker::init_or_load_with_passphrase(passphrase)?;
     let key = locker.get_key().context("Failed to get encryption key")?;
     let locker_dir = locker.base_dir().clone();
 
     let mut store = SecretsStore::load(&locker_dir, key)?;
 
-    if store.get_secret(name).is_none() {
-        anyhow::bail!("Token '{}' not found", name);
+    if dry_run {
+        let names = store.expired_names(older_than_days);
+        if names.is_empty() {
+            println!("No expired tokens to prune");
+        }
+        for name in &names {
+            println!("Would remove '{}'", name);
+        }
+        return Ok(());
+    }
+
+    let removed = store.prune_expired(older_than_days, &locker_dir, key)?;
+    if removed.is_empty() {
+        println!("No expired tokens to prune");
+    }
+    for name in &removed {
+        println!("✅ Token '{}' removed (expired)", name);
+    }
+    let names: Vec<&str> = removed.iter().map(String::as_str).collect();
+    hooks::on_change(&locker_dir, ChangeAction::Remove, &names);
+
+    Ok(())
+}
+
+/// Whether a removal may go ahead: a single token needs no confirmation,
+/// several need `yes` or a positive answer from `ask`
+fn removal_confirmed(count: usize, yes: bool, ask: impl FnOnce() -> Result<bool>) -> Result<bool> {
+    if count <= 1 || yes {
+        return Ok(true);
+    }
+    ask()
+}
+
+/// Shell-style match of `name` against `pattern`: `*` is any run of
+/// characters, `?` any single character
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position of the last `*` and the name position it currently covers up to
+    let mut backtrack = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star, covered)) => {
+                    p = star + 1;
+                    n = covered + 1;
+                    backtrack = Some((star, covered + 1));
+                }
+                None => return false,
+            },
+        }
     }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Rename a token, keeping its value, expiration and metadata
+pub fn cmd_token_rename(
+    old: &str,
+    new: &str,
+    allow_any_name: bool,
+    passphrase: &str,
+) -> Result<()> {
+    let locker = Locker::init_or_load_with_passphrase(passphrase)?;
+    let key = locker.get_key().context("Failed to get encryption key")?;
+    let locker_dir = locker.base_dir().clone();
 
-    store.delete_secret(name, &locker_dir, key)?;
-    println!("✅ Token '{}' removed", name);
+    let mut store = SecretsStore::load(&locker_dir, key)?;
+    if allow_any_name {
+        store.allow_any_names();
+    }
+    store.rename_secret(old, new, &locker_dir, key)?;
+    println!("✅ Token '{}' renamed to '{}'", old, new);
+    // The new name first, as LAZY_LOCKER_NAME
+    hooks::on_change(&locker_dir, ChangeAction::Rename, &[new, old]);
 
     Ok(())
 }
@@ -239,14 +1869,24 @@ pub fn cmd_token_remove(name: &str, passphrase: &str) -> Result<()> {
 // IMPORT COMMAND
 // ============================================================================
 
-/// Import tokens from a .env file or stdin
+/// Import tokens from a .env, JSON or YAML file or stdin
+/// Existing names are handled per `on_conflict`; `dry_run` only reports the plan.
 pub fn cmd_import(
     file: Option<&str>,
     stdin: bool,
     format: &str,
-    expires_days: Option<u32>,
+    options: ImportOptions<'_>,
     passphrase: &str,
 ) -> Result<()> {
+    let ImportOptions {
+        expires,
+        on_conflict,
+        dry_run,
+        no_analyze,
+        allow_large,
+        allow_any_name,
+        prefix,
+    } = options;
     let content = if stdin {
         let mut buf = String::new();
         io::stdin().read_to_string(&mut buf)?;
@@ -257,64 +1897,577 @@ pub fn cmd_import(
         anyhow::bail!("Provide a file path or use --stdin");
     };
 
-    let secrets = match format {
-        "env" => parse_env_format(&content)?,
+    let mut secrets = match format {
+        "env" => parse_env_format(&content)?
+            .into_iter()
+            .map(|(name, value)| PlainSecret::new(name, value))
+            .collect(),
         "json" => parse_json_format(&content)?,
-        _ => anyhow::bail!("Unknown format: {}. Supported: env, json", format),
+        "yaml" | "yml" => parse_yaml_format(&content)?,
+        _ => anyhow::bail!("Unknown format: {}. Supported: env, json, yaml", format),
+    };
+
+    if secrets.is_empty() {
+        println!("⚠️  No secrets found in input");
+        return Ok(());
+    }
+    if let Some(prefix) = prefix {
+        add_name_prefix(&mut secrets, prefix)?;
+    }
+
+    let locker = Locker::init_or_load_with_passphrase(passphrase)?;
+    let key = locker.get_key().context("Failed to get encryption key")?;
+    let locker_dir = locker.base_dir().clone();
+
+    apply_expiration(&mut secrets, expires, &locker_dir)?;
+
+    if !no_analyze {
+        warn_about_values(
+            secrets
+                .iter()
+                .map(|secret| (secret.name.as_str(), secret.value.as_str())),
+            &locker_dir,
+        );
+    }
+
+    let mut store = SecretsStore::load(&locker_dir, key)?;
+    if allow_large {
+        store.allow_large_values();
+    }
+    if allow_any_name {
+        store.allow_any_names();
+    }
+    if dry_run {
+        // Staged but never committed, so invalid names and oversized values show up too
+        let summary = store.stage_import(&secrets, on_conflict, key)?;
+        for name in &summary.imported {
+            println!("  + {} (would import)", name);
+        }
+        for name in &summary.overwritten {
+            println!("  ~ {} (would overwrite)", name);
+        }
+        for name in &summary.skipped {
+            println!("  = {} (would skip, already exists)", name);
+        }
+        println!("Dry run: {}", import_counts(&summary));
+        return Ok(());
+    }
+
+    let summary = store.import(&secrets, on_conflict, &locker_dir, key)?;
+
+    println!("✅ {}", import_counts(&summary));
+    if !summary.skipped.is_empty() {
+        println!(
+            "   Kept existing: {} (use --on-conflict overwrite to replace)",
+            summary.skipped.join(", ")
+        );
+    }
+    match expires {
+        Some(Expiration::Never) => println!("   All tokens are permanent"),
+        Some(expires) => println!("   All tokens expire {}", expires.describe()),
+        None => {}
+    }
+    hooks::on_change(&locker_dir, ChangeAction::Import, &written_names(&summary));
+
+    Ok(())
+}
+
+/// How `cmd_import` stores the parsed secrets
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ImportOptions<'a> {
+    /// Overrides any expiration carried by the input
+    pub expires: Option<Expiration>,
+    pub on_conflict: ConflictStrategy,
+    /// Print what would change without writing anything
+    pub dry_run: bool,
+    /// Skip the advisory checks of the values (`analyze::value_warning`)
+    pub no_analyze: bool,
+    /// Accept values above `store.max_secret_bytes`
+    pub allow_large: bool,
+    /// Accept names that are not valid environment variable names
+    pub allow_any_name: bool,
+    /// Prepended to every imported name, e.g. `STRIPE_`
+    pub prefix: Option<&'a str>,
+}
+
+/// An explicit --expires/--expires-at overrides any expiration carried by the
+/// input; otherwise `store.default_expiration_days` fills in the missing ones
+fn apply_expiration(
+    secrets: &mut [PlainSecret],
+    expires: Option<Expiration>,
+    locker_dir: &Path,
+) -> Result<()> {
+    if let Some(expires) = expires {
+        let expires_at = expires.timestamp()?;
+        for secret in secrets.iter_mut() {
+            secret.expires_at = expires_at;
+        }
+    } else if let Some(default) = Expiration::or_default(None, locker_dir) {
+        let expires_at = default.timestamp()?;
+        for secret in secrets.iter_mut().filter(|s| s.expires_at.is_none()) {
+            secret.expires_at = expires_at;
+        }
+    }
+    Ok(())
+}
+
+/// Prepends `prefix` to every name, checking each result is a valid env variable name
+fn add_name_prefix(secrets: &mut [PlainSecret], prefix: &str) -> Result<()> {
+    for secret in secrets.iter_mut() {
+        let name = format!("{}{}", prefix, secret.name);
+        if !is_valid_env_name(&name) {
+            anyhow::bail!(
+                "--prefix '{}' turns '{}' into '{}', which is not a valid env variable name \
+                 (letters, digits and '_', not starting with a digit)",
+                prefix,
+                secret.name,
+                name
+            );
+        }
+        secret.name = name;
+    }
+    Ok(())
+}
+
+/// Names an import or restore added or overwrote, sorted
+fn written_names(summary: &ImportSummary) -> Vec<&str> {
+    let mut names: Vec<&str> = summary
+        .imported
+        .iter()
+        .chain(&summary.overwritten)
+        .map(String::as_str)
+        .collect();
+    names.sort_unstable();
+    names
+}
+
+fn import_counts(summary: &ImportSummary) -> String {
+    format!(
+        "imported {}, skipped {}, overwritten {}",
+        summary.imported.len(),
+        summary.skipped.len(),
+        summary.overwritten.len()
+    )
+}
+
+// ============================================================================
+// PROFILE COMMANDS
+// ============================================================================
+
+/// List the profiles of the locker, marking the active one
+pub fn cmd_profile_list() -> Result<()> {
+    let base_dir = Config::get_base_dir()?;
+    let active = config::active_profile()?;
+
+    for name in config::list_profiles(&base_dir) {
+        let marker = if name == active { "*" } else { " " };
+        let dir = config::profile_dir(&base_dir, &name);
+        if dir.join("salt").exists() {
+            println!("{} {}", marker, name);
+        } else {
+            println!("{} {} (not initialized)", marker, name);
+        }
+    }
+    // A profile selected before its first init has no directory yet
+    if !config::profile_dir(&base_dir, &active).exists() {
+        println!("* {} (not initialized)", active);
+    }
+
+    Ok(())
+}
+
+// ============================================================================
+// BACKUP COMMANDS
+// ============================================================================
+
+/// Write an encrypted backup of every token to `out`.
+/// It is sealed with `backup_passphrase`, or the locker passphrase by default.
+pub fn cmd_backup(out: &str, backup_passphrase: Option<&str>, passphrase: &str) -> Result<()> {
+    let locker = Locker::init_or_load_with_passphrase(passphrase)?;
+    let key = locker.get_key().context("Failed to get encryption key")?;
+    let locker_dir = locker.base_dir().clone();
+
+    let store = SecretsStore::load(&locker_dir, key)?;
+    let data = backup::create(&store, key, backup_passphrase.unwrap_or(passphrase))?;
+    let path = std::path::Path::new(out);
+    executor::write_secret_file(&data, path).map_err(|e| match e.downcast::<io::Error>() {
+        Ok(e) => write_error(path, e),
+        Err(e) => e,
+    })?;
+
+    println!("✅ Backed up {} tokens to {}", store.len(), out);
+    Ok(())
+}
+
+/// Merge the tokens of an encrypted backup into the locker.
+/// Existing names are handled per `on_conflict`.
+pub fn cmd_restore(
+    input: &str,
+    backup_passphrase: Option<&str>,
+    on_conflict: ConflictStrategy,
+    allow_any_name: bool,
+    passphrase: &str,
+) -> Result<()> {
+    let data = std::fs::read(input).context(format!("Failed to read backup: {}", input))?;
+    let backup = Backup::open(&data, backup_passphrase.unwrap_or(passphrase))?;
+
+    let locker = Locker::init_or_load_with_passphrase(passphrase)?;
+    let key = locker.get_key().context("Failed to get encryption key")?;
+    let locker_dir = locker.base_dir().clone();
+
+    let mut store = SecretsStore::load(&locker_dir, key)?;
+    if allow_any_name {
+        store.allow_any_names();
+    }
+    let summary = backup.restore_into(&mut store, on_conflict, &locker_dir, key)?;
+
+    println!(
+        "✅ Restored {} tokens from {}: {}",
+        backup.len(),
+        input,
+        import_counts(&summary)
+    );
+    if !summary.skipped.is_empty() {
+        println!(
+            "   Kept existing: {} (use --on-conflict overwrite to replace)",
+            summary.skipped.join(", ")
+        );
+    }
+    hooks::on_change(&locker_dir, ChangeAction::Import, &written_names(&summary));
+    Ok(())
+}
+
+// ============================================================================
+// EXPORT COMMAND (bonus)
+// ============================================================================
+
+/// How `cmd_export` renders tokens
+#[derive(Debug, Clone, Copy)]
+pub struct ExportOptions<'a> {
+    pub format: OutputFormat,
+    /// JSON array with expiration, tags and description
+    pub with_metadata: bool,
+    /// Environment whose variants are selected
+    pub env: Option<&'a str>,
+    /// `export NAME='value'` lines instead of `.env` lines
+    pub shell: bool,
+    /// A flat YAML mapping instead of `.env` lines
+    pub yaml: bool,
+    /// One line per token from e.g. `{name}={value}`
+    pub template: Option<&'a str>,
+    /// Write `format::MASK` instead of every value
+    pub mask: bool,
+    /// Removed from the start of the names that have it, undoing `import --prefix`
+    pub strip_prefix: Option<&'a str>,
+    /// Also write the values of protected tokens
+    pub force: bool,
+}
+
+impl Default for ExportOptions<'_> {
+    fn default() -> Self {
+        Self {
+            format: OutputFormat::Env,
+            with_metadata: false,
+            env: None,
+            shell: false,
+            yaml: false,
+            template: None,
+            mask: false,
+            strip_prefix: None,
+            force: false,
+        }
+    }
+}
+
+/// Export the tokens matching `filter` (all by default) to stdout
+///
+/// JSON exports use a simple `{"NAME": "value"}` object by default. With
+/// `with_metadata`, they use the array format
+/// `[{name, value, expires_at, tags, description}]` which `import` reads back
+/// losslessly. `env` selects environment variants for env and object output.
+/// With `shell`, env lines become `export NAME='value'` for `eval` or `source`,
+/// and with `yaml` a `NAME: "value"` mapping that `import` reads back;
+/// `template` renders one line per token from e.g. `{name}={value}`. With
+/// `mask`, values are replaced by a fixed-width placeholder and never decrypted,
+/// to share which variables exist without what they hold.
+pub fn cmd_export(options: &ExportOptions, filter: &SecretFilter, passphrase: &str) -> Result<()> {
+    let metadata_only = matches!(options.format, OutputFormat::Csv | OutputFormat::Human);
+    if options.shell && (options.format == OutputFormat::Json || options.yaml) {
+        anyhow::bail!("--shell only applies to env output");
+    }
+    if options.yaml && (options.format == OutputFormat::Json || options.with_metadata) {
+        anyhow::bail!("--yaml cannot be combined with --json or --with-metadata");
+    }
+    if metadata_only
+        && (options.with_metadata || options.shell || options.yaml || options.template.is_some())
+    {
+        anyhow::bail!(CliError::Usage(
+            "--format csv and table list metadata only; drop --with-metadata, --shell, --yaml and --template".into()
+        ));
+    }
+
+    let locker = Locker::init_or_load_with_passphrase(passphrase)?;
+    let key = locker.get_key().context("Failed to get encryption key")?;
+    let locker_dir = locker.base_dir().clone();
+
+    let store = SecretsStore::load(&locker_dir, key)?;
+    let selected = filter.select(&store)?;
+    // Masked and metadata-only output show no value, protected or not
+    let selected = if options.mask || metadata_only {
+        selected
+    } else {
+        without_protected(selected, options.force)
+    };
+
+    // Names, expiration and tags only: nothing is decrypted
+    if options.format == OutputFormat::Csv {
+        print!("{}", format::to_csv(&selected));
+        return Ok(());
+    }
+    if options.format == OutputFormat::Human {
+        let warn_days = Config::load_or_default(&locker_dir)
+            .store
+            .warn_expiring_days;
+        print!("{}", token_table(&selected, warn_days, use_color(false)));
+        return Ok(());
+    }
+
+    if options.with_metadata {
+        let secrets: Vec<PlainSecret> = if options.mask {
+            selected
+                .iter()
+                .map(|s| PlainSecret {
+                    name: s.name.clone(),
+                    value: format::MASK.to_string(),
+                    expires_at: s.expires_at,
+                    tags: s.tags.clone(),
+                    description: s.description.clone(),
+                })
+                .collect()
+        } else {
+            let names: Vec<&str> = selected.iter().map(|s| s.name.as_str()).collect();
+            store
+                .plain_secrets(key)?
+                .into_iter()
+                .filter(|s| names.contains(&s.name.as_str()))
+                .collect()
+        };
+        let mut secrets = secrets;
+        for secret in &mut secrets {
+            secret.name = export_name(&secret.name, options.strip_prefix).to_string();
+        }
+        check_unique_names(
+            secrets.iter().map(|s| s.name.as_str()),
+            options.strip_prefix,
+        )?;
+        let output = Zeroizing::new(format::to_json_with_metadata(&secrets)?);
+        println!("{}", *output);
+        return Ok(());
+    }
+
+    let active: Vec<&Secret> = selected.into_iter().filter(|s| !s.is_expired()).collect();
+    // Masked output never needs the key
+    let key = (!options.mask).then_some(key);
+    let env = options.env;
+    let strip = options.strip_prefix;
+    check_unique_names(active.iter().map(|s| export_name(&s.name, strip)), strip)?;
+    match (options.format, options.template) {
+        (_, Some(template)) => print_env_values(&active, env, key, strip, |entries| {
+            Ok(format::to_template(entries, template))
+        }),
+        (OutputFormat::Json, None) => print_env_values(&active, env, key, strip, |entries| {
+            format::to_json(entries).map(|json| json + "\n")
+        }),
+        _ if options.shell => print_env_values(&active, env, key, strip, |entries| {
+            Ok(format::to_shell_exports(entries))
+        }),
+        _ if options.yaml => print_env_values(&active, env, key, strip, format::to_yaml),
+        _ => print_env_values(&active, env, key, strip, |entries| {
+            Ok(format::to_env(entries))
+        }),
+    }
+}
+
+/// `name` as exported: without `strip_prefix` when it starts with it
+fn export_name<'a>(name: &'a str, strip_prefix: Option<&str>) -> &'a str {
+    strip_prefix
+        .and_then(|prefix| name.strip_prefix(prefix))
+        .filter(|rest| !rest.is_empty())
+        .unwrap_or(name)
+}
+
+/// Fails when stripping the prefix gives two tokens the same exported name
+fn check_unique_names<'a>(
+    names: impl IntoIterator<Item = &'a str>,
+    strip_prefix: Option<&str>,
+) -> Result<()> {
+    let Some(prefix) = strip_prefix else {
+        return Ok(());
     };
+    let mut seen = std::collections::HashSet::new();
+    for name in names {
+        if !seen.insert(name) {
+            anyhow::bail!(
+                "--strip-prefix '{}' would export two tokens as '{}'; use --only or --except",
+                prefix,
+                name
+            );
+        }
+    }
+    Ok(())
+}
 
-    if secrets.is_empty() {
-        println!("⚠️  No secrets found in input");
+/// Print `export NAME='value'` lines for `eval "$(lazy-locker shell-env)"`.
+///
+/// Prefers the agent so no passphrase is needed, and falls back to the
+/// passphrase from `--passphrase`, `input`, the environment or a terminal prompt. Only
+/// export lines reach stdout, so `eval` only sees valid shell.
+pub fn cmd_shell_env(
+    filter: &SecretFilter,
+    env: Option<&str>,
+    passphrase: Option<&str>,
+    input: PassphraseInput,
+) -> Result<()> {
+    // The agent only knows names and values, so tag filters need the store
+    if filter.tags.is_empty()
+        && crate::core::agent::is_agent_running()
+        && let Ok(mut secrets) = crate::core::agent::AgentClient::get_secrets_with_retry(env)
+    {
+        let selected = filter.retain_values(&mut secrets);
+        let mut output = String::new();
+        if selected.is_ok() {
+            let entries: Vec<(&str, &str)> = secrets
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_str()))
+                .collect();
+            output = format::to_shell_exports(&entries);
+        }
+        for value in secrets.values_mut() {
+            value.zeroize();
+        }
+        selected?;
+        print!("{}", output);
+        output.zeroize();
         return Ok(());
     }
 
-    let locker = Locker::init_or_load_with_passphrase(passphrase)?;
-    let key = locker.get_key().context("Failed to get encryption key")?;
-    let locker_dir = locker.base_dir().clone();
+    let passphrase = get_passphrase(passphrase, input)
+        .context("Agent not started and no passphrase available")?;
+    // Evaluated by the shell like `run` injects, not displayed
+    let options = ExportOptions {
+        env,
+        shell: true,
+        force: true,
+        ..Default::default()
+    };
+    cmd_export(&options, filter, &passphrase)
+}
 
-    let mut store = SecretsStore::load(&locker_dir, key)?;
-    let mut count = 0;
+/// Restricts which tokens a command applies to
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SecretFilter {
+    /// Exact token names; empty means all tokens
+    pub only: Vec<String>,
+    /// Exact token names left out
+    pub except: Vec<String>,
+    /// Only tokens carrying all of these tags (ignoring case)
+    pub tags: Vec<String>,
+}
 
-    for (name, value) in secrets {
-        store.add_secret(name.clone(), value, expires_days, &locker_dir, key)?;
-        count += 1;
+impl SecretFilter {
+    /// Builds a filter from `--only A,B`, `--except C,D` and `--tag T` values
+    pub fn from_args(only: Option<&str>, except: Option<&str>, tags: &[String]) -> Self {
+        let names = |list: Option<&str>| -> Vec<String> {
+            list.map(|list| {
+                list.split(',')
+                    .map(|name| name.trim().to_string())
+                    .filter(|name| !name.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default()
+        };
+        Self {
+            only: names(only),
+            except: names(except),
+            tags: normalize_tags(tags),
+        }
     }
 
-    println!("✅ Imported {} tokens", count);
-    if let Some(days) = expires_days {
-        println!("   All tokens expire in {} days", days);
+    /// Whether `name` passes `--only` and `--except`, tags aside
+    fn accepts_name(&self, name: &str) -> bool {
+        (self.only.is_empty() || self.only.iter().any(|n| n == name))
+            && !self.except.iter().any(|n| n == name)
     }
 
-    Ok(())
-}
+    /// Keeps the selected entries of a name → value map, such as the agent
+    /// serves, wiping the values dropped. Tags are not known there, so they
+    /// are ignored. Fails if a name given with `--only` is missing.
+    pub fn retain_values(&self, values: &mut BTreeMap<String, String>) -> Result<()> {
+        if let Some(missing) = self.only.iter().find(|n| !values.contains_key(*n)) {
+            anyhow::bail!(CliError::token_not_found(missing));
+        }
+        values.retain(|name, value| {
+            let keep = self.accepts_name(name);
+            if !keep {
+                value.zeroize();
+            }
+            keep
+        });
+        Ok(())
+    }
 
-// ============================================================================
-// EXPORT COMMAND (bonus)
-// ============================================================================
+    /// Returns the matching tokens, sorted by name.
+    /// Fails if a name given with `--only` does not exist.
+    pub fn select<'a>(&self, store: &'a SecretsStore) -> Result<Vec<&'a Secret>> {
+        if let Some(missing) = self.only.iter().find(|n| !store.contains(n)) {
+            anyhow::bail!(CliError::token_not_found(missing));
+        }
 
-/// Export all tokens to stdout
-pub fn cmd_export(format: OutputFormat, passphrase: &str) -> Result<()> {
-    // Reuse token list with env format for export
-    cmd_token_list(format, passphrase)
+        Ok(store
+            .list_secrets()
+            .into_iter()
+            .filter(|s| self.accepts_name(&s.name))
+            .filter(|s| self.tags.iter().all(|tag| s.has_tag(tag)))
+            .collect())
+    }
 }
 
 // ============================================================================
 // HELPER FUNCTIONS
 // ============================================================================
 
-fn get_locker_dir() -> Result<PathBuf> {
-    use directories::BaseDirs;
-
-    let base_dirs = BaseDirs::new().context("Unable to determine user directories")?;
-    let config_dir = base_dirs.config_dir();
+/// Decrypts `secrets` (resolving `env` variants), renders them and prints the
+/// result, wiping every plaintext buffer afterwards. Without a key, every value
+/// is `format::MASK`. Names are rendered without `strip_prefix`.
+fn print_env_values(
+    secrets: &[&Secret],
+    env: Option<&str>,
+    key: Option<&[u8]>,
+    strip_prefix: Option<&str>,
+    render: impl Fn(&[(&str, &str)]) -> Result<String>,
+) -> Result<()> {
+    // Values decrypted before a later failure are wiped on drop too
+    let mut values: Vec<Zeroizing<String>> = Vec::with_capacity(secrets.len());
+    for secret in secrets {
+        values.push(Zeroizing::new(match key {
+            Some(key) => secret.decrypt_for(env, key)?,
+            None => format::MASK.to_string(),
+        }));
+    }
 
-    #[cfg(unix)]
-    let sub_dir = ".lazy-locker";
-    #[cfg(not(unix))]
-    let sub_dir = "lazy-locker";
+    let entries: Vec<(&str, &str)> = secrets
+        .iter()
+        .zip(&values)
+        .map(|(secret, value)| (export_name(&secret.name, strip_prefix), value.as_str()))
+        .collect();
+    let output = Zeroizing::new(render(&entries)?);
+    print!("{}", *output);
+    Ok(())
+}
 
-    let locker_dir = config_dir.join(sub_dir);
-    std::fs::create_dir_all(&locker_dir)?;
+fn get_locker_dir() -> Result<PathBuf> {
+    let locker_dir = Config::get_locker_dir()?;
+    std::fs::create_dir_all(&locker_dir).map_err(|e| write_error(&locker_dir, e))?;
 
     Ok(locker_dir)
 }
@@ -341,59 +2494,133 @@ fn read_value_from_stdin() -> Result<String> {
     Ok(value)
 }
 
-fn parse_env_format(content: &str) -> Result<HashMap<String, String>> {
-    let mut secrets = HashMap::new();
+fn parse_env_format(content: &str) -> Result<BTreeMap<String, String>> {
+    let mut secrets = BTreeMap::new();
+    let mut lines = content.lines().enumerate();
 
-    for line in content.lines() {
-        let line = line.trim();
+    while let Some((index, line)) = lines.next() {
+        let line = line.trim_start();
 
         // Skip empty lines and comments
-        if line.is_empty() || line.starts_with('#') {
+        if line.trim_end().is_empty() || line.starts_with('#') {
             continue;
         }
 
-        // Parse KEY=VALUE or KEY="VALUE" or KEY='VALUE'
-        if let Some(eq_pos) = line.find('=') {
-            let key = line[..eq_pos].trim().to_string();
-            let mut value = line[eq_pos + 1..].trim().to_string();
-
-            // Remove surrounding quotes
-            if (value.starts_with('"') && value.ends_with('"'))
-                || (value.starts_with('\'') && value.ends_with('\''))
-            {
-                value = value[1..value.len() - 1].to_string();
+        // Parse [export ]KEY=VALUE or KEY="VALUE" or KEY='VALUE'
+        let Some(eq_pos) = line.find('=') else {
+            continue;
+        };
+        let key = line[..eq_pos].trim();
+        let key = key
+            .strip_prefix("export")
+            .filter(|rest| rest.starts_with(char::is_whitespace))
+            .map_or(key, str::trim_start);
+        let mut value = line[eq_pos + 1..].trim_start().to_string();
+
+        // A quoted value runs to its closing quote, possibly on a later line.
+        // Double quotes take the escapes written by `format::to_env` plus `\t`;
+        // single quotes are literal, as in dotenv.
+        if let Some(quote) = value.chars().next().filter(|c| matches!(c, '"' | '\'')) {
+            let end = loop {
+                if let Some(end) = closing_quote(&value, quote) {
+                    break end;
+                }
+                let Some((_, next)) = lines.next() else {
+                    anyhow::bail!(
+                        "Line {}: no closing {} for the value of {}",
+                        index + 1,
+                        quote,
+                        key
+                    );
+                };
+                value.push('\n');
+                value.push_str(next);
+            };
+            let rest = value[end + 1..].trim();
+            if !rest.is_empty() && !rest.starts_with('#') {
+                anyhow::bail!(
+                    "Line {}: unexpected text after the value of {}",
+                    index + 1,
+                    key
+                );
             }
+            value = if quote == '"' {
+                unescape_double_quoted(&value[1..end])
+            } else {
+                value[1..end].to_string()
+            };
+        } else {
+            value.truncate(value.trim_end().len());
+        }
 
-            if !key.is_empty() {
-                secrets.insert(key, value);
-            }
+        if !key.is_empty() {
+            secrets.insert(key.to_string(), value);
         }
     }
 
     Ok(secrets)
 }
 
-fn parse_json_format(content: &str) -> Result<HashMap<String, String>> {
-    // Support both object format and array format
+/// Byte index of the quote closing a value that opens with `quote`.
+/// Backslashes escape characters inside double quotes only.
+fn closing_quote(value: &str, quote: char) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in value.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quote == '"' => escaped = true,
+            c if c == quote => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Undoes the `\\`, `\"`, `\n` and `\t` escapes of a double-quoted `.env` value
+fn unescape_double_quoted(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some(escaped @ ('\\' | '"')) => out.push(escaped),
+            Some(other) => {
+                out.push('\\');
+                out.push(other);
+            }
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+fn parse_json_format(content: &str) -> Result<Vec<PlainSecret>> {
+    // Support both object format and array format (with optional metadata)
     let json: serde_json::Value = serde_json::from_str(content)?;
-    let mut secrets = HashMap::new();
+    let mut secrets = Vec::new();
 
     match json {
         serde_json::Value::Object(obj) => {
             for (key, value) in obj {
                 if let Some(v) = value.as_str() {
-                    secrets.insert(key, v.to_string());
+                    secrets.push(PlainSecret::new(key, v.to_string()));
                 }
             }
         }
         serde_json::Value::Array(arr) => {
-            for item in arr {
-                if let (Some(name), Some(value)) = (
-                    item.get("name").and_then(|v| v.as_str()),
-                    item.get("value").and_then(|v| v.as_str()),
-                ) {
-                    secrets.insert(name.to_string(), value.to_string());
-                }
+            for (index, item) in arr.into_iter().enumerate() {
+                let label = match item.get("name").and_then(|v| v.as_str()) {
+                    Some(name) => format!("'{}'", name),
+                    None => format!("#{}", index + 1),
+                };
+                let secret = serde_json::from_value::<PlainSecret>(item)
+                    .with_context(|| format!("Invalid JSON entry {}", label))?;
+                secrets.push(secret);
             }
         }
         _ => anyhow::bail!("JSON must be an object or array"),
@@ -402,10 +2629,594 @@ fn parse_json_format(content: &str) -> Result<HashMap<String, String>> {
     Ok(secrets)
 }
 
+/// Reads a flat `NAME: value` mapping, or a list of `{name, value}` mappings
+/// carrying the same metadata as the JSON array form. Scalars such as `5432`
+/// or `true` are imported as written; nested values are rejected.
+fn parse_yaml_format(content: &str) -> Result<Vec<PlainSecret>> {
+    fn string_value(name: &str, node: yaml::Node) -> Result<String> {
+        match node {
+            yaml::Node::Scalar(value) => Ok(value),
+            yaml::Node::Null => {
+                anyhow::bail!("{} has no value (write \"\" for an empty one)", name)
+            }
+            yaml::Node::Map(_) | yaml::Node::List(_) => {
+                anyhow::bail!("{}: nested values are not supported, use a string", name)
+            }
+        }
+    }
+
+    let mut secrets = Vec::new();
+    match yaml::parse(content)? {
+        yaml::Node::Null => {}
+        yaml::Node::Map(entries) => {
+            for (name, node) in entries {
+                let value = string_value(&name, node)?;
+                secrets.push(PlainSecret::new(name, value));
+            }
+        }
+        yaml::Node::List(items) => {
+            for (index, item) in items.into_iter().enumerate() {
+                let yaml::Node::Map(fields) = item else {
+                    anyhow::bail!("Item {} must be a mapping with name and value", index + 1);
+                };
+                let mut secret = PlainSecret::new(String::new(), String::new());
+                let mut has_value = false;
+                for (field, node) in fields {
+                    match field.as_str() {
+                        "name" => secret.name = string_value("name", node)?,
+                        "value" => {
+                            secret.value = string_value("value", node)?;
+                            has_value = true;
+                        }
+                        "description" if node != yaml::Node::Null => {
+                            secret.description = Some(string_value("description", node)?);
+                        }
+                        "expires_at" if node != yaml::Node::Null => {
+                            let expires_at = string_value("expires_at", node)?;
+                            secret.expires_at = Some(expires_at.parse().map_err(|_| {
+                                anyhow::anyhow!("Invalid expires_at: {}", expires_at)
+                            })?);
+                        }
+                        "tags" => match node {
+                            yaml::Node::List(tags) => {
+                                for tag in tags {
+                                    secret.tags.push(string_value("tags", tag)?);
+                                }
+                            }
+                            yaml::Node::Null => {}
+                            _ => anyhow::bail!("tags must be a list"),
+                        },
+                        _ => {}
+                    }
+                }
+                if secret.name.is_empty() || !has_value {
+                    anyhow::bail!("Item {} must be a mapping with name and value", index + 1);
+                }
+                secrets.push(secret);
+            }
+        }
+        yaml::Node::Scalar(_) => anyhow::bail!("YAML must be a mapping or a list"),
+    }
+
+    Ok(secrets)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_token_status_exit_codes() {
+        let temp_dir = tempfile::TempDir::new().expect("Failed to create temp dir");
+        let key = [0x42u8; 32];
+        let mut store = SecretsStore::new();
+        store
+            .add_many(
+                &[
+                    PlainSecret::new("LIVE".to_string(), "v".to_string()),
+                    PlainSecret::new("OLD".to_string(), "v".to_string()),
+                ],
+                temp_dir.path(),
+                &key,
+            )
+            .unwrap();
+        store.secrets.get_mut("OLD").unwrap().expires_at = Some(0);
+
+        assert!(token_status(&store, "LIVE").is_ok());
+        assert_eq!(token_status(&store, "OLD").unwrap_err().exit_code(), 2);
+        assert_eq!(token_status(&store, "MISSING").unwrap_err().exit_code(), 1);
+    }
+
+    #[test]
+    fn test_check_secrets_counts_corrupted_entries() {
+        let temp_dir = tempfile::TempDir::new().expect("Failed to create temp dir");
+        let key = [0x42u8; 32];
+        let mut store = SecretsStore::new();
+        store
+            .add_many(
+                &[
+                    PlainSecret::new("GOOD".to_string(), "v".to_string()),
+                    PlainSecret::new("BROKEN".to_string(), "v".to_string()),
+                ],
+                temp_dir.path(),
+                &key,
+            )
+            .unwrap();
+
+        assert_eq!(check_secrets(&store, &key), (0, 0));
+
+        let broken = store.secrets.get_mut("BROKEN").unwrap();
+        broken.encrypted_value[1] ^= 0xFF;
+        broken.updated_at = broken.created_at - 1;
+
+        assert_eq!(check_secrets(&store, &key), (1, 1));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_loose_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+        let temp_dir = tempfile::TempDir::new().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("hash");
+        std::fs::write(&path, "x").unwrap();
+
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)).unwrap();
+        assert_eq!(loose_permissions(&path), None);
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o640)).unwrap();
+        assert_eq!(loose_permissions(&path), Some(0o640));
+        assert_eq!(loose_permissions(&temp_dir.path().join("missing")), None);
+    }
+
+    #[test]
+    fn test_note_value_and_truncation() {
+        assert_eq!(note_value("  deploy key "), Some("deploy key".to_string()));
+        assert_eq!(note_value(""), None);
+        assert_eq!(format::truncate_chars("short", 10), "short");
+        assert_eq!(format::truncate_chars("clé de déploiement", 6), "clé d…");
+    }
+
+    fn listed(name: &str, expires_in_days: Option<i64>) -> Secret {
+        Secret {
+            name: name.to_string(),
+            expires_at: expires_in_days.map(|days| now_timestamp() + days * 86400 + 3600),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_token_table_name_column_fits_longest_name() {
+        let long = "A_RATHER_LONG_TOKEN_NAME_FOR_THE_API";
+        let short = listed("KEY", None);
+        let long_secret = listed(long, None);
+        let table = token_table(&[&short, &long_secret], 7, false);
+        let lines: Vec<&str> = table.lines().collect();
+
+        let expires_column = lines[0].find("EXPIRES").unwrap();
+        assert_eq!(expires_column, long.len() + 1);
+        assert_eq!(lines[2].find("∞"), Some(expires_column));
+        assert!(lines[3].starts_with(long));
+
+        let alone = token_table(&[&short], 7, false);
+        assert!(alone.starts_with("NAME EXPIRES"));
+
+        let huge = listed(&"X".repeat(80), None);
+        let cut = token_table(&[&huge], 7, false);
+        let row = cut.lines().nth(2).unwrap();
+        assert!(row.starts_with(&format!("{}…", "X".repeat(NAME_COLUMN_MAX_WIDTH - 1))));
+    }
+
+    #[test]
+    fn test_token_table_colors_expired_and_expiring_rows() {
+        let expired = listed("OLD", Some(-3));
+        let soon = listed("SOON", Some(2));
+        let later = listed("LATER", Some(60));
+        let secrets = [&expired, &soon, &later];
+
+        let colored = token_table(&secrets, 7, true);
+        let rows: Vec<&str> = colored.lines().skip(2).collect();
+        assert!(rows[0].starts_with(ANSI_RED) && rows[0].ends_with(ANSI_RESET));
+        assert!(rows[1].starts_with(ANSI_YELLOW));
+        assert!(!rows[2].contains('\x1b'));
+
+        assert!(!token_table(&secrets, 7, false).contains('\x1b'));
+        // warn_expiring_days = 0 leaves only expired rows colored
+        assert!(!token_table(&[&soon], 0, true).contains('\x1b'));
+    }
+
+    #[test]
+    fn test_exit_code_classifies_errors() {
+        let usage: anyhow::Error =
+            CliError::Usage("Usage: lazy-locker token get <NAME>".into()).into();
+        let wrapped = anyhow::Error::new(IncorrectPassphrase).context("Failed to open locker");
+
+        assert_eq!(exit_code(&usage), 4);
+        assert_eq!(exit_code(&wrapped), 3);
+        assert_eq!(exit_code(&StoreKeyMismatch.into()), 3);
+        assert_eq!(exit_code(&anyhow::anyhow!("disk full")), 1);
+    }
+
+    #[test]
+    fn test_error_json_carries_stable_code() {
+        let wrapped = anyhow::Error::new(IncorrectPassphrase).context("Failed to open locker");
+
+        assert_eq!(
+            error_json(&CliError::token_not_found("API_KEY").into()),
+            serde_json::json!({
+                "error": {"code": "not_found", "message": "Token 'API_KEY' not found"}
+            })
+        );
+        assert_eq!(error_json(&wrapped)["error"]["code"], "auth");
+        assert_eq!(
+            error_json(&anyhow::anyhow!("disk full"))["error"]["code"],
+            "error"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_edit_in_editor_with_fake_editor() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = tempfile::TempDir::new().expect("Failed to create temp dir");
+        let seen = temp_dir.path().join("seen");
+        let script = |name: &str, body: &str| {
+            let path = temp_dir.path().join(name);
+            std::fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o700)).unwrap();
+            path.display().to_string()
+        };
+        // Records the temp file path and its mode, then rewrites it like `vi` would
+        let rewrite = script(
+            "rewrite",
+            &format!(
+                "(stat -c %a \"$1\" || stat -f %Lp \"$1\") > {0}; echo \"$1\" >> {0}; printf 'new-value\\n' > \"$1\"",
+                seen.display()
+            ),
+        );
+
+        assert_eq!(
+            edit_in_editor("old-value", &rewrite).unwrap().as_deref(),
+            Some("new-value")
+        );
+        let seen = std::fs::read_to_string(&seen).unwrap();
+        let mut lines = seen.lines();
+        assert_eq!(lines.next(), Some("600"));
+        assert!(!std::path::Path::new(lines.next().unwrap()).exists());
+
+        assert_eq!(edit_in_editor("old-value", "true").unwrap(), None);
+        assert!(edit_in_editor("old-value", &script("fail", "exit 1")).is_err());
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("PROJ_*", "PROJ_API_KEY"));
+        assert!(glob_match("PROJ_*", "PROJ_"));
+        assert!(!glob_match("PROJ_*", "OTHER_PROJ_KEY"));
+        assert!(glob_match("*_KEY", "PROJ_API_KEY"));
+        assert!(glob_match("*API*", "PROJ_API_KEY"));
+        assert!(glob_match("DB_?", "DB_1"));
+        assert!(!glob_match("DB_?", "DB_12"));
+        assert!(glob_match("A*B*C", "AxxBxxBxC"));
+        assert!(!glob_match("A*B*C", "AxxBxx"));
+        assert!(glob_match("EXACT", "EXACT"));
+        assert!(!glob_match("EXACT", "EXACTLY"));
+    }
+
+    #[test]
+    fn test_removal_confirmed_guards_bulk_deletes() {
+        let never_asked = || -> Result<bool> { panic!("should not prompt") };
+        assert!(removal_confirmed(1, false, never_asked).unwrap());
+        assert!(removal_confirmed(3, true, never_asked).unwrap());
+
+        assert!(!removal_confirmed(3, false, || Ok(false)).unwrap());
+        assert!(removal_confirmed(3, false, || Ok(true)).unwrap());
+        assert!(removal_confirmed(3, false, || anyhow::bail!("no terminal")).is_err());
+    }
+
+    #[test]
+    fn test_overwrite_confirmed_needs_path_or_yes() {
+        let dir = Path::new("/home/me/.config/.lazy-locker");
+        let never_asked = |_: &Path| -> Result<String> { panic!("should not prompt") };
+        assert!(overwrite_confirmed(dir, true, never_asked).unwrap());
+
+        let answer = |text: &'static str| move |_: &Path| Ok(text.to_string());
+        assert!(overwrite_confirmed(dir, false, answer("yes\n")).unwrap());
+        assert!(
+            overwrite_confirmed(dir, false, answer("/home/me/.config/.lazy-locker\n")).unwrap()
+        );
+        assert!(!overwrite_confirmed(dir, false, answer("y\n")).unwrap());
+        assert!(!overwrite_confirmed(dir, false, answer("\n")).unwrap());
+        assert!(!overwrite_confirmed(dir, false, answer("/home/me")).unwrap());
+    }
+
+    #[test]
+    fn test_secret_filter_selects_names_and_tags() {
+        let temp_dir = tempfile::TempDir::new().expect("Failed to create temp dir");
+        let key = [0x42u8; 32];
+        let mut store = SecretsStore::new();
+        let mut ci = PlainSecret::new("CI_TOKEN".to_string(), "ghp".to_string());
+        ci.tags = vec!["ci".to_string()];
+        let plains = vec![
+            PlainSecret::new("API_KEY".to_string(), "sk".to_string()),
+            PlainSecret::new("DB_URL".to_string(), "pg".to_string()),
+            ci,
+        ];
+        store.add_many(&plains, temp_dir.path(), &key).unwrap();
+
+        let names = |filter: SecretFilter| -> Vec<String> {
+            filter
+                .select(&store)
+                .unwrap()
+                .iter()
+                .map(|s| s.name.clone())
+                .collect()
+        };
+
+        assert_eq!(names(SecretFilter::default()).len(), 3);
+        assert_eq!(
+            names(SecretFilter::from_args(Some("DB_URL, API_KEY"), None, &[])),
+            vec!["API_KEY", "DB_URL"]
+        );
+        assert_eq!(
+            names(SecretFilter::from_args(None, None, &["CI".to_string()])),
+            vec!["CI_TOKEN"]
+        );
+        assert!(
+            SecretFilter::from_args(Some("API_KEY,MISSING"), None, &[])
+                .select(&store)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_secret_filter_except_and_agent_values() {
+        let filter = SecretFilter::from_args(None, Some("DB_URL"), &[]);
+        let mut values: BTreeMap<String, String> = ["API_KEY", "DB_URL", "CI_TOKEN"]
+            .iter()
+            .map(|n| (n.to_string(), "v".to_string()))
+            .collect();
+
+        filter.retain_values(&mut values).unwrap();
+
+        let mut names: Vec<_> = values.keys().cloned().collect();
+        names.sort();
+        assert_eq!(names, vec!["API_KEY", "CI_TOKEN"]);
+        assert!(
+            SecretFilter::from_args(Some("MISSING"), None, &[])
+                .retain_values(&mut values)
+                .is_err()
+        );
+        let only = SecretFilter::from_args(Some("API_KEY,CI_TOKEN"), Some("CI_TOKEN"), &[]);
+        only.retain_values(&mut values).unwrap();
+        assert_eq!(values.keys().collect::<Vec<_>>(), vec!["API_KEY"]);
+    }
+
+    #[test]
+    fn test_secret_filter_requires_every_tag() {
+        let temp_dir = tempfile::TempDir::new().expect("Failed to create temp dir");
+        let key = [0x42u8; 32];
+        let mut store = SecretsStore::new();
+        let tagged = |name: &str, tags: &[&str]| {
+            let mut plain = PlainSecret::new(name.to_string(), "v".to_string());
+            plain.tags = tags.iter().map(|t| t.to_string()).collect();
+            plain
+        };
+        let plains = vec![
+            tagged("API_DB", &["backend", "db"]),
+            tagged("API_KEY", &["Backend"]),
+            tagged("UNTAGGED", &[]),
+            tagged("WEB_KEY", &["frontend"]),
+        ];
+        store.add_many(&plains, temp_dir.path(), &key).unwrap();
+
+        let names = |tags: &[&str]| -> Vec<String> {
+            let tags: Vec<String> = tags.iter().map(|t| t.to_string()).collect();
+            SecretFilter::from_args(None, None, &tags)
+                .select(&store)
+                .unwrap()
+                .iter()
+                .map(|s| s.name.clone())
+                .collect()
+        };
+
+        assert_eq!(names(&["backend"]), vec!["API_DB", "API_KEY"]);
+        assert_eq!(names(&["BACKEND", "db"]), vec!["API_DB"]);
+        assert!(names(&["backend", "frontend"]).is_empty());
+        assert!(!names(&["db"]).contains(&"UNTAGGED".to_string()));
+    }
+
+    #[test]
+    fn test_parse_env_format_reads_back_to_env() {
+        let value = "say \"hi\"\nC:\\tmp";
+        let rendered = format::to_env(&[("MOTD", value)]);
+
+        let secrets = parse_env_format(&rendered).unwrap();
+
+        assert_eq!(secrets.get("MOTD").map(String::as_str), Some(value));
+    }
+
+    #[test]
+    fn test_confirm_passphrase() {
+        assert_eq!(
+            confirm_passphrase("secret".to_string(), "secret".to_string()).unwrap(),
+            "secret"
+        );
+        assert!(confirm_passphrase("secret".to_string(), "secrte".to_string()).is_err());
+        assert!(confirm_passphrase(String::new(), String::new()).is_err());
+    }
+
+    #[test]
+    fn test_read_passphrase_line_takes_the_first_line() {
+        let read = |input: &str| read_passphrase_line(io::Cursor::new(input), "stdin");
+        assert_eq!(read("s3cret pass\r\nnext line\n").unwrap(), "s3cret pass");
+        assert_eq!(read("no newline").unwrap(), "no newline");
+        let empty = read("\n").unwrap_err();
+        assert_eq!(exit_code(&empty), 3);
+        assert!(read("").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_get_passphrase_reads_file_descriptor_after_argument() {
+        use std::os::fd::AsRawFd;
+
+        let mut file = tempfile::tempfile().unwrap();
+        std::io::Write::write_all(&mut file, b"from-fd\n").unwrap();
+        std::io::Seek::rewind(&mut file).unwrap();
+        let fd = file.as_raw_fd();
+
+        // An explicit argument wins without touching the descriptor
+        assert_eq!(
+            get_passphrase(Some("from-arg"), PassphraseInput::Fd(fd)).unwrap(),
+            "from-arg"
+        );
+        assert_eq!(
+            get_passphrase(None, PassphraseInput::Fd(fd)).unwrap(),
+            "from-fd"
+        );
+        // The descriptor stays open for its owner
+        assert!(std::io::Seek::rewind(&mut file).is_ok());
+
+        let closed = get_passphrase(None, PassphraseInput::Fd(987_654)).unwrap_err();
+        assert_eq!(exit_code(&closed), 4);
+    }
+
+    #[test]
+    fn test_parse_expires() {
+        assert_eq!(parse_expires("30").unwrap(), Some(30));
+        assert_eq!(parse_expires("never").unwrap(), None);
+        assert_eq!(parse_expires("0").unwrap(), None);
+        assert!(parse_expires("30x").is_err());
+        assert!(parse_expires("-1").is_err());
+        assert!(parse_expires("").is_err());
+    }
+
+    #[test]
+    fn test_default_expiration_applies_unless_explicit() {
+        let temp_dir = tempfile::TempDir::new().expect("Failed to create temp dir");
+        std::fs::write(
+            temp_dir.path().join("config.toml"),
+            "[store]\ndefault_expiration_days = 90\n",
+        )
+        .unwrap();
+        let dir = temp_dir.path();
+
+        assert_eq!(
+            Expiration::or_default(None, dir),
+            Some(Expiration::Days(90))
+        );
+        let explicit = Some(Expiration::Days(7));
+        assert_eq!(Expiration::or_default(explicit, dir), explicit);
+        let never = Some(Expiration::Never);
+        assert_eq!(Expiration::or_default(never, dir), never);
+        assert_eq!(Expiration::Never.timestamp().unwrap(), None);
+
+        let mut carried = PlainSecret::new("CARRIED".to_string(), "v".to_string());
+        carried.expires_at = Some(42);
+        let mut secrets = vec![
+            PlainSecret::new("NEW".to_string(), "v".to_string()),
+            carried,
+        ];
+        apply_expiration(&mut secrets, None, dir).unwrap();
+        let in_90_days = expires_at_from_days(90).unwrap();
+        assert!((secrets[0].expires_at.unwrap() - in_90_days).abs() < 5);
+        assert_eq!(secrets[1].expires_at, Some(42));
+
+        apply_expiration(&mut secrets, never, dir).unwrap();
+        assert!(secrets.iter().all(|s| s.expires_at.is_none()));
+    }
+
+    #[test]
+    fn test_token_add_request_parses_full_object() {
+        let request = TokenAddRequest::parse(
+            r#"{"name": "API_KEY", "value": "it's \"quoted\"", "expires": 30,
+                "note": "CI deploy key", "tags": ["ci", "backend"]}"#,
+        )
+        .unwrap();
+        assert_eq!(request.name, "API_KEY");
+        assert_eq!(request.value, "it's \"quoted\"");
+        assert_eq!(request.note.as_deref(), Some("CI deploy key"));
+        assert_eq!(request.tags, vec!["ci", "backend"]);
+        assert_eq!(request.expiration().unwrap(), Some(Expiration::Days(30)));
+
+        let minimal = TokenAddRequest::parse(r#"{"name": "A", "value": "v"}"#).unwrap();
+        assert_eq!(minimal.expiration().unwrap(), None);
+        assert!(minimal.note.is_none() && minimal.tags.is_empty());
+
+        let expiration = |expires: &str| {
+            TokenAddRequest::parse(&format!(
+                r#"{{"name": "A", "value": "v", "expires": {expires}}}"#
+            ))
+            .unwrap()
+            .expiration()
+        };
+        assert_eq!(expiration(r#""never""#).unwrap(), Some(Expiration::Never));
+        assert_eq!(expiration("0").unwrap(), Some(Expiration::Never));
+        assert!(matches!(
+            expiration(r#""2099-12-31""#).unwrap(),
+            Some(Expiration::At(_))
+        ));
+        assert!(expiration("true").is_err());
+    }
+
+    #[test]
+    fn test_token_add_request_rejects_arrays_and_missing_fields() {
+        let array = TokenAddRequest::parse(r#"[{"name": "A", "value": "v"}]"#).unwrap_err();
+        assert_eq!(exit_code(&array), 4);
+        assert!(TokenAddRequest::parse(r#"{"name": "A"}"#).is_err());
+        assert!(TokenAddRequest::parse(r#"{"value": "v"}"#).is_err());
+        assert!(TokenAddRequest::parse(r#"{"name": "A", "value": "v", "expirs": 3}"#).is_err());
+        assert!(TokenAddRequest::parse("not json").is_err());
+    }
+
+    #[test]
+    fn test_parse_env_format_strips_export() {
+        let content = "export API_KEY=sk-123\nexport  DB_URL=\"postgres://db\"\nexporter=kept\n";
+
+        let secrets = parse_env_format(content).unwrap();
+
+        assert_eq!(secrets.get("API_KEY").map(String::as_str), Some("sk-123"));
+        assert_eq!(
+            secrets.get("DB_URL").map(String::as_str),
+            Some("postgres://db")
+        );
+        assert_eq!(secrets.get("exporter").map(String::as_str), Some("kept"));
+    }
+
+    #[test]
+    fn test_parse_env_format_multiline_double_quoted() {
+        let content = "\
+PEM=\"-----BEGIN KEY-----
+  abc=
+-----END KEY-----\" # deploy key
+ESCAPED=\"a\\nb\\tc\"
+NEXT=1
+";
+
+        let secrets = parse_env_format(content).unwrap();
+
+        assert_eq!(
+            secrets.get("PEM").map(String::as_str),
+            Some("-----BEGIN KEY-----\n  abc=\n-----END KEY-----")
+        );
+        assert_eq!(secrets.get("ESCAPED").map(String::as_str), Some("a\nb\tc"));
+        assert_eq!(secrets.get("NEXT").map(String::as_str), Some("1"));
+    }
+
+    #[test]
+    fn test_parse_env_format_single_quotes_are_literal() {
+        let content = "PATH_WIN='C:\\new\\tmp'\nLINES='one\ntwo'\n";
+
+        let secrets = parse_env_format(content).unwrap();
+
+        assert_eq!(
+            secrets.get("PATH_WIN").map(String::as_str),
+            Some("C:\\new\\tmp")
+        );
+        assert_eq!(secrets.get("LINES").map(String::as_str), Some("one\ntwo"));
+        assert!(parse_env_format("A=\"never closed\nB=2\n").is_err());
+    }
+
     #[test]
     fn test_parse_env_format() {
         let content = r#"
@@ -433,17 +3244,22 @@ SPACES = value with spaces
         );
     }
 
+    /// Finds a parsed secret value by name
+    fn value_of<'a>(secrets: &'a [PlainSecret], name: &str) -> Option<&'a str> {
+        secrets
+            .iter()
+            .find(|s| s.name == name)
+            .map(|s| s.value.as_str())
+    }
+
     #[test]
     fn test_parse_json_object_format() {
         let content = r#"{"API_KEY": "sk-123", "DB_URL": "postgres://localhost"}"#;
 
         let secrets = parse_json_format(content).unwrap();
 
-        assert_eq!(secrets.get("API_KEY"), Some(&"sk-123".to_string()));
-        assert_eq!(
-            secrets.get("DB_URL"),
-            Some(&"postgres://localhost".to_string())
-        );
+        assert_eq!(value_of(&secrets, "API_KEY"), Some("sk-123"));
+        assert_eq!(value_of(&secrets, "DB_URL"), Some("postgres://localhost"));
     }
 
     #[test]
@@ -455,13 +3271,173 @@ SPACES = value with spaces
 
         let secrets = parse_json_format(content).unwrap();
 
-        assert_eq!(secrets.get("API_KEY"), Some(&"sk-123".to_string()));
+        assert_eq!(value_of(&secrets, "API_KEY"), Some("sk-123"));
+        assert_eq!(value_of(&secrets, "DB_URL"), Some("postgres://localhost"));
+    }
+
+    #[test]
+    fn test_parse_json_array_with_metadata() {
+        let content = r#"[
+            {
+                "name": "DEPLOY_KEY",
+                "value": "ghp_123",
+                "expires_at": 4102444800,
+                "tags": ["ci", "github"],
+                "description": "GitHub Actions deploy key"
+            },
+            {"name": "PLAIN", "value": "v", "expires_at": null}
+        ]"#;
+
+        let secrets = parse_json_format(content).unwrap();
+
+        assert_eq!(
+            secrets[0],
+            PlainSecret {
+                name: "DEPLOY_KEY".to_string(),
+                value: "ghp_123".to_string(),
+                expires_at: Some(4_102_444_800),
+                tags: vec!["ci".to_string(), "github".to_string()],
+                description: Some("GitHub Actions deploy key".to_string()),
+            }
+        );
+        assert_eq!(secrets[1].expires_at, None);
+        assert!(secrets[1].tags.is_empty());
+        assert!(secrets[1].description.is_none());
+    }
+
+    #[test]
+    fn test_parse_json_array_rejects_malformed_entries() {
+        let cases = [
+            (
+                r#"[{"name": "A", "value": "1", "expires_at": "2030-01-01"}]"#,
+                "'A'",
+            ),
+            (r#"[{"name": "A", "value": "1", "tags": ["ci", 3]}]"#, "'A'"),
+            (r#"[{"name": "A", "value": "1"}, {"value": "2"}]"#, "#2"),
+            (r#"[{"name": "B"}]"#, "'B'"),
+        ];
+
+        for (content, label) in cases {
+            let err = parse_json_format(content).unwrap_err();
+            assert_eq!(err.to_string(), format!("Invalid JSON entry {}", label));
+        }
+    }
+
+    #[test]
+    fn test_parse_yaml_mapping_format() {
+        let content =
+            "API_KEY: sk-123\nDB_URL: \"postgres://localhost\"\nPORT: 5432\nDEBUG: true\n";
+
+        let secrets = parse_yaml_format(content).unwrap();
+
+        assert_eq!(value_of(&secrets, "API_KEY"), Some("sk-123"));
+        assert_eq!(value_of(&secrets, "DB_URL"), Some("postgres://localhost"));
+        assert_eq!(value_of(&secrets, "PORT"), Some("5432"));
+        assert_eq!(value_of(&secrets, "DEBUG"), Some("true"));
+    }
+
+    #[test]
+    fn test_parse_yaml_list_with_metadata() {
+        let content = "\
+- name: DEPLOY_KEY
+  value: ghp_123
+  expires_at: 4102444800
+  tags: [ci, github]
+  description: GitHub Actions deploy key
+- name: PLAIN
+  value: v
+";
+
+        let secrets = parse_yaml_format(content).unwrap();
+
+        assert_eq!(
+            secrets[0],
+            PlainSecret {
+                name: "DEPLOY_KEY".to_string(),
+                value: "ghp_123".to_string(),
+                expires_at: Some(4_102_444_800),
+                tags: vec!["ci".to_string(), "github".to_string()],
+                description: Some("GitHub Actions deploy key".to_string()),
+            }
+        );
+        assert_eq!(value_of(&secrets, "PLAIN"), Some("v"));
+    }
+
+    #[test]
+    fn test_parse_yaml_rejects_nested_and_missing_values() {
+        let nested = parse_yaml_format("DB:\n  USER: admin\n").unwrap_err();
+        assert!(nested.to_string().contains("nested"));
+        let list = parse_yaml_format("HOSTS: [a, b]\n").unwrap_err();
+        assert!(list.to_string().contains("nested"));
+        let null = parse_yaml_format("EMPTY:\n").unwrap_err();
+        assert!(null.to_string().contains("no value"));
+        assert!(parse_yaml_format("- name: NO_VALUE\n").is_err());
         assert_eq!(
-            secrets.get("DB_URL"),
-            Some(&"postgres://localhost".to_string())
+            value_of(&parse_yaml_format("EMPTY: \"\"\n").unwrap(), "EMPTY"),
+            Some("")
         );
     }
 
+    #[test]
+    fn test_yaml_export_roundtrips_through_import() {
+        let entries = [
+            ("API_KEY", "sk-123"),
+            ("MOTD", "say \"hi\" # not a comment\n\tC:\\tmp"),
+        ];
+
+        let secrets = parse_yaml_format(&format::to_yaml(&entries).unwrap()).unwrap();
+
+        for (name, value) in entries {
+            assert_eq!(value_of(&secrets, name), Some(value));
+        }
+    }
+
+    #[test]
+    fn test_import_prefix_renames_and_validates() {
+        let mut secrets = vec![
+            PlainSecret::new("SECRET_KEY".to_string(), "sk".to_string()),
+            PlainSecret::new("PUBLISHABLE_KEY".to_string(), "pk".to_string()),
+        ];
+        add_name_prefix(&mut secrets, "STRIPE_").unwrap();
+        assert_eq!(value_of(&secrets, "STRIPE_SECRET_KEY"), Some("sk"));
+        assert_eq!(value_of(&secrets, "STRIPE_PUBLISHABLE_KEY"), Some("pk"));
+
+        let mut secrets = vec![PlainSecret::new("KEY".to_string(), "v".to_string())];
+        let err = add_name_prefix(&mut secrets, "1X-")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("'1X-KEY'"), "{err}");
+        assert!(err.contains("not a valid env variable name"), "{err}");
+    }
+
+    #[test]
+    fn test_export_name_strips_prefix_only_when_something_remains() {
+        assert_eq!(export_name("STRIPE_KEY", Some("STRIPE_")), "KEY");
+        assert_eq!(export_name("OTHER_KEY", Some("STRIPE_")), "OTHER_KEY");
+        assert_eq!(export_name("STRIPE_", Some("STRIPE_")), "STRIPE_");
+        assert_eq!(export_name("STRIPE_KEY", None), "STRIPE_KEY");
+
+        assert!(check_unique_names(["KEY", "OTHER"], Some("S_")).is_ok());
+        let err = check_unique_names(["KEY", "KEY"], Some("S_")).unwrap_err();
+        assert!(err.to_string().contains("'KEY'"));
+    }
+
+    #[test]
+    fn test_metadata_export_roundtrips_through_import() {
+        let original = vec![PlainSecret {
+            name: "API_KEY".to_string(),
+            value: "sk-123".to_string(),
+            expires_at: Some(1_700_000_000),
+            tags: vec!["backend".to_string()],
+            description: Some("Stripe".to_string()),
+        }];
+
+        let exported = serde_json::to_string_pretty(&original).unwrap();
+        let imported = parse_json_format(&exported).unwrap();
+
+        assert_eq!(imported, original);
+    }
+
     #[test]
     fn test_output_format_from_args() {
         assert_eq!(OutputFormat::from_args(false, false), OutputFormat::Human);
@@ -1,11 +1,122 @@
 //! Configuration module for lazy-locker
 //!
-//! Manages user configuration including analyzer settings.
+//! Manages user configuration including analyzer and crypto settings.
 //! Configuration is stored in `~/.config/.lazy-locker/config.toml`
-
+//!
+//! Also resolves the locker directory of the active profile: the default
+//! profile is `~/.config/.lazy-locker` itself, any other one lives in
+//! `~/.config/.lazy-locker/profiles/<NAME>` with its own secrets, config and agent.
+//! `--home` or `LAZY_LOCKER_HOME` moves that base directory elsewhere.
+
+use crate::core::crypto::CipherAlgo;
+use crate::core::init::write_error;
+use crate::core::usages::MatchMode;
 use anyhow::Result;
+use crossterm::event::KeyCode;
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Environment variable selecting the locker profile
+pub const PROFILE_ENV_VAR: &str = "LAZY_LOCKER_PROFILE";
+
+/// Environment variable relocating the base locker directory
+pub const HOME_ENV_VAR: &str = "LAZY_LOCKER_HOME";
+
+/// Base locker directory chosen with `--home` for this process
+static HOME: OnceLock<PathBuf> = OnceLock::new();
+
+/// Relocates the base locker directory for this process (the `--home` flag),
+/// taking priority over `LAZY_LOCKER_HOME`
+pub fn set_home(dir: &Path) -> Result<()> {
+    if dir.as_os_str().is_empty() {
+        anyhow::bail!("The locker home cannot be empty");
+    }
+    let dir = std::path::absolute(dir)?;
+    HOME.get_or_init(|| dir);
+    Ok(())
+}
+
+/// Base locker directory given by `--home`, then `LAZY_LOCKER_HOME`, if any.
+/// A relative path is resolved against the current directory.
+pub fn home_override() -> Result<Option<PathBuf>> {
+    if let Some(dir) = HOME.get() {
+        return Ok(Some(dir.clone()));
+    }
+    match std::env::var_os(HOME_ENV_VAR) {
+        Some(dir) if !dir.is_empty() => Ok(Some(std::path::absolute(dir)?)),
+        _ => Ok(None),
+    }
+}
+
+/// Profile stored directly in the base locker directory
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Profile chosen with `--profile` for this process
+static PROFILE: OnceLock<String> = OnceLock::new();
+
+/// Selects the profile for this process (the `--profile` flag), taking
+/// priority over `LAZY_LOCKER_PROFILE`
+pub fn set_profile(name: &str) -> Result<()> {
+    validate_profile_name(name)?;
+    PROFILE.get_or_init(|| name.to_string());
+    Ok(())
+}
+
+/// Active profile: `--profile`, then `LAZY_LOCKER_PROFILE`, then the default
+pub fn active_profile() -> Result<String> {
+    if let Some(name) = PROFILE.get() {
+        return Ok(name.clone());
+    }
+    match std::env::var(PROFILE_ENV_VAR) {
+        Ok(name) if !name.trim().is_empty() => {
+            let name = name.trim();
+            validate_profile_name(name)?;
+            Ok(name.to_string())
+        }
+        _ => Ok(DEFAULT_PROFILE.to_string()),
+    }
+}
+
+/// Profile names are used as directory names: letters, digits, `-` and `_`
+pub fn validate_profile_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name.len() <= 64
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        anyhow::bail!(
+            "Invalid profile name '{}': use letters, digits, '-' and '_'",
+            name
+        );
+    }
+    Ok(())
+}
+
+/// Locker directory of `profile` under the base locker directory
+pub fn profile_dir(base_dir: &Path, profile: &str) -> PathBuf {
+    if profile == DEFAULT_PROFILE {
+        base_dir.to_path_buf()
+    } else {
+        base_dir.join("profiles").join(profile)
+    }
+}
+
+/// Profiles found under the base locker directory, the default one first
+pub fn list_profiles(base_dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir(base_dir.join("profiles"))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| name != DEFAULT_PROFILE && validate_profile_name(name).is_ok())
+        .collect();
+    names.sort();
+    names.insert(0, DEFAULT_PROFILE.to_string());
+    names
+}
 
 /// Main configuration structure
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -13,6 +124,302 @@ use std::path::{Path, PathBuf};
 pub struct Config {
     /// Analyzer-specific settings
     pub analyzer: AnalyzerSettings,
+
+    /// Encryption settings
+    pub crypto: CryptoSettings,
+
+    /// Settings for stored secrets
+    pub store: StoreSettings,
+
+    /// Clipboard settings
+    pub clipboard: ClipboardSettings,
+
+    /// Revealed values in the TUI
+    pub reveal: RevealSettings,
+
+    /// Keys of the secrets list in the TUI
+    pub keybindings: Keybindings,
+
+    /// Commands run when the CLI changes secrets
+    pub hooks: HookSettings,
+}
+
+/// Actions of the secrets list that can be bound to keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyAction {
+    MoveUp,
+    MoveDown,
+    Add,
+    Delete,
+    Reveal,
+    Copy,
+    Command,
+    Sort,
+    TagFilter,
+    Help,
+    Quit,
+}
+
+/// Keys with a fixed meaning in the secrets list (rename, jump, search,
+/// TOTP code, clear filter), which cannot be bound to an action
+const RESERVED_KEYS: [KeyCode; 5] = [
+    KeyCode::Char('r'),
+    KeyCode::Char('g'),
+    KeyCode::Char('/'),
+    KeyCode::Char('o'),
+    KeyCode::Esc,
+];
+
+/// Keys bound to each action of the secrets list. Text inputs (passphrase,
+/// modals) always use their literal keys.
+///
+/// Keys are written by name in config.toml: a single character, or one of
+/// "up", "down", "left", "right", "enter", "tab", "backspace", "delete",
+/// "home", "end", "pageup", "pagedown", "space" and "f1" to "f12".
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Keybindings {
+    #[serde(with = "key_names")]
+    pub move_up: Vec<KeyCode>,
+    #[serde(with = "key_names")]
+    pub move_down: Vec<KeyCode>,
+    #[serde(with = "key_names")]
+    pub add: Vec<KeyCode>,
+    #[serde(with = "key_names")]
+    pub delete: Vec<KeyCode>,
+    #[serde(with = "key_names")]
+    pub reveal: Vec<KeyCode>,
+    #[serde(with = "key_names")]
+    pub copy: Vec<KeyCode>,
+    #[serde(with = "key_names")]
+    pub command: Vec<KeyCode>,
+    #[serde(with = "key_names")]
+    pub sort: Vec<KeyCode>,
+    #[serde(with = "key_names")]
+    pub tag_filter: Vec<KeyCode>,
+    #[serde(with = "key_names")]
+    pub help: Vec<KeyCode>,
+    #[serde(with = "key_names")]
+    pub quit: Vec<KeyCode>,
+}
+
+impl Default for Keybindings {
+    fn default() -> Self {
+        Self {
+            move_up: vec![KeyCode::Up],
+            move_down: vec![KeyCode::Down],
+            add: vec![KeyCode::Char('a')],
+            delete: vec![KeyCode::Char('d')],
+            reveal: vec![KeyCode::Char('e')],
+            copy: vec![KeyCode::Char('y')],
+            command: vec![KeyCode::Char(':')],
+            sort: vec![KeyCode::Char('s')],
+            tag_filter: vec![KeyCode::Char('t')],
+            help: vec![KeyCode::Char('h')],
+            quit: vec![KeyCode::Char('q')],
+        }
+    }
+}
+
+impl Keybindings {
+    fn bindings(&self) -> [(KeyAction, &[KeyCode]); 11] {
+        [
+            (KeyAction::MoveUp, &self.move_up),
+            (KeyAction::MoveDown, &self.move_down),
+            (KeyAction::Add, &self.add),
+            (KeyAction::Delete, &self.delete),
+            (KeyAction::Reveal, &self.reveal),
+            (KeyAction::Copy, &self.copy),
+            (KeyAction::Command, &self.command),
+            (KeyAction::Sort, &self.sort),
+            (KeyAction::TagFilter, &self.tag_filter),
+            (KeyAction::Help, &self.help),
+            (KeyAction::Quit, &self.quit),
+        ]
+    }
+
+    /// Action bound to `key`, if any
+    pub fn action(&self, key: KeyCode) -> Option<KeyAction> {
+        self.bindings()
+            .into_iter()
+            .find(|(_, keys)| keys.contains(&key))
+            .map(|(action, _)| action)
+    }
+
+    /// Rejects a key bound to two actions, or to a reserved key
+    pub fn validate(&self) -> Result<()> {
+        let mut seen: Vec<(KeyCode, KeyAction)> = Vec::new();
+        for (action, keys) in self.bindings() {
+            for &key in keys {
+                if RESERVED_KEYS.contains(&key) {
+                    anyhow::bail!(
+                        "Key '{}' of {:?} is reserved and cannot be rebound",
+                        key_name(key),
+                        action
+                    );
+                }
+                if let Some((_, other)) = seen.iter().find(|(k, _)| *k == key) {
+                    anyhow::bail!(
+                        "Key '{}' is bound to both {:?} and {:?}",
+                        key_name(key),
+                        other,
+                        action
+                    );
+                }
+                seen.push((key, action));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses a key name of config.toml (see `Keybindings`)
+pub fn parse_key(name: &str) -> Result<KeyCode> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Ok(KeyCode::Char(c));
+    }
+    let key = match name.to_lowercase().as_str() {
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "enter" => KeyCode::Enter,
+        "esc" => KeyCode::Esc,
+        "tab" => KeyCode::Tab,
+        "backspace" => KeyCode::Backspace,
+        "delete" => KeyCode::Delete,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "space" => KeyCode::Char(' '),
+        other => match other.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+            Some(n @ 1..=12) => KeyCode::F(n),
+            _ => anyhow::bail!("Unknown key '{}'", name),
+        },
+    };
+    Ok(key)
+}
+
+/// Name of `key` in config.toml, the inverse of `parse_key`
+pub fn key_name(key: KeyCode) -> String {
+    match key {
+        KeyCode::Char(' ') => "space".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::F(n) => format!("f{}", n),
+        KeyCode::Up => "up".to_string(),
+        KeyCode::Down => "down".to_string(),
+        KeyCode::Left => "left".to_string(),
+        KeyCode::Right => "right".to_string(),
+        KeyCode::Enter => "enter".to_string(),
+        KeyCode::Esc => "esc".to_string(),
+        KeyCode::Tab => "tab".to_string(),
+        KeyCode::Backspace => "backspace".to_string(),
+        KeyCode::Delete => "delete".to_string(),
+        KeyCode::Home => "home".to_string(),
+        KeyCode::End => "end".to_string(),
+        KeyCode::PageUp => "pageup".to_string(),
+        KeyCode::PageDown => "pagedown".to_string(),
+        other => format!("{:?}", other).to_lowercase(),
+    }
+}
+
+/// Serde helpers storing key lists by name
+mod key_names {
+    use super::{key_name, parse_key};
+    use crossterm::event::KeyCode;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(keys: &[KeyCode], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(keys.iter().map(|&key| key_name(key)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<KeyCode>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|name| parse_key(name).map_err(serde::de::Error::custom))
+            .collect()
+    }
+}
+
+/// Settings for values copied to the clipboard
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClipboardSettings {
+    /// Seconds before a copied secret is cleared from the clipboard (0 = never)
+    pub clear_after_secs: u64,
+}
+
+impl Default for ClipboardSettings {
+    fn default() -> Self {
+        Self {
+            clear_after_secs: 30,
+        }
+    }
+}
+
+/// Settings for values revealed in the TUI
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RevealSettings {
+    /// Seconds before a revealed value is masked again (0 = never)
+    pub hide_after_secs: u64,
+    /// Whether the value typed in the add form starts masked (F3 toggles it)
+    pub mask_input_by_default: bool,
+}
+
+impl Default for RevealSettings {
+    fn default() -> Self {
+        Self {
+            hide_after_secs: 10,
+            mask_input_by_default: false,
+        }
+    }
+}
+
+/// Settings for secret encryption
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CryptoSettings {
+    /// Cipher used when writing secrets ("aes-256-gcm" or "chacha20-poly1305").
+    /// Existing secrets stay readable whichever cipher wrote them.
+    pub cipher: CipherAlgo,
+}
+
+/// Settings for stored secrets
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StoreSettings {
+    /// Largest value accepted in bytes, unless added with `--allow-large` (0 = no limit).
+    /// The whole store is decrypted at once, so huge values slow every load.
+    pub max_secret_bytes: usize,
+    /// Days until new tokens expire when no expiration is given (None or 0 = permanent)
+    pub default_expiration_days: Option<u32>,
+    /// Tokens expiring within this many days are reported on TUI unlock and
+    /// by `token list` (0 = never)
+    pub warn_expiring_days: u32,
+}
+
+impl Default for StoreSettings {
+    fn default() -> Self {
+        Self {
+            max_secret_bytes: 1024 * 1024,
+            default_expiration_days: None,
+            warn_expiring_days: 7,
+        }
+    }
+}
+
+/// Commands run when the CLI changes secrets
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HookSettings {
+    /// Shell command run after a token is added, updated, renamed, removed or
+    /// imported (None = no hook)
+    pub on_change: Option<String>,
 }
 
 /// Settings for the token security analyzer
@@ -46,6 +453,13 @@ pub struct AnalyzerSettings {
 
     /// Include hidden files in analysis
     pub include_hidden: bool,
+
+    /// Skip files matched by the project's `.gitignore` files (nested ones included)
+    pub respect_gitignore: bool,
+
+    /// How token names are matched: "word" (default), "substring" or
+    /// `{ regex = "..." }` where `{name}` stands for the token name
+    pub match_mode: MatchMode,
 }
 
 impl Default for AnalyzerSettings {
@@ -107,6 +521,8 @@ impl Default for AnalyzerSettings {
             skip_paths: vec![],
             extensions: vec![], // Empty = use defaults from token-analyzer
             include_hidden: false,
+            respect_gitignore: true,
+            match_mode: MatchMode::Word,
         }
     }
 }
@@ -119,13 +535,20 @@ impl Config {
 
         if config_path.exists() {
             let content = std::fs::read_to_string(&config_path)?;
-            let config: Config = toml::from_str(&content).unwrap_or_else(|e| {
+            let mut config: Config = toml::from_str(&content).unwrap_or_else(|e| {
                 eprintln!(
                     "Warning: Failed to parse config.toml: {}. Using defaults.",
                     e
                 );
                 Config::default()
             });
+            if let Err(e) = config.keybindings.validate() {
+                eprintln!(
+                    "Warning: Invalid [keybindings] in config.toml: {}. Using default keys.",
+                    e
+                );
+                config.keybindings = Keybindings::default();
+            }
             Ok(config)
         } else {
             // Create default config file for user reference
@@ -135,11 +558,20 @@ impl Config {
         }
     }
 
+    /// Load configuration without creating a default file.
+    /// Missing or unparsable files yield the defaults.
+    pub fn load_or_default(locker_dir: &Path) -> Self {
+        std::fs::read_to_string(locker_dir.join("config.toml"))
+            .ok()
+            .and_then(|content| toml::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
     /// Save configuration to the locker directory
     pub fn save(&self, locker_dir: &Path) -> Result<()> {
         let config_path = locker_dir.join("config.toml");
         let content = Self::generate_config_with_comments(self)?;
-        std::fs::write(&config_path, content)?;
+        std::fs::write(&config_path, content).map_err(|e| write_error(&config_path, e))?;
         Ok(())
     }
 
@@ -165,6 +597,46 @@ impl Config {
 #   - Set enabled = false to disable automatic analysis
 #   - Add large directories to ignore_dirs to speed up analysis
 #   - Decrease max_files if analysis is still slow
+#   - Set respect_gitignore = false to also report usages in ignored files
+#   - match_mode = "word" (default) matches token names as whole words,
+#     "substring" anywhere (API also matches RAPIDLY), and
+#     match_mode = { regex = '(?i)\b{name}\b' } uses a regular expression in
+#     which {name} is the token name. Exposures are only detected in word mode.
+#
+# Crypto Settings
+#   - cipher = "aes-256-gcm" (default) or "chacha20-poly1305"
+#     ChaCha20-Poly1305 is faster on machines without AES hardware support
+#
+# Store Settings
+#   - max_secret_bytes = 1048576 (default, 1 MiB): larger values are refused
+#     unless added with --allow-large. 0 disables the limit.
+#   - default_expiration_days = 90: tokens added, generated or imported without
+#     an expiration expire after this many days. --expires never (or
+#     --permanent) still adds a permanent token. Unset by default.
+#   - warn_expiring_days = 7 (default): tokens expiring within this many days
+#     are listed when the TUI is unlocked and by token list. 0 disables it.
+#
+# Clipboard Settings
+#   - clear_after_secs = 30 (default): copied secrets are cleared after this
+#     many seconds, unless something else was copied since. 0 disables it.
+#
+# Reveal Settings
+#   - hide_after_secs = 10 (default): a value revealed with e is masked again
+#     after this many seconds. 0 keeps it shown until hidden.
+#   - mask_input_by_default = false (default): set to true to show the value
+#     typed in the add form as * (e.g. when sharing your screen). F3 toggles it.
+#
+# Keybindings (secrets list of the TUI)
+#   - Each action takes a list of keys: a character, or "up", "down", "enter",
+#     "tab", "space", "pageup", "f2", ... e.g. move_down = ["down", "j"]
+#   - A key may only be bound to one action; r, g, /, o and esc are reserved
+#
+# Hooks
+#   - on_change = "~/bin/redeploy.sh": shell command run, detached, after the CLI
+#     adds, updates, renames, removes or imports tokens. It gets
+#     LAZY_LOCKER_ACTION, LAZY_LOCKER_NAME (first name) and LAZY_LOCKER_NAMES
+#     (comma-separated), never a value; its output goes to hooks.log.
+#     Skip it with --no-hooks. Unset by default.
 
 "#;
 
@@ -174,8 +646,17 @@ impl Config {
         Ok(content)
     }
 
-    /// Get the locker directory path
+    /// Get the locker directory path of the active profile
     pub fn get_locker_dir() -> Result<PathBuf> {
+        Ok(profile_dir(&Self::get_base_dir()?, &active_profile()?))
+    }
+
+    /// Get the base locker directory, holding the default profile and `profiles/`.
+    /// Every locker path (keys, store, agent socket) derives from this one.
+    pub fn get_base_dir() -> Result<PathBuf> {
+        if let Some(home) = home_override()? {
+            return Ok(home);
+        }
         let base_dirs = directories::BaseDirs::new()
             .ok_or_else(|| anyhow::anyhow!("Unable to determine user directories"))?;
 
@@ -253,6 +734,7 @@ mod tests {
         assert_eq!(config.analyzer.max_files, 1000);
         assert!(config.analyzer.ignore_dirs.contains(&"node_modules".into()));
         assert!(config.analyzer.ignore_dirs.contains(&".venv".into()));
+        assert!(config.analyzer.respect_gitignore);
     }
 
     #[test]
@@ -265,6 +747,156 @@ mod tests {
 
         assert_eq!(loaded.analyzer.enabled, config.analyzer.enabled);
         assert_eq!(loaded.analyzer.timeout_ms, config.analyzer.timeout_ms);
+        assert_eq!(loaded.crypto.cipher, CipherAlgo::Aes256Gcm);
+        assert_eq!(loaded.clipboard.clear_after_secs, 30);
+        assert_eq!(loaded.reveal.hide_after_secs, 10);
+    }
+
+    #[test]
+    fn test_config_cipher_from_toml() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(
+            dir.path().join("config.toml"),
+            "[crypto]\ncipher = \"chacha20-poly1305\"\n",
+        )
+        .unwrap();
+
+        let loaded = Config::load_or_default(dir.path());
+
+        assert_eq!(loaded.crypto.cipher, CipherAlgo::ChaCha20Poly1305);
+        assert!(loaded.analyzer.enabled);
+    }
+
+    #[test]
+    fn test_match_mode_from_toml_and_saved() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(
+            dir.path().join("config.toml"),
+            "[analyzer]\nmatch_mode = { regex = '(?i)\\b{name}\\b' }\n",
+        )
+        .unwrap();
+
+        let mut loaded = Config::load(dir.path()).unwrap();
+        assert_eq!(
+            loaded.analyzer.match_mode,
+            MatchMode::Regex(r"(?i)\b{name}\b".to_string())
+        );
+
+        loaded.analyzer.match_mode = MatchMode::Substring;
+        loaded.save(dir.path()).unwrap();
+        let reloaded = Config::load(dir.path()).unwrap();
+        assert_eq!(reloaded.analyzer.match_mode, MatchMode::Substring);
+        assert_eq!(Config::default().analyzer.match_mode, MatchMode::Word);
+    }
+
+    #[test]
+    fn test_keybindings_from_toml() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(
+            dir.path().join("config.toml"),
+            "[keybindings]\nmove_up = [\"up\", \"k\"]\nmove_down = [\"down\", \"j\"]\n",
+        )
+        .unwrap();
+
+        let loaded = Config::load(dir.path()).unwrap();
+
+        let keys = &loaded.keybindings;
+        assert_eq!(keys.action(KeyCode::Char('j')), Some(KeyAction::MoveDown));
+        assert_eq!(keys.action(KeyCode::Up), Some(KeyAction::MoveUp));
+        assert_eq!(keys.action(KeyCode::Char('q')), Some(KeyAction::Quit)); // default kept
+        assert_eq!(keys.action(KeyCode::Char('x')), None);
+    }
+
+    #[test]
+    fn test_keybindings_reject_duplicate_keys() {
+        assert!(Keybindings::default().validate().is_ok());
+
+        let keys = Keybindings {
+            delete: vec![KeyCode::Char('d')],
+            quit: vec![KeyCode::Char('d')],
+            ..Default::default()
+        };
+        let err = keys.validate().unwrap_err().to_string();
+        assert!(err.contains("bound to both Delete and Quit"), "{}", err);
+
+        let keys = Keybindings {
+            add: vec![KeyCode::Char('/')],
+            ..Default::default()
+        };
+        assert!(keys.validate().is_err());
+    }
+
+    #[test]
+    fn test_invalid_keybindings_fall_back_to_defaults() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(
+            dir.path().join("config.toml"),
+            "[keybindings]\nhelp = [\"q\"]\n",
+        )
+        .unwrap();
+
+        let loaded = Config::load(dir.path()).unwrap();
+
+        assert_eq!(loaded.keybindings, Keybindings::default());
+    }
+
+    #[test]
+    fn test_key_names_round_trip() {
+        for name in ["j", ":", "space", "up", "pagedown", "enter", "f2"] {
+            assert_eq!(key_name(parse_key(name).unwrap()), name);
+        }
+        assert_eq!(parse_key("Down").unwrap(), KeyCode::Down);
+        assert!(parse_key("f13").is_err());
+        assert!(parse_key("ctrl-x").is_err());
+    }
+
+    #[test]
+    fn test_profile_dirs() {
+        let base = Path::new("/home/user/.config/.lazy-locker");
+
+        assert_eq!(profile_dir(base, DEFAULT_PROFILE), base);
+        assert_eq!(
+            profile_dir(base, "work"),
+            base.join("profiles").join("work")
+        );
+
+        assert!(validate_profile_name("work-2_b").is_ok());
+        for invalid in ["", "../etc", "a/b", "with space", "."] {
+            assert!(validate_profile_name(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_profiles_are_independent() {
+        use crate::core::store::SecretsStore;
+        use crate::core::transport::{AgentTransport, Transport};
+
+        let base = TempDir::new().unwrap();
+        let key = [0x42u8; 32];
+        let work = profile_dir(base.path(), "work");
+        let personal = profile_dir(base.path(), "personal");
+        for (dir, name) in [(&work, "WORK_TOKEN"), (&personal, "PERSONAL_TOKEN")] {
+            std::fs::create_dir_all(dir).unwrap();
+            let mut store = SecretsStore::new();
+            store
+                .add_secret(name.to_string(), "value".to_string(), None, dir, &key)
+                .unwrap();
+        }
+
+        let work_store = SecretsStore::load(&work, &key).unwrap();
+        assert!(work_store.contains("WORK_TOKEN"));
+        assert!(!work_store.contains("PERSONAL_TOKEN"));
+        let default_store = SecretsStore::load(base.path(), &key).unwrap();
+        assert!(default_store.is_empty());
+
+        // Each profile has its own agent endpoint
+        assert_ne!(Transport::endpoint(&work), Transport::endpoint(&personal));
+        assert_ne!(Transport::endpoint(&work), Transport::endpoint(base.path()));
+
+        assert_eq!(
+            list_profiles(base.path()),
+            vec!["default", "personal", "work"]
+        );
     }
 
     #[test]
@@ -1,26 +1,156 @@
-use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::aead::{Aead, KeyInit, Payload};
 use aes_gcm::{Aes256Gcm, Key, Nonce};
 use anyhow::Result;
+use chacha20poly1305::ChaCha20Poly1305;
 use rand::Rng;
+use serde::{Deserialize, Serialize};
 
+/// Ciphertext format version: AES-256-GCM with a 12-byte nonce.
+/// Layout: `[version][nonce][ciphertext + tag]`.
+pub const VERSION_AES_GCM: u8 = 0x01;
+
+/// Ciphertext format version: AES-256-GCM authenticating associated data.
+/// Same layout as `VERSION_AES_GCM`; the AAD itself is not stored.
+pub const VERSION_AES_GCM_AAD: u8 = 0x02;
+
+/// Ciphertext format version: ChaCha20-Poly1305 (associated data optional).
+/// Same layout as `VERSION_AES_GCM`.
+pub const VERSION_CHACHA20_POLY1305: u8 = 0x03;
+
+const NONCE_LEN: usize = 12;
+
+/// AEAD cipher used when encrypting. Decryption picks the cipher from the version byte.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CipherAlgo {
+    #[default]
+    #[serde(rename = "aes-256-gcm")]
+    Aes256Gcm,
+    #[serde(rename = "chacha20-poly1305")]
+    ChaCha20Poly1305,
+}
+
+#[allow(dead_code)]
 pub fn encrypt(data: &[u8], key: &[u8]) -> Result<Vec<u8>> {
-    let key = Key::<Aes256Gcm>::from_slice(key);
-    let cipher = Aes256Gcm::new(key);
-    let nonce: [u8; 12] = rand::rng().random();
-    let ciphertext = cipher
-        .encrypt(Nonce::from_slice(&nonce), data)
-        .map_err(|e| anyhow::anyhow!("Encryption error: {}", e))?;
-    let mut result = nonce.to_vec();
+    encrypt_with(CipherAlgo::Aes256Gcm, data, key, None)
+}
+
+/// Encrypts `data` with the given cipher, optionally bound to `aad`.
+///
+/// With `aad`, decryption only succeeds given the same `aad`. This ties a secret
+/// value to its name so values can't be swapped between entries.
+pub fn encrypt_with(
+    cipher: CipherAlgo,
+    data: &[u8],
+    key: &[u8],
+    aad: Option<&[u8]>,
+) -> Result<Vec<u8>> {
+    let nonce: [u8; NONCE_LEN] = rand::rng().random();
+    let payload = Payload {
+        msg: data,
+        aad: aad.unwrap_or_default(),
+    };
+    let (version, ciphertext) = match cipher {
+        CipherAlgo::Aes256Gcm => {
+            let version = if aad.is_some() {
+                VERSION_AES_GCM_AAD
+            } else {
+                VERSION_AES_GCM
+            };
+            let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+            (version, cipher.encrypt(Nonce::from_slice(&nonce), payload))
+        }
+        CipherAlgo::ChaCha20Poly1305 => {
+            let cipher = ChaCha20Poly1305::new(chacha20poly1305::Key::from_slice(key));
+            let nonce = chacha20poly1305::Nonce::from_slice(&nonce);
+            (VERSION_CHACHA20_POLY1305, cipher.encrypt(nonce, payload))
+        }
+    };
+    let ciphertext = ciphertext.map_err(|e| anyhow::anyhow!("Encryption error: {}", e))?;
+    let mut result = Vec::with_capacity(1 + NONCE_LEN + ciphertext.len());
+    result.push(version);
+    result.extend_from_slice(&nonce);
     result.extend(ciphertext);
     Ok(result)
 }
 
+/// Decrypts a versioned blob, dispatching on its leading version byte.
+///
+/// Blobs written before versioning have no header and start directly with
+/// the nonce, so they are still accepted through `decrypt_legacy`.
 pub fn decrypt(data: &[u8], key: &[u8]) -> Result<Vec<u8>> {
+    match data.first() {
+        Some(&VERSION_AES_GCM) => decrypt_aes_gcm(&data[1..], key, &[])
+            .or_else(|e| decrypt_legacy(data, key).map_err(|_| e)),
+        Some(&VERSION_AES_GCM_AAD) => decrypt_legacy(data, key).map_err(|_| {
+            anyhow::anyhow!(
+                "Ciphertext version {} is bound to associated data and cannot be decrypted without it",
+                VERSION_AES_GCM_AAD
+            )
+        }),
+        Some(&VERSION_CHACHA20_POLY1305) => decrypt_chacha20_poly1305(&data[1..], key, &[])
+            .or_else(|e| decrypt_legacy(data, key).map_err(|_| e)),
+        Some(&version) => decrypt_legacy(data, key)
+            .map_err(|_| anyhow::anyhow!("Unsupported ciphertext version {}", version)),
+        None => anyhow::bail!("Decryption error: empty ciphertext"),
+    }
+}
+
+/// Decrypts a headerless blob (`[nonce][ciphertext + tag]`) from before versioning.
+pub fn decrypt_legacy(data: &[u8], key: &[u8]) -> Result<Vec<u8>> {
+    decrypt_aes_gcm(data, key, &[])
+}
+
+/// Decrypts a blob written by `encrypt_with` with the same `aad`.
+///
+/// Only blobs bound to associated data are accepted: falling back to a blob
+/// written without it would let a value be moved under another name.
+/// Values from before AAD binding are read once with `decrypt`, to be
+/// re-encrypted with their AAD.
+pub fn decrypt_with_aad(data: &[u8], key: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+    match data.first() {
+        Some(&VERSION_AES_GCM_AAD) => decrypt_aes_gcm(&data[1..], key, aad),
+        Some(&VERSION_CHACHA20_POLY1305) => decrypt_chacha20_poly1305(&data[1..], key, aad),
+        Some(&version) => anyhow::bail!(
+            "Ciphertext version {} is not bound to associated data",
+            version
+        ),
+        None => anyhow::bail!("Decryption error: empty ciphertext"),
+    }
+}
+
+fn decrypt_aes_gcm(data: &[u8], key: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+    if data.len() < NONCE_LEN {
+        anyhow::bail!("Decryption error: ciphertext too short");
+    }
     let key = Key::<Aes256Gcm>::from_slice(key);
     let cipher = Aes256Gcm::new(key);
-    let (nonce, ciphertext) = data.split_at(12);
+    let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+    let plaintext = cipher
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad,
+            },
+        )
+        .map_err(|e| anyhow::anyhow!("Decryption error: {}", e))?;
+    Ok(plaintext)
+}
+
+fn decrypt_chacha20_poly1305(data: &[u8], key: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+    if data.len() < NONCE_LEN {
+        anyhow::bail!("Decryption error: ciphertext too short");
+    }
+    let cipher = ChaCha20Poly1305::new(chacha20poly1305::Key::from_slice(key));
+    let (nonce, ciphertext) = data.split_at(NONCE_LEN);
     let plaintext = cipher
-        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .decrypt(
+            chacha20poly1305::Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad,
+            },
+        )
         .map_err(|e| anyhow::anyhow!("Decryption error: {}", e))?;
     Ok(plaintext)
 }
@@ -43,8 +173,8 @@ mod tests {
 
         // Encrypted data should be different from plaintext
         assert_ne!(encrypted.as_slice(), plaintext);
-        // Encrypted data should include nonce (12 bytes) + ciphertext + tag (16 bytes)
-        assert!(encrypted.len() > plaintext.len() + 12);
+        // Encrypted data should include version (1 byte) + nonce (12 bytes) + ciphertext + tag (16 bytes)
+        assert_eq!(encrypted.len(), 1 + 12 + plaintext.len() + 16);
     }
 
     #[test]
@@ -137,4 +267,130 @@ mod tests {
             "Clé secrète: 日本語 🔐 émojis"
         );
     }
+
+    #[test]
+    fn test_encrypt_writes_v1_header() {
+        let key = test_key();
+
+        let encrypted = encrypt(b"value", &key).expect("Encryption should succeed");
+
+        assert_eq!(encrypted[0], VERSION_AES_GCM);
+        assert_eq!(decrypt(&encrypted, &key).unwrap(), b"value");
+    }
+
+    #[test]
+    fn test_decrypt_accepts_headerless_legacy_blobs() {
+        let key = test_key();
+        // Legacy layout: nonce + ciphertext, no version byte
+        let legacy = encrypt(b"old value", &key).unwrap()[1..].to_vec();
+
+        assert_eq!(decrypt(&legacy, &key).unwrap(), b"old value");
+        assert_eq!(decrypt_legacy(&legacy, &key).unwrap(), b"old value");
+    }
+
+    #[test]
+    fn test_decrypt_unknown_version_fails_clearly() {
+        let key = test_key();
+        let mut encrypted = encrypt(b"value", &key).unwrap();
+        encrypted[0] = 0x7F;
+
+        let err = decrypt(&encrypted, &key).unwrap_err();
+
+        assert_eq!(err.to_string(), "Unsupported ciphertext version 127");
+    }
+
+    #[test]
+    fn test_decrypt_truncated_data_fails() {
+        let key = test_key();
+
+        assert!(decrypt(&[], &key).is_err());
+        assert!(decrypt(&[VERSION_AES_GCM, 1, 2], &key).is_err());
+    }
+
+    #[test]
+    fn test_aad_must_match() {
+        let key = test_key();
+
+        let encrypted =
+            encrypt_with(CipherAlgo::Aes256Gcm, b"value", &key, Some(b"API_KEY")).unwrap();
+
+        assert_eq!(encrypted[0], VERSION_AES_GCM_AAD);
+        assert_eq!(
+            decrypt_with_aad(&encrypted, &key, b"API_KEY").unwrap(),
+            b"value"
+        );
+        assert!(decrypt_with_aad(&encrypted, &key, b"DB_PASSWORD").is_err());
+        assert!(decrypt(&encrypted, &key).is_err());
+    }
+
+    #[test]
+    fn test_decrypt_with_aad_rejects_pre_aad_blobs() {
+        let key = test_key();
+        let v1 = encrypt(b"value", &key).unwrap();
+        let chacha = encrypt_with(CipherAlgo::ChaCha20Poly1305, b"value", &key, None).unwrap();
+
+        let err = decrypt_with_aad(&v1, &key, b"ANY").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Ciphertext version 1 is not bound to associated data"
+        );
+        assert!(decrypt_with_aad(&chacha, &key, b"ANY").is_err());
+        assert_eq!(decrypt(&v1, &key).unwrap(), b"value");
+        assert_eq!(decrypt(&chacha, &key).unwrap(), b"value");
+        assert_eq!(decrypt(&v1[1..], &key).unwrap(), b"value");
+    }
+
+    #[test]
+    fn test_decrypt_without_aad_names_the_missing_aad() {
+        let key = test_key();
+        let encrypted =
+            encrypt_with(CipherAlgo::Aes256Gcm, b"value", &key, Some(b"API_KEY")).unwrap();
+
+        let err = decrypt(&encrypted, &key).unwrap_err();
+
+        assert!(
+            err.to_string().contains("cannot be decrypted without it"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_chacha20_poly1305_roundtrip() {
+        let key = test_key();
+
+        let encrypted = encrypt_with(CipherAlgo::ChaCha20Poly1305, b"value", &key, None).unwrap();
+
+        assert_eq!(encrypted[0], VERSION_CHACHA20_POLY1305);
+        assert_eq!(decrypt(&encrypted, &key).unwrap(), b"value");
+    }
+
+    #[test]
+    fn test_chacha20_poly1305_aad_must_match() {
+        let key = test_key();
+
+        let encrypted = encrypt_with(
+            CipherAlgo::ChaCha20Poly1305,
+            b"value",
+            &key,
+            Some(b"API_KEY"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            decrypt_with_aad(&encrypted, &key, b"API_KEY").unwrap(),
+            b"value"
+        );
+        assert!(decrypt_with_aad(&encrypted, &key, b"DB_PASSWORD").is_err());
+    }
+
+    #[test]
+    fn test_encrypt_with_aes_matches_default() {
+        let key = test_key();
+
+        let encrypted = encrypt_with(CipherAlgo::Aes256Gcm, b"value", &key, None).unwrap();
+
+        assert_eq!(encrypted[0], VERSION_AES_GCM);
+        assert_eq!(decrypt(&encrypted, &key).unwrap(), b"value");
+    }
 }
@@ -5,37 +5,88 @@
 //! plain text values to disk.
 
 use anyhow::Result;
+use std::collections::BTreeMap;
 use std::path::PathBuf;
-use std::process::{Command, Stdio};
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use zeroize::Zeroize;
 
-use crate::core::store::SecretsStore;
+use crate::core::format;
+use crate::core::store::{Secret, SecretsStore};
 
-/// Executes a command with secrets injected as environment variables.
+/// Executes a command with the given secrets injected as environment variables,
+/// sharing this process's stdin, stdout and stderr, and returns its exit status.
 /// Secrets are decrypted in memory and zeroized after execution.
+/// `env` selects environment-specific variants, falling back to default values.
 pub fn execute_with_secrets(
-    command: &str,
-    store: &SecretsStore,
+    argv: &[String],
+    shell: bool,
+    secrets: &[&Secret],
     key: &[u8],
-) -> Result<std::process::Output> {
-    // Decrypt all secrets in memory
-    let mut env_vars = store.decrypt_all(key)?;
-
-    // Execute the command with environment variables
-    let output = Command::new("sh")
-        .arg("-c")
-        .arg(command)
-        .envs(&env_vars)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .output()?;
+    env: Option<&str>,
+) -> Result<ExitStatus> {
+    // Decrypt the selected secrets in memory
+    let mut env_vars = BTreeMap::new();
+    for secret in secrets {
+        match secret.decrypt_for(env, key) {
+            Ok(value) => {
+                env_vars.insert(secret.name.clone(), value);
+            }
+            Err(e) => {
+                zeroize_values(&mut env_vars);
+                return Err(e);
+            }
+        }
+    }
+
+    let status = run_with_env(argv, shell, &env_vars);
 
     // Zeroize secrets after use
-    for (_, mut value) in env_vars.drain() {
+    zeroize_values(&mut env_vars);
+
+    status
+}
+
+/// Runs a command with extra environment variables and waits for it.
+/// `argv[0]` is executed directly, so arguments reach it exactly as given;
+/// with `shell`, the arguments are joined and interpreted by the system shell.
+pub fn run_with_env(
+    argv: &[String],
+    shell: bool,
+    env_vars: &BTreeMap<String, String>,
+) -> Result<ExitStatus> {
+    let (program, args) = argv
+        .split_first()
+        .ok_or_else(|| anyhow::anyhow!("No command to run"))?;
+
+    let mut command = if shell {
+        let line = argv.join(" ");
+        if cfg!(windows) {
+            let mut command = Command::new("cmd");
+            command.arg("/C").arg(line);
+            command
+        } else {
+            let mut command = Command::new("sh");
+            command.arg("-c").arg(line);
+            command
+        }
+    } else {
+        let mut command = Command::new(program);
+        command.args(args);
+        command
+    };
+
+    command
+        .envs(env_vars)
+        .status()
+        .map_err(|e| anyhow::anyhow!("Cannot run '{}': {}", program, e))
+}
+
+fn zeroize_values(env_vars: &mut BTreeMap<String, String>) {
+    for value in env_vars.values_mut() {
         value.zeroize();
     }
-
-    Ok(output)
 }
 
 /// Generates a Python wrapper script that uses lazy-locker to inject secrets.
@@ -48,6 +99,7 @@ pub fn generate_python_wrapper(script_path: &str, locker_path: &std::path::Path)
 Secure wrapper generated by lazy-locker.
 This script injects secrets in memory before executing the target script.
 """
+import json
 import subprocess
 import sys
 import os
@@ -55,7 +107,7 @@ import os
 def main():
     # Call lazy-locker to get secrets (via secure pipe)
     result = subprocess.run(
-        ['lazy-locker', 'export', '--format', 'env'],
+        ['lazy-locker', 'export', '--json'],
         capture_output=True,
         text=True,
         cwd='{locker_dir}'
@@ -65,12 +117,9 @@ def main():
         print("Error: Unable to load secrets", file=sys.stderr)
         sys.exit(1)
     
-    # Parse environment variables
+    # Inject secrets into the environment
     env = os.environ.copy()
-    for line in result.stdout.strip().split('\n'):
-        if '=' in line:
-            key, value = line.split('=', 1)
-            env[key] = value
+    env.update(json.loads(result.stdout))
     
     # Execute target script with injected secrets
     subprocess.run([sys.executable, '{script}'] + sys.argv[1:], env=env)
@@ -108,132 +157,311 @@ pub fn generate_env_reference(store: &SecretsStore, output_path: &PathBuf) -> Re
 /// WARNING: This function writes secrets in plain text. Use with caution.
 #[allow(dead_code)]
 pub fn export_env_format(store: &SecretsStore, key: &[u8]) -> Result<String> {
-    let secrets = store.decrypt_all(key)?;
-    let mut output = String::new();
+    render_all(store, key, |entries| Ok(format::to_env(entries)))
+}
 
-    for (name, mut value) in secrets {
-        // Escape special characters
-        let escaped_value = value.replace('\\', "\\\\").replace('"', "\\\"");
-        output.push_str(&format!("{}=\"{}\"\n", name, escaped_value));
+/// Renders every secret sorted by name with `format::MASK` as its value,
+/// without decrypting anything
+fn render_masked(
+    store: &SecretsStore,
+    render: impl Fn(&[(&str, &str)]) -> Result<String>,
+) -> Result<String> {
+    let mut names: Vec<&str> = store
+        .list_secrets()
+        .iter()
+        .map(|s| s.name.as_str())
+        .collect();
+    names.sort_unstable();
+    let entries: Vec<(&str, &str)> = names.into_iter().map(|name| (name, format::MASK)).collect();
+    render(&entries)
+}
+
+/// Decrypts every secret, renders them sorted by name and wipes the plaintext
+fn render_all(
+    store: &SecretsStore,
+    key: &[u8],
+    render: impl Fn(&[(&str, &str)]) -> Result<String>,
+) -> Result<String> {
+    let mut secrets: Vec<(String, String)> = store.decrypt_all(key)?.into_iter().collect();
+
+    let entries: Vec<(&str, &str)> = secrets
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.as_str()))
+        .collect();
+    let output = render(&entries);
+
+    for (_, value) in &mut secrets {
         value.zeroize();
     }
-
-    Ok(output)
+    output
 }
 
 /// Copies a value to clipboard (cross-platform).
+/// Fails when no clipboard tool works, e.g. on a machine without a display.
 pub fn copy_to_clipboard(value: &str) -> Result<()> {
     #[cfg(target_os = "linux")]
     {
-        // Try xclip then xsel
-        let result = Command::new("xclip")
-            .args(["-selection", "clipboard"])
-            .stdin(Stdio::piped())
-            .spawn()
-            .and_then(|mut child| {
-                use std::io::Write;
-                if let Some(ref mut stdin) = child.stdin {
-                    stdin.write_all(value.as_bytes())?;
-                }
-                child.wait()
-            });
-
-        if result.is_ok() {
-            return Ok(());
-        }
-
-        // Fallback to xsel
-        let result = Command::new("xsel")
-            .args(["--clipboard", "--input"])
-            .stdin(Stdio::piped())
-            .spawn()
-            .and_then(|mut child| {
-                use std::io::Write;
-                if let Some(ref mut stdin) = child.stdin {
-                    stdin.write_all(value.as_bytes())?;
-                }
-                child.wait()
-            });
-
-        if result.is_ok() {
-            return Ok(());
-        }
-
-        // Try wl-copy for Wayland
-        let result = Command::new("wl-copy")
-            .stdin(Stdio::piped())
-            .spawn()
-            .and_then(|mut child| {
-                use std::io::Write;
-                if let Some(ref mut stdin) = child.stdin {
-                    stdin.write_all(value.as_bytes())?;
-                }
-                child.wait()
-            });
-
-        result
-            .map_err(|_| anyhow::anyhow!("No clipboard tool available (xclip, xsel, wl-copy)"))?;
+        // Try xclip, then xsel, then wl-copy for Wayland
+        pipe_to("xclip", &["-selection", "clipboard"], value)
+            .or_else(|_| pipe_to("xsel", &["--clipboard", "--input"], value))
+            .or_else(|_| pipe_to("wl-copy", &[], value))
+            .map_err(|_| {
+                anyhow::anyhow!(
+                    "No clipboard available: xclip, xsel or wl-copy must be installed \
+                     and a graphical session running"
+                )
+            })?;
     }
 
     #[cfg(target_os = "macos")]
     {
-        Command::new("pbcopy")
-            .stdin(Stdio::piped())
-            .spawn()
-            .and_then(|mut child| {
-                use std::io::Write;
-                if let Some(ref mut stdin) = child.stdin {
-                    stdin.write_all(value.as_bytes())?;
-                }
-                child.wait()
-            })
-            .map_err(|e| anyhow::anyhow!("pbcopy error: {}", e))?;
+        pipe_to("pbcopy", &[], value).map_err(|e| anyhow::anyhow!("pbcopy error: {}", e))?;
     }
 
     #[cfg(target_os = "windows")]
     {
-        Command::new("clip")
-            .stdin(Stdio::piped())
-            .spawn()
-            .and_then(|mut child| {
-                use std::io::Write;
-                if let Some(ref mut stdin) = child.stdin {
-                    stdin.write_all(value.as_bytes())?;
-                }
-                child.wait()
-            })
-            .map_err(|e| anyhow::anyhow!("clip error: {}", e))?;
+        pipe_to("clip", &[], value).map_err(|e| anyhow::anyhow!("clip error: {}", e))?;
     }
 
     Ok(())
 }
 
+/// Writes `value` to the stdin of a clipboard tool, which must exit successfully
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+fn pipe_to(program: &str, args: &[&str], value: &str) -> std::io::Result<()> {
+    use std::io::Write;
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(value.as_bytes())?;
+    }
+    let status = child.wait()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(std::io::Error::other(format!(
+            "{} failed ({})",
+            program, status
+        )))
+    }
+}
+
+/// Reads the current clipboard text (cross-platform).
+pub fn read_clipboard() -> Result<String> {
+    #[cfg(target_os = "linux")]
+    let candidates: &[(&str, &[&str])] = &[
+        ("xclip", &["-selection", "clipboard", "-o"]),
+        ("xsel", &["--clipboard", "--output"]),
+        ("wl-paste", &["--no-newline"]),
+    ];
+    #[cfg(target_os = "macos")]
+    let candidates: &[(&str, &[&str])] = &[("pbpaste", &[])];
+    #[cfg(target_os = "windows")]
+    let candidates: &[(&str, &[&str])] =
+        &[("powershell", &["-NoProfile", "-Command", "Get-Clipboard"])];
+    #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+    let candidates: &[(&str, &[&str])] = &[];
+
+    for (program, args) in candidates {
+        if let Ok(output) = Command::new(program)
+            .args(*args)
+            .stderr(Stdio::null())
+            .output()
+            && output.status.success()
+        {
+            let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+            // Get-Clipboard terminates its output with a newline
+            if cfg!(target_os = "windows") && text.ends_with("\r\n") {
+                text.truncate(text.len() - 2);
+            }
+            return Ok(text);
+        }
+    }
+    anyhow::bail!("No clipboard tool available to read the clipboard")
+}
+
+/// Clears the clipboard through `write` only if `read` still returns `expected`,
+/// so a value the user copied since is left alone. Returns whether it cleared.
+fn clear_if_unchanged(
+    expected: &str,
+    read: impl FnOnce() -> Result<String>,
+    write: impl FnOnce(&str) -> Result<()>,
+) -> Result<bool> {
+    let mut current = read()?;
+    let unchanged = current == expected;
+    current.zeroize();
+    if unchanged {
+        write("")?;
+    }
+    Ok(unchanged)
+}
+
+/// Clears `value` from the clipboard after `after` from a detached copy of
+/// this program, for commands that exit right after copying. The value
+/// reaches it through stdin, never its arguments.
+pub fn schedule_detached_clipboard_clear(value: &str, after: Duration) -> Result<()> {
+    use std::io::Write;
+    let mut command = Command::new(std::env::current_exe()?);
+    command
+        .arg("clipboard-clear")
+        .arg("--after")
+        .arg(after.as_secs().to_string());
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        // Outlive the console the command ran in
+        const DETACHED_PROCESS: u32 = 0x0000_0008;
+        command.creation_flags(DETACHED_PROCESS);
+    }
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(value.as_bytes())?;
+    }
+    Ok(())
+}
+
+/// Entry point of the detached clear: reads the copied value from stdin,
+/// waits, then clears the clipboard if it still holds that value
+pub fn run_clipboard_clear(after: Duration) -> Result<()> {
+    use std::io::Read;
+    let mut value = String::new();
+    std::io::stdin().read_to_string(&mut value)?;
+    std::thread::sleep(after);
+    let result = clear_if_unchanged(&value, read_clipboard, copy_to_clipboard);
+    value.zeroize();
+    result.map(|_| ())
+}
+
+/// A copied secret waiting to be cleared from the clipboard.
+///
+/// A background thread clears it once the delay has passed. The xclip, xsel
+/// and wl-copy helpers keep serving the selection after lazy-locker exits, so
+/// the value would outlive the process: call `flush` on exit to clear it early.
+pub struct ClipboardClear {
+    value: Arc<Mutex<Option<String>>>,
+}
+
+impl ClipboardClear {
+    /// Schedules `value` to be cleared from the clipboard after `after`
+    pub fn schedule(value: &str, after: Duration) -> Self {
+        let value = Arc::new(Mutex::new(Some(value.to_string())));
+        let pending = Arc::clone(&value);
+        std::thread::spawn(move || {
+            std::thread::sleep(after);
+            Self::clear(&pending);
+        });
+        Self { value }
+    }
+
+    /// Clears now if the clipboard still holds the copied value
+    pub fn flush(&self) {
+        Self::clear(&self.value);
+    }
+
+    /// Forgets the copied value without touching the clipboard
+    pub fn cancel(&self) {
+        if let Some(mut value) = self.value.lock().ok().and_then(|mut v| v.take()) {
+            value.zeroize();
+        }
+    }
+
+    fn clear(value: &Mutex<Option<String>>) {
+        if let Some(mut value) = value.lock().ok().and_then(|mut v| v.take()) {
+            clear_if_unchanged(&value, read_clipboard, copy_to_clipboard).ok();
+            value.zeroize();
+        }
+    }
+}
+
 /// Marker comment used to identify lazy-locker exports in shell profiles
 const SHELL_MARKER_START: &str = "# >>> lazy-locker exports >>>";
 const SHELL_MARKER_END: &str = "# <<< lazy-locker exports <<<";
 
 /// Generates a .env file with secrets in plain text.
 /// WARNING: This writes secrets in plain text to disk.
+/// With `mask`, every value is written as `format::MASK` and nothing is decrypted.
 pub fn generate_env_file(
     store: &SecretsStore,
     key: &[u8],
     output_path: &std::path::PathBuf,
+    mask: bool,
 ) -> Result<()> {
-    let secrets = store.decrypt_all(key)?;
     let mut content = String::from("# Generated by lazy-locker\n");
+    if mask {
+        content.push_str("# Values are masked: this file only shows which variables exist.\n\n");
+        content.push_str(&render_masked(store, |entries| {
+            Ok(format::to_env(entries))
+        })?);
+        std::fs::write(output_path, &content)?;
+        return Ok(());
+    }
     content.push_str("# WARNING: This file contains secrets in plain text!\n");
     content.push_str("# Do not commit this file to version control.\n\n");
 
-    for (name, mut value) in secrets {
-        let escaped_value = value.replace('\\', "\\\\").replace('"', "\\\"");
-        content.push_str(&format!("{}=\"{}\"\n", name, escaped_value));
-        value.zeroize();
+    let mut env = render_all(store, key, |entries| Ok(format::to_env(entries)))?;
+    content.push_str(&env);
+    env.zeroize();
+
+    std::fs::write(output_path, &content)?;
+    content.zeroize();
+    Ok(())
+}
+
+/// Generates a `.env.example` style file: every name with an empty value.
+/// Safe to commit, as nothing is decrypted.
+pub fn generate_env_template(store: &SecretsStore, output_path: &std::path::Path) -> Result<()> {
+    let mut names: Vec<&str> = store
+        .list_secrets()
+        .iter()
+        .map(|s| s.name.as_str())
+        .collect();
+    names.sort_unstable();
+
+    let mut content = String::from("# Generated by lazy-locker\n");
+    content.push_str("# Fill in the values, or use 'lazy-locker run <command>'.\n\n");
+    for name in names {
+        content.push_str(name);
+        content.push_str("=\n");
     }
 
     std::fs::write(output_path, content)?;
     Ok(())
 }
 
+/// Writes a single secret value (or other sensitive bytes) to a file readable
+/// only by the owner.
+/// WARNING: This writes the secret in plain text to disk.
+pub fn write_secret_file(value: impl AsRef<[u8]>, output_path: &std::path::Path) -> Result<()> {
+    use std::io::Write;
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(output_path)?;
+
+    // An existing file keeps its mode on open, so tighten it explicitly
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    }
+
+    file.write_all(value.as_ref())?;
+    Ok(())
+}
+
 /// Exports secrets to a shell profile file (bash, zsh, fish).
 /// Adds export statements within markers for easy removal.
 pub fn export_to_shell_profile(
@@ -252,23 +480,16 @@ pub fn export_to_shell_profile(
     };
 
     // Generate export lines
-    let secrets = store.decrypt_all(key)?;
+    let mut lines = if shell == "fish" {
+        render_all(store, key, |entries| Ok(format::to_fish_exports(entries)))?
+    } else {
+        render_all(store, key, |entries| Ok(format::to_shell_exports(entries)))?
+    };
     let mut exports = String::new();
     exports.push_str(&format!("\n{}\n", SHELL_MARKER_START));
     exports.push_str("# WARNING: Secrets in plain text - generated by lazy-locker\n");
-
-    for (name, mut value) in secrets {
-        let escaped_value = value
-            .replace('\\', "\\\\")
-            .replace('"', "\\\"")
-            .replace('$', "\\$");
-        if shell == "fish" {
-            exports.push_str(&format!("set -gx {} \"{}\"\n", name, escaped_value));
-        } else {
-            exports.push_str(&format!("export {}=\"{}\"\n", name, escaped_value));
-        }
-        value.zeroize();
-    }
+    exports.push_str(&lines);
+    lines.zeroize();
 
     exports.push_str(&format!("{}\n", SHELL_MARKER_END));
 
@@ -342,14 +563,20 @@ pub fn clear_shell_exports() -> Result<Vec<std::path::PathBuf>> {
 }
 
 /// Exports secrets as a JSON file.
+/// With `mask`, every value is written as `format::MASK` and nothing is decrypted.
 pub fn export_to_json(
     store: &SecretsStore,
     key: &[u8],
     output_path: &std::path::PathBuf,
+    mask: bool,
 ) -> Result<()> {
-    let secrets = store.decrypt_all(key)?;
-    let json = serde_json::to_string_pretty(&secrets)?;
-    std::fs::write(output_path, json)?;
+    if mask {
+        std::fs::write(output_path, render_masked(store, format::to_json)?)?;
+        return Ok(());
+    }
+    let mut json = render_all(store, key, format::to_json)?;
+    std::fs::write(output_path, &json)?;
+    json.zeroize();
     Ok(())
 }
 
@@ -418,6 +645,87 @@ mod tests {
         assert!(content.contains("${LAZY_LOCKER:PASSWORD}"));
     }
 
+    // ========================
+    // masked export tests
+    // ========================
+
+    fn store_with(dir: &std::path::Path, key: &[u8], secrets: &[(&str, &str)]) -> SecretsStore {
+        let mut store = SecretsStore::new();
+        for (name, value) in secrets {
+            store
+                .add_secret(name.to_string(), value.to_string(), None, dir, key)
+                .expect("Failed to add secret");
+        }
+        store
+    }
+
+    #[test]
+    fn test_masked_exports_hide_values_and_lengths() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let key = [0x42u8; 32];
+        let store = store_with(
+            temp_dir.path(),
+            &key,
+            &[("SHORT", "x"), ("LONG", "sk-live-0123456789abcdef")],
+        );
+
+        let env_path = temp_dir.path().join(".env");
+        generate_env_file(&store, &key, &env_path, true).unwrap();
+        let env = fs::read_to_string(&env_path).unwrap();
+        let json_path = temp_dir.path().join("secrets.json");
+        export_to_json(&store, &key, &json_path, true).unwrap();
+        let json = fs::read_to_string(&json_path).unwrap();
+
+        for content in [&env, &json] {
+            assert!(!content.contains("sk-live"));
+            assert!(!content.contains("\"x\""));
+        }
+        assert!(env.contains("LONG=\"********\""));
+        assert!(env.contains("SHORT=\"********\""));
+        let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(json["SHORT"], format::MASK);
+        assert_eq!(json["LONG"], format::MASK);
+    }
+
+    #[test]
+    fn test_generate_env_template_lists_names_only() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let key = [0x42u8; 32];
+        let store = store_with(temp_dir.path(), &key, &[("B_KEY", "b"), ("A_KEY", "a")]);
+
+        let path = temp_dir.path().join(".env.example");
+        generate_env_template(&store, &path).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+
+        let lines: Vec<&str> = content.lines().filter(|l| !l.starts_with('#')).collect();
+        assert_eq!(lines, vec!["", "A_KEY=", "B_KEY="]);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_generate_env_file_is_sourceable() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let key = [0x42u8; 32];
+        let store = store_with(
+            temp_dir.path(),
+            &key,
+            &[("API_KEY", "sk 123"), ("_DB_URL2", "postgres://db")],
+        );
+
+        let path = temp_dir.path().join(".env");
+        generate_env_file(&store, &key, &path, false).unwrap();
+        let output = Command::new("sh")
+            .arg("-c")
+            .arg(r#"set -e; . "$1"; printf '%s|%s' "$API_KEY" "$_DB_URL2""#)
+            .arg("sh")
+            .arg(&path)
+            .output()
+            .unwrap();
+
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"sk 123|postgres://db");
+    }
+
     // ========================
     // generate_python_wrapper tests
     // ========================
@@ -433,4 +741,123 @@ mod tests {
         assert!(wrapper.contains("/home/user/.lazy-locker"));
         assert!(wrapper.contains("def main()"));
     }
+
+    // ========================
+    // write_secret_file tests
+    // ========================
+
+    #[test]
+    fn test_write_secret_file_is_owner_only() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("token.txt");
+        fs::write(&path, "previous content that is longer").unwrap();
+
+        write_secret_file("sk-123", &path).expect("Failed to write secret");
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "sk-123");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    // ========================
+    // clipboard auto-clear tests
+    // ========================
+
+    #[test]
+    fn test_clear_if_unchanged_clears_our_value() {
+        let mut written = None;
+
+        let cleared = clear_if_unchanged(
+            "sk-123",
+            || Ok("sk-123".to_string()),
+            |v| {
+                written = Some(v.to_string());
+                Ok(())
+            },
+        )
+        .unwrap();
+
+        assert!(cleared);
+        assert_eq!(written, Some(String::new()));
+    }
+
+    #[test]
+    fn test_clear_if_unchanged_keeps_newer_copy() {
+        let mut written = None;
+
+        let cleared = clear_if_unchanged(
+            "sk-123",
+            || Ok("copied afterwards".to_string()),
+            |v| {
+                written = Some(v.to_string());
+                Ok(())
+            },
+        )
+        .unwrap();
+
+        assert!(!cleared);
+        assert_eq!(written, None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_execute_with_secrets_injects_only_given_secrets() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let key = [0x42u8; 32];
+        let mut store = crate::core::store::SecretsStore::new();
+        for (name, value) in [("API_KEY", "sk-123"), ("DB_URL", "postgres://db")] {
+            store
+                .add_secret(
+                    name.to_string(),
+                    value.to_string(),
+                    None,
+                    temp_dir.path(),
+                    &key,
+                )
+                .expect("Failed to add secret");
+        }
+        let api_key = store.get_secret("API_KEY").unwrap();
+        let argv: Vec<String> = [
+            "sh",
+            "-c",
+            r#"[ "$API_KEY" = sk-123 ] && [ -z "${DB_URL+x}" ]"#,
+        ]
+        .iter()
+        .map(|a| a.to_string())
+        .collect();
+
+        let status = execute_with_secrets(&argv, false, &[api_key], &key, None).unwrap();
+
+        assert!(status.success());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_with_env_keeps_arguments_and_exit_code() {
+        let env_vars = BTreeMap::new();
+        let argv: Vec<String> = [
+            "sh",
+            "-c",
+            r#"[ "$1" = "a b" ] && [ "$2" = "*" ] && exit 7"#,
+            "_",
+            "a b",
+            "*",
+        ]
+        .iter()
+        .map(|a| a.to_string())
+        .collect();
+
+        let status = run_with_env(&argv, false, &env_vars).unwrap();
+        assert_eq!(status.code(), Some(7));
+
+        let shell = ["exit", "3"].map(String::from);
+        assert_eq!(
+            run_with_env(&shell, true, &env_vars).unwrap().code(),
+            Some(3)
+        );
+    }
 }
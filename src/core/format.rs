@@ -0,0 +1,306 @@
+//! Output rendering shared by the CLI and the executor.
+//!
+//! Every renderer takes `(name, value)` pairs in the order they should appear,
+//! so a new output format only has to be added here.
+
+use anyhow::Result;
+
+use crate::core::store::{PlainSecret, Secret, format_timestamp};
+
+/// Placeholder written instead of a value by masked exports.
+/// Fixed width, so the output reveals nothing about value lengths.
+pub const MASK: &str = "********";
+
+/// Renders `.env` lines: `NAME="value"`, escaping `\`, `"` and newlines.
+/// This is what `parse_env_format` reads back.
+pub fn to_env(entries: &[(&str, &str)]) -> String {
+    let mut out = String::new();
+    for (name, value) in entries {
+        out.push_str(name);
+        out.push_str("=\"");
+        for c in value.chars() {
+            match c {
+                '\\' => out.push_str("\\\\"),
+                '"' => out.push_str("\\\""),
+                '\n' => out.push_str("\\n"),
+                c => out.push(c),
+            }
+        }
+        out.push_str("\"\n");
+    }
+    out
+}
+
+/// Renders POSIX `export NAME='value'` lines for `eval` or `source`.
+pub fn to_shell_exports(entries: &[(&str, &str)]) -> String {
+    entries
+        .iter()
+        .map(|(name, value)| format!("export {}={}\n", name, shell_quote(value)))
+        .collect()
+}
+
+/// Renders fish `set -gx NAME "value"` lines, escaping `\`, `"` and `$`.
+pub fn to_fish_exports(entries: &[(&str, &str)]) -> String {
+    entries
+        .iter()
+        .map(|(name, value)| {
+            let escaped_value = value
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('$', "\\$");
+            format!("set -gx {} \"{}\"\n", name, escaped_value)
+        })
+        .collect()
+}
+
+/// Renders a pretty JSON object `{"NAME": "value"}` (keys sorted).
+pub fn to_json(entries: &[(&str, &str)]) -> Result<String> {
+    let obj: serde_json::Map<String, serde_json::Value> = entries
+        .iter()
+        .map(|(name, value)| (name.to_string(), serde_json::Value::from(*value)))
+        .collect();
+    Ok(serde_json::to_string_pretty(&obj)?)
+}
+
+/// Renders the lossless JSON array `[{name, value, expires_at, tags, description}]`.
+pub fn to_json_with_metadata(secrets: &[PlainSecret]) -> Result<String> {
+    Ok(serde_json::to_string_pretty(secrets)?)
+}
+
+/// Renders a flat YAML mapping `NAME: "value"`.
+/// Values are JSON-escaped, which is valid YAML double-quoted scalar syntax.
+pub fn to_yaml(entries: &[(&str, &str)]) -> Result<String> {
+    let mut out = String::new();
+    for (name, value) in entries {
+        out.push_str(&format!("{}: {}\n", name, serde_json::to_string(value)?));
+    }
+    Ok(out)
+}
+
+/// Renders a metadata-only CSV with the header
+/// `name,expires_at,is_expired,days_remaining,tags`, for spreadsheets.
+/// Values are never included; permanent tokens leave the expiration cells empty.
+pub fn to_csv(secrets: &[&Secret]) -> String {
+    let mut out = String::from("name,expires_at,is_expired,days_remaining,tags\n");
+    for secret in secrets {
+        let fields = [
+            secret.name.clone(),
+            secret.expires_at.map(format_timestamp).unwrap_or_default(),
+            secret.is_expired().to_string(),
+            secret
+                .days_until_expiration()
+                .map(|days| days.to_string())
+                .unwrap_or_default(),
+            secret.tags.join(","),
+        ];
+        let row: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+        out.push_str(&row.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+/// Quotes a CSV field when it holds a comma, quote or line break (RFC 4180),
+/// doubling the quotes inside
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Renders one line per entry from a template such as `{name}={value}`.
+pub fn to_template(entries: &[(&str, &str)], template: &str) -> String {
+    entries
+        .iter()
+        .map(|(name, value)| {
+            let line = template.replace("{name}", name).replace("{value}", value);
+            format!("{}\n", line)
+        })
+        .collect()
+}
+
+/// Quotes a value for POSIX shells (sh, bash, zsh).
+///
+/// The value is wrapped in single quotes, inside which nothing is expanded;
+/// each embedded `'` is written as `'\''` (close, escaped quote, reopen).
+/// The result is safe in `export NAME=<quoted>` lines meant for `eval` or `source`.
+pub fn shell_quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('\'');
+    for c in value.chars() {
+        if c == '\'' {
+            quoted.push_str(r"'\''");
+        } else {
+            quoted.push(c);
+        }
+    }
+    quoted.push('\'');
+    quoted
+}
+
+/// Shortens `text` to at most `max` characters for display, ending with `…`
+/// when cut. Counts characters rather than bytes, so accented or CJK text is
+/// never split inside a character.
+pub fn truncate_chars(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(max.saturating_sub(1)).collect();
+    if max > 0 {
+        truncated.push('…');
+    }
+    truncated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENTRIES: &[(&str, &str)] = &[("API_KEY", "sk-123"), ("MOTD", "say \"hi\"\nC:\\tmp")];
+
+    #[test]
+    fn test_to_env_escapes_values() {
+        assert_eq!(
+            to_env(ENTRIES),
+            "API_KEY=\"sk-123\"\nMOTD=\"say \\\"hi\\\"\\nC:\\\\tmp\"\n"
+        );
+        assert_eq!(to_env(&[]), "");
+    }
+
+    #[test]
+    fn test_to_shell_exports() {
+        assert_eq!(to_shell_exports(&[("A", "it's")]), "export A='it'\\''s'\n");
+    }
+
+    #[test]
+    fn test_to_fish_exports() {
+        assert_eq!(
+            to_fish_exports(&[("A", "$x \"y\"")]),
+            "set -gx A \"\\$x \\\"y\\\"\"\n"
+        );
+    }
+
+    #[test]
+    fn test_to_json_object() {
+        let json: serde_json::Value = serde_json::from_str(&to_json(ENTRIES).unwrap()).unwrap();
+
+        assert_eq!(json["API_KEY"], "sk-123");
+        assert_eq!(json["MOTD"], "say \"hi\"\nC:\\tmp");
+    }
+
+    #[test]
+    fn test_to_yaml_quotes_values() {
+        assert_eq!(
+            to_yaml(ENTRIES).unwrap(),
+            "API_KEY: \"sk-123\"\nMOTD: \"say \\\"hi\\\"\\nC:\\\\tmp\"\n"
+        );
+    }
+
+    #[test]
+    fn test_to_csv_quotes_commas_and_quotes() {
+        let tricky = Secret {
+            name: "DB,\"PRIMARY\"".to_string(),
+            expires_at: Some(4102444800),
+            tags: vec!["ci".to_string(), "db".to_string()],
+            ..Default::default()
+        };
+        let plain = Secret {
+            name: "API_KEY".to_string(),
+            ..Default::default()
+        };
+
+        let csv = to_csv(&[&tricky, &plain]);
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next(),
+            Some("name,expires_at,is_expired,days_remaining,tags")
+        );
+        let row = lines.next().unwrap();
+        assert!(row.starts_with("\"DB,\"\"PRIMARY\"\"\",2100-01-01 00:00 UTC,false,"));
+        assert!(row.ends_with(",\"ci,db\""));
+        assert_eq!(lines.next(), Some("API_KEY,,false,,"));
+        assert_eq!(lines.next(), None);
+    }
+
+    #[test]
+    fn test_to_template() {
+        assert_eq!(
+            to_template(&[("A", "1"), ("B", "2")], "{name}: {value}"),
+            "A: 1\nB: 2\n"
+        );
+    }
+
+    #[test]
+    fn test_shell_quote_plain_and_empty() {
+        assert_eq!(shell_quote("value"), "'value'");
+        assert_eq!(shell_quote(""), "''");
+    }
+
+    #[test]
+    fn test_shell_quote_spaces() {
+        assert_eq!(shell_quote("two words"), "'two words'");
+    }
+
+    #[test]
+    fn test_shell_quote_single_quotes() {
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+        assert_eq!(shell_quote("'"), r"''\'''");
+    }
+
+    #[test]
+    fn test_shell_quote_leaves_other_metacharacters_literal() {
+        assert_eq!(shell_quote(r#"say "hi""#), r#"'say "hi"'"#);
+        assert_eq!(shell_quote("$HOME ${PATH}"), "'$HOME ${PATH}'");
+        assert_eq!(shell_quote("`whoami` $(id)"), "'`whoami` $(id)'");
+        assert_eq!(shell_quote(r"back\slash"), r"'back\slash'");
+    }
+
+    #[test]
+    fn test_shell_quote_newlines_and_unicode() {
+        assert_eq!(shell_quote("line1\nline2"), "'line1\nline2'");
+        assert_eq!(shell_quote("clé 🔑"), "'clé 🔑'");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_shell_quote_roundtrips_through_sh() {
+        let values = [
+            "",
+            "two words",
+            "it's",
+            r#"say "hi""#,
+            "$HOME `whoami` $(id)",
+            r"back\slash",
+            "line1\nline2",
+            "clé 🔑",
+        ];
+
+        for value in values {
+            let output = std::process::Command::new("sh")
+                .arg("-c")
+                .arg(format!("printf '%s' {}", shell_quote(value)))
+                .output()
+                .expect("Failed to run sh");
+
+            assert_eq!(String::from_utf8(output.stdout).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn test_truncate_chars_keeps_multibyte_characters_whole() {
+        // 39 ASCII bytes, then 'é' spans bytes 39 and 40
+        let line = format!("{}é comment with accents", "x".repeat(39));
+        assert!(!line.is_char_boundary(40));
+
+        let truncated = truncate_chars(&line, 41);
+        assert_eq!(truncated, format!("{}é…", "x".repeat(39)));
+        assert_eq!(truncated.chars().count(), 41);
+
+        assert_eq!(truncate_chars("short", 10), "short");
+        assert_eq!(truncate_chars("clé de déploiement", 6), "clé d…");
+        assert_eq!(truncate_chars("日本語", 2), "日…");
+        assert_eq!(truncate_chars("日本語", 0), "");
+    }
+}
@@ -0,0 +1,150 @@
+//! User commands run when the CLI changes secrets, e.g. to trigger a redeploy.
+//!
+//! The `[hooks]` section of config.toml holds the commands. Each is run by the
+//! system shell, detached, with the change described in environment variables:
+//! the action and the affected names, never a value. The locker passphrase is
+//! removed from its environment. A hook never fails the command: its output
+//! goes to `hooks.log` in the locker directory, and a hook that cannot start
+//! only prints a warning.
+
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::core::cli::PASSPHRASE_ENV_VAR;
+use crate::core::config::Config;
+use crate::core::store::{format_timestamp, now_timestamp};
+
+/// Log of the hooks' output, next to the secrets
+pub const LOG_FILE: &str = "hooks.log";
+
+/// Set by `--no-hooks` for the rest of the process
+static DISABLED: AtomicBool = AtomicBool::new(false);
+
+/// Skips every hook from now on
+pub fn disable() {
+    DISABLED.store(true, Ordering::Relaxed);
+}
+
+/// How the secrets changed, passed to the hook as `LAZY_LOCKER_ACTION`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeAction {
+    Add,
+    Update,
+    Remove,
+    Rename,
+    Import,
+}
+
+impl ChangeAction {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ChangeAction::Add => "add",
+            ChangeAction::Update => "update",
+            ChangeAction::Remove => "remove",
+            ChangeAction::Rename => "rename",
+            ChangeAction::Import => "import",
+        }
+    }
+}
+
+/// Runs the `on_change` hook of the locker in `locker_dir`, if one is set,
+/// for a change of `names`. Never fails: problems are reported on stderr.
+pub fn on_change(locker_dir: &Path, action: ChangeAction, names: &[&str]) {
+    if DISABLED.load(Ordering::Relaxed) || names.is_empty() {
+        return;
+    }
+    let config = Config::load_or_default(locker_dir);
+    let Some(command) = config.hooks.on_change.filter(|c| !c.trim().is_empty()) else {
+        return;
+    };
+    if let Err(e) = spawn(&command, locker_dir, action, names) {
+        eprintln!("⚠️  on_change hook not run: {:#}", e);
+    }
+}
+
+/// Starts `command` without waiting for it
+fn spawn(command: &str, locker_dir: &Path, action: ChangeAction, names: &[&str]) -> Result<()> {
+    let log_path = locker_dir.join(LOG_FILE);
+    let mut log = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&log_path)
+        .with_context(|| format!("cannot open {}", log_path.display()))?;
+    writeln!(
+        log,
+        "[{}] on_change {} {}",
+        format_timestamp(now_timestamp()),
+        action.as_str(),
+        names.join(",")
+    )?;
+
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    shell
+        .arg(command)
+        .env_remove(PASSPHRASE_ENV_VAR)
+        .env("LAZY_LOCKER_ACTION", action.as_str())
+        .env("LAZY_LOCKER_NAME", names[0])
+        .env("LAZY_LOCKER_NAMES", names.join(","))
+        .env("LAZY_LOCKER_DIR", locker_dir)
+        .stdin(Stdio::null())
+        .stdout(log.try_clone()?)
+        .stderr(log)
+        .spawn()
+        .context("cannot start the shell")?;
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    /// Waits for a detached hook to write `path`
+    fn wait_for(path: &Path) -> String {
+        for _ in 0..100 {
+            if let Ok(content) = std::fs::read_to_string(path)
+                && content.ends_with('\n')
+            {
+                return content;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+        panic!("hook did not write {}", path.display());
+    }
+
+    #[test]
+    fn test_on_change_runs_hook_with_action_and_names() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            dir.path().join("config.toml"),
+            "[hooks]\non_change = 'echo \"$LAZY_LOCKER_ACTION $LAZY_LOCKER_NAMES\" > \"$LAZY_LOCKER_DIR/marker\"'\n",
+        )
+        .unwrap();
+
+        on_change(dir.path(), ChangeAction::Remove, &["API_KEY", "DB_URL"]);
+
+        let marker = wait_for(&dir.path().join("marker"));
+        assert_eq!(marker, "remove API_KEY,DB_URL\n");
+        let log = std::fs::read_to_string(dir.path().join(LOG_FILE)).unwrap();
+        assert!(log.contains("on_change remove API_KEY,DB_URL"));
+    }
+
+    #[test]
+    fn test_on_change_without_hook_does_nothing() {
+        let dir = tempfile::TempDir::new().unwrap();
+
+        on_change(dir.path(), ChangeAction::Add, &["API_KEY"]);
+
+        assert!(!dir.path().join(LOG_FILE).exists());
+    }
+}
@@ -1,11 +1,202 @@
-use anyhow::Result;
+use crate::core::config::Config;
+use crate::core::lock::StoreLock;
+use crate::core::recovery::{self, RecoveryFile};
+use crate::core::store::SecretsStore;
+use anyhow::{Context, Result};
 use argon2::{
-    Argon2,
+    Algorithm, Argon2, Params, Version,
     password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString, rand_core::OsRng},
 };
-use directories::BaseDirs;
-use std::path::PathBuf;
-use zeroize::Zeroize;
+use serde::{Deserialize, Serialize};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use zeroize::{Zeroize, Zeroizing};
+
+/// The passphrase does not match the locker's stored hash
+#[derive(Debug)]
+pub struct IncorrectPassphrase;
+
+impl std::fmt::Display for IncorrectPassphrase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Incorrect passphrase")
+    }
+}
+
+impl std::error::Error for IncorrectPassphrase {}
+
+/// Turns an IO error from writing under the locker into an actionable message
+/// naming the path and the likely cause, instead of a raw `Os { code: .. }`.
+pub fn write_error(path: &Path, err: std::io::Error) -> anyhow::Error {
+    let hint = match err.kind() {
+        ErrorKind::PermissionDenied => "permission denied — is this filesystem read-only?",
+        ErrorKind::ReadOnlyFilesystem => "read-only filesystem",
+        ErrorKind::StorageFull | ErrorKind::QuotaExceeded => "no space left — is the disk full?",
+        _ => return anyhow::Error::new(err).context(format!("cannot write to {}", path.display())),
+    };
+    anyhow::anyhow!("cannot write to {}: {}", path.display(), hint)
+}
+
+/// Argon2id costs used when creating a locker. An existing locker always
+/// derives its key with the parameters recorded in its `hash` file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KdfParams {
+    /// Memory cost in KiB
+    pub m_cost: u32,
+    /// Number of passes
+    pub t_cost: u32,
+    /// Degree of parallelism
+    pub p_cost: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self {
+            m_cost: Params::DEFAULT_M_COST,
+            t_cost: Params::DEFAULT_T_COST,
+            p_cost: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+impl KdfParams {
+    /// Rejects parameters weaker than the defaults
+    pub fn validate(&self) -> Result<()> {
+        let default = Self::default();
+        if self.m_cost < default.m_cost || self.t_cost < default.t_cost {
+            anyhow::bail!(
+                "Key derivation costs below the defaults ({} MiB, {} passes) are not allowed",
+                default.m_cost / 1024,
+                default.t_cost
+            );
+        }
+        Ok(())
+    }
+
+    pub fn argon2(&self) -> Result<Argon2<'static>> {
+        let params = Params::new(self.m_cost, self.t_cost, self.p_cost, None)
+            .map_err(|e| anyhow::anyhow!("Invalid key derivation parameters: {}", e))?;
+        Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
+    }
+}
+
+impl std::fmt::Display for KdfParams {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Argon2id, {} MiB, {} passes, {} lane(s)",
+            self.m_cost / 1024,
+            self.t_cost,
+            self.p_cost
+        )
+    }
+}
+
+/// Rough strength of a passphrase, as reported by `estimate_strength`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Strength {
+    Weak,
+    Fair,
+    Strong,
+}
+
+impl Strength {
+    pub fn label(self) -> &'static str {
+        match self {
+            Strength::Weak => "weak",
+            Strength::Fair => "fair",
+            Strength::Strong => "strong",
+        }
+    }
+}
+
+/// Passphrases that appear at the top of every leaked-password list
+const COMMON_PASSPHRASES: &[&str] = &[
+    "123456",
+    "12345678",
+    "123456789",
+    "1234567890",
+    "password",
+    "password1",
+    "passw0rd",
+    "qwerty",
+    "qwertyuiop",
+    "azerty",
+    "letmein",
+    "welcome",
+    "iloveyou",
+    "admin",
+    "secret",
+    "changeme",
+    "trustno1",
+    "abc123",
+    "111111",
+    "000000",
+    "monkey",
+    "dragon",
+    "sunshine",
+];
+
+/// Estimates how hard a passphrase is to guess from its length, the character
+/// classes it mixes and a short list of common passwords. This is a hint for
+/// the user, not a guarantee: length dominates, since a long passphrase of
+/// plain words is stronger than a short one full of symbols.
+pub fn estimate_strength(pass: &str) -> Strength {
+    let length = pass.chars().count();
+    let distinct: std::collections::HashSet<char> = pass.chars().collect();
+    if length < 8
+        || distinct.len() < 4
+        || COMMON_PASSPHRASES.contains(&pass.to_lowercase().as_str())
+    {
+        return Strength::Weak;
+    }
+
+    let classes = [
+        pass.chars().any(|c| c.is_lowercase()),
+        pass.chars().any(|c| c.is_uppercase()),
+        pass.chars().any(|c| c.is_ascii_digit()),
+        pass.chars().any(|c| !c.is_alphanumeric()),
+    ]
+    .iter()
+    .filter(|&&present| present)
+    .count();
+
+    if length >= 16 || (length >= 12 && classes >= 3) {
+        Strength::Strong
+    } else if length >= 12 || classes >= 2 {
+        Strength::Fair
+    } else {
+        Strength::Weak
+    }
+}
+
+/// The Argon2 instance recorded in a PHC hash: variant, version and costs
+fn argon2_from_hash(hash: &PasswordHash) -> Result<Argon2<'static>> {
+    let algorithm = Algorithm::try_from(hash.algorithm)
+        .map_err(|e| anyhow::anyhow!("Unsupported hash algorithm: {}", e))?;
+    let version = match hash.version {
+        Some(version) => Version::try_from(version)
+            .map_err(|e| anyhow::anyhow!("Unsupported Argon2 version: {}", e))?,
+        None => Version::default(),
+    };
+    let params =
+        Params::try_from(hash).map_err(|e| anyhow::anyhow!("Invalid hash parameters: {}", e))?;
+    Ok(Argon2::new(algorithm, version, params))
+}
+
+/// Derives the 256-bit encryption key from the passphrase and the raw salt
+fn derive_key(argon2: &Argon2, passphrase: &str, salt: &SaltString) -> Result<Vec<u8>> {
+    let mut salt_bytes = [0u8; 16];
+    salt.decode_b64(&mut salt_bytes)
+        .map_err(|e| anyhow::anyhow!("Salt decoding error: {}", e))?;
+    let mut key = [0u8; 32];
+    argon2
+        .hash_password_into(passphrase.as_bytes(), &salt_bytes, &mut key)
+        .map_err(|e| anyhow::anyhow!("Key derivation error: {}", e))?;
+
+    let result = key.to_vec();
+    key.zeroize();
+    Ok(result)
+}
 
 pub struct Locker {
     base_dir: PathBuf,
@@ -16,17 +207,8 @@ impl Locker {
     /// Tries to create the locker without prompt (checks if already initialized).
     #[allow(dead_code)]
     pub fn try_new() -> Result<Self> {
-        let base_dirs = BaseDirs::new()
-            .ok_or_else(|| anyhow::anyhow!("Unable to determine user directories"))?;
-        let config_dir = base_dirs.config_dir();
-
-        #[cfg(unix)]
-        let sub_dir = ".lazy-locker";
-        #[cfg(not(unix))]
-        let sub_dir = "lazy-locker";
-
-        let locker_dir = config_dir.join(sub_dir);
-        std::fs::create_dir_all(&locker_dir)?;
+        let locker_dir = Config::get_locker_dir()?;
+        std::fs::create_dir_all(&locker_dir).map_err(|e| write_error(&locker_dir, e))?;
 
         let salt_path = locker_dir.join("salt");
         if !salt_path.exists() {
@@ -39,56 +221,212 @@ impl Locker {
 
     /// Initializes or loads the locker with the provided passphrase.
     pub fn init_or_load_with_passphrase(passphrase: &str) -> Result<Self> {
-        let base_dirs = BaseDirs::new()
-            .ok_or_else(|| anyhow::anyhow!("Unable to determine user directories"))?;
-        let config_dir = base_dirs.config_dir();
-
-        #[cfg(unix)]
-        let sub_dir = ".lazy-locker";
-        #[cfg(not(unix))]
-        let sub_dir = "lazy-locker";
+        Self::init_or_load_at(&Config::get_locker_dir()?, passphrase)
+    }
 
-        let locker_dir = config_dir.join(sub_dir);
-        std::fs::create_dir_all(&locker_dir)?;
+    /// Like `init_or_load_with_passphrase`, for the locker in `locker_dir`
+    /// instead of the active profile's
+    pub fn init_or_load_at(locker_dir: &Path, passphrase: &str) -> Result<Self> {
+        std::fs::create_dir_all(locker_dir).map_err(|e| write_error(locker_dir, e))?;
 
         let salt_path = locker_dir.join("salt");
         let key = if salt_path.exists() {
-            Self::load_key(&locker_dir, passphrase)?
+            Self::open_key(locker_dir, passphrase)?
         } else {
-            Self::init_key(&locker_dir, passphrase)?
+            Self::init_key(locker_dir, passphrase)?
         };
 
+        Ok(Self {
+            base_dir: locker_dir.to_path_buf(),
+            key: Some(key),
+        })
+    }
+
+    /// Creates a new locker whose key is derived with `params`.
+    /// Fails if the locker already exists.
+    pub fn create(passphrase: &str, params: &KdfParams) -> Result<Self> {
+        let locker_dir = Config::get_locker_dir()?;
+        std::fs::create_dir_all(&locker_dir).map_err(|e| write_error(&locker_dir, e))?;
+        if locker_dir.join("salt").exists() {
+            anyhow::bail!("Locker already exists at {:?}", locker_dir);
+        }
+
+        let key = Self::init_key_with(&locker_dir, passphrase, params)?;
         Ok(Self {
             base_dir: locker_dir,
             key: Some(key),
         })
     }
 
+    /// Loads an existing locker, failing if it was never initialized.
+    /// Unlike `init_or_load_with_passphrase`, this never creates a new locker.
+    pub fn load_existing(passphrase: &str) -> Result<Self> {
+        let locker_dir = Config::get_locker_dir()?;
+        if !locker_dir.join("salt").exists() {
+            return Err(anyhow::anyhow!("Locker not initialized"));
+        }
+
+        let key = Self::open_key(&locker_dir, passphrase)?;
+        Ok(Self {
+            base_dir: locker_dir,
+            key: Some(key),
+        })
+    }
+
+    /// Changes the passphrase and re-encrypts every secret under a key derived
+    /// from a fresh salt. Returns the number of secrets re-encrypted.
+    ///
+    /// The new `salt`, `hash` and `secrets.json` are all written to temporary
+    /// files and fsynced before any is renamed into place, so a failure midway
+    /// leaves the original files intact. This locker keeps the old key; load
+    /// the locker again with the new passphrase to keep working.
+    pub fn change_passphrase(&self, old: &str, new: &str) -> Result<usize> {
+        let _lock = StoreLock::acquire(&self.base_dir)?;
+        let old_key = Zeroizing::new(Self::load_key(&self.base_dir, old)?);
+        Self::rekey(&self.base_dir, &old_key, new)
+    }
+
+    /// Sets a new passphrase using the recovery key instead of the old
+    /// passphrase. Returns the number of secrets re-encrypted.
+    pub fn recover(recovery_key: &str, new_passphrase: &str) -> Result<usize> {
+        let locker_dir = Config::get_locker_dir()?;
+        if !locker_dir.join("salt").exists() {
+            return Err(anyhow::anyhow!("Locker not initialized"));
+        }
+        Self::recover_in(&locker_dir, recovery_key, new_passphrase)
+    }
+
+    fn recover_in(locker_dir: &Path, recovery_key: &str, new_passphrase: &str) -> Result<usize> {
+        let _lock = StoreLock::acquire(locker_dir)?;
+        undo_interrupted_replace(locker_dir)?;
+        let file = RecoveryFile::read(locker_dir)?
+            .ok_or_else(|| anyhow::anyhow!("Recovery is not enabled for this locker"))?;
+        let key = file.open(recovery_key)?;
+        Self::rekey(locker_dir, &key, new_passphrase)
+    }
+
+    /// Creates a recovery key for this locker, replacing any previous one.
+    /// The returned key is not stored anywhere: show it to the user once.
+    pub fn enable_recovery(&self) -> Result<Zeroizing<String>> {
+        let key = self.get_key().context("Locker is not unlocked")?;
+        let _lock = StoreLock::acquire(&self.base_dir)?;
+
+        let recovery_key = recovery::generate_recovery_key();
+        let file = RecoveryFile::seal(&recovery_key, key)?;
+        replace_files(
+            &self.base_dir,
+            &[(recovery::RECOVERY_FILE, &file.to_bytes()?)],
+        )?;
+        Ok(recovery_key)
+    }
+
+    /// Removes the recovery key. Returns false if recovery was not enabled.
+    pub fn disable_recovery(&self) -> Result<bool> {
+        let _lock = StoreLock::acquire(&self.base_dir)?;
+        let path = self.base_dir.join(recovery::RECOVERY_FILE);
+        match std::fs::remove_file(&path) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(write_error(&path, e)),
+        }
+    }
+
+    /// Re-encrypts the locker from `old_key` to a key derived from
+    /// `passphrase` with a fresh salt. The recovery file, if any, is re-sealed
+    /// in the same atomic replacement so the recovery key keeps working.
+    /// The caller holds the store lock.
+    fn rekey(locker_dir: &Path, old_key: &[u8], passphrase: &str) -> Result<usize> {
+        // Keep the costs the locker was created with
+        let params = Self::kdf_params(locker_dir)?;
+        let (salt, hash, new_key) = Self::new_key_material(passphrase, &params)?;
+        let new_key = Zeroizing::new(new_key);
+
+        let mut store = SecretsStore::load(locker_dir, old_key)?;
+        let count = store.reencrypt(old_key, &new_key)?;
+        let secrets = store.to_encrypted_bytes(&new_key)?;
+        let recovery = match RecoveryFile::read(locker_dir)? {
+            Some(file) => Some(file.rekey(old_key, &new_key)?.to_bytes()?),
+            None => None,
+        };
+
+        // The store is swapped first and the key material last, all under one
+        // journal so a crash in between is undone on the next open
+        let mut files: Vec<(&str, &[u8])> = vec![
+            ("secrets.json", &secrets),
+            ("hash", hash.as_bytes()),
+            ("salt", salt.as_str().as_bytes()),
+        ];
+        if let Some(ref recovery) = recovery {
+            files.push((recovery::RECOVERY_FILE, recovery));
+        }
+        replace_files(locker_dir, &files)?;
+        Ok(count)
+    }
+
     /// Initializes the key for the first time: generates salt, asks passphrase, derives key.
-    fn init_key(locker_dir: &std::path::Path, passphrase: &str) -> Result<Vec<u8>> {
+    fn init_key(locker_dir: &Path, passphrase: &str) -> Result<Vec<u8>> {
+        Self::init_key_with(locker_dir, passphrase, &KdfParams::default())
+    }
+
+    /// Same as `init_key`, deriving with `params`
+    fn init_key_with(locker_dir: &Path, passphrase: &str, params: &KdfParams) -> Result<Vec<u8>> {
+        let (salt, hash, key) = Self::new_key_material(passphrase, params)?;
+
+        write_synced(&locker_dir.join("salt"), salt.as_str().as_bytes())?;
+        write_synced(&locker_dir.join("hash"), hash.as_bytes())?;
+
+        Ok(key)
+    }
+
+    /// Generates a fresh salt, then the passphrase hash and derived key for it.
+    /// The hash records `params`, which `load_key` derives with later.
+    fn new_key_material(
+        passphrase: &str,
+        params: &KdfParams,
+    ) -> Result<(SaltString, String, Vec<u8>)> {
         let salt = SaltString::generate(&mut OsRng);
-        std::fs::write(locker_dir.join("salt"), salt.as_str())?;
 
-        let argon2 = Argon2::default();
-        let hash = argon2
+        let hash = params
+            .argon2()?
             .hash_password(passphrase.as_bytes(), &salt)
             .map_err(|e| anyhow::anyhow!("Hash error: {}", e))?
             .to_string();
-        std::fs::write(locker_dir.join("hash"), &hash)?;
 
-        let mut key = [0u8; 32];
-        let mut salt_bytes = [0u8; 16];
-        salt.decode_b64(&mut salt_bytes)
-            .map_err(|e| anyhow::anyhow!("Salt decoding error: {}", e))?;
-        argon2
-            .hash_password_into(passphrase.as_bytes(), &salt_bytes, &mut key)
-            .map_err(|e| anyhow::anyhow!("Key derivation error: {}", e))?;
+        // Derive from the hash just recorded, exactly as `load_key` will
+        let recorded =
+            PasswordHash::new(&hash).map_err(|e| anyhow::anyhow!("Hash error: {}", e))?;
+        let key = derive_key(&argon2_from_hash(&recorded)?, passphrase, &salt)?;
+
+        Ok((salt, hash, key))
+    }
 
-        Ok(key.to_vec())
+    /// Key derivation costs recorded in the locker's `hash` file
+    pub fn kdf_params(locker_dir: &Path) -> Result<KdfParams> {
+        let hash_str = std::fs::read_to_string(locker_dir.join("hash"))?;
+        let hash =
+            PasswordHash::new(&hash_str).map_err(|e| anyhow::anyhow!("Hash error: {}", e))?;
+        let params = Params::try_from(&hash)
+            .map_err(|e| anyhow::anyhow!("Invalid hash parameters: {}", e))?;
+        Ok(KdfParams {
+            m_cost: params.m_cost(),
+            t_cost: params.t_cost(),
+            p_cost: params.p_cost(),
+        })
     }
 
     /// Loads existing key: reads salt, asks passphrase, verifies and derives.
-    fn load_key(locker_dir: &std::path::Path, passphrase: &str) -> Result<Vec<u8>> {
+    /// Both use the Argon2 variant, version and costs recorded in the hash,
+    /// so the key never depends on the library's current defaults.
+    /// Like `load_key`, first undoing a key change a crash interrupted
+    fn open_key(locker_dir: &Path, passphrase: &str) -> Result<Vec<u8>> {
+        if locker_dir.join(REPLACE_JOURNAL).exists() {
+            let _lock = StoreLock::acquire(locker_dir)?;
+            undo_interrupted_replace(locker_dir)?;
+        }
+        Self::load_key(locker_dir, passphrase)
+    }
+
+    fn load_key(locker_dir: &Path, passphrase: &str) -> Result<Vec<u8>> {
         let salt_str = std::fs::read_to_string(locker_dir.join("salt"))?;
         let salt =
             SaltString::from_b64(&salt_str).map_err(|e| anyhow::anyhow!("Salt error: {}", e))?;
@@ -97,20 +435,12 @@ impl Locker {
         let expected_hash =
             PasswordHash::new(&hash_str).map_err(|e| anyhow::anyhow!("Hash error: {}", e))?;
 
-        let argon2 = Argon2::default();
+        let argon2 = argon2_from_hash(&expected_hash)?;
         argon2
             .verify_password(passphrase.as_bytes(), &expected_hash)
-            .map_err(|e| anyhow::anyhow!("Incorrect passphrase: {}", e))?;
-
-        let mut salt_bytes = [0u8; 16];
-        salt.decode_b64(&mut salt_bytes)
-            .map_err(|e| anyhow::anyhow!("Salt decoding error: {}", e))?;
-        let mut key = [0u8; 32];
-        argon2
-            .hash_password_into(passphrase.as_bytes(), &salt_bytes, &mut key)
-            .map_err(|e| anyhow::anyhow!("Key derivation error: {}", e))?;
+            .map_err(|_| IncorrectPassphrase)?;
 
-        Ok(key.to_vec())
+        derive_key(&argon2, passphrase, &salt)
     }
 
     /// Returns the path to a file in the locker.
@@ -130,6 +460,244 @@ impl Locker {
     }
 }
 
+/// Journal naming the files of a multi-file replacement still in progress
+pub const REPLACE_JOURNAL: &str = "replace.journal";
+
+/// Replaces files in `dir` together. Every new content is written to a
+/// `.tmp` sibling and fsynced before the first rename, so if any write fails
+/// the temporaries are removed and the original files are left untouched.
+///
+/// Each original is kept as a `.bak` hard link until every rename succeeded:
+/// a failed rename puts back the files already replaced. When several files
+/// change, their names are journaled first so a replacement interrupted by a
+/// crash is undone by `undo_interrupted_replace` on the next open.
+pub fn replace_files(dir: &Path, files: &[(&str, &[u8])]) -> Result<()> {
+    let names: Vec<&str> = files.iter().map(|(name, _)| *name).collect();
+    let mut staged = Vec::with_capacity(files.len());
+    for (name, content) in files {
+        let tmp_path = dir.join(format!("{}.tmp", name));
+        let written = write_synced(&tmp_path, content);
+        staged.push(tmp_path);
+        if let Err(e) = written {
+            discard_staged(dir, &names);
+            return Err(e);
+        }
+    }
+
+    let journal = dir.join(REPLACE_JOURNAL);
+    let prepared = backup_originals(dir, &names).and_then(|()| {
+        if names.len() > 1 {
+            write_synced(&journal, names.join("\n").as_bytes())?;
+            sync_dir(dir);
+        }
+        Ok(())
+    });
+    if let Err(e) = prepared {
+        discard_staged(dir, &names);
+        return Err(e);
+    }
+
+    for (tmp_path, name) in staged.iter().zip(&names) {
+        let path = dir.join(name);
+        if let Err(e) = std::fs::rename(tmp_path, &path) {
+            rollback(dir, &names);
+            return Err(write_error(&path, e));
+        }
+    }
+    // Persist the renames, then drop the journal: from here on the
+    // replacement stands even if removing the backups is interrupted
+    sync_dir(dir);
+    if names.len() > 1 {
+        std::fs::remove_file(&journal).map_err(|e| write_error(&journal, e))?;
+        sync_dir(dir);
+    }
+    for name in &names {
+        std::fs::remove_file(dir.join(format!("{}.bak", name))).ok();
+    }
+    Ok(())
+}
+
+/// Undoes a multi-file replacement that a crash interrupted, restoring the
+/// originals from their `.bak` links. Returns false if none was pending.
+/// The caller holds the store lock.
+pub fn undo_interrupted_replace(dir: &Path) -> Result<bool> {
+    let journal = dir.join(REPLACE_JOURNAL);
+    let names = match std::fs::read_to_string(&journal) {
+        Ok(names) => names,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e).with_context(|| format!("Cannot read {:?}", journal)),
+    };
+    let names: Vec<&str> = names.lines().filter(|name| !name.is_empty()).collect();
+    rollback(dir, &names);
+    Ok(true)
+}
+
+/// Links every existing file among `names` to its `.bak` sibling
+fn backup_originals(dir: &Path, names: &[&str]) -> Result<()> {
+    for name in names {
+        let path = dir.join(name);
+        let backup = dir.join(format!("{}.bak", name));
+        std::fs::remove_file(&backup).ok();
+        match std::fs::symlink_metadata(&path) {
+            Ok(meta) if !meta.is_dir() => {
+                std::fs::hard_link(&path, &backup)
+                    .or_else(|_| std::fs::copy(&path, &backup).map(|_| ()))
+                    .map_err(|e| write_error(&backup, e))?;
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Removes the `.tmp` and `.bak` siblings of `names`, leaving the files as they are
+fn discard_staged(dir: &Path, names: &[&str]) {
+    for name in names {
+        std::fs::remove_file(dir.join(format!("{}.tmp", name))).ok();
+        std::fs::remove_file(dir.join(format!("{}.bak", name))).ok();
+    }
+}
+
+/// Puts back every file among `names` as it was before the replacement:
+/// originals come back from their `.bak` link, files that did not exist
+/// are removed. Once the backups are taken, a name without one had no original.
+fn rollback(dir: &Path, names: &[&str]) {
+    for name in names.iter().rev() {
+        let path = dir.join(name);
+        let backup = dir.join(format!("{}.bak", name));
+        if backup.exists() {
+            std::fs::rename(&backup, &path).ok();
+            // Renaming a link over the same file leaves both in place
+            std::fs::remove_file(&backup).ok();
+        } else if std::fs::symlink_metadata(&path).is_ok_and(|meta| !meta.is_dir()) {
+            std::fs::remove_file(&path).ok();
+        }
+        std::fs::remove_file(dir.join(format!("{}.tmp", name))).ok();
+    }
+    std::fs::remove_file(dir.join(REPLACE_JOURNAL)).ok();
+    sync_dir(dir);
+}
+
+/// Flushes the entries of `dir` (creations, renames, removals) to disk
+fn sync_dir(dir: &Path) {
+    #[cfg(unix)]
+    if let Ok(dir) = std::fs::File::open(dir) {
+        dir.sync_all().ok();
+    }
+    #[cfg(not(unix))]
+    let _ = dir;
+}
+
+/// Writes `content` to `path`, readable by the owner only, and flushes it to disk.
+fn write_synced(path: &Path, content: &[u8]) -> Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path).map_err(|e| write_error(path, e))?;
+
+    // A leftover file keeps its mode on open, so tighten it explicitly
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))
+            .map_err(|e| write_error(path, e))?;
+    }
+
+    file.write_all(content)
+        .and_then(|_| file.sync_all())
+        .map_err(|e| write_error(path, e))
+}
+
+/// Subdirectory of the locker holding the files set aside by `init --force`
+pub const BACKUP_DIR: &str = "backup";
+
+/// Files that make up a locker's keys and secrets
+const LOCKER_FILES: [&str; 4] = ["salt", "hash", "secrets.json", recovery::RECOVERY_FILE];
+
+/// Moves the locker files of `locker_dir` into `backup/<TIMESTAMP>/` and
+/// returns the timestamp, or `None` if there was nothing to move.
+pub fn backup_locker_files(locker_dir: &Path) -> Result<Option<String>> {
+    let present: Vec<&str> = LOCKER_FILES
+        .into_iter()
+        .filter(|name| locker_dir.join(name).exists())
+        .collect();
+    if present.is_empty() {
+        return Ok(None);
+    }
+
+    // Two backups within the same second get consecutive timestamps
+    let backups = locker_dir.join(BACKUP_DIR);
+    let mut timestamp = crate::core::store::now_timestamp();
+    while backups.join(timestamp.to_string()).exists() {
+        timestamp += 1;
+    }
+    let timestamp = timestamp.to_string();
+    let target = backups.join(&timestamp);
+    create_private_dir(&target)?;
+
+    for name in present {
+        let path = target.join(name);
+        std::fs::rename(locker_dir.join(name), &path).map_err(|e| write_error(&path, e))?;
+    }
+    Ok(Some(timestamp))
+}
+
+/// Puts the files moved aside by `backup_locker_files` under `timestamp`
+/// back in place. The current locker, if any, is backed up first so that
+/// restoring never destroys anything; its timestamp is returned.
+pub fn restore_locker_files(locker_dir: &Path, timestamp: &str) -> Result<Option<String>> {
+    if timestamp.is_empty() || !timestamp.bytes().all(|b| b.is_ascii_digit()) {
+        anyhow::bail!("Invalid backup timestamp '{}'", timestamp);
+    }
+    let source = locker_dir.join(BACKUP_DIR).join(timestamp);
+    if !source.join("salt").exists() {
+        anyhow::bail!(
+            "No locker backup '{}' in {:?}",
+            timestamp,
+            source.parent().unwrap_or(&source)
+        );
+    }
+
+    let _lock = StoreLock::acquire(locker_dir)?;
+    let replaced = backup_locker_files(locker_dir)?;
+    for name in LOCKER_FILES {
+        let path = locker_dir.join(name);
+        if source.join(name).exists() {
+            std::fs::rename(source.join(name), &path).map_err(|e| write_error(&path, e))?;
+        }
+    }
+    std::fs::remove_dir(&source).ok();
+    Ok(replaced)
+}
+
+/// Timestamps of the backups left by `init --force`, oldest first
+pub fn list_backups(locker_dir: &Path) -> Vec<String> {
+    let mut stamps: Vec<String> = std::fs::read_dir(locker_dir.join(BACKUP_DIR))
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter(|name| name.bytes().all(|b| b.is_ascii_digit()))
+        .collect();
+    stamps.sort_by_key(|stamp| stamp.parse::<u64>().unwrap_or(0));
+    stamps
+}
+
+/// Creates `path` and its parents, the last one readable by the owner only
+fn create_private_dir(path: &Path) -> Result<()> {
+    std::fs::create_dir_all(path).map_err(|e| write_error(path, e))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o700))
+            .map_err(|e| write_error(path, e))?;
+    }
+    Ok(())
+}
+
 impl Drop for Locker {
     fn drop(&mut self) {
         if let Some(ref mut key) = self.key {
@@ -137,3 +705,351 @@ impl Drop for Locker {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Creates a locker in a temp dir holding one secret
+    fn locker_with_secret(dir: &Path, passphrase: &str) -> Locker {
+        let key = Locker::init_key(dir, passphrase).unwrap();
+        let mut store = SecretsStore::load(dir, &key).unwrap();
+        store
+            .add_secret("API_KEY".to_string(), "sk-123".to_string(), None, dir, &key)
+            .unwrap();
+        Locker {
+            base_dir: dir.to_path_buf(),
+            key: Some(key),
+        }
+    }
+
+    #[test]
+    fn test_change_passphrase_reencrypts_secrets() {
+        let dir = TempDir::new().unwrap();
+        let locker = locker_with_secret(dir.path(), "old-pass");
+
+        let count = locker.change_passphrase("old-pass", "new-pass").unwrap();
+
+        assert_eq!(count, 1);
+        assert!(Locker::load_key(dir.path(), "old-pass").is_err());
+        let new_key = Locker::load_key(dir.path(), "new-pass").unwrap();
+        let store = SecretsStore::load(dir.path(), &new_key).unwrap();
+        assert_eq!(store.decrypt_secret("API_KEY", &new_key).unwrap(), "sk-123");
+        assert!(!dir.path().join("secrets.json.tmp").exists());
+    }
+
+    #[test]
+    fn test_interrupted_key_change_is_undone_on_open() {
+        let dir = TempDir::new().unwrap();
+        locker_with_secret(dir.path(), "old-pass");
+
+        // What a crash right after the store was swapped leaves behind
+        for name in ["secrets.json", "hash", "salt"] {
+            let path = dir.path().join(name);
+            std::fs::hard_link(&path, dir.path().join(format!("{}.bak", name))).unwrap();
+        }
+        std::fs::write(dir.path().join(REPLACE_JOURNAL), "secrets.json\nhash\nsalt").unwrap();
+        std::fs::remove_file(dir.path().join("secrets.json")).unwrap();
+        std::fs::write(dir.path().join("secrets.json"), "sealed under the new key").unwrap();
+
+        let locker = Locker::init_or_load_at(dir.path(), "old-pass").unwrap();
+
+        let key = locker.get_key().unwrap();
+        let store = SecretsStore::load(dir.path(), key).unwrap();
+        assert_eq!(store.decrypt_secret("API_KEY", key).unwrap(), "sk-123");
+        assert!(!dir.path().join(REPLACE_JOURNAL).exists());
+        assert!(!dir.path().join("secrets.json.bak").exists());
+    }
+
+    #[test]
+    fn test_change_passphrase_rejects_wrong_old_passphrase() {
+        let dir = TempDir::new().unwrap();
+        let locker = locker_with_secret(dir.path(), "old-pass");
+        let salt_before = std::fs::read(dir.path().join("salt")).unwrap();
+
+        assert!(locker.change_passphrase("wrong", "new-pass").is_err());
+
+        assert_eq!(std::fs::read(dir.path().join("salt")).unwrap(), salt_before);
+        assert!(Locker::load_key(dir.path(), "old-pass").is_ok());
+    }
+
+    #[test]
+    fn test_locker_with_custom_kdf_params_unlocks() {
+        let dir = TempDir::new().unwrap();
+        let params = KdfParams {
+            m_cost: 8 * 1024,
+            t_cost: 3,
+            p_cost: 2,
+        };
+
+        let key = Locker::init_key_with(dir.path(), "pass", &params).unwrap();
+
+        let hash = std::fs::read_to_string(dir.path().join("hash")).unwrap();
+        assert!(
+            hash.starts_with("$argon2id$v=19$m=8192,t=3,p=2$"),
+            "{}",
+            hash
+        );
+        assert_eq!(Locker::kdf_params(dir.path()).unwrap(), params);
+        assert_eq!(Locker::load_key(dir.path(), "pass").unwrap(), key);
+        assert!(Locker::load_key(dir.path(), "wrong").is_err());
+
+        // Changing the passphrase keeps the costs
+        let locker = Locker {
+            base_dir: dir.path().to_path_buf(),
+            key: Some(key),
+        };
+        locker.change_passphrase("pass", "new-pass").unwrap();
+        assert_eq!(Locker::kdf_params(dir.path()).unwrap(), params);
+        assert!(Locker::load_key(dir.path(), "new-pass").is_ok());
+    }
+
+    /// Known answers for "correct horse" with the salt "saltsaltsaltsalt":
+    /// an existing locker must always derive the same key, whatever the
+    /// defaults of the Argon2 crate become.
+    const PINNED_KEYS: [(&str, &str); 2] = [
+        (
+            "$argon2id$v=19$m=19456,t=2,p=1$c2FsdHNhbHRzYWx0c2FsdA$AdweBLwcflnNX2HVW8i1Mtu7frrn4Ki7h/rYSGuU7Is",
+            "01dc1e04bc1c7e59cd5f61d55bc8b532dbbb7ebae7e0a8bb87fad8486b94ec8b",
+        ),
+        (
+            "$argon2id$v=19$m=8192,t=1,p=1$c2FsdHNhbHRzYWx0c2FsdA$ulKcj6VJ78RNwnS/MJMzy87l8JYJKGxNMZ6yzgcex6M",
+            "ba529c8fa549efc44dc274bf309333cbcee5f09609286c4d319eb2ce071ec7a3",
+        ),
+    ];
+
+    #[test]
+    fn test_recovery_key_unlocks_after_forgotten_passphrase() {
+        let dir = TempDir::new().unwrap();
+        let locker = locker_with_secret(dir.path(), "forgotten-pass");
+        let recovery_key = locker.enable_recovery().unwrap();
+
+        let count = Locker::recover_in(dir.path(), &recovery_key, "new-pass").unwrap();
+
+        assert_eq!(count, 1);
+        let key = Locker::load_key(dir.path(), "new-pass").unwrap();
+        let store = SecretsStore::load(dir.path(), &key).unwrap();
+        assert_eq!(store.decrypt_secret("API_KEY", &key).unwrap(), "sk-123");
+        assert!(Locker::load_key(dir.path(), "forgotten-pass").is_err());
+    }
+
+    #[test]
+    fn test_recovery_key_survives_passphrase_change() {
+        let dir = TempDir::new().unwrap();
+        let locker = locker_with_secret(dir.path(), "old-pass");
+        let recovery_key = locker.enable_recovery().unwrap();
+        locker.change_passphrase("old-pass", "new-pass").unwrap();
+
+        Locker::recover_in(dir.path(), &recovery_key, "newer-pass").unwrap();
+
+        let key = Locker::load_key(dir.path(), "newer-pass").unwrap();
+        let store = SecretsStore::load(dir.path(), &key).unwrap();
+        assert_eq!(store.decrypt_secret("API_KEY", &key).unwrap(), "sk-123");
+    }
+
+    #[test]
+    fn test_disable_recovery_removes_wrapped_key() {
+        let dir = TempDir::new().unwrap();
+        let locker = locker_with_secret(dir.path(), "pass");
+        let recovery_key = locker.enable_recovery().unwrap();
+
+        assert!(locker.disable_recovery().unwrap());
+
+        assert!(!dir.path().join(recovery::RECOVERY_FILE).exists());
+        assert!(!locker.disable_recovery().unwrap());
+        assert!(Locker::recover_in(dir.path(), &recovery_key, "new-pass").is_err());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_locker_files_are_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = TempDir::new().unwrap();
+        locker_with_secret(dir.path(), "pass");
+
+        for name in ["salt", "hash", "secrets.json"] {
+            let mode = std::fs::metadata(dir.path().join(name))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_estimate_strength() {
+        for weak in [
+            "",
+            "1234",
+            "Password1",
+            "aaaaaaaaaaaaaaaaaaaa",
+            "abcdefgh",
+            "short!1",
+        ] {
+            assert_eq!(estimate_strength(weak), Strength::Weak, "{:?}", weak);
+        }
+        for fair in ["hunter2go", "Tr0ub4dor", "abcdefghijkl"] {
+            assert_eq!(estimate_strength(fair), Strength::Fair, "{:?}", fair);
+        }
+        for strong in [
+            "correct horse battery staple",
+            "Tr0ub4dor&3xy",
+            "mot de passe très long",
+        ] {
+            assert_eq!(estimate_strength(strong), Strength::Strong, "{:?}", strong);
+        }
+    }
+
+    #[test]
+    fn test_load_key_derives_pinned_keys() {
+        for (hash, expected) in PINNED_KEYS {
+            let dir = TempDir::new().unwrap();
+            std::fs::write(dir.path().join("salt"), "c2FsdHNhbHRzYWx0c2FsdA").unwrap();
+            std::fs::write(dir.path().join("hash"), hash).unwrap();
+
+            let key = Locker::load_key(dir.path(), "correct horse").unwrap();
+
+            assert_eq!(hex::encode(key), expected, "{}", hash);
+        }
+    }
+
+    #[test]
+    fn test_new_key_material_matches_load_key() {
+        let dir = TempDir::new().unwrap();
+        let params = KdfParams {
+            m_cost: 8 * 1024,
+            t_cost: 1,
+            p_cost: 1,
+        };
+
+        let created = Locker::init_key_with(dir.path(), "correct horse", &params).unwrap();
+
+        assert_eq!(
+            Locker::load_key(dir.path(), "correct horse").unwrap(),
+            created
+        );
+    }
+
+    #[test]
+    fn test_kdf_params_reject_weaker_than_default() {
+        assert!(KdfParams::default().validate().is_ok());
+        let weak = KdfParams {
+            m_cost: 4096,
+            ..Default::default()
+        };
+        assert!(weak.validate().is_err());
+    }
+
+    #[test]
+    fn test_replace_files_keeps_originals_on_failure() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("salt"), "original").unwrap();
+
+        // A directory in the way makes staging the second file fail
+        std::fs::create_dir(dir.path().join("hash.tmp")).unwrap();
+        let result = replace_files(dir.path(), &[("salt", b"new"), ("hash", b"new")]);
+
+        assert!(result.is_err());
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("salt")).unwrap(),
+            "original"
+        );
+        assert!(!dir.path().join("salt.tmp").exists());
+    }
+
+    #[test]
+    fn test_replace_files_restores_originals_when_a_rename_fails() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("secrets.json"), "original").unwrap();
+
+        // Renaming a file over a directory fails once secrets.json is replaced
+        std::fs::create_dir(dir.path().join("salt")).unwrap();
+        let result = replace_files(
+            dir.path(),
+            &[("secrets.json", b"new"), ("hash", b"new"), ("salt", b"new")],
+        );
+
+        assert!(result.is_err());
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("secrets.json")).unwrap(),
+            "original"
+        );
+        assert!(!dir.path().join("hash").exists());
+        for leftover in [
+            "secrets.json.bak",
+            "secrets.json.tmp",
+            "hash.tmp",
+            "salt.tmp",
+        ] {
+            assert!(
+                !dir.path().join(leftover).exists(),
+                "{leftover} left behind"
+            );
+        }
+    }
+
+    #[test]
+    fn test_replace_files_removes_backups_on_success() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("salt"), "original").unwrap();
+
+        replace_files(dir.path(), &[("salt", b"new"), ("hash", b"new")]).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("salt")).unwrap(),
+            "new"
+        );
+        assert!(!dir.path().join("salt.bak").exists());
+    }
+
+    #[test]
+    fn test_write_error_explains_permission_denied() {
+        let err = write_error(
+            Path::new("/etc/.lazy-locker"),
+            std::io::Error::from(ErrorKind::PermissionDenied),
+        );
+
+        assert_eq!(
+            err.to_string(),
+            "cannot write to /etc/.lazy-locker: permission denied — is this filesystem read-only?"
+        );
+    }
+
+    #[test]
+    fn test_write_error_keeps_unknown_causes() {
+        let err = write_error(Path::new("/tmp/locker"), std::io::Error::other("boom"));
+
+        assert_eq!(err.to_string(), "cannot write to /tmp/locker");
+        assert_eq!(err.root_cause().to_string(), "boom");
+    }
+
+    #[test]
+    fn test_backup_then_restore_recovers_the_locker() {
+        let dir = TempDir::new().unwrap();
+        drop(locker_with_secret(dir.path(), "old-pass"));
+
+        let stamp = backup_locker_files(dir.path()).unwrap().unwrap();
+        assert!(!dir.path().join("salt").exists());
+        assert!(!dir.path().join("secrets.json").exists());
+        assert_eq!(list_backups(dir.path()), vec![stamp.clone()]);
+
+        // A new locker takes its place, then the old one comes back
+        Locker::init_key(dir.path(), "new-pass").unwrap();
+        let replaced = restore_locker_files(dir.path(), &stamp).unwrap().unwrap();
+
+        let key = Locker::load_key(dir.path(), "old-pass").unwrap();
+        let store = SecretsStore::load(dir.path(), &key).unwrap();
+        assert_eq!(store.decrypt_secret("API_KEY", &key).unwrap(), "sk-123");
+        assert_eq!(list_backups(dir.path()), vec![replaced]);
+    }
+
+    #[test]
+    fn test_restore_rejects_unknown_or_unsafe_timestamps() {
+        let dir = TempDir::new().unwrap();
+
+        assert!(restore_locker_files(dir.path(), "123").is_err());
+        assert!(restore_locker_files(dir.path(), "../..").is_err());
+        assert_eq!(backup_locker_files(dir.path()).unwrap(), None);
+    }
+}
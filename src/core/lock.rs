@@ -0,0 +1,119 @@
+//! Advisory lock serializing writes to the locker between processes.
+//!
+//! The TUI, the CLI and scripts may all modify `secrets.json`. Each write
+//! holds `secrets.lock` (via `flock` on Unix) for its load-modify-save cycle,
+//! so concurrent writers wait for each other instead of dropping changes.
+
+use crate::core::init::write_error;
+use anyhow::{Context, Result};
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Lock file name inside the locker directory
+pub const LOCK_FILE: &str = "secrets.lock";
+
+/// How long to wait for another process to release the lock
+const LOCK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Delay between attempts while the lock is held elsewhere
+const LOCK_RETRY_DELAY: Duration = Duration::from_millis(20);
+
+/// Exclusive hold on the locker's lock file, released on drop.
+/// The file records the holder's PID so a blocked process can name it.
+pub struct StoreLock {
+    file: File,
+}
+
+impl StoreLock {
+    /// Acquires the lock, waiting up to `LOCK_TIMEOUT` for another holder
+    pub fn acquire(locker_dir: &Path) -> Result<Self> {
+        Self::acquire_with_timeout(locker_dir, LOCK_TIMEOUT)
+    }
+
+    fn acquire_with_timeout(locker_dir: &Path, timeout: Duration) -> Result<Self> {
+        let path = locker_dir.join(LOCK_FILE);
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .map_err(|e| write_error(&path, e))?;
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            match file.try_lock() {
+                Ok(()) => break,
+                Err(TryLockError::WouldBlock) if Instant::now() < deadline => {
+                    std::thread::sleep(LOCK_RETRY_DELAY);
+                }
+                Err(TryLockError::WouldBlock) => {
+                    let mut holder = String::new();
+                    file.read_to_string(&mut holder).ok();
+                    match holder.trim() {
+                        "" => anyhow::bail!("locker is in use by another process"),
+                        pid => anyhow::bail!("locker is in use by another process (pid {})", pid),
+                    }
+                }
+                Err(TryLockError::Error(e)) => {
+                    return Err(e).context(format!("cannot lock {}", path.display()));
+                }
+            }
+        }
+
+        file.set_len(0)
+            .and_then(|_| file.write_all(std::process::id().to_string().as_bytes()))
+            .map_err(|e| write_error(&path, e))?;
+        Ok(Self { file })
+    }
+}
+
+impl Drop for StoreLock {
+    fn drop(&mut self) {
+        // The file itself stays: removing it while others wait on it would
+        // let two processes lock different inodes at once.
+        self.file.set_len(0).ok();
+        self.file.unlock().ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_lock_records_pid_and_clears_it_on_drop() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(LOCK_FILE);
+
+        let lock = StoreLock::acquire(dir.path()).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            std::process::id().to_string()
+        );
+
+        drop(lock);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "");
+    }
+
+    #[test]
+    fn test_lock_held_elsewhere_names_the_holder() {
+        let dir = TempDir::new().unwrap();
+        let _held = StoreLock::acquire(dir.path()).unwrap();
+
+        let err = StoreLock::acquire_with_timeout(dir.path(), Duration::from_millis(50))
+            .err()
+            .unwrap();
+
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "locker is in use by another process (pid {})",
+                std::process::id()
+            )
+        );
+    }
+}
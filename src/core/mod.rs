@@ -1,7 +1,21 @@
 pub mod agent;
+pub mod analyze;
+pub mod backup;
 pub mod cli;
 pub mod config;
 pub mod crypto;
 pub mod executor;
+pub mod format;
+pub mod generator;
+pub mod hooks;
 pub mod init;
+pub(crate) mod lock;
+pub mod recovery;
 pub mod store;
+pub mod totp;
+pub(crate) mod transport;
+pub mod usages;
+// Library API for Rust programs; the binary goes through the CLI functions
+#[allow(dead_code)]
+pub mod vault;
+pub(crate) mod yaml;
@@ -0,0 +1,236 @@
+//! Recovery key: a second way to unlock the locker when the passphrase is lost.
+//!
+//! The `recovery` file holds the locker key sealed under a key derived (Argon2id)
+//! from a random recovery key, shown to the user once. It also holds that
+//! derived key sealed under the locker key, so that changing the passphrase can
+//! re-seal the new locker key without asking for the recovery key again.
+//! Anyone holding the locker key can already read every secret, so this gives
+//! nothing away; the recovery key itself is never stored.
+
+use crate::core::crypto::{CipherAlgo, decrypt_with_aad, encrypt_with};
+use crate::core::init::KdfParams;
+use anyhow::{Context, Result};
+use argon2::password_hash::{SaltString, rand_core::OsRng};
+use rand::rngs::OsRng as RandOsRng;
+use rand::{Rng, TryRngCore};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use zeroize::{Zeroize, Zeroizing};
+
+/// Name of the recovery file in the locker directory
+pub const RECOVERY_FILE: &str = "recovery";
+
+/// Current recovery file format version
+const RECOVERY_VERSION: u8 = 1;
+
+/// Recovery key alphabet: 32 symbols, without the look-alikes 0/O and 1/I
+const ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+
+/// Characters in a recovery key (5 bits each, 160 bits in total)
+const KEY_CHARS: usize = 32;
+
+/// Characters between dashes when the key is displayed
+const GROUP_LEN: usize = 4;
+
+/// Associated data binding each sealed value to its role
+const LOCKER_KEY_AAD: &[u8] = b"lazy-locker recovery: locker key";
+const RECOVERY_KEY_AAD: &[u8] = b"lazy-locker recovery: recovery key";
+
+/// The recovery key does not open the locker's recovery file
+#[derive(Debug)]
+pub struct IncorrectRecoveryKey;
+
+impl std::fmt::Display for IncorrectRecoveryKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Incorrect recovery key")
+    }
+}
+
+impl std::error::Error for IncorrectRecoveryKey {}
+
+/// Whether the locker in `locker_dir` has a recovery key
+pub fn is_enabled(locker_dir: &Path) -> bool {
+    locker_dir.join(RECOVERY_FILE).exists()
+}
+
+/// Generates a new recovery key, formatted as dash-separated groups
+/// (`ABCD-EFGH-...`) to be written down
+pub fn generate_recovery_key() -> Zeroizing<String> {
+    let mut rng = RandOsRng.unwrap_err();
+    let mut key = Zeroizing::new(String::with_capacity(KEY_CHARS + KEY_CHARS / GROUP_LEN));
+    for i in 0..KEY_CHARS {
+        if i > 0 && i % GROUP_LEN == 0 {
+            key.push('-');
+        }
+        key.push(ALPHABET[rng.random_range(0..ALPHABET.len())] as char);
+    }
+    key
+}
+
+/// Canonical form of a typed recovery key: dashes and spaces dropped,
+/// uppercased. Fails on a key of the wrong length or alphabet.
+fn normalize(recovery_key: &str) -> Result<Zeroizing<String>> {
+    let key: Zeroizing<String> = Zeroizing::new(
+        recovery_key
+            .chars()
+            .filter(|c| *c != '-' && !c.is_whitespace())
+            .map(|c| c.to_ascii_uppercase())
+            .collect(),
+    );
+    if key.len() != KEY_CHARS || !key.bytes().all(|b| ALPHABET.contains(&b)) {
+        return Err(IncorrectRecoveryKey.into());
+    }
+    Ok(key)
+}
+
+/// Contents of the `recovery` file
+#[derive(Serialize, Deserialize)]
+pub struct RecoveryFile {
+    version: u8,
+    /// Argon2 salt for the recovery key (base64, as in the `salt` file)
+    salt: String,
+    /// Argon2id costs the recovery key is derived with
+    kdf: KdfParams,
+    /// The locker key, sealed under the recovery-derived key
+    sealed_locker_key: Vec<u8>,
+    /// The recovery-derived key, sealed under the locker key
+    sealed_recovery_key: Vec<u8>,
+}
+
+impl RecoveryFile {
+    /// Seals `locker_key` under a key derived from `recovery_key`
+    pub fn seal(recovery_key: &str, locker_key: &[u8]) -> Result<Self> {
+        let salt = SaltString::generate(&mut OsRng);
+        let kdf = KdfParams::default();
+        let derived = derive_key(&normalize(recovery_key)?, &salt, &kdf)?;
+        Self::seal_with(salt, kdf, &derived, locker_key)
+    }
+
+    fn seal_with(
+        salt: SaltString,
+        kdf: KdfParams,
+        derived: &[u8],
+        locker_key: &[u8],
+    ) -> Result<Self> {
+        Ok(Self {
+            version: RECOVERY_VERSION,
+            salt: salt.as_str().to_string(),
+            kdf,
+            sealed_locker_key: seal(locker_key, derived, LOCKER_KEY_AAD)?,
+            sealed_recovery_key: seal(derived, locker_key, RECOVERY_KEY_AAD)?,
+        })
+    }
+
+    /// Returns the locker key sealed in this file
+    pub fn open(&self, recovery_key: &str) -> Result<Zeroizing<Vec<u8>>> {
+        let derived = derive_key(&normalize(recovery_key)?, &self.salt()?, &self.kdf)?;
+        decrypt_with_aad(&self.sealed_locker_key, &derived, LOCKER_KEY_AAD)
+            .map(Zeroizing::new)
+            .map_err(|_| IncorrectRecoveryKey.into())
+    }
+
+    /// Re-seals for a new locker key, after a passphrase change. The same
+    /// recovery key keeps working.
+    pub fn rekey(&self, old_key: &[u8], new_key: &[u8]) -> Result<Self> {
+        let derived = Zeroizing::new(
+            decrypt_with_aad(&self.sealed_recovery_key, old_key, RECOVERY_KEY_AAD)
+                .context("Recovery file does not match the locker key")?,
+        );
+        Self::seal_with(self.salt()?, self.kdf, &derived, new_key)
+    }
+
+    /// Reads the recovery file of `locker_dir`, if recovery is enabled
+    pub fn read(locker_dir: &Path) -> Result<Option<Self>> {
+        let path = locker_dir.join(RECOVERY_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let file: Self =
+            serde_json::from_slice(&std::fs::read(&path)?).context("Recovery file is malformed")?;
+        if file.version != RECOVERY_VERSION {
+            anyhow::bail!(
+                "Unsupported recovery file version {} (this build reads version {})",
+                file.version,
+                RECOVERY_VERSION
+            );
+        }
+        Ok(Some(file))
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(self)?)
+    }
+
+    fn salt(&self) -> Result<SaltString> {
+        SaltString::from_b64(&self.salt).map_err(|e| anyhow::anyhow!("Salt error: {}", e))
+    }
+}
+
+/// Derives the 256-bit key that seals the locker key
+fn derive_key(
+    recovery_key: &str,
+    salt: &SaltString,
+    kdf: &KdfParams,
+) -> Result<Zeroizing<Vec<u8>>> {
+    let mut salt_bytes = [0u8; 16];
+    salt.decode_b64(&mut salt_bytes)
+        .map_err(|e| anyhow::anyhow!("Salt decoding error: {}", e))?;
+    let mut key = [0u8; 32];
+    kdf.argon2()?
+        .hash_password_into(recovery_key.as_bytes(), &salt_bytes, &mut key)
+        .map_err(|e| anyhow::anyhow!("Key derivation error: {}", e))?;
+
+    let result = Zeroizing::new(key.to_vec());
+    key.zeroize();
+    Ok(result)
+}
+
+fn seal(data: &[u8], key: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+    encrypt_with(CipherAlgo::Aes256Gcm, data, key, Some(aad))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_key_format() {
+        let key = generate_recovery_key();
+
+        assert_eq!(key.len(), KEY_CHARS + KEY_CHARS / GROUP_LEN - 1);
+        assert!(key.split('-').all(|group| group.len() == GROUP_LEN));
+        assert_ne!(*key, *generate_recovery_key());
+    }
+
+    #[test]
+    fn test_open_accepts_key_typed_loosely() {
+        let recovery_key = generate_recovery_key();
+        let locker_key = [7u8; 32];
+        let file = RecoveryFile::seal(&recovery_key, &locker_key).unwrap();
+
+        let typed = recovery_key.replace('-', " ").to_lowercase();
+
+        assert_eq!(file.open(&typed).unwrap().as_slice(), &locker_key);
+    }
+
+    #[test]
+    fn test_open_rejects_wrong_key() {
+        let file = RecoveryFile::seal(&generate_recovery_key(), &[7u8; 32]).unwrap();
+
+        let err = file.open(&generate_recovery_key()).err().unwrap();
+        assert!(err.is::<IncorrectRecoveryKey>());
+        let err = file.open("not a key").err().unwrap();
+        assert!(err.is::<IncorrectRecoveryKey>());
+    }
+
+    #[test]
+    fn test_rekey_keeps_recovery_key() {
+        let recovery_key = generate_recovery_key();
+        let file = RecoveryFile::seal(&recovery_key, &[1u8; 32]).unwrap();
+
+        let rekeyed = file.rekey(&[1u8; 32], &[2u8; 32]).unwrap();
+
+        assert_eq!(rekeyed.open(&recovery_key).unwrap().as_slice(), &[2u8; 32]);
+        assert!(file.rekey(&[9u8; 32], &[2u8; 32]).is_err());
+    }
+}
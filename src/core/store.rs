@@ -1,20 +1,112 @@
-use crate::core::crypto::{decrypt, encrypt};
-use anyhow::Result;
+use crate::core::config::{Config, StoreSettings};
+use crate::core::crypto::{CipherAlgo, decrypt, decrypt_with_aad, encrypt_with};
+use crate::core::init::replace_files;
+use crate::core::lock::StoreLock;
+use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::path::PathBuf;
 use zeroize::Zeroize;
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// `secrets.json` does not authenticate under the key in use: it was written
+/// under another passphrase or key, e.g. after a recovery gone wrong
+#[derive(Debug)]
+pub struct StoreKeyMismatch;
+
+impl std::fmt::Display for StoreKeyMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(
+            "Store could not be decrypted with the provided passphrase \
+             (wrong passphrase or mismatched key)",
+        )
+    }
+}
+
+impl std::error::Error for StoreKeyMismatch {}
+
+/// Smallest encrypted store: a nonce and an authentication tag
+const MIN_STORE_LEN: usize = 12 + 16;
+
+/// What a secret's value holds
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SecretKind {
+    /// Any value, used as-is
+    #[default]
+    Opaque,
+    /// A base32 TOTP seed, used to generate one-time codes
+    Totp,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Secret {
     pub name: String,
     pub encrypted_value: Vec<u8>,
     /// Expiration date as Unix timestamp (None = no expiration)
     pub expires_at: Option<i64>,
+    /// Free-form labels used to group secrets
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Optional human-readable note about the secret
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Encrypted per-environment values (e.g. "prod" -> value), overriding the default
+    #[serde(default)]
+    pub variants: HashMap<String, Vec<u8>>,
+    /// When the secret was first added, as Unix timestamp (0 = unknown, older stores)
+    #[serde(default)]
+    pub created_at: i64,
+    /// When the secret was last written, as Unix timestamp (0 = unknown, older stores)
+    #[serde(default)]
+    pub updated_at: i64,
+    /// What the value holds (opaque unless stored as a TOTP seed)
+    #[serde(default)]
+    pub kind: SecretKind,
+    /// Copy only: the TUI never reveals the value, and `token get`, `token list --env`
+    /// and `export` print it only with `--force`
+    #[serde(default)]
+    pub protected: bool,
 }
 
 impl Secret {
+    /// Returns the encrypted value for an environment, falling back to the default value
+    pub fn encrypted_value_for(&self, env: Option<&str>) -> &[u8] {
+        self.resolved_variant(env)
+            .and_then(|env| self.variants.get(env))
+            .unwrap_or(&self.encrypted_value)
+    }
+
+    /// Decrypts the value for an environment, falling back to the default value
+    pub fn decrypt_for(&self, env: Option<&str>, key: &[u8]) -> Result<String> {
+        let variant = self.resolved_variant(env);
+        let aad = value_aad(&self.name, variant);
+        let decrypted = decrypt_with_aad(self.encrypted_value_for(variant), key, &aad)?;
+        // The bytes move into the String on success; wipe them if they are not UTF-8
+        String::from_utf8(decrypted).map_err(|e| {
+            let utf8_error = e.utf8_error();
+            e.into_bytes().zeroize();
+            anyhow::Error::from(utf8_error)
+        })
+    }
+
+    /// Returns the variant that resolves for an environment (None means the default value)
+    pub fn resolved_variant<'a>(&self, env: Option<&'a str>) -> Option<&'a str> {
+        env.filter(|env| self.variants.contains_key(*env))
+    }
+
+    /// Returns the sorted names of the environment variants
+    pub fn variant_names(&self) -> Vec<&str> {
+        let mut names: Vec<_> = self.variants.keys().map(|k| k.as_str()).collect();
+        names.sort();
+        names
+    }
+
+    /// Whether the secret carries `tag`, ignoring case
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))
+    }
+
     /// Checks if the secret is expired
     pub fn is_expired(&self) -> bool {
         if let Some(expires_at) = self.expires_at {
@@ -53,6 +145,369 @@ impl Secret {
             None => "∞ Permanent".to_string(),
         }
     }
+
+    /// Formats how long ago the secret was added (None for older stores without it)
+    pub fn added_display(&self) -> Option<String> {
+        if self.created_at == 0 {
+            return None;
+        }
+        Some(match (now_timestamp() - self.created_at) / 86400 {
+            days if days <= 0 => "added today".to_string(),
+            1 => "added yesterday".to_string(),
+            days => format!("added {} days ago", days),
+        })
+    }
+
+    /// Re-encrypts the values written before AAD binding so they are bound to
+    /// this secret's name. Values that do not decrypt at all are left as they are.
+    fn bind_values(&mut self, cipher: CipherAlgo, key: &[u8]) -> Result<()> {
+        let name = &self.name;
+        for (env, encrypted_value) in std::iter::once((None, &mut self.encrypted_value)).chain(
+            self.variants
+                .iter_mut()
+                .map(|(env, v)| (Some(env.as_str()), v)),
+        ) {
+            let aad = value_aad(name, env);
+            if let Ok(mut plaintext) = decrypt_with_aad(encrypted_value, key, &aad) {
+                plaintext.zeroize();
+                continue;
+            }
+            let Ok(mut plaintext) = decrypt(encrypted_value, key) else {
+                continue;
+            };
+            let encrypted = encrypt_with(cipher, &plaintext, key, Some(&aad));
+            plaintext.zeroize();
+            encrypted_value.zeroize();
+            *encrypted_value = encrypted?;
+        }
+        Ok(())
+    }
+
+    /// Re-encrypts every value (default and variants) from `old_key` to `new_key`,
+    /// binding them to `new_name`
+    fn reseal(
+        &mut self,
+        cipher: CipherAlgo,
+        old_key: &[u8],
+        new_key: &[u8],
+        new_name: &str,
+    ) -> Result<()> {
+        let name = &self.name;
+        for (env, encrypted_value) in std::iter::once((None, &mut self.encrypted_value)).chain(
+            self.variants
+                .iter_mut()
+                .map(|(env, v)| (Some(env.as_str()), v)),
+        ) {
+            let mut plaintext =
+                decrypt_with_aad(encrypted_value, old_key, &value_aad(name, env))
+                    .map_err(|e| anyhow::anyhow!("Failed to decrypt '{}': {}", name, e))?;
+            let aad = value_aad(new_name, env);
+            let encrypted = encrypt_with(cipher, &plaintext, new_key, Some(&aad));
+            plaintext.zeroize();
+            encrypted_value.zeroize();
+            *encrypted_value = encrypted?;
+        }
+        Ok(())
+    }
+
+    /// Sets the timestamps for a write, keeping `created_at` from the entry it replaces
+    fn touch(&mut self, previous: Option<&Secret>) {
+        let now = now_timestamp();
+        self.created_at = previous.map_or(now, |p| p.created_at);
+        self.updated_at = now;
+    }
+
+    /// Keeps what a new value must not silently drop from the entry it
+    /// replaces: its protection, its kind and its environment variants,
+    /// unless this write sets them itself
+    fn inherit(&mut self, previous: Option<&Secret>) {
+        let Some(previous) = previous else {
+            return;
+        };
+        self.protected |= previous.protected;
+        if self.kind == SecretKind::default() {
+            self.kind = previous.kind;
+        }
+        if self.variants.is_empty() {
+            self.variants = previous.variants.clone();
+        }
+    }
+}
+
+/// A decrypted secret together with its metadata.
+/// Used for import/export round-trips; the value is zeroized on drop.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct PlainSecret {
+    pub name: String,
+    pub value: String,
+    #[serde(default)]
+    pub expires_at: Option<i64>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+impl PlainSecret {
+    /// Creates a plain secret without expiration or metadata
+    pub fn new(name: String, value: String) -> Self {
+        Self {
+            name,
+            value,
+            expires_at: None,
+            tags: Vec::new(),
+            description: None,
+        }
+    }
+}
+
+impl Drop for PlainSecret {
+    fn drop(&mut self) {
+        self.value.zeroize();
+    }
+}
+
+/// What an import does with a name that already exists
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConflictStrategy {
+    /// Keep the existing value
+    #[default]
+    Skip,
+    /// Replace the existing value
+    Overwrite,
+    /// Abort the import without writing anything
+    Error,
+}
+
+impl ConflictStrategy {
+    /// Parses an `--on-conflict` value
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "skip" => Ok(Self::Skip),
+            "overwrite" => Ok(Self::Overwrite),
+            "error" => Ok(Self::Error),
+            _ => anyhow::bail!(
+                "Invalid conflict strategy '{}': use skip, overwrite or error",
+                value
+            ),
+        }
+    }
+}
+
+/// Names an import adds, leaves alone and replaces
+#[derive(Debug, Default, PartialEq)]
+pub struct ImportSummary {
+    pub imported: Vec<String>,
+    pub skipped: Vec<String>,
+    pub overwritten: Vec<String>,
+}
+
+/// Associated data binding an encrypted value to its secret name and variant,
+/// so a value copied into another entry fails to authenticate.
+fn value_aad(name: &str, env: Option<&str>) -> Vec<u8> {
+    match env {
+        Some(env) => format!("{}\0{}", name, env).into_bytes(),
+        None => name.as_bytes().to_vec(),
+    }
+}
+
+/// Tags as stored: trimmed, without empty ones or case-insensitive duplicates,
+/// in the order given
+pub fn normalize_tags(tags: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags.iter().map(|t| t.trim()).filter(|t| !t.is_empty()) {
+        if !normalized.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            normalized.push(tag.to_string());
+        }
+    }
+    normalized
+}
+
+/// True if `name` can be injected as an environment variable:
+/// `[A-Za-z_][A-Za-z0-9_]*`
+pub fn is_valid_env_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Errors with an explanation when `name` is not a valid environment variable name
+pub fn check_env_name(name: &str) -> Result<()> {
+    if !is_valid_env_name(name) {
+        anyhow::bail!(
+            "Invalid token name '{}': use letters, digits and '_', not starting with a digit",
+            name
+        );
+    }
+    Ok(())
+}
+
+/// Returns the current time as a Unix timestamp
+pub fn now_timestamp() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Maximum accepted expiration in days (~100 years)
+pub const MAX_EXPIRATION_DAYS: u32 = 36500;
+
+/// Converts a number of days from now into an expiration timestamp.
+/// Rejects values above `MAX_EXPIRATION_DAYS` and guards against overflow.
+pub fn expires_at_from_days(days: u32) -> Result<i64> {
+    if days > MAX_EXPIRATION_DAYS {
+        anyhow::bail!(
+            "Expiration of {} days exceeds the maximum of {} days",
+            days,
+            MAX_EXPIRATION_DAYS
+        );
+    }
+    (days as i64)
+        .checked_mul(86400)
+        .and_then(|secs| now_timestamp().checked_add(secs))
+        .ok_or_else(|| anyhow::anyhow!("Expiration timestamp overflow"))
+}
+
+/// Parses an absolute expiration: `YYYY-MM-DD` (midnight UTC) or an RFC 3339
+/// timestamp such as `2025-12-31T18:00:00Z` or `2025-12-31T18:00:00+02:00`.
+/// The date must lie in the future and within `MAX_EXPIRATION_DAYS`.
+pub fn parse_expiration_date(value: &str) -> Result<i64> {
+    let invalid = || {
+        anyhow::anyhow!(
+            "Invalid expiration date '{}': use YYYY-MM-DD or RFC 3339 (e.g. 2025-12-31T18:00:00Z)",
+            value
+        )
+    };
+    let value = value.trim();
+    let (date, time) = match value.find(['T', 't', ' ']) {
+        Some(i) => (&value[..i], Some(&value[i + 1..])),
+        None => (value, None),
+    };
+
+    let days = parse_civil_date(date).ok_or_else(invalid)?;
+    let seconds = match time {
+        Some(time) => parse_rfc3339_time(time).ok_or_else(invalid)?,
+        None => 0,
+    };
+    let expires_at = days * 86400 + seconds;
+
+    let now = now_timestamp();
+    if expires_at <= now {
+        anyhow::bail!("Expiration date '{}' is in the past", value);
+    }
+    if expires_at - now > MAX_EXPIRATION_DAYS as i64 * 86400 {
+        anyhow::bail!(
+            "Expiration date '{}' exceeds the maximum of {} days",
+            value,
+            MAX_EXPIRATION_DAYS
+        );
+    }
+    Ok(expires_at)
+}
+
+/// `YYYY-MM-DD` to days since the Unix epoch
+fn parse_civil_date(date: &str) -> Option<i64> {
+    let mut parts = date.split('-');
+    let year = number(parts.next()?, 4)?;
+    let month = number(parts.next()?, 2)?;
+    let day = number(parts.next()?, 2)?;
+    if parts.next().is_some() || !(1..=12).contains(&month) {
+        return None;
+    }
+    let leap = (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+    let month_days = match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    };
+    if !(1..=month_days).contains(&day) {
+        return None;
+    }
+
+    // Days from civil (proleptic Gregorian calendar), with years starting in March
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let year_of_era = y - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    Some(era * 146097 + day_of_era - 719468)
+}
+
+/// `HH:MM:SS[.fraction](Z|±HH:MM)` to seconds after midnight UTC
+fn parse_rfc3339_time(time: &str) -> Option<i64> {
+    let (clock, offset) = if let Some(clock) = time.strip_suffix(['Z', 'z']) {
+        (clock, 0)
+    } else {
+        let i = time.rfind(['+', '-'])?;
+        let (hours, minutes) = time[i + 1..].split_once(':')?;
+        let (hours, minutes) = (number(hours, 2)?, number(minutes, 2)?);
+        if hours > 23 || minutes > 59 {
+            return None;
+        }
+        let offset = hours * 3600 + minutes * 60;
+        (
+            &time[..i],
+            if &time[i..=i] == "-" { -offset } else { offset },
+        )
+    };
+
+    let clock = match clock.split_once('.') {
+        Some((clock, fraction))
+            if !fraction.is_empty() && fraction.bytes().all(|b| b.is_ascii_digit()) =>
+        {
+            clock
+        }
+        Some(_) => return None,
+        None => clock,
+    };
+    let mut parts = clock.split(':');
+    let hours = number(parts.next()?, 2)?;
+    let minutes = number(parts.next()?, 2)?;
+    let seconds = number(parts.next()?, 2)?;
+    // 60 allows a leap second
+    if parts.next().is_some() || hours > 23 || minutes > 59 || seconds > 60 {
+        return None;
+    }
+    Some(hours * 3600 + minutes * 60 + seconds - offset)
+}
+
+/// Parses exactly `width` ASCII digits
+fn number(digits: &str, width: usize) -> Option<i64> {
+    if digits.len() != width || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+/// Formats a Unix timestamp as `YYYY-MM-DD HH:MM UTC`
+pub fn format_timestamp(timestamp: i64) -> String {
+    let days = timestamp.div_euclid(86400);
+    let seconds = timestamp.rem_euclid(86400);
+
+    // Civil from days, the inverse of `parse_civil_date`
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds % 3600 / 60
+    )
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -60,6 +515,23 @@ pub struct SecretsStore {
     pub secrets: HashMap<String, Secret>,
     #[serde(skip)]
     path: Option<PathBuf>,
+    /// Cipher for newly written data, from the locker's `crypto.cipher` setting
+    #[serde(skip)]
+    cipher: CipherAlgo,
+    /// Largest value accepted when writing, from `store.max_secret_bytes` (0 = no limit)
+    #[serde(skip)]
+    max_secret_bytes: usize,
+    /// Secrets added by `stage_secret` and not yet written by `commit`, kept
+    /// apart so a `modify` reloading `secrets` in between does not lose them
+    #[serde(skip)]
+    staged: Vec<Secret>,
+    /// Whether names that are not valid environment variable names are accepted
+    #[serde(skip)]
+    allow_any_name: bool,
+    /// Every value is bound to its name (see `bind_values`); false in stores
+    /// written before binding, until they are migrated on load
+    #[serde(default)]
+    values_bound: bool,
 }
 
 impl Default for SecretsStore {
@@ -74,39 +546,85 @@ impl SecretsStore {
         Self {
             secrets: HashMap::new(),
             path: None,
+            cipher: CipherAlgo::default(),
+            max_secret_bytes: StoreSettings::default().max_secret_bytes,
+            staged: Vec::new(),
+            allow_any_name: false,
+            values_bound: true,
         }
     }
 
     pub fn load(locker_dir: &std::path::Path, key: &[u8]) -> Result<Self> {
-        let file_path = locker_dir.join("secrets.json");
-        if file_path.exists() {
-            let data = fs::read(&file_path)?;
-            let decrypted = decrypt(&data, key)?;
-            let mut store: SecretsStore = serde_json::from_slice(&decrypted)?;
-            store.path = Some(file_path);
-            Ok(store)
-        } else {
-            Ok(Self {
-                secrets: HashMap::new(),
-                path: Some(file_path),
-            })
-        }
+        Self::load_from_path(&locker_dir.join("secrets.json"), key)
     }
 
     /// Loads from a specific path (used by agent)
-    pub fn load_from_path(path: &PathBuf, key: &[u8]) -> Result<Self> {
-        if path.exists() {
-            let data = fs::read(path)?;
-            let decrypted = decrypt(&data, key)?;
-            let mut store: SecretsStore = serde_json::from_slice(&decrypted)?;
-            store.path = Some(path.clone());
-            Ok(store)
+    pub fn load_from_path(path: &std::path::Path, key: &[u8]) -> Result<Self> {
+        let mut store = if path.exists() {
+            Self::read_file(path, key)?
         } else {
-            Ok(Self {
-                secrets: HashMap::new(),
-                path: Some(path.clone()),
-            })
+            Self::default()
+        };
+        store.path = Some(path.to_path_buf());
+        if let Some(locker_dir) = path.parent() {
+            let config = Config::load_or_default(locker_dir);
+            store.cipher = config.crypto.cipher;
+            store.max_secret_bytes = config.store.max_secret_bytes;
+        }
+        store.bind_values(key)?;
+        Ok(store)
+    }
+
+    /// Binds the values of a store written before AAD binding to their names,
+    /// once: the next save records it and from then on a value that is not
+    /// bound to its name no longer decrypts.
+    pub fn bind_values(&mut self, key: &[u8]) -> Result<()> {
+        if self.values_bound {
+            return Ok(());
+        }
+        let cipher = self.cipher;
+        for secret in self.secrets.values_mut() {
+            secret.bind_values(cipher, key)?;
+        }
+        self.values_bound = true;
+        Ok(())
+    }
+
+    /// Accepts values of any size from now on (`--allow-large`)
+    pub fn allow_large_values(&mut self) {
+        self.max_secret_bytes = 0;
+    }
+
+    /// Accepts names that are not valid environment variable names from now on
+    /// (`--allow-any-name`). Such secrets can be read back but not injected by `run`.
+    pub fn allow_any_names(&mut self) {
+        self.allow_any_name = true;
+    }
+
+    /// Fails if `name` is not a valid environment variable name, unless
+    /// `allow_any_names` was called. Every write path checks names here.
+    pub fn check_name(&self, name: &str) -> Result<()> {
+        if name.is_empty() {
+            anyhow::bail!("Token name cannot be empty");
+        }
+        if self.allow_any_name {
+            return Ok(());
         }
+        check_env_name(name)
+    }
+
+    /// Fails if `value` is larger than the configured `store.max_secret_bytes`
+    pub fn check_value_size(&self, name: &str, value: &str) -> Result<()> {
+        if self.max_secret_bytes > 0 && value.len() > self.max_secret_bytes {
+            anyhow::bail!(
+                "Value of '{}' is {} bytes, above the limit of {} bytes. \
+                 Pass --allow-large to token add or import, or raise store.max_secret_bytes in config.toml",
+                name,
+                value.len(),
+                self.max_secret_bytes
+            );
+        }
+        Ok(())
     }
 
     /// Returns the secrets file path
@@ -114,13 +632,69 @@ impl SecretsStore {
         self.path.as_ref().expect("Store path not set")
     }
 
+    /// Reads and decrypts a store file. Failing to read it, to authenticate
+    /// it (`StoreKeyMismatch`) and to parse it are told apart.
+    fn read_file(path: &std::path::Path, key: &[u8]) -> Result<Self> {
+        let data = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        if data.len() < MIN_STORE_LEN {
+            anyhow::bail!(
+                "{} is truncated ({} bytes), not an encrypted store",
+                path.display(),
+                data.len()
+            );
+        }
+        let mut decrypted = decrypt(&data, key).map_err(|_| StoreKeyMismatch)?;
+        let store = serde_json::from_slice(&decrypted);
+        decrypted.zeroize();
+        store.with_context(|| format!("{} decrypted but is not a valid store", path.display()))
+    }
+
+    /// Writes the store, holding the lock so it can't interleave with another writer
+    #[allow(dead_code)]
     pub fn save(&self, locker_dir: &std::path::Path, key: &[u8]) -> Result<()> {
-        let json = serde_json::to_vec(self)?;
-        let encrypted = encrypt(&json, key)?;
-        fs::write(locker_dir.join("secrets.json"), encrypted)?;
-        Ok(())
+        let _lock = StoreLock::acquire(locker_dir)?;
+        self.write(locker_dir, key)
+    }
+
+    /// Replaces `secrets.json` atomically; the caller holds the lock
+    fn write(&self, locker_dir: &std::path::Path, key: &[u8]) -> Result<()> {
+        let encrypted = self.to_encrypted_bytes(key)?;
+        replace_files(locker_dir, &[("secrets.json", &encrypted)])
+    }
+
+    /// Runs a load-modify-save cycle under the lock: reloads the secrets on
+    /// disk, applies `change`, then writes. Changes made meanwhile by other
+    /// processes are kept rather than overwritten with this stale copy.
+    fn modify<T>(
+        &mut self,
+        locker_dir: &std::path::Path,
+        key: &[u8],
+        change: impl FnOnce(&mut Self) -> Result<T>,
+    ) -> Result<T> {
+        let _lock = StoreLock::acquire(locker_dir)?;
+        let path = locker_dir.join("secrets.json");
+        if path.exists() {
+            let mut on_disk = Self::read_file(&path, key)?;
+            // A store written before AAD binding is migrated before it is saved
+            on_disk.cipher = self.cipher;
+            on_disk.bind_values(key)?;
+            self.secrets = std::mem::take(&mut on_disk.secrets);
+            self.values_bound = on_disk.values_bound;
+        }
+        let result = change(self)?;
+        self.write(locker_dir, key)?;
+        Ok(result)
+    }
+
+    /// Serializes and encrypts the store as it is written to `secrets.json`
+    pub fn to_encrypted_bytes(&self, key: &[u8]) -> Result<Vec<u8>> {
+        let mut json = serde_json::to_vec(self)?;
+        let encrypted = encrypt_with(self.cipher, &json, key, None);
+        json.zeroize();
+        encrypted
     }
 
+    #[allow(dead_code)]
     pub fn add_secret(
         &mut self,
         name: String,
@@ -129,30 +703,273 @@ impl SecretsStore {
         locker_dir: &std::path::Path,
         key: &[u8],
     ) -> Result<()> {
-        let encrypted_value = encrypt(value.as_bytes(), key)?;
-
-        let expires_at = expiration_days.map(|days| {
-            let now = std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .map(|d| d.as_secs() as i64)
-                .unwrap_or(0);
-            now + (days as i64 * 86400)
-        });
+        let expires_at = expiration_days.map(expires_at_from_days).transpose()?;
+        self.add_secret_at(name, value, expires_at, locker_dir, key)
+    }
 
+    /// Like `add_secret`, with an expiration timestamp instead of a number of days
+    pub fn add_secret_at(
+        &mut self,
+        name: String,
+        value: String,
+        expires_at: Option<i64>,
+        locker_dir: &std::path::Path,
+        key: &[u8],
+    ) -> Result<()> {
         let secret = Secret {
-            name: name.clone(),
-            encrypted_value,
+            name,
             expires_at,
+            ..Default::default()
+        };
+        self.add_secret_with(secret, &value, locker_dir, key)
+    }
+
+    /// Adds `value` under `secret.name` together with the metadata already set
+    /// on `secret` (expiration, tags, description, kind, protection), in a
+    /// single save so the secret never reaches disk half-written.
+    pub fn add_secret_with(
+        &mut self,
+        mut secret: Secret,
+        value: &str,
+        locker_dir: &std::path::Path,
+        key: &[u8],
+    ) -> Result<()> {
+        self.check_name(&secret.name)?;
+        self.check_value_size(&secret.name, value)?;
+        let aad = value_aad(&secret.name, None);
+        secret.encrypted_value = encrypt_with(self.cipher, value.as_bytes(), key, Some(&aad))?;
+        secret.tags = normalize_tags(&secret.tags);
+
+        let name = secret.name.clone();
+        self.modify(locker_dir, key, |store| {
+            let previous = store.secrets.get(&name);
+            secret.touch(previous);
+            secret.inherit(previous);
+            store.secrets.insert(name, secret);
+            Ok(())
+        })
+    }
+
+    /// Adds secrets with all their metadata (expiration timestamp, tags, description)
+    /// and saves once.
+    /// Returns the number of secrets added.
+    pub fn add_many(
+        &mut self,
+        plains: &[PlainSecret],
+        locker_dir: &std::path::Path,
+        key: &[u8],
+    ) -> Result<usize> {
+        self.modify(locker_dir, key, |store| {
+            for plain in plains {
+                store.insert_plain(plain, key)?;
+            }
+            Ok(plains.len())
+        })
+    }
+
+    /// Validates a secret like `add_secret_at` (name, size) and inserts it in
+    /// memory only. Nothing reaches disk until `commit`, so a batch can be
+    /// checked in full before any of it is written.
+    pub fn stage_secret(&mut self, plain: &PlainSecret, key: &[u8]) -> Result<()> {
+        self.insert_plain(plain, key)?;
+        let secret = self.secrets[&plain.name].clone();
+        match self.staged.iter_mut().find(|s| s.name == plain.name) {
+            Some(staged) => *staged = secret,
+            None => self.staged.push(secret),
+        }
+        Ok(())
+    }
+
+    /// Writes every staged secret in one save, over the secrets on disk.
+    /// Returns the number of secrets written.
+    pub fn commit(&mut self, locker_dir: &std::path::Path, key: &[u8]) -> Result<usize> {
+        let staged = self.staged.clone();
+        let count = self.modify(locker_dir, key, |store| {
+            let count = staged.len();
+            for mut secret in staged {
+                let previous = store.secrets.get(&secret.name);
+                secret.touch(previous);
+                secret.inherit(previous);
+                store.secrets.insert(secret.name.clone(), secret);
+            }
+            Ok(count)
+        })?;
+        self.staged.clear();
+        Ok(count)
+    }
+
+    /// Sorts `plains` into new, skipped and overwritten names without writing anything.
+    /// With `ConflictStrategy::Error`, any existing name is an error.
+    pub fn plan_import(
+        &self,
+        plains: &[PlainSecret],
+        strategy: ConflictStrategy,
+    ) -> Result<ImportSummary> {
+        self.plan_names(plains.iter().map(|plain| plain.name.as_str()), strategy)
+    }
+
+    /// Sorts incoming `names` by what `strategy` does with them
+    fn plan_names<'a>(
+        &self,
+        names: impl Iterator<Item = &'a str>,
+        strategy: ConflictStrategy,
+    ) -> Result<ImportSummary> {
+        let mut summary = ImportSummary::default();
+        let mut seen = std::collections::HashSet::new();
+        for name in names {
+            // A name repeated in the input is one token, the last value wins
+            if !seen.insert(name) {
+                continue;
+            }
+            let name = name.to_string();
+            match (self.contains(&name), strategy) {
+                (false, _) => summary.imported.push(name),
+                (true, ConflictStrategy::Skip) => summary.skipped.push(name),
+                (true, ConflictStrategy::Overwrite) => summary.overwritten.push(name),
+                (true, ConflictStrategy::Error) => summary.skipped.push(name),
+            }
+        }
+
+        if strategy == ConflictStrategy::Error && !summary.skipped.is_empty() {
+            anyhow::bail!(
+                "Tokens already exist: {}. Use --on-conflict skip or overwrite",
+                summary.skipped.join(", ")
+            );
+        }
+        Ok(summary)
+    }
+
+    /// Imports `plains`: every secret `strategy` keeps is staged first, so an
+    /// invalid entry writes nothing, then all of them are committed in one save.
+    pub fn import(
+        &mut self,
+        plains: &[PlainSecret],
+        strategy: ConflictStrategy,
+        locker_dir: &std::path::Path,
+        key: &[u8],
+    ) -> Result<ImportSummary> {
+        let summary = self.stage_import(plains, strategy, key)?;
+        self.commit(locker_dir, key)?;
+        Ok(summary)
+    }
+
+    /// Stages the secrets of `plains` that `strategy` keeps, without writing
+    /// anything. A dry run stops here.
+    pub fn stage_import(
+        &mut self,
+        plains: &[PlainSecret],
+        strategy: ConflictStrategy,
+        key: &[u8],
+    ) -> Result<ImportSummary> {
+        let summary = self.plan_import(plains, strategy)?;
+        for plain in plains {
+            if !summary.skipped.contains(&plain.name) {
+                self.stage_secret(plain, key)?;
+            }
+        }
+        Ok(summary)
+    }
+
+    /// Merges the secrets of `incoming`, sealed under `incoming_key`, resealing
+    /// them under `key` with all their variants and metadata. Conflicts are
+    /// handled like `import`.
+    pub fn merge(
+        &mut self,
+        incoming: &SecretsStore,
+        incoming_key: &[u8],
+        strategy: ConflictStrategy,
+        locker_dir: &std::path::Path,
+        key: &[u8],
+    ) -> Result<ImportSummary> {
+        let mut names: Vec<&str> = incoming.secrets.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        self.modify(locker_dir, key, |store| {
+            let summary = store.plan_names(names.into_iter(), strategy)?;
+            for (name, secret) in &incoming.secrets {
+                if summary.skipped.contains(name) {
+                    continue;
+                }
+                store.check_name(name)?;
+                let mut secret = secret.clone();
+                secret.reseal(store.cipher, incoming_key, key, name)?;
+                store.secrets.insert(name.clone(), secret);
+            }
+            Ok(summary)
+        })
+    }
+
+    /// Encrypts and inserts `plain` in memory, after the same name and size
+    /// checks as `add_secret_at`
+    fn insert_plain(&mut self, plain: &PlainSecret, key: &[u8]) -> Result<()> {
+        self.check_name(&plain.name)?;
+        self.check_value_size(&plain.name, &plain.value)?;
+        let aad = value_aad(&plain.name, None);
+        let encrypted_value = encrypt_with(self.cipher, plain.value.as_bytes(), key, Some(&aad))?;
+
+        let mut secret = Secret {
+            name: plain.name.clone(),
+            encrypted_value,
+            expires_at: plain.expires_at,
+            tags: plain.tags.clone(),
+            description: plain.description.clone(),
+            ..Default::default()
         };
-        self.secrets.insert(name, secret);
-        self.save(locker_dir, key)?;
+        let previous = self.secrets.get(&plain.name);
+        secret.touch(previous);
+        secret.inherit(previous);
+        self.secrets.insert(plain.name.clone(), secret);
         Ok(())
     }
 
+    /// Stores an environment-specific value for an existing secret
+    pub fn add_variant(
+        &mut self,
+        name: &str,
+        env: &str,
+        value: &str,
+        locker_dir: &std::path::Path,
+        key: &[u8],
+    ) -> Result<()> {
+        if env.is_empty() {
+            anyhow::bail!("Environment name cannot be empty");
+        }
+        self.check_value_size(name, value)?;
+        let aad = value_aad(name, Some(env));
+        let encrypted_value = encrypt_with(self.cipher, value.as_bytes(), key, Some(&aad))?;
+        self.modify(locker_dir, key, |store| {
+            let secret = store.secrets.get_mut(name).ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Token '{}' has no default value. Add it first without --env.",
+                    name
+                )
+            })?;
+            if let Some(mut previous) = secret.variants.insert(env.to_string(), encrypted_value) {
+                previous.zeroize();
+            }
+            secret.updated_at = now_timestamp();
+            Ok(())
+        })
+    }
+
     pub fn get_secret(&self, name: &str) -> Option<&Secret> {
         self.secrets.get(name)
     }
 
+    /// Returns true if a secret with this name exists
+    pub fn contains(&self, name: &str) -> bool {
+        self.secrets.contains_key(name)
+    }
+
+    /// Number of secrets in the store
+    pub fn len(&self) -> usize {
+        self.secrets.len()
+    }
+
+    /// Returns true if the store holds no secrets
+    pub fn is_empty(&self) -> bool {
+        self.secrets.is_empty()
+    }
+
     pub fn list_secrets(&self) -> Vec<&Secret> {
         let mut secrets: Vec<_> = self.secrets.values().collect();
         secrets.sort_by(|a, b| a.name.cmp(&b.name));
@@ -165,40 +982,369 @@ impl SecretsStore {
         locker_dir: &std::path::Path,
         key: &[u8],
     ) -> Result<()> {
-        self.secrets.remove(name);
-        self.save(locker_dir, key)?;
-        Ok(())
+        self.modify(locker_dir, key, |store| {
+            store.secrets.remove(name);
+            Ok(())
+        })
+    }
+
+    /// Names of the secrets that expired more than `grace_days` days ago, sorted.
+    /// Permanent secrets are never included.
+    pub fn expired_names(&self, grace_days: i64) -> Vec<String> {
+        let cutoff = now_timestamp() - grace_days * 86400;
+        let mut names: Vec<String> = self
+            .secrets
+            .values()
+            .filter(|s| s.expires_at.is_some_and(|expires_at| cutoff > expires_at))
+            .map(|s| s.name.clone())
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Secrets expiring within `days` days from now, soonest first.
+    /// Already expired secrets are only included with `include_expired`.
+    pub fn expiring_within(&self, days: i64, include_expired: bool) -> Vec<&Secret> {
+        let now = now_timestamp();
+        let cutoff = now + days * 86400;
+        let mut secrets: Vec<&Secret> = self
+            .secrets
+            .values()
+            .filter(|s| {
+                s.expires_at.is_some_and(|expires_at| {
+                    expires_at <= cutoff && (include_expired || expires_at >= now)
+                })
+            })
+            .collect();
+        secrets.sort_by(|a, b| a.expires_at.cmp(&b.expires_at).then(a.name.cmp(&b.name)));
+        secrets
+    }
+
+    /// "3 secrets expire within 7 days: X, Y, Z", or None when none does
+    pub fn expiring_summary(&self, days: u32) -> Option<String> {
+        /// Names listed before "and N more"
+        const MAX_NAMES: usize = 5;
+
+        let expiring = self.expiring_within(days.into(), false);
+        if days == 0 || expiring.is_empty() {
+            return None;
+        }
+        let mut names: Vec<&str> = expiring
+            .iter()
+            .take(MAX_NAMES)
+            .map(|s| s.name.as_str())
+            .collect();
+        let more = expiring.len().saturating_sub(MAX_NAMES);
+        let more = format!("and {} more", more);
+        if expiring.len() > MAX_NAMES {
+            names.push(&more);
+        }
+        let (noun, verb) = if expiring.len() == 1 {
+            ("secret", "expires")
+        } else {
+            ("secrets", "expire")
+        };
+        Some(format!(
+            "{} {} {} within {} days: {}",
+            expiring.len(),
+            noun,
+            verb,
+            days,
+            names.join(", ")
+        ))
+    }
+
+    /// Removes the secrets that expired more than `grace_days` days ago and
+    /// saves once. Returns the removed names.
+    pub fn prune_expired(
+        &mut self,
+        grace_days: i64,
+        locker_dir: &std::path::Path,
+        key: &[u8],
+    ) -> Result<Vec<String>> {
+        self.modify(locker_dir, key, |store| {
+            let names = store.expired_names(grace_days);
+            for name in &names {
+                store.secrets.remove(name);
+            }
+            Ok(names)
+        })
+    }
+
+    /// Removes several secrets and saves once.
+    /// Returns the names that were actually present.
+    pub fn delete_many(
+        &mut self,
+        names: &[String],
+        locker_dir: &std::path::Path,
+        key: &[u8],
+    ) -> Result<Vec<String>> {
+        self.modify(locker_dir, key, |store| {
+            Ok(names
+                .iter()
+                .filter(|name| store.secrets.remove(name.as_str()).is_some())
+                .cloned()
+                .collect())
+        })
     }
 
     pub fn decrypt_secret(&self, name: &str, key: &[u8]) -> Result<String> {
+        self.decrypt_secret_for(name, None, key)
+    }
+
+    /// Decrypts a secret, resolving the variant for `env` if present
+    pub fn decrypt_secret_for(&self, name: &str, env: Option<&str>, key: &[u8]) -> Result<String> {
         if let Some(secret) = self.get_secret(name) {
-            let decrypted = decrypt(&secret.encrypted_value, key)?;
-            let value = String::from_utf8(decrypted)?;
-            Ok(value)
+            secret.decrypt_for(env, key)
         } else {
             Err(anyhow::anyhow!("Secret not found"))
         }
     }
 
-    /// Decrypts all secrets and returns a HashMap name -> value
-    pub fn decrypt_all(&self, key: &[u8]) -> Result<HashMap<String, String>> {
-        let mut result = HashMap::new();
-        for secret in self.secrets.values() {
-            let decrypted = decrypt(&secret.encrypted_value, key)?;
-            let value = String::from_utf8(decrypted)?;
-            result.insert(secret.name.clone(), value);
-        }
-        Ok(result)
+    /// Decrypts all secrets and returns a map name -> value, ordered by name
+    pub fn decrypt_all(&self, key: &[u8]) -> Result<BTreeMap<String, String>> {
+        self.decrypt_all_for(None, key)
     }
-}
 
-impl Drop for SecretsStore {
-    fn drop(&mut self) {
-        for secret in self.secrets.values_mut() {
-            secret.encrypted_value.zeroize();
+    /// Decrypts every secret that can be, instead of failing on the first bad one.
+    /// Returns the values plus (name, error) pairs for the failures, sorted by name.
+    #[allow(dead_code)]
+    pub fn decrypt_all_reporting(
+        &self,
+        key: &[u8],
+    ) -> (BTreeMap<String, String>, Vec<(String, String)>) {
+        self.decrypt_all_for_reporting(None, key)
+    }
+
+    /// Like `decrypt_all_reporting`, resolving the variant for `env` where one exists
+    pub fn decrypt_all_for_reporting(
+        &self,
+        env: Option<&str>,
+        key: &[u8],
+    ) -> (BTreeMap<String, String>, Vec<(String, String)>) {
+        let mut values = BTreeMap::new();
+        let mut failures = Vec::new();
+        for secret in self.secrets.values() {
+            match secret.decrypt_for(env, key) {
+                Ok(value) => {
+                    values.insert(secret.name.clone(), value);
+                }
+                Err(e) => failures.push((secret.name.clone(), e.to_string())),
+            }
         }
+        failures.sort();
+        (values, failures)
     }
-}
+
+    /// Decrypts all secrets, resolving the variant for `env` where one exists
+    pub fn decrypt_all_for(
+        &self,
+        env: Option<&str>,
+        key: &[u8],
+    ) -> Result<BTreeMap<String, String>> {
+        let mut result = BTreeMap::new();
+        for secret in self.secrets.values() {
+            result.insert(secret.name.clone(), secret.decrypt_for(env, key)?);
+        }
+        Ok(result)
+    }
+
+    /// Attempts to decrypt every secret without keeping the plaintext.
+    /// Returns (name, decrypts_ok) pairs sorted by name.
+    pub fn verify_all(&self, key: &[u8]) -> Vec<(String, bool)> {
+        self.list_secrets()
+            .iter()
+            .map(|secret| {
+                let ok = std::iter::once((None, &secret.encrypted_value))
+                    .chain(
+                        secret
+                            .variants
+                            .iter()
+                            .map(|(env, v)| (Some(env.as_str()), v)),
+                    )
+                    .all(|(env, encrypted)| {
+                        match decrypt_with_aad(encrypted, key, &value_aad(&secret.name, env)) {
+                            Ok(mut plaintext) => {
+                                plaintext.zeroize();
+                                true
+                            }
+                            Err(_) => false,
+                        }
+                    });
+                (secret.name.clone(), ok)
+            })
+            .collect()
+    }
+
+    /// Inconsistent metadata, as (name, problem) pairs sorted by name:
+    /// timestamps in the future or out of order, negative expirations, or an
+    /// entry filed under another name.
+    pub fn metadata_issues(&self) -> Vec<(String, String)> {
+        // Leeway for clocks that disagree between machines
+        let future = now_timestamp() + 24 * 60 * 60;
+        let mut issues = Vec::new();
+        let mut entries: Vec<_> = self.secrets.iter().collect();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+
+        for (key, secret) in entries {
+            let mut issue = |problem: &str| issues.push((key.clone(), problem.to_string()));
+            if *key != secret.name {
+                issue(&format!("stored under another name ('{}')", secret.name));
+            }
+            if secret.created_at < 0 || secret.updated_at < 0 {
+                issue("negative creation or update time");
+            }
+            if secret.created_at > future || secret.updated_at > future {
+                issue("created or updated in the future");
+            }
+            if secret.created_at > 0
+                && secret.updated_at > 0
+                && secret.updated_at < secret.created_at
+            {
+                issue("updated before it was created");
+            }
+            if let Some(expires_at) = secret.expires_at {
+                if expires_at <= 0 {
+                    issue("invalid expiration time");
+                } else if secret.created_at > 0 && expires_at < secret.created_at {
+                    issue("expires before it was created");
+                }
+            }
+        }
+        issues
+    }
+
+    /// Re-encrypts every secret value from `old_key` to `new_key` in memory.
+    /// Nothing is written to disk; call `save` with the new key afterwards.
+    /// Values are bound to their name (and variant) on the way, upgrading older entries.
+    pub fn reencrypt(&mut self, old_key: &[u8], new_key: &[u8]) -> Result<usize> {
+        let cipher = self.cipher;
+        for secret in self.secrets.values_mut() {
+            let name = secret.name.clone();
+            secret.reseal(cipher, old_key, new_key, &name)?;
+        }
+        Ok(self.secrets.len())
+    }
+
+    /// Every tag in use, lowercased, sorted and without duplicates
+    pub fn all_tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = self
+            .secrets
+            .values()
+            .flat_map(|s| s.tags.iter().map(|t| t.to_lowercase()))
+            .collect();
+        tags.sort();
+        tags.dedup();
+        tags
+    }
+
+    /// Replaces the value of an existing secret, keeping its expiration, metadata
+    /// and environment variants. Unlike `add_secret`, a missing secret is an error.
+    pub fn update_secret(
+        &mut self,
+        name: &str,
+        new_value: &str,
+        locker_dir: &std::path::Path,
+        key: &[u8],
+    ) -> Result<()> {
+        self.update_secret_with(name, Some(new_value), locker_dir, key, |_| {})
+    }
+
+    /// Replaces the value of an existing secret when `new_value` is given and
+    /// applies `change` to its metadata (tags are normalized afterwards), all
+    /// in a single save. A missing secret is an error.
+    pub fn update_secret_with(
+        &mut self,
+        name: &str,
+        new_value: Option<&str>,
+        locker_dir: &std::path::Path,
+        key: &[u8],
+        change: impl FnOnce(&mut Secret),
+    ) -> Result<()> {
+        let encrypted_value = match new_value {
+            Some(value) => {
+                self.check_value_size(name, value)?;
+                let aad = value_aad(name, None);
+                Some(encrypt_with(
+                    self.cipher,
+                    value.as_bytes(),
+                    key,
+                    Some(&aad),
+                )?)
+            }
+            None => None,
+        };
+        self.modify(locker_dir, key, |store| {
+            let secret = store
+                .secrets
+                .get_mut(name)
+                .ok_or_else(|| anyhow::anyhow!("Token '{}' not found", name))?;
+            if let Some(encrypted_value) = encrypted_value {
+                let mut previous = std::mem::replace(&mut secret.encrypted_value, encrypted_value);
+                previous.zeroize();
+                secret.updated_at = now_timestamp();
+            }
+            change(secret);
+            secret.tags = normalize_tags(&secret.tags);
+            Ok(())
+        })
+    }
+
+    /// Renames a secret, keeping its value, expiration and metadata.
+    /// Values are bound to the name, so they are re-encrypted under the new one.
+    pub fn rename_secret(
+        &mut self,
+        old: &str,
+        new: &str,
+        locker_dir: &std::path::Path,
+        key: &[u8],
+    ) -> Result<()> {
+        self.check_name(new)?;
+        self.modify(locker_dir, key, |store| {
+            if store.secrets.contains_key(new) {
+                anyhow::bail!("Token '{}' already exists", new);
+            }
+            let mut secret = store
+                .secrets
+                .get(old)
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("Token '{}' not found", old))?;
+            secret.reseal(store.cipher, key, key, new)?;
+            secret.name = new.to_string();
+            secret.updated_at = now_timestamp();
+
+            store.secrets.remove(old);
+            store.secrets.insert(new.to_string(), secret);
+            Ok(())
+        })
+    }
+
+    /// Decrypts all secrets with their metadata, sorted by name
+    pub fn plain_secrets(&self, key: &[u8]) -> Result<Vec<PlainSecret>> {
+        let mut result = Vec::new();
+        for secret in self.list_secrets() {
+            result.push(PlainSecret {
+                name: secret.name.clone(),
+                value: secret.decrypt_for(None, key)?,
+                expires_at: secret.expires_at,
+                tags: secret.tags.clone(),
+                description: secret.description.clone(),
+            });
+        }
+        Ok(result)
+    }
+}
+
+impl Drop for SecretsStore {
+    fn drop(&mut self) {
+        for secret in self.secrets.values_mut() {
+            secret.encrypted_value.zeroize();
+            for variant in secret.variants.values_mut() {
+                variant.zeroize();
+            }
+        }
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -220,6 +1366,7 @@ mod tests {
             name: "TEST_TOKEN".to_string(),
             encrypted_value: vec![1, 2, 3],
             expires_at: None,
+            ..Default::default()
         };
 
         assert!(!secret.is_expired());
@@ -239,6 +1386,7 @@ mod tests {
             name: "EXPIRED_TOKEN".to_string(),
             encrypted_value: vec![1, 2, 3],
             expires_at: Some(past_timestamp),
+            ..Default::default()
         };
 
         assert!(secret.is_expired());
@@ -257,6 +1405,7 @@ mod tests {
             name: "EXPIRING_TODAY".to_string(),
             encrypted_value: vec![1, 2, 3],
             expires_at: Some(now + 3600), // In 1 hour
+            ..Default::default()
         };
 
         assert!(!secret.is_expired());
@@ -277,6 +1426,7 @@ mod tests {
             name: "EXPIRING_TOMORROW".to_string(),
             encrypted_value: vec![1, 2, 3],
             expires_at: Some(tomorrow),
+            ..Default::default()
         };
 
         assert!(!secret.is_expired());
@@ -296,6 +1446,7 @@ mod tests {
             name: "EXPIRING_WEEK".to_string(),
             encrypted_value: vec![1, 2, 3],
             expires_at: Some(in_5_days),
+            ..Default::default()
         };
 
         assert!(!secret.is_expired());
@@ -310,7 +1461,8 @@ mod tests {
     #[test]
     fn test_store_new_is_empty() {
         let store = SecretsStore::new();
-        assert!(store.secrets.is_empty());
+        assert!(store.is_empty());
+        assert_eq!(store.len(), 0);
         assert!(store.list_secrets().is_empty());
     }
 
@@ -330,9 +1482,10 @@ mod tests {
             )
             .expect("Failed to add secret");
 
-        assert_eq!(store.secrets.len(), 1);
+        assert_eq!(store.len(), 1);
+        assert!(store.contains("MY_API_KEY"));
+        assert!(!store.contains("NONEXISTENT"));
         assert!(store.get_secret("MY_API_KEY").is_some());
-        assert!(store.get_secret("NONEXISTENT").is_none());
     }
 
     #[test]
@@ -357,6 +1510,121 @@ mod tests {
         assert_eq!(decrypted, "super_secure_password");
     }
 
+    #[test]
+    fn test_store_swapped_values_fail_to_decrypt() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let key = test_key();
+        let mut store = SecretsStore::new();
+
+        for (name, value) in [("API_KEY", "sk-123"), ("DB_PASSWORD", "hunter2")] {
+            store
+                .add_secret(
+                    name.to_string(),
+                    value.to_string(),
+                    None,
+                    temp_dir.path(),
+                    &key,
+                )
+                .expect("Failed to add secret");
+        }
+        let api_key = store.secrets["API_KEY"].encrypted_value.clone();
+        let db_password = store.secrets["DB_PASSWORD"].encrypted_value.clone();
+        store.secrets.get_mut("API_KEY").unwrap().encrypted_value = db_password;
+        store
+            .secrets
+            .get_mut("DB_PASSWORD")
+            .unwrap()
+            .encrypted_value = api_key;
+
+        assert!(store.decrypt_secret("API_KEY", &key).is_err());
+        assert!(store.decrypt_secret("DB_PASSWORD", &key).is_err());
+    }
+
+    #[test]
+    fn test_store_binds_values_without_aad_on_load() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let key = test_key();
+        let mut store = SecretsStore::new();
+        store.values_bound = false;
+        store.secrets.insert(
+            "OLD_TOKEN".to_string(),
+            Secret {
+                name: "OLD_TOKEN".to_string(),
+                encrypted_value: crate::core::crypto::encrypt(b"old-value", &key).unwrap(),
+                ..Default::default()
+            },
+        );
+        store.write(temp_dir.path(), &key).unwrap();
+
+        let loaded = SecretsStore::load(temp_dir.path(), &key).unwrap();
+
+        assert!(loaded.values_bound);
+        assert_eq!(
+            loaded.get_secret("OLD_TOKEN").unwrap().encrypted_value[0],
+            crate::core::crypto::VERSION_AES_GCM_AAD
+        );
+        assert_eq!(
+            loaded.decrypt_secret("OLD_TOKEN", &key).unwrap(),
+            "old-value"
+        );
+    }
+
+    #[test]
+    fn test_store_keeps_legacy_values_readable_after_first_write() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let key = test_key();
+        let mut legacy = SecretsStore::new();
+        legacy.values_bound = false;
+        legacy.secrets.insert(
+            "OLD_TOKEN".to_string(),
+            Secret {
+                name: "OLD_TOKEN".to_string(),
+                encrypted_value: crate::core::crypto::encrypt(b"old-value", &key).unwrap(),
+                ..Default::default()
+            },
+        );
+        legacy.write(temp_dir.path(), &key).unwrap();
+
+        let mut store = SecretsStore::load(temp_dir.path(), &key).unwrap();
+        store
+            .add_secret_at(
+                "NEW_TOKEN".to_string(),
+                "new-value".to_string(),
+                None,
+                temp_dir.path(),
+                &key,
+            )
+            .unwrap();
+
+        let reloaded = SecretsStore::load(temp_dir.path(), &key).unwrap();
+        assert_eq!(
+            reloaded.decrypt_secret("OLD_TOKEN", &key).unwrap(),
+            "old-value"
+        );
+        assert_eq!(
+            reloaded.get_secret("OLD_TOKEN").unwrap().encrypted_value[0],
+            crate::core::crypto::VERSION_AES_GCM_AAD
+        );
+    }
+
+    #[test]
+    fn test_store_rejects_unbound_values_once_migrated() {
+        let key = test_key();
+        let mut store = SecretsStore::new();
+        store.secrets.insert(
+            "OLD_TOKEN".to_string(),
+            Secret {
+                name: "OLD_TOKEN".to_string(),
+                encrypted_value: crate::core::crypto::encrypt(b"old-value", &key).unwrap(),
+                ..Default::default()
+            },
+        );
+
+        let err = store.decrypt_secret("OLD_TOKEN", &key).unwrap_err();
+
+        assert!(err.to_string().contains("not bound"), "{}", err);
+    }
+
     #[test]
     fn test_store_decrypt_nonexistent_fails() {
         let store = SecretsStore::new();
@@ -391,6 +1659,103 @@ mod tests {
         assert!(store.get_secret("TO_DELETE").is_none());
     }
 
+    #[test]
+    fn test_store_expiring_within_boundaries() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let key = test_key();
+        let mut store = SecretsStore::new();
+        let now = now_timestamp();
+
+        for (name, expires_at) in [
+            ("PERMANENT", None),
+            ("EXACTLY_7_DAYS", Some(now + 7 * 86400)),
+            ("JUST_AFTER_7_DAYS", Some(now + 7 * 86400 + 60)),
+            ("TOMORROW", Some(now + 86400)),
+            ("EXPIRED", Some(now - 3600)),
+        ] {
+            store
+                .add_secret_at(
+                    name.to_string(),
+                    "value".to_string(),
+                    expires_at,
+                    temp_dir.path(),
+                    &key,
+                )
+                .unwrap();
+        }
+        let names = |secrets: Vec<&Secret>| -> Vec<String> {
+            secrets.iter().map(|s| s.name.clone()).collect()
+        };
+
+        assert_eq!(
+            names(store.expiring_within(7, false)),
+            vec!["TOMORROW", "EXACTLY_7_DAYS"]
+        );
+        assert_eq!(
+            names(store.expiring_within(7, true)),
+            vec!["EXPIRED", "TOMORROW", "EXACTLY_7_DAYS"]
+        );
+        assert!(store.expiring_within(0, false).is_empty());
+
+        assert_eq!(
+            store.expiring_summary(7).unwrap(),
+            "2 secrets expire within 7 days: TOMORROW, EXACTLY_7_DAYS"
+        );
+        assert_eq!(
+            store.expiring_summary(2).unwrap(),
+            "1 secret expires within 2 days: TOMORROW"
+        );
+        assert!(store.expiring_summary(0).is_none());
+    }
+
+    #[test]
+    fn test_store_prune_expired() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let key = test_key();
+        let mut store = SecretsStore::new();
+        let now = now_timestamp();
+
+        for (name, expires_at) in [
+            ("PERMANENT", None),
+            ("VALID", Some(now + 86400)),
+            ("JUST_EXPIRED", Some(now - 3600)),
+            ("LONG_EXPIRED", Some(now - 90 * 86400)),
+        ] {
+            store
+                .add_secret_at(
+                    name.to_string(),
+                    "value".to_string(),
+                    expires_at,
+                    temp_dir.path(),
+                    &key,
+                )
+                .unwrap();
+        }
+
+        assert_eq!(store.expired_names(0), vec!["JUST_EXPIRED", "LONG_EXPIRED"]);
+        assert!(store.expired_names(365).is_empty());
+
+        let removed = store.prune_expired(30, temp_dir.path(), &key).unwrap();
+        assert_eq!(removed, vec!["LONG_EXPIRED"]);
+
+        let removed = store.prune_expired(0, temp_dir.path(), &key).unwrap();
+        assert_eq!(removed, vec!["JUST_EXPIRED"]);
+
+        let reloaded = SecretsStore::load(temp_dir.path(), &key).unwrap();
+        let names: Vec<_> = reloaded
+            .list_secrets()
+            .iter()
+            .map(|s| s.name.clone())
+            .collect();
+        assert_eq!(names, vec!["PERMANENT", "VALID"]);
+        assert!(
+            store
+                .prune_expired(0, temp_dir.path(), &key)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
     #[test]
     fn test_store_list_secrets_sorted() {
         let temp_dir = TempDir::new().expect("Failed to create temp dir");
@@ -446,6 +1811,33 @@ mod tests {
         assert_eq!(all.get("KEY2").unwrap(), "value2");
     }
 
+    #[test]
+    fn test_decrypt_all_reporting_skips_corrupted_entry() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let key = test_key();
+        let mut store = SecretsStore::new();
+        for name in ["GOOD", "BROKEN"] {
+            store
+                .add_secret(
+                    name.to_string(),
+                    "value".to_string(),
+                    None,
+                    temp_dir.path(),
+                    &key,
+                )
+                .expect("Failed to add secret");
+        }
+        store.secrets.get_mut("BROKEN").unwrap().encrypted_value = vec![0x02, 0xff];
+
+        assert!(store.decrypt_all(&key).is_err());
+        let (values, failures) = store.decrypt_all_reporting(&key);
+        assert_eq!(values.len(), 1);
+        assert_eq!(values.get("GOOD").unwrap(), "value");
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].0, "BROKEN");
+        assert!(!failures[0].1.is_empty());
+    }
+
     #[test]
     fn test_store_save_and_load() {
         let temp_dir = TempDir::new().expect("Failed to create temp dir");
@@ -510,14 +1902,208 @@ mod tests {
         assert!((6..=7).contains(&days));
     }
 
+    #[test]
+    fn test_store_add_secret_rejects_absurd_expiration() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let key = test_key();
+        let mut store = SecretsStore::new();
+
+        let result = store.add_secret(
+            "FOREVER".to_string(),
+            "value".to_string(),
+            Some(u32::MAX),
+            temp_dir.path(),
+            &key,
+        );
+
+        assert!(result.is_err());
+        assert!(store.get_secret("FOREVER").is_none());
+    }
+
+    #[test]
+    fn test_is_valid_env_name() {
+        for name in ["API_KEY", "_PRIVATE", "a", "key2", "X_1_Y"] {
+            assert!(is_valid_env_name(name), "{name}");
+        }
+        for name in [
+            "", "2FA_SEED", "MY KEY", "A=B", "api-key", "db.url", "CLÉ", "$HOME",
+        ] {
+            assert!(!is_valid_env_name(name), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_store_add_secret_rejects_invalid_env_name() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let key = test_key();
+        let mut store = SecretsStore::new();
+
+        let err = store
+            .add_secret(
+                "MY KEY".to_string(),
+                "v".to_string(),
+                None,
+                temp_dir.path(),
+                &key,
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("Invalid token name 'MY KEY'"));
+        assert!(store.is_empty());
+
+        store.allow_any_names();
+        store
+            .add_secret_at(
+                "my.key".to_string(),
+                "v".to_string(),
+                None,
+                temp_dir.path(),
+                &key,
+            )
+            .unwrap();
+        assert_eq!(store.decrypt_secret("my.key", &key).unwrap(), "v");
+    }
+
+    #[test]
+    fn test_import_and_add_many_reject_invalid_env_names() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let key = test_key();
+        let mut store = SecretsStore::new();
+        let plains = vec![
+            PlainSecret::new("API_KEY".to_string(), "sk-123".to_string()),
+            PlainSecret::new("9FOO".to_string(), "v".to_string()),
+        ];
+
+        let err = store
+            .import(&plains, ConflictStrategy::Skip, temp_dir.path(), &key)
+            .unwrap_err();
+        assert!(err.to_string().contains("Invalid token name '9FOO'"));
+        assert!(store.add_many(&plains, temp_dir.path(), &key).is_err());
+        assert!(!temp_dir.path().join("secrets.json").exists());
+
+        store.allow_any_names();
+        store
+            .import(&plains, ConflictStrategy::Skip, temp_dir.path(), &key)
+            .unwrap();
+        assert_eq!(store.decrypt_secret("9FOO", &key).unwrap(), "v");
+    }
+
+    #[test]
+    fn test_rename_rejects_invalid_env_name_unless_allowed() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let key = test_key();
+        let mut store = store_for_rename(temp_dir.path(), &key);
+
+        let err = store
+            .rename_secret("API_KEY", "A-B", temp_dir.path(), &key)
+            .unwrap_err();
+        assert!(err.to_string().contains("Invalid token name 'A-B'"));
+        assert!(store.contains("API_KEY"));
+
+        store.allow_any_names();
+        store
+            .rename_secret("API_KEY", "A-B", temp_dir.path(), &key)
+            .unwrap();
+        assert_eq!(store.decrypt_secret("A-B", &key).unwrap(), "sk-123");
+        assert!(
+            store
+                .rename_secret("A-B", "", temp_dir.path(), &key)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_expires_at_from_days_bounds() {
+        assert!(expires_at_from_days(0).is_ok());
+        assert!(expires_at_from_days(MAX_EXPIRATION_DAYS).is_ok());
+        assert!(expires_at_from_days(MAX_EXPIRATION_DAYS + 1).is_err());
+    }
+
+    #[test]
+    fn test_parse_expiration_date_formats() {
+        let end_of_2099 = 4102358400;
+        assert_eq!(parse_expiration_date("2099-12-31").unwrap(), end_of_2099);
+        assert_eq!(parse_expiration_date("2096-02-29").unwrap(), 3981312000);
+        assert_eq!(
+            parse_expiration_date("2099-12-31T18:00:00Z").unwrap(),
+            end_of_2099 + 18 * 3600
+        );
+        assert_eq!(
+            parse_expiration_date("2099-12-31t18:00:00.250z").unwrap(),
+            end_of_2099 + 18 * 3600
+        );
+        assert_eq!(
+            parse_expiration_date("2099-12-31T18:00:00+02:00").unwrap(),
+            end_of_2099 + 16 * 3600
+        );
+        assert_eq!(
+            parse_expiration_date("2099-12-31 18:00:00-01:30").unwrap(),
+            end_of_2099 + 19 * 3600 + 1800
+        );
+        assert_eq!(format_timestamp(end_of_2099 + 3660), "2099-12-31 01:01 UTC");
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00 UTC");
+    }
+
+    #[test]
+    fn test_parse_expiration_date_rejects_invalid() {
+        for value in [
+            "",
+            "31/12/2099",
+            "2099-1-31",
+            "2099-13-01",
+            "2099-02-29",
+            "2099-04-31",
+            "2099-12-31T18:00:00",
+            "2099-12-31T24:00:00Z",
+            "2099-12-31T18:00Z",
+            "2099-12-31T18:00:00+2:00",
+        ] {
+            let err = parse_expiration_date(value).unwrap_err().to_string();
+            assert!(
+                err.contains("Invalid expiration date"),
+                "{}: {}",
+                value,
+                err
+            );
+        }
+        let err = parse_expiration_date("2000-01-01").unwrap_err();
+        assert!(err.to_string().contains("in the past"));
+        assert!(parse_expiration_date("2999-01-01").is_err());
+    }
+
+    #[test]
+    fn test_add_secret_at_stores_timestamp() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let key = test_key();
+        let mut store = SecretsStore::new();
+        let expires_at = parse_expiration_date("2099-12-31T18:00:00Z").unwrap();
+
+        store
+            .add_secret_at(
+                "API_KEY".to_string(),
+                "v".to_string(),
+                Some(expires_at),
+                temp_dir.path(),
+                &key,
+            )
+            .unwrap();
+
+        let reloaded = SecretsStore::load(temp_dir.path(), &key).unwrap();
+        assert_eq!(
+            reloaded.get_secret("API_KEY").unwrap().expires_at,
+            Some(expires_at)
+        );
+    }
+
     #[test]
     fn test_store_unicode_secret_names_and_values() {
         let temp_dir = TempDir::new().expect("Failed to create temp dir");
         let key = test_key();
         let mut store = SecretsStore::new();
 
+        // Not a valid env variable name, stored with the escape hatch
+        store.allow_any_names();
         store
-            .add_secret(
+            .add_secret_at(
                 "日本語_KEY".to_string(),
                 "Valeur avec émojis 🔐🔑".to_string(),
                 None,
@@ -531,4 +2117,915 @@ mod tests {
             .expect("Failed to decrypt");
         assert_eq!(decrypted, "Valeur avec émojis 🔐🔑");
     }
+
+    #[test]
+    fn test_store_plain_secrets_roundtrip_metadata() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let key = test_key();
+        let mut store = SecretsStore::new();
+
+        let plain = PlainSecret {
+            name: "DEPLOY_KEY".to_string(),
+            value: "ghp_123".to_string(),
+            expires_at: Some(4_102_444_800),
+            tags: vec!["ci".to_string(), "github".to_string()],
+            description: Some("GitHub Actions deploy key".to_string()),
+        };
+        let plains = vec![plain];
+        store
+            .add_many(&plains, temp_dir.path(), &key)
+            .expect("Failed to add secret");
+
+        let loaded = SecretsStore::load(temp_dir.path(), &key).expect("Failed to load store");
+        let exported = loaded.plain_secrets(&key).expect("Failed to export");
+
+        assert_eq!(exported, plains);
+    }
+
+    #[test]
+    fn test_store_variants_resolve_with_fallback() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let key = test_key();
+        let mut store = SecretsStore::new();
+
+        for (name, value) in [("DB_URL", "postgres://dev"), ("API_KEY", "sk-shared")] {
+            store
+                .add_secret(
+                    name.to_string(),
+                    value.to_string(),
+                    None,
+                    temp_dir.path(),
+                    &key,
+                )
+                .expect("Failed to add secret");
+        }
+        store
+            .add_variant("DB_URL", "prod", "postgres://prod", temp_dir.path(), &key)
+            .expect("Failed to add variant");
+
+        let loaded = SecretsStore::load(temp_dir.path(), &key).expect("Failed to load store");
+
+        let prod = loaded.decrypt_all_for(Some("prod"), &key).unwrap();
+        assert_eq!(prod.get("DB_URL").unwrap(), "postgres://prod");
+        assert_eq!(prod.get("API_KEY").unwrap(), "sk-shared"); // No variant: default
+
+        let default = loaded.decrypt_all(&key).unwrap();
+        assert_eq!(default.get("DB_URL").unwrap(), "postgres://dev");
+
+        let staging = loaded
+            .decrypt_secret_for("DB_URL", Some("staging"), &key)
+            .unwrap();
+        assert_eq!(staging, "postgres://dev");
+
+        let db_url = loaded.get_secret("DB_URL").unwrap();
+        assert_eq!(db_url.variant_names(), vec!["prod"]);
+        assert_eq!(db_url.resolved_variant(Some("prod")), Some("prod"));
+        assert_eq!(db_url.resolved_variant(Some("staging")), None);
+        assert_eq!(db_url.resolved_variant(None), None);
+    }
+
+    #[test]
+    fn test_store_concurrent_adds_lose_no_writes() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let key = test_key();
+
+        std::thread::scope(|scope| {
+            for prefix in ["LEFT", "RIGHT"] {
+                let dir = temp_dir.path();
+                scope.spawn(move || {
+                    // Each thread works from its own, soon stale, copy
+                    let mut store = SecretsStore::load(dir, &key).unwrap();
+                    for i in 0..10 {
+                        store
+                            .add_secret(
+                                format!("{}_{}", prefix, i),
+                                "v".to_string(),
+                                None,
+                                dir,
+                                &key,
+                            )
+                            .unwrap();
+                    }
+                });
+            }
+        });
+
+        let loaded = SecretsStore::load(temp_dir.path(), &key).expect("Failed to load store");
+        assert_eq!(loaded.len(), 20);
+    }
+
+    #[test]
+    fn test_store_readd_keeps_created_at_and_bumps_updated_at() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let key = test_key();
+        let mut store = SecretsStore::new();
+
+        store
+            .add_secret(
+                "API_KEY".to_string(),
+                "v1".to_string(),
+                None,
+                temp_dir.path(),
+                &key,
+            )
+            .expect("Failed to add secret");
+        // Pretend the first write happened a while ago
+        let secret = store.secrets.get_mut("API_KEY").unwrap();
+        secret.created_at -= 1000;
+        secret.updated_at -= 1000;
+        let first = secret.clone();
+        store.write(temp_dir.path(), &key).unwrap();
+
+        store
+            .add_secret(
+                "API_KEY".to_string(),
+                "v2".to_string(),
+                None,
+                temp_dir.path(),
+                &key,
+            )
+            .expect("Failed to re-add secret");
+
+        let second = store.get_secret("API_KEY").unwrap();
+        assert!(first.created_at > 0);
+        assert_eq!(second.created_at, first.created_at);
+        assert!(second.updated_at > first.updated_at);
+    }
+
+    #[test]
+    fn test_secret_without_timestamps_deserializes() {
+        let json = r#"{"name":"OLD","encrypted_value":[1,2,3],"expires_at":null}"#;
+
+        let secret: Secret = serde_json::from_str(json).unwrap();
+
+        assert_eq!(secret.kind, SecretKind::Opaque);
+        assert_eq!(secret.created_at, 0);
+        assert_eq!(secret.updated_at, 0);
+        assert_eq!(secret.added_display(), None);
+    }
+
+    /// Creates a store in `dir` holding API_KEY (with a prod variant) and DB_PASSWORD
+    fn store_for_rename(dir: &std::path::Path, key: &[u8]) -> SecretsStore {
+        let mut store = SecretsStore::new();
+        store
+            .add_secret(
+                "API_KEY".to_string(),
+                "sk-123".to_string(),
+                Some(30),
+                dir,
+                key,
+            )
+            .unwrap();
+        store
+            .add_variant("API_KEY", "prod", "sk-prod", dir, key)
+            .unwrap();
+        store
+            .add_secret(
+                "DB_PASSWORD".to_string(),
+                "hunter2".to_string(),
+                None,
+                dir,
+                key,
+            )
+            .unwrap();
+        store
+    }
+
+    #[test]
+    fn test_store_update_secret_keeps_expiration() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let key = test_key();
+        let mut store = store_for_rename(temp_dir.path(), &key);
+        let before = store.get_secret("API_KEY").unwrap().clone();
+
+        store
+            .update_secret("API_KEY", "sk-456", temp_dir.path(), &key)
+            .expect("Failed to update");
+
+        let loaded = SecretsStore::load(temp_dir.path(), &key).expect("Failed to load store");
+        let after = loaded.get_secret("API_KEY").unwrap();
+        assert_eq!(loaded.decrypt_secret("API_KEY", &key).unwrap(), "sk-456");
+        assert_eq!(after.expires_at, before.expires_at);
+        assert_eq!(after.created_at, before.created_at);
+        assert_eq!(
+            loaded
+                .decrypt_secret_for("API_KEY", Some("prod"), &key)
+                .unwrap(),
+            "sk-prod"
+        );
+    }
+
+    #[test]
+    fn test_store_kind_persists() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let key = test_key();
+        let mut store = store_for_rename(temp_dir.path(), &key);
+
+        store
+            .update_secret_with("API_KEY", None, temp_dir.path(), &key, |secret| {
+                secret.kind = SecretKind::Totp
+            })
+            .expect("Failed to set kind");
+
+        let loaded = SecretsStore::load(temp_dir.path(), &key).expect("Failed to load store");
+        assert_eq!(loaded.get_secret("API_KEY").unwrap().kind, SecretKind::Totp);
+        assert_eq!(
+            loaded.get_secret("DB_PASSWORD").unwrap().kind,
+            SecretKind::Opaque
+        );
+    }
+
+    #[test]
+    fn test_load_with_wrong_key_is_a_key_mismatch() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let key = test_key();
+        store_for_rename(temp_dir.path(), &key);
+
+        let err = SecretsStore::load(temp_dir.path(), &[0x24u8; 32]).unwrap_err();
+        assert!(err.is::<StoreKeyMismatch>());
+        assert!(err.to_string().contains("mismatched key"));
+    }
+
+    #[test]
+    fn test_load_tells_parse_and_truncation_errors_from_key_mismatch() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let key = test_key();
+        let path = temp_dir.path().join("secrets.json");
+
+        std::fs::write(
+            &path,
+            encrypt_with(CipherAlgo::default(), b"not json", &key, None).unwrap(),
+        )
+        .unwrap();
+        let parse = SecretsStore::load(temp_dir.path(), &key).unwrap_err();
+        assert!(!parse.is::<StoreKeyMismatch>());
+        assert!(parse.to_string().contains("not a valid store"));
+
+        std::fs::write(&path, b"short").unwrap();
+        let truncated = SecretsStore::load(temp_dir.path(), &key).unwrap_err();
+        assert!(!truncated.is::<StoreKeyMismatch>());
+        assert!(truncated.to_string().contains("truncated"));
+
+        std::fs::remove_file(&path).unwrap();
+        std::fs::create_dir(&path).unwrap();
+        let io = SecretsStore::load(temp_dir.path(), &key).unwrap_err();
+        assert!(io.downcast_ref::<std::io::Error>().is_some());
+    }
+
+    #[test]
+    fn test_staged_commit_matches_individual_adds() {
+        let staged_dir = TempDir::new().expect("Failed to create temp dir");
+        let added_dir = TempDir::new().expect("Failed to create temp dir");
+        let key = test_key();
+        let plains = [
+            PlainSecret::new("API_KEY".to_string(), "sk-123".to_string()),
+            PlainSecret::new("DB_PASSWORD".to_string(), "hunter2".to_string()),
+            PlainSecret::new("API_KEY".to_string(), "sk-456".to_string()),
+        ];
+
+        let mut staged = SecretsStore::load(staged_dir.path(), &key).unwrap();
+        for plain in &plains {
+            staged.stage_secret(plain, &key).expect("Failed to stage");
+        }
+        let names: Vec<&str> = staged.staged.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["API_KEY", "DB_PASSWORD"]);
+        assert!(!staged_dir.path().join("secrets.json").exists());
+        assert_eq!(staged.commit(staged_dir.path(), &key).unwrap(), 2);
+        assert!(staged.staged.is_empty());
+
+        let mut added = SecretsStore::load(added_dir.path(), &key).unwrap();
+        for plain in &plains {
+            added
+                .add_secret_at(
+                    plain.name.clone(),
+                    plain.value.clone(),
+                    None,
+                    added_dir.path(),
+                    &key,
+                )
+                .unwrap();
+        }
+
+        let on_disk = |dir: &TempDir| {
+            SecretsStore::load(dir.path(), &key)
+                .unwrap()
+                .plain_secrets(&key)
+                .unwrap()
+        };
+        assert_eq!(on_disk(&staged_dir), on_disk(&added_dir));
+    }
+
+    #[test]
+    fn test_commit_keeps_staged_secrets_across_a_write() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let key = test_key();
+
+        let mut store = SecretsStore::load(temp_dir.path(), &key).unwrap();
+        let plain = PlainSecret::new("API_KEY".to_string(), "sk-123".to_string());
+        store.stage_secret(&plain, &key).unwrap();
+        // Reloads the secrets from disk, where API_KEY is not yet
+        store
+            .add_secret_at(
+                "DB_PASSWORD".to_string(),
+                "hunter2".to_string(),
+                None,
+                temp_dir.path(),
+                &key,
+            )
+            .unwrap();
+        assert_eq!(store.commit(temp_dir.path(), &key).unwrap(), 1);
+
+        let reloaded = SecretsStore::load(temp_dir.path(), &key).unwrap();
+        assert_eq!(reloaded.decrypt_secret("API_KEY", &key).unwrap(), "sk-123");
+        assert_eq!(
+            reloaded.decrypt_secret("DB_PASSWORD", &key).unwrap(),
+            "hunter2"
+        );
+    }
+
+    #[test]
+    fn test_staging_failure_persists_nothing() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let key = test_key();
+        let mut store = SecretsStore::load(temp_dir.path(), &key).unwrap();
+
+        store
+            .stage_secret(
+                &PlainSecret::new("API_KEY".to_string(), "sk-123".to_string()),
+                &key,
+            )
+            .unwrap();
+        let invalid = PlainSecret::new("not a name".to_string(), "x".to_string());
+        assert!(store.stage_secret(&invalid, &key).is_err());
+        store.max_secret_bytes = 4;
+        let large = PlainSecret::new("LARGE".to_string(), "too large".to_string());
+        assert!(store.stage_secret(&large, &key).is_err());
+
+        // The caller gives up on the batch instead of committing it
+        drop(store);
+        let loaded = SecretsStore::load(temp_dir.path(), &key).unwrap();
+        assert!(loaded.is_empty());
+        assert!(!temp_dir.path().join("secrets.json").exists());
+    }
+
+    #[test]
+    fn test_add_secret_with_writes_value_and_metadata_together() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let key = test_key();
+        let mut store = SecretsStore::new();
+        let secret = Secret {
+            name: "ROOT_PW".to_string(),
+            expires_at: Some(4102358400),
+            tags: vec!["infra".to_string(), "Infra".to_string()],
+            description: Some("Root password".to_string()),
+            kind: SecretKind::Opaque,
+            protected: true,
+            ..Default::default()
+        };
+
+        store
+            .add_secret_with(secret, "hunter2", temp_dir.path(), &key)
+            .expect("Failed to add");
+
+        let loaded = SecretsStore::load(temp_dir.path(), &key).unwrap();
+        let saved = loaded.get_secret("ROOT_PW").unwrap();
+        assert!(saved.protected);
+        assert_eq!(saved.expires_at, Some(4102358400));
+        assert_eq!(saved.tags, vec!["infra".to_string()]);
+        assert_eq!(saved.description.as_deref(), Some("Root password"));
+        assert_eq!(loaded.decrypt_secret("ROOT_PW", &key).unwrap(), "hunter2");
+    }
+
+    #[test]
+    fn test_readding_a_secret_keeps_protection_kind_and_variants() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let key = test_key();
+        let mut store = SecretsStore::new();
+        let secret = Secret {
+            name: "ROOT_PW".to_string(),
+            kind: SecretKind::Totp,
+            protected: true,
+            ..Default::default()
+        };
+        store
+            .add_secret_with(secret, "JBSWY3DPEHPK3PXP", temp_dir.path(), &key)
+            .unwrap();
+        store
+            .add_variant("ROOT_PW", "prod", "prod-seed", temp_dir.path(), &key)
+            .unwrap();
+
+        store
+            .add_secret_at(
+                "ROOT_PW".to_string(),
+                "KRSXG5CTMVRXEZLU".to_string(),
+                None,
+                temp_dir.path(),
+                &key,
+            )
+            .unwrap();
+        let plains = [PlainSecret::new(
+            "ROOT_PW".to_string(),
+            "imported".to_string(),
+        )];
+        store
+            .import(&plains, ConflictStrategy::Overwrite, temp_dir.path(), &key)
+            .unwrap();
+
+        let loaded = SecretsStore::load(temp_dir.path(), &key).unwrap();
+        let saved = loaded.get_secret("ROOT_PW").unwrap();
+        assert!(saved.protected);
+        assert_eq!(saved.kind, SecretKind::Totp);
+        assert_eq!(saved.variant_names(), vec!["prod"]);
+        assert_eq!(loaded.decrypt_secret("ROOT_PW", &key).unwrap(), "imported");
+    }
+
+    #[test]
+    fn test_update_secret_with_applies_value_and_metadata_or_nothing() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let key = test_key();
+        let mut store = store_for_rename(temp_dir.path(), &key);
+
+        store
+            .update_secret_with("API_KEY", Some("sk-456"), temp_dir.path(), &key, |secret| {
+                secret.protected = true
+            })
+            .unwrap();
+        let loaded = SecretsStore::load(temp_dir.path(), &key).unwrap();
+        assert!(loaded.get_secret("API_KEY").unwrap().protected);
+        assert_eq!(loaded.decrypt_secret("API_KEY", &key).unwrap(), "sk-456");
+
+        // An oversized value fails before the metadata change is written
+        store.max_secret_bytes = 4;
+        assert!(
+            store
+                .update_secret_with("API_KEY", Some("too large"), temp_dir.path(), &key, |s| {
+                    s.protected = false
+                })
+                .is_err()
+        );
+        let loaded = SecretsStore::load(temp_dir.path(), &key).unwrap();
+        assert!(loaded.get_secret("API_KEY").unwrap().protected);
+    }
+
+    #[test]
+    fn test_store_protected_flag_persists_and_survives_update() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let key = test_key();
+        let mut store = store_for_rename(temp_dir.path(), &key);
+
+        store
+            .update_secret_with("API_KEY", None, temp_dir.path(), &key, |secret| {
+                secret.protected = true
+            })
+            .expect("Failed to protect");
+        store
+            .update_secret("API_KEY", "sk-rotated", temp_dir.path(), &key)
+            .expect("Failed to update");
+
+        let loaded = SecretsStore::load(temp_dir.path(), &key).expect("Failed to load store");
+        assert!(loaded.get_secret("API_KEY").unwrap().protected);
+        assert!(!loaded.get_secret("DB_PASSWORD").unwrap().protected);
+    }
+
+    #[test]
+    fn test_store_description_survives_save_and_load() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let key = test_key();
+        let mut store = store_for_rename(temp_dir.path(), &key);
+
+        store
+            .update_secret_with("API_KEY", None, temp_dir.path(), &key, |secret| {
+                secret.description = Some("GitHub Actions deploy key".to_string())
+            })
+            .expect("Failed to set description");
+
+        let mut loaded = SecretsStore::load(temp_dir.path(), &key).expect("Failed to load store");
+        assert_eq!(
+            loaded.get_secret("API_KEY").unwrap().description.as_deref(),
+            Some("GitHub Actions deploy key")
+        );
+        assert!(
+            loaded
+                .get_secret("DB_PASSWORD")
+                .unwrap()
+                .description
+                .is_none()
+        );
+
+        loaded
+            .update_secret_with("API_KEY", None, temp_dir.path(), &key, |secret| {
+                secret.description = None
+            })
+            .expect("Failed to clear description");
+        let loaded = SecretsStore::load(temp_dir.path(), &key).expect("Failed to load store");
+        assert!(loaded.get_secret("API_KEY").unwrap().description.is_none());
+    }
+
+    #[test]
+    fn test_store_tags_are_normalized_and_match_ignoring_case() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let key = test_key();
+        let mut store = store_for_rename(temp_dir.path(), &key);
+        let tags = ["Backend", " billing ", "backend", ""].map(String::from);
+
+        store
+            .update_secret_with("API_KEY", None, temp_dir.path(), &key, |secret| {
+                secret.tags = tags.to_vec()
+            })
+            .expect("Failed to set tags");
+
+        let loaded = SecretsStore::load(temp_dir.path(), &key).expect("Failed to load store");
+        let secret = loaded.get_secret("API_KEY").unwrap();
+        assert_eq!(secret.tags, vec!["Backend", "billing"]);
+        assert!(secret.has_tag("BACKEND") && secret.has_tag("billing"));
+        assert!(!secret.has_tag("back"));
+        assert_eq!(loaded.all_tags(), vec!["backend", "billing"]);
+    }
+
+    #[test]
+    fn test_store_update_missing_secret_fails() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let key = test_key();
+        let mut store = SecretsStore::new();
+
+        let err = store
+            .update_secret("MISSING", "value", temp_dir.path(), &key)
+            .unwrap_err();
+
+        assert_eq!(err.to_string(), "Token 'MISSING' not found");
+        assert!(!store.contains("MISSING"));
+    }
+
+    #[test]
+    fn test_store_rename_secret_keeps_value_and_metadata() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let key = test_key();
+        let mut store = store_for_rename(temp_dir.path(), &key);
+        let expires_at = store.get_secret("API_KEY").unwrap().expires_at;
+
+        store
+            .rename_secret("API_KEY", "OPENAI_KEY", temp_dir.path(), &key)
+            .expect("Failed to rename");
+
+        let loaded = SecretsStore::load(temp_dir.path(), &key).expect("Failed to load store");
+        assert!(!loaded.contains("API_KEY"));
+        let renamed = loaded.get_secret("OPENAI_KEY").unwrap();
+        assert_eq!(renamed.name, "OPENAI_KEY");
+        assert_eq!(renamed.expires_at, expires_at);
+        assert_eq!(loaded.decrypt_secret("OPENAI_KEY", &key).unwrap(), "sk-123");
+        assert_eq!(
+            loaded
+                .decrypt_secret_for("OPENAI_KEY", Some("prod"), &key)
+                .unwrap(),
+            "sk-prod"
+        );
+    }
+
+    #[test]
+    fn test_store_rename_missing_secret_fails() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let key = test_key();
+        let mut store = store_for_rename(temp_dir.path(), &key);
+
+        let err = store
+            .rename_secret("MISSING", "OTHER", temp_dir.path(), &key)
+            .unwrap_err();
+
+        assert_eq!(err.to_string(), "Token 'MISSING' not found");
+        assert!(!store.contains("OTHER"));
+    }
+
+    #[test]
+    fn test_store_rename_onto_existing_secret_fails() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let key = test_key();
+        let mut store = store_for_rename(temp_dir.path(), &key);
+
+        let err = store
+            .rename_secret("API_KEY", "DB_PASSWORD", temp_dir.path(), &key)
+            .unwrap_err();
+
+        assert_eq!(err.to_string(), "Token 'DB_PASSWORD' already exists");
+        assert_eq!(store.decrypt_secret("API_KEY", &key).unwrap(), "sk-123");
+        assert_eq!(
+            store.decrypt_secret("DB_PASSWORD", &key).unwrap(),
+            "hunter2"
+        );
+    }
+
+    #[test]
+    fn test_store_add_many_saves_all() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let key = test_key();
+        let mut store = SecretsStore::new();
+
+        let mut with_expiry = PlainSecret::new("B".to_string(), "2".to_string());
+        with_expiry.expires_at = Some(now_timestamp() + 3600);
+        let plains = vec![
+            PlainSecret::new("A".to_string(), "1".to_string()),
+            with_expiry,
+        ];
+
+        let count = store
+            .add_many(&plains, temp_dir.path(), &key)
+            .expect("Failed to add secrets");
+        assert_eq!(count, 2);
+
+        let loaded = SecretsStore::load(temp_dir.path(), &key).expect("Failed to load store");
+        assert_eq!(loaded.plain_secrets(&key).unwrap(), plains);
+    }
+
+    fn import_fixture(dir: &std::path::Path, key: &[u8]) -> (SecretsStore, Vec<PlainSecret>) {
+        let mut store = SecretsStore::new();
+        store
+            .add_many(
+                &[PlainSecret::new("EXISTING".to_string(), "prod".to_string())],
+                dir,
+                key,
+            )
+            .unwrap();
+        let plains = vec![
+            PlainSecret::new("EXISTING".to_string(), "stale".to_string()),
+            PlainSecret::new("NEW".to_string(), "fresh".to_string()),
+        ];
+        (store, plains)
+    }
+
+    #[test]
+    fn test_import_skip_keeps_existing_values() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let key = test_key();
+        let (mut store, plains) = import_fixture(temp_dir.path(), &key);
+
+        let summary = store
+            .import(&plains, ConflictStrategy::Skip, temp_dir.path(), &key)
+            .unwrap();
+
+        assert_eq!(summary.imported, vec!["NEW"]);
+        assert_eq!(summary.skipped, vec!["EXISTING"]);
+        let loaded = SecretsStore::load(temp_dir.path(), &key).unwrap();
+        assert_eq!(loaded.decrypt_secret("EXISTING", &key).unwrap(), "prod");
+        assert_eq!(loaded.decrypt_secret("NEW", &key).unwrap(), "fresh");
+    }
+
+    #[test]
+    fn test_import_overwrite_replaces_values() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let key = test_key();
+        let (mut store, plains) = import_fixture(temp_dir.path(), &key);
+
+        let summary = store
+            .import(&plains, ConflictStrategy::Overwrite, temp_dir.path(), &key)
+            .unwrap();
+
+        assert_eq!(summary.overwritten, vec!["EXISTING"]);
+        let loaded = SecretsStore::load(temp_dir.path(), &key).unwrap();
+        assert_eq!(loaded.decrypt_secret("EXISTING", &key).unwrap(), "stale");
+    }
+
+    #[test]
+    fn test_import_error_writes_nothing() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let key = test_key();
+        let (mut store, plains) = import_fixture(temp_dir.path(), &key);
+
+        let err = store
+            .import(&plains, ConflictStrategy::Error, temp_dir.path(), &key)
+            .unwrap_err();
+
+        assert!(err.to_string().contains("EXISTING"));
+        let loaded = SecretsStore::load(temp_dir.path(), &key).unwrap();
+        assert!(!loaded.contains("NEW"));
+    }
+
+    #[test]
+    fn test_plan_import_leaves_store_untouched() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let key = test_key();
+        let (store, plains) = import_fixture(temp_dir.path(), &key);
+        let before = std::fs::read(temp_dir.path().join("secrets.json")).unwrap();
+
+        let summary = store
+            .plan_import(&plains, ConflictStrategy::Overwrite)
+            .unwrap();
+
+        assert_eq!(summary.imported, vec!["NEW"]);
+        assert_eq!(summary.overwritten, vec!["EXISTING"]);
+        assert!(!store.contains("NEW"));
+        assert_eq!(
+            std::fs::read(temp_dir.path().join("secrets.json")).unwrap(),
+            before
+        );
+    }
+
+    #[test]
+    fn test_store_mixed_ciphers_roundtrip() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let key = test_key();
+        let mut store = SecretsStore::new();
+
+        store
+            .add_secret(
+                "AES_TOKEN".to_string(),
+                "aes-value".to_string(),
+                None,
+                temp_dir.path(),
+                &key,
+            )
+            .expect("Failed to add secret");
+        store.cipher = CipherAlgo::ChaCha20Poly1305;
+        store
+            .add_secret(
+                "CHACHA_TOKEN".to_string(),
+                "chacha-value".to_string(),
+                None,
+                temp_dir.path(),
+                &key,
+            )
+            .expect("Failed to add secret");
+
+        let loaded = SecretsStore::load(temp_dir.path(), &key).expect("Failed to load store");
+        let data = fs::read(temp_dir.path().join("secrets.json")).unwrap();
+
+        assert_eq!(data[0], crate::core::crypto::VERSION_CHACHA20_POLY1305);
+        assert_eq!(
+            loaded.secrets["AES_TOKEN"].encrypted_value[0],
+            crate::core::crypto::VERSION_AES_GCM_AAD
+        );
+        assert_eq!(
+            loaded.secrets["CHACHA_TOKEN"].encrypted_value[0],
+            crate::core::crypto::VERSION_CHACHA20_POLY1305
+        );
+        assert_eq!(
+            loaded.decrypt_secret("AES_TOKEN", &key).unwrap(),
+            "aes-value"
+        );
+        assert_eq!(
+            loaded.decrypt_secret("CHACHA_TOKEN", &key).unwrap(),
+            "chacha-value"
+        );
+    }
+
+    #[test]
+    fn test_store_load_uses_configured_cipher() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        fs::write(
+            temp_dir.path().join("config.toml"),
+            "[crypto]\ncipher = \"chacha20-poly1305\"\n",
+        )
+        .unwrap();
+
+        let store = SecretsStore::load(temp_dir.path(), &test_key()).expect("Failed to load");
+
+        assert_eq!(store.cipher, CipherAlgo::ChaCha20Poly1305);
+    }
+
+    #[test]
+    fn test_store_rejects_values_above_size_limit() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let key = test_key();
+        fs::write(
+            temp_dir.path().join("config.toml"),
+            "[store]\nmax_secret_bytes = 8\n",
+        )
+        .unwrap();
+        let mut store = SecretsStore::load(temp_dir.path(), &key).expect("Failed to load");
+
+        let add = |store: &mut SecretsStore, name: &str, value: &str| {
+            store.add_secret(
+                name.to_string(),
+                value.to_string(),
+                None,
+                temp_dir.path(),
+                &key,
+            )
+        };
+        add(&mut store, "SMALL", "12345678").expect("Value at the limit is accepted");
+        let err = add(&mut store, "BIG", "123456789").unwrap_err().to_string();
+        assert!(err.contains("9 bytes") && err.contains("--allow-large"));
+        assert!(
+            store
+                .update_secret("SMALL", "123456789", temp_dir.path(), &key)
+                .is_err()
+        );
+        let oversized = [PlainSecret::new("BIG".to_string(), "123456789".to_string())];
+        assert!(store.add_many(&oversized, temp_dir.path(), &key).is_err());
+        assert!(store.get_secret("BIG").is_none());
+
+        store.allow_large_values();
+        add(&mut store, "BIG", "123456789").expect("Override accepts any size");
+        assert_eq!(
+            store
+                .get_secret("BIG")
+                .unwrap()
+                .decrypt_for(None, &key)
+                .unwrap(),
+            "123456789"
+        );
+    }
+
+    #[test]
+    fn test_store_add_variant_requires_existing_secret() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let key = test_key();
+        let mut store = SecretsStore::new();
+
+        let result = store.add_variant("MISSING", "prod", "value", temp_dir.path(), &key);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_store_verify_all_reports_failures() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let key = test_key();
+        let mut store = SecretsStore::new();
+
+        for name in ["GOOD", "BROKEN"] {
+            store
+                .add_secret(
+                    name.to_string(),
+                    "value".to_string(),
+                    None,
+                    temp_dir.path(),
+                    &key,
+                )
+                .expect("Failed to add secret");
+        }
+        let broken = store.secrets.get_mut("BROKEN").unwrap();
+        let last = broken.encrypted_value.len() - 1;
+        broken.encrypted_value[last] ^= 0xFF;
+
+        assert_eq!(
+            store.verify_all(&key),
+            vec![("BROKEN".to_string(), false), ("GOOD".to_string(), true)]
+        );
+    }
+
+    #[test]
+    fn test_store_metadata_issues() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let key = test_key();
+        let mut store = SecretsStore::new();
+        for name in ["GOOD", "BACKWARDS", "MOVED"] {
+            store
+                .add_secret(
+                    name.to_string(),
+                    "value".to_string(),
+                    Some(30),
+                    temp_dir.path(),
+                    &key,
+                )
+                .expect("Failed to add secret");
+        }
+        let backwards = store.secrets.get_mut("BACKWARDS").unwrap();
+        backwards.updated_at = backwards.created_at - 10;
+        backwards.expires_at = Some(-1);
+        store.secrets.get_mut("MOVED").unwrap().name = "OTHER".to_string();
+
+        assert_eq!(
+            store.metadata_issues(),
+            vec![
+                (
+                    "BACKWARDS".to_string(),
+                    "updated before it was created".to_string()
+                ),
+                (
+                    "BACKWARDS".to_string(),
+                    "invalid expiration time".to_string()
+                ),
+                (
+                    "MOVED".to_string(),
+                    "stored under another name ('OTHER')".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_store_reencrypt_changes_key() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let old_key = test_key();
+        let new_key = [0x24u8; 32];
+        let mut store = SecretsStore::new();
+
+        store
+            .add_secret(
+                "API_KEY".to_string(),
+                "sk-123".to_string(),
+                Some(30),
+                temp_dir.path(),
+                &old_key,
+            )
+            .expect("Failed to add secret");
+
+        let count = store
+            .reencrypt(&old_key, &new_key)
+            .expect("Failed to re-encrypt");
+
+        assert_eq!(count, 1);
+        assert!(store.decrypt_secret("API_KEY", &old_key).is_err());
+        assert_eq!(store.decrypt_secret("API_KEY", &new_key).unwrap(), "sk-123");
+        assert!(store.get_secret("API_KEY").unwrap().expires_at.is_some());
+    }
 }
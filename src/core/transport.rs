@@ -0,0 +1,412 @@
+//! Local transport between the agent and its clients.
+//!
+//! The agent speaks the same JSON-over-lines protocol everywhere; only the
+//! channel differs: a Unix socket (mode 0600) on Unix, a named pipe restricted
+//! to its owner on Windows. `Transport` is the implementation for the platform.
+
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// The process on the other end of a connection, as reported by the OS
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerCred {
+    pub pid: u32,
+    /// Unix user id (None on Windows, where the pipe is owner-only anyway)
+    pub uid: Option<u32>,
+}
+
+/// A connection-oriented local channel the agent listens on
+pub trait AgentTransport {
+    /// One client connection
+    type Stream: Read + Write + Send + 'static;
+    /// Bound endpoint waiting for clients
+    type Listener: Send;
+
+    /// Endpoint for a locker directory
+    fn endpoint(locker_dir: &Path) -> PathBuf;
+
+    /// Connects to a listening agent. Where the platform allows it, reads and
+    /// writes give up after `CLIENT_TIMEOUT` so a wedged agent cannot block a client.
+    fn connect(endpoint: &Path) -> io::Result<Self::Stream>;
+
+    /// Binds the endpoint so that only the current user can connect
+    fn bind(endpoint: &Path) -> io::Result<Self::Listener>;
+
+    /// Accepts a pending client without blocking (None when nobody is waiting)
+    fn accept(listener: &mut Self::Listener) -> io::Result<Option<Self::Stream>>;
+
+    /// Credentials of a connected client, where the platform reports them
+    fn peer_cred(stream: &Self::Stream) -> Option<PeerCred>;
+
+    /// Whether something is listening on (or still occupies) the endpoint
+    fn exists(endpoint: &Path) -> bool;
+
+    /// Removes what a stopped agent left behind at the endpoint
+    fn cleanup(endpoint: &Path);
+}
+
+/// How long a client waits on the agent before giving up
+#[cfg_attr(windows, allow(dead_code))]
+pub const CLIENT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+#[cfg(unix)]
+pub use unix::UnixSocket as Transport;
+#[cfg(windows)]
+pub use windows::NamedPipe as Transport;
+
+#[cfg(unix)]
+mod unix {
+    use super::*;
+    use std::os::unix::net::{UnixListener, UnixStream};
+
+    /// Unix domain socket in the locker directory
+    pub struct UnixSocket;
+
+    impl AgentTransport for UnixSocket {
+        type Stream = UnixStream;
+        type Listener = UnixListener;
+
+        fn endpoint(locker_dir: &Path) -> PathBuf {
+            locker_dir.join("agent.sock")
+        }
+
+        fn connect(endpoint: &Path) -> io::Result<UnixStream> {
+            let stream = UnixStream::connect(endpoint)?;
+            stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+            stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+            Ok(stream)
+        }
+
+        fn bind(endpoint: &Path) -> io::Result<UnixListener> {
+            use std::os::unix::fs::PermissionsExt;
+
+            let listener = UnixListener::bind(endpoint)?;
+            // Non-blocking to allow periodic shutdown checks
+            listener.set_nonblocking(true)?;
+            std::fs::set_permissions(endpoint, std::fs::Permissions::from_mode(0o600))?;
+            Ok(listener)
+        }
+
+        fn accept(listener: &mut UnixListener) -> io::Result<Option<UnixStream>> {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    // Accepted sockets inherit non-blocking mode on some platforms
+                    stream.set_nonblocking(false)?;
+                    Ok(Some(stream))
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => Ok(None),
+                Err(e) => Err(e),
+            }
+        }
+
+        /// `SO_PEERCRED`, as the client was when it connected
+        #[cfg(target_os = "linux")]
+        fn peer_cred(stream: &UnixStream) -> Option<PeerCred> {
+            use std::os::unix::io::AsRawFd;
+
+            let mut cred = libc::ucred {
+                pid: 0,
+                uid: 0,
+                gid: 0,
+            };
+            let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+            // SAFETY: cred and len describe a writable ucred buffer for the call
+            let rc = unsafe {
+                libc::getsockopt(
+                    stream.as_raw_fd(),
+                    libc::SOL_SOCKET,
+                    libc::SO_PEERCRED,
+                    (&mut cred as *mut libc::ucred).cast(),
+                    &mut len,
+                )
+            };
+            (rc == 0).then_some(PeerCred {
+                pid: cred.pid as u32,
+                uid: Some(cred.uid),
+            })
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        fn peer_cred(_stream: &UnixStream) -> Option<PeerCred> {
+            None
+        }
+
+        fn exists(endpoint: &Path) -> bool {
+            endpoint.exists()
+        }
+
+        fn cleanup(endpoint: &Path) {
+            std::fs::remove_file(endpoint).ok();
+        }
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use super::*;
+    use std::ffi::c_void;
+    use std::fs::File;
+    use std::os::windows::ffi::OsStrExt;
+    use std::os::windows::io::{AsRawHandle, FromRawHandle};
+    use windows_sys::Win32::Foundation::{
+        ERROR_BROKEN_PIPE, ERROR_NO_DATA, ERROR_PIPE_CONNECTED, ERROR_PIPE_LISTENING,
+        ERROR_SEM_TIMEOUT, GetLastError, INVALID_HANDLE_VALUE, LocalFree,
+    };
+    use windows_sys::Win32::Security::Authorization::{
+        ConvertStringSecurityDescriptorToSecurityDescriptorW, SDDL_REVISION_1,
+    };
+    use windows_sys::Win32::Security::SECURITY_ATTRIBUTES;
+    use windows_sys::Win32::Storage::FileSystem::{
+        FILE_FLAG_FIRST_PIPE_INSTANCE, PIPE_ACCESS_DUPLEX,
+    };
+    use windows_sys::Win32::System::Pipes::{
+        ConnectNamedPipe, CreateNamedPipeW, DisconnectNamedPipe, GetNamedPipeClientProcessId,
+        PIPE_NOWAIT, PIPE_READMODE_BYTE, PIPE_REJECT_REMOTE_CLIENTS, PIPE_TYPE_BYTE,
+        PIPE_UNLIMITED_INSTANCES, PIPE_WAIT, SetNamedPipeHandleState, WaitNamedPipeW,
+    };
+
+    /// Only the pipe's owner gets access: the 0600 of a Unix socket
+    const OWNER_ONLY_SDDL: &str = "D:P(A;;GA;;;OW)";
+
+    const BUFFER_SIZE: u32 = 64 * 1024;
+
+    /// Named pipe `\\.\pipe\lazy-locker-<user>`
+    pub struct NamedPipe;
+
+    /// Pipe instance waiting for the next client
+    pub struct PipeListener {
+        name: Vec<u16>,
+        pending: File,
+    }
+
+    /// Server or client end of a connected pipe
+    pub struct PipeStream(File);
+
+    impl Read for PipeStream {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.0.read(buf) {
+                // The other end closing the pipe is end of stream
+                Err(e) if e.raw_os_error() == Some(ERROR_BROKEN_PIPE as i32) => Ok(0),
+                result => result,
+            }
+        }
+    }
+
+    impl Write for PipeStream {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.write(buf)
+        }
+
+        /// Waits until the peer has read everything, as closing the handle
+        /// earlier could discard the response
+        fn flush(&mut self) -> io::Result<()> {
+            self.0.sync_all()
+        }
+    }
+
+    fn wide(path: &Path) -> Vec<u16> {
+        path.as_os_str().encode_wide().chain(Some(0)).collect()
+    }
+
+    fn create_instance(name: &[u16], first: bool) -> io::Result<File> {
+        let sddl: Vec<u16> = OWNER_ONLY_SDDL.encode_utf16().chain(Some(0)).collect();
+        let mut descriptor: *mut c_void = std::ptr::null_mut();
+        // SAFETY: sddl is NUL-terminated and descriptor receives a LocalAlloc'd buffer
+        let converted = unsafe {
+            ConvertStringSecurityDescriptorToSecurityDescriptorW(
+                sddl.as_ptr(),
+                SDDL_REVISION_1,
+                &mut descriptor,
+                std::ptr::null_mut(),
+            )
+        };
+        if converted == 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let attributes = SECURITY_ATTRIBUTES {
+            nLength: std::mem::size_of::<SECURITY_ATTRIBUTES>() as u32,
+            lpSecurityDescriptor: descriptor,
+            bInheritHandle: 0,
+        };
+        let open_mode = PIPE_ACCESS_DUPLEX
+            | if first {
+                FILE_FLAG_FIRST_PIPE_INSTANCE
+            } else {
+                0
+            };
+        // PIPE_NOWAIT makes ConnectNamedPipe return at once, for a non-blocking accept
+        let pipe_mode =
+            PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_NOWAIT | PIPE_REJECT_REMOTE_CLIENTS;
+        // SAFETY: name is NUL-terminated and attributes outlives the call
+        let handle = unsafe {
+            CreateNamedPipeW(
+                name.as_ptr(),
+                open_mode,
+                pipe_mode,
+                PIPE_UNLIMITED_INSTANCES,
+                BUFFER_SIZE,
+                BUFFER_SIZE,
+                0,
+                &attributes,
+            )
+        };
+        let error = io::Error::last_os_error();
+        // SAFETY: descriptor was allocated by the conversion above
+        unsafe { LocalFree(descriptor) };
+
+        if handle == INVALID_HANDLE_VALUE {
+            return Err(error);
+        }
+        // SAFETY: handle is a valid pipe handle owned by nobody else
+        Ok(unsafe { File::from_raw_handle(handle) })
+    }
+
+    impl AgentTransport for NamedPipe {
+        type Stream = PipeStream;
+        type Listener = PipeListener;
+
+        /// Pipes are global, so profiles (`profiles/<NAME>`) get their own name
+        fn endpoint(locker_dir: &Path) -> PathBuf {
+            let user = std::env::var("USERNAME").unwrap_or_default();
+            let profile = locker_dir
+                .parent()
+                .filter(|parent| parent.file_name() == Some("profiles".as_ref()))
+                .and_then(|_| locker_dir.file_name())
+                .map(|name| format!("-{}", name.to_string_lossy()))
+                .unwrap_or_default();
+            PathBuf::from(format!(r"\\.\pipe\lazy-locker-{}{}", user, profile))
+        }
+
+        fn connect(endpoint: &Path) -> io::Result<PipeStream> {
+            std::fs::OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(endpoint)
+                .map(PipeStream)
+        }
+
+        fn bind(endpoint: &Path) -> io::Result<PipeListener> {
+            let name = wide(endpoint);
+            let pending = create_instance(&name, true)?;
+            Ok(PipeListener { name, pending })
+        }
+
+        fn accept(listener: &mut PipeListener) -> io::Result<Option<PipeStream>> {
+            let handle = listener.pending.as_raw_handle();
+            // SAFETY: handle is the listener's own pipe instance
+            let connected = unsafe { ConnectNamedPipe(handle, std::ptr::null_mut()) } != 0;
+            if !connected {
+                // SAFETY: reads the calling thread's last error
+                match unsafe { GetLastError() } {
+                    ERROR_PIPE_CONNECTED => {}
+                    ERROR_PIPE_LISTENING => return Ok(None),
+                    // A client connected and left already: recycle the instance
+                    ERROR_NO_DATA => {
+                        // SAFETY: handle is the listener's own pipe instance
+                        unsafe { DisconnectNamedPipe(handle) };
+                        return Ok(None);
+                    }
+                    code => return Err(io::Error::from_raw_os_error(code as i32)),
+                }
+            }
+
+            // Blocking I/O for the connected client, then a fresh instance for the next
+            let mode = PIPE_READMODE_BYTE | PIPE_WAIT;
+            // SAFETY: handle is valid and mode outlives the call
+            if unsafe { SetNamedPipeHandleState(handle, &mode, std::ptr::null(), std::ptr::null()) }
+                == 0
+            {
+                return Err(io::Error::last_os_error());
+            }
+            let next = create_instance(&listener.name, false)?;
+            let stream = std::mem::replace(&mut listener.pending, next);
+            Ok(Some(PipeStream(stream)))
+        }
+
+        fn peer_cred(stream: &PipeStream) -> Option<PeerCred> {
+            let mut pid = 0u32;
+            // SAFETY: the handle is the stream's own pipe and pid outlives the call
+            let ok = unsafe { GetNamedPipeClientProcessId(stream.0.as_raw_handle(), &mut pid) };
+            (ok != 0).then_some(PeerCred { pid, uid: None })
+        }
+
+        fn exists(endpoint: &Path) -> bool {
+            let name = wide(endpoint);
+            // SAFETY: name is NUL-terminated
+            let available = unsafe { WaitNamedPipeW(name.as_ptr(), 1) } != 0;
+            // A timeout means the pipe exists but all instances are busy
+            available || io::Error::last_os_error().raw_os_error() == Some(ERROR_SEM_TIMEOUT as i32)
+        }
+
+        fn cleanup(_endpoint: &Path) {
+            // Pipes vanish with the last handle
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_transport_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let endpoint = if cfg!(windows) {
+            PathBuf::from(format!(r"\\.\pipe\lazy-locker-test-{}", std::process::id()))
+        } else {
+            Transport::endpoint(dir.path())
+        };
+        let mut listener = Transport::bind(&endpoint).unwrap();
+        assert!(Transport::exists(&endpoint));
+
+        let client_endpoint = endpoint.clone();
+        let client = std::thread::spawn(move || {
+            let mut stream = Transport::connect(&client_endpoint).unwrap();
+            writeln!(stream, "ping").unwrap();
+            stream.flush().unwrap();
+            let mut reply = String::new();
+            BufReader::new(stream).read_line(&mut reply).unwrap();
+            reply
+        });
+
+        let stream = loop {
+            if let Some(stream) = Transport::accept(&mut listener).unwrap() {
+                break stream;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        };
+        let mut reader = BufReader::new(stream);
+        let mut request = String::new();
+        reader.read_line(&mut request).unwrap();
+        assert_eq!(request, "ping\n");
+        writeln!(reader.get_mut(), "pong").unwrap();
+        reader.get_mut().flush().unwrap();
+
+        assert_eq!(client.join().unwrap(), "pong\n");
+        Transport::cleanup(&endpoint);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_peer_cred_reports_connecting_process() {
+        let dir = TempDir::new().unwrap();
+        let endpoint = Transport::endpoint(dir.path());
+        let mut listener = Transport::bind(&endpoint).unwrap();
+
+        let _client = Transport::connect(&endpoint).unwrap();
+        let stream = loop {
+            if let Some(stream) = Transport::accept(&mut listener).unwrap() {
+                break stream;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        };
+
+        let peer = Transport::peer_cred(&stream).unwrap();
+        assert_eq!(peer.pid, std::process::id());
+        // SAFETY: getuid has no preconditions
+        assert_eq!(peer.uid, Some(unsafe { libc::getuid() }));
+    }
+}
@@ -0,0 +1,452 @@
+//! Filtering of token usage reports.
+//!
+//! token-analyzer only applies `.gitignore` files inside a git repository and
+//! matches `ignore_dirs` by exact name. This filter drops the files a project
+//! ignores either way, plus anything inside the locker directory itself.
+//!
+//! token-analyzer always matches a token name as a whole word. The substring
+//! and regex modes of `MatchMode` are searched here instead, by `scan_usages`.
+
+use crate::core::config::AnalyzerSettings;
+use anyhow::Result;
+use ignore::Match;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use token_analyzer::{AnalysisReport, FileAnalysis, RiskLevel};
+
+/// Files larger than this are not searched by `scan_usages`, like token-analyzer
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+/// How a token name is matched when looking for its usages
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchMode {
+    /// The name as a whole word: `API` does not match `RAPIDLY` nor `API_KEY`
+    #[default]
+    Word,
+    /// The name anywhere, even inside a longer word
+    Substring,
+    /// A regular expression in which `{name}` stands for the escaped name,
+    /// e.g. `(?i)\b{name}\b` to ignore case
+    Regex(String),
+}
+
+impl MatchMode {
+    /// The regular expression finding `token` in this mode
+    pub fn matcher(&self, token: &str) -> Result<Regex> {
+        let name = regex::escape(token);
+        let pattern = match self {
+            Self::Word => format!(r"\b{}\b", name),
+            Self::Substring => name,
+            Self::Regex(pattern) => pattern.replace("{name}", &name),
+        };
+        Regex::new(&pattern).map_err(|e| anyhow::anyhow!("Invalid usage pattern: {}", e))
+    }
+}
+
+/// One match of a token name in a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenUsage {
+    /// Line number, from 1
+    pub line: usize,
+    /// Byte range of the match within the line
+    pub span: Range<usize>,
+}
+
+/// Every match of `token` in `content`
+#[allow(dead_code)]
+pub fn find_token_usages(content: &str, token: &str, mode: &MatchMode) -> Result<Vec<TokenUsage>> {
+    Ok(find_matches(content, &mode.matcher(token)?))
+}
+
+fn find_matches(content: &str, matcher: &Regex) -> Vec<TokenUsage> {
+    content
+        .lines()
+        .enumerate()
+        .flat_map(|(i, line)| {
+            matcher.find_iter(line).map(move |m| TokenUsage {
+                line: i + 1,
+                span: m.range(),
+            })
+        })
+        .collect()
+}
+
+/// Searches the files under `root` for `token` with `settings.match_mode`,
+/// honouring the same limits and exclusions as token-analyzer and
+/// `filter_report`. Files only count usages: exposures are not analyzed.
+pub fn scan_usages(
+    token: &str,
+    root: &Path,
+    settings: &AnalyzerSettings,
+    locker_dir: Option<&Path>,
+) -> Result<AnalysisReport> {
+    let start = Instant::now();
+    let matcher = settings.match_mode.matcher(token)?;
+    let timeout = (settings.timeout_ms > 0).then(|| Duration::from_millis(settings.timeout_ms));
+
+    // Skip ignored directories without walking into them
+    let ignore_dirs = ignore_dirs_matcher(settings, &canonical(root));
+    let mut walker = ignore::WalkBuilder::new(root);
+    walker
+        .hidden(!settings.include_hidden)
+        .git_ignore(false)
+        .git_global(false)
+        .git_exclude(false)
+        .ignore(false)
+        .parents(false)
+        .filter_entry(move |entry| {
+            let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+            !ignore_dirs
+                .matched(canonical(entry.path()), is_dir)
+                .is_ignore()
+        });
+
+    let mut files = Vec::new();
+    let mut files_scanned = 0;
+    let mut truncated = false;
+    for entry in walker.build().flatten() {
+        if timeout.is_some_and(|t| start.elapsed() >= t)
+            || (settings.max_files > 0 && files_scanned >= settings.max_files)
+        {
+            truncated = true;
+            break;
+        }
+        let path = entry.path();
+        let is_file = entry.file_type().is_some_and(|t| t.is_file());
+        let wanted_extension = settings.extensions.is_empty()
+            || path
+                .extension()
+                .is_some_and(|ext| settings.extensions.iter().any(|e| ext == e.as_str()));
+        if !is_file
+            || !wanted_extension
+            || entry.metadata().map_or(true, |m| m.len() > MAX_FILE_SIZE)
+        {
+            continue;
+        }
+        files_scanned += 1;
+
+        // Binary files are not valid UTF-8 and are skipped
+        let Ok(content) = std::fs::read_to_string(path) else {
+            continue;
+        };
+        let usages = find_matches(&content, &matcher);
+        if usages.is_empty() {
+            continue;
+        }
+        let mut occurrence_lines: Vec<usize> = usages.iter().map(|u| u.line).collect();
+        occurrence_lines.dedup();
+        files.push(FileAnalysis {
+            path: path.to_path_buf(),
+            call_count: usages.len(),
+            has_exposure: false,
+            risk_level: RiskLevel::Low,
+            risk_score: usages.len(),
+            exposures: Vec::new(),
+            exposure_lines: Vec::new(),
+            occurrence_lines,
+        });
+    }
+
+    let mut report = AnalysisReport {
+        token_name: token.to_string(),
+        search_dir: root.to_path_buf(),
+        total_calls: 0,
+        exposure_count: 0,
+        total_risk_score: 0,
+        critical_files: 0,
+        files,
+        duration: Duration::ZERO,
+        files_scanned,
+        truncated,
+        errors: Vec::new(),
+    };
+    filter_report(&mut report, settings, locker_dir);
+    report.duration = start.elapsed();
+    Ok(report)
+}
+
+/// Matches the directories of `settings.ignore_dirs` anywhere under `root`
+fn ignore_dirs_matcher(settings: &AnalyzerSettings, root: &Path) -> Gitignore {
+    let mut builder = GitignoreBuilder::new(root);
+    for dir in &settings.ignore_dirs {
+        // A trailing slash restricts the pattern to directories
+        builder.add_line(None, &format!("{}/", dir)).ok();
+    }
+    builder.build().unwrap_or_else(|_| Gitignore::empty())
+}
+
+/// Decides which files under a scanned directory count as usages
+pub struct UsageFilter {
+    root: PathBuf,
+    ignore_dirs: Gitignore,
+    respect_gitignore: bool,
+    locker_dir: Option<PathBuf>,
+    // Parsed `.gitignore` per directory (None when the directory has none)
+    gitignores: HashMap<PathBuf, Option<Gitignore>>,
+}
+
+impl UsageFilter {
+    pub fn new(settings: &AnalyzerSettings, root: &Path, locker_dir: Option<&Path>) -> Self {
+        let root = canonical(root);
+
+        Self {
+            ignore_dirs: ignore_dirs_matcher(settings, &root),
+            root,
+            respect_gitignore: settings.respect_gitignore,
+            locker_dir: locker_dir.map(canonical),
+            gitignores: HashMap::new(),
+        }
+    }
+
+    /// Whether `path` should be left out of the usages
+    pub fn is_excluded(&mut self, path: &Path) -> bool {
+        let path = canonical(path);
+        if let Some(ref locker_dir) = self.locker_dir
+            && path.starts_with(locker_dir)
+        {
+            return true;
+        }
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return false;
+        };
+        if self
+            .ignore_dirs
+            .matched_path_or_any_parents(&path, false)
+            .is_ignore()
+        {
+            return true;
+        }
+        if !self.respect_gitignore {
+            return false;
+        }
+
+        // Deeper .gitignore files take precedence, so the last match wins
+        let mut verdict = Match::None;
+        let mut dir = self.root.clone();
+        let mut components = relative.components().peekable();
+        while let Some(component) = components.next() {
+            if let Some(gitignore) = self.gitignore_in(&dir) {
+                let matched = gitignore.matched_path_or_any_parents(&path, false);
+                if !matched.is_none() {
+                    verdict = matched.map(|_| ());
+                }
+            }
+            if components.peek().is_none() {
+                break;
+            }
+            dir.push(component);
+        }
+        verdict.is_ignore()
+    }
+
+    fn gitignore_in(&mut self, dir: &Path) -> Option<&Gitignore> {
+        self.gitignores
+            .entry(dir.to_path_buf())
+            .or_insert_with(|| {
+                let path = dir.join(".gitignore");
+                if !path.is_file() {
+                    return None;
+                }
+                let (gitignore, _) = Gitignore::new(&path);
+                Some(gitignore)
+            })
+            .as_ref()
+    }
+}
+
+/// Removes excluded files from `report` and recomputes its totals
+pub fn filter_report(
+    report: &mut AnalysisReport,
+    settings: &AnalyzerSettings,
+    locker_dir: Option<&Path>,
+) {
+    let mut filter = UsageFilter::new(settings, &report.search_dir, locker_dir);
+    report.files.retain(|file| !filter.is_excluded(&file.path));
+
+    report.total_calls = report.files.iter().map(|f| f.call_count).sum();
+    report.exposure_count = report.files.iter().filter(|f| f.has_exposure).count();
+    report.total_risk_score = report.files.iter().map(|f| f.risk_score).sum();
+    report.critical_files = report
+        .files
+        .iter()
+        .filter(|f| f.risk_level == RiskLevel::Critical)
+        .count();
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn project(files: &[(&str, &str)]) -> TempDir {
+        let dir = TempDir::new().unwrap();
+        for (path, content) in files {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        dir
+    }
+
+    fn excluded(filter: &mut UsageFilter, dir: &TempDir, path: &str) -> bool {
+        filter.is_excluded(&dir.path().join(path))
+    }
+
+    #[test]
+    fn test_gitignore_patterns_and_negation() {
+        let dir = project(&[
+            (".gitignore", "generated/\n*.log\n!keep.log\n"),
+            ("src/main.py", ""),
+            ("generated/client.py", ""),
+            ("debug.log", ""),
+            ("keep.log", ""),
+        ]);
+        let mut filter = UsageFilter::new(&AnalyzerSettings::default(), dir.path(), None);
+
+        assert!(!excluded(&mut filter, &dir, "src/main.py"));
+        assert!(excluded(&mut filter, &dir, "generated/client.py"));
+        assert!(excluded(&mut filter, &dir, "debug.log"));
+        assert!(!excluded(&mut filter, &dir, "keep.log"));
+    }
+
+    #[test]
+    fn test_nested_gitignore_overrides_parent() {
+        let dir = project(&[
+            (".gitignore", "*.py\n"),
+            ("app/.gitignore", "!settings.py\n"),
+            ("app/settings.py", ""),
+            ("app/other.py", ""),
+        ]);
+        let mut filter = UsageFilter::new(&AnalyzerSettings::default(), dir.path(), None);
+
+        assert!(!excluded(&mut filter, &dir, "app/settings.py"));
+        assert!(excluded(&mut filter, &dir, "app/other.py"));
+    }
+
+    #[test]
+    fn test_respect_gitignore_disabled() {
+        let dir = project(&[(".gitignore", "*.log\n"), ("debug.log", "")]);
+        let settings = AnalyzerSettings {
+            respect_gitignore: false,
+            ..Default::default()
+        };
+        let mut filter = UsageFilter::new(&settings, dir.path(), None);
+
+        assert!(!excluded(&mut filter, &dir, "debug.log"));
+    }
+
+    #[test]
+    fn test_ignore_dirs_globs_and_locker_dir() {
+        let dir = project(&[
+            ("pkg.egg-info/PKG-INFO.py", ""),
+            ("node_modules/lib/index.js", ""),
+            (".locker/secrets.json", ""),
+            ("app.py", ""),
+        ]);
+        let locker_dir = dir.path().join(".locker");
+        let mut filter =
+            UsageFilter::new(&AnalyzerSettings::default(), dir.path(), Some(&locker_dir));
+
+        assert!(excluded(&mut filter, &dir, "pkg.egg-info/PKG-INFO.py"));
+        assert!(excluded(&mut filter, &dir, "node_modules/lib/index.js"));
+        assert!(excluded(&mut filter, &dir, ".locker/secrets.json"));
+        assert!(!excluded(&mut filter, &dir, "app.py"));
+    }
+
+    #[test]
+    fn test_filter_report_drops_ignored_usages() {
+        let dir = project(&[
+            (".gitignore", "generated/\n"),
+            ("app.py", "import os\nkey = os.environ['API_KEY']\n"),
+            ("generated/client.py", "API_KEY = os.environ['API_KEY']\n"),
+        ]);
+        let settings = AnalyzerSettings::default();
+        let analyzer = token_analyzer::TokenSecurityAnalyzer::new(settings.to_analyzer_config());
+        let mut report = analyzer.analyze("API_KEY", dir.path()).unwrap();
+        assert_eq!(report.files.len(), 2);
+
+        filter_report(&mut report, &settings, None);
+
+        assert_eq!(report.files.len(), 1);
+        assert!(report.files[0].path.ends_with("app.py"));
+        assert_eq!(report.total_calls, report.files[0].call_count);
+    }
+
+    #[test]
+    fn test_word_mode_does_not_match_inside_words() {
+        let content = "RAPIDLY = 1\nkey = API\n";
+
+        let word = find_token_usages(content, "API", &MatchMode::Word).unwrap();
+        let substring = find_token_usages(content, "API", &MatchMode::Substring).unwrap();
+
+        assert_eq!(
+            word,
+            vec![TokenUsage {
+                line: 2,
+                span: 6..9
+            }]
+        );
+        assert_eq!(
+            substring,
+            vec![
+                TokenUsage {
+                    line: 1,
+                    span: 1..4
+                },
+                TokenUsage {
+                    line: 2,
+                    span: 6..9
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_regex_mode_substitutes_escaped_name() {
+        let mode = MatchMode::Regex(r"(?i)\b{name}\b".to_string());
+        let usages = find_token_usages("db.url = x\ndb_url = DB.URL", "DB.URL", &mode).unwrap();
+
+        assert_eq!(usages.len(), 2);
+        assert_eq!(
+            usages[1],
+            TokenUsage {
+                line: 2,
+                span: 9..15
+            }
+        );
+        assert!(find_token_usages("", "A", &MatchMode::Regex("(".to_string())).is_err());
+    }
+
+    #[test]
+    fn test_scan_usages_in_substring_mode() {
+        let dir = project(&[
+            (".gitignore", "generated/\n"),
+            ("app.py", "API_KEY = os.environ['API_KEY']\n"),
+            ("generated/client.py", "API_KEY\n"),
+            ("node_modules/lib.js", "API_KEY\n"),
+            ("notes.txt", "nothing here\n"),
+        ]);
+        let settings = AnalyzerSettings {
+            match_mode: MatchMode::Substring,
+            ..AnalyzerSettings::default()
+        };
+
+        let report = scan_usages("API", dir.path(), &settings, None).unwrap();
+
+        assert_eq!(report.files.len(), 1);
+        assert!(report.files[0].path.ends_with("app.py"));
+        assert_eq!(report.total_calls, 2);
+        assert_eq!(report.files[0].occurrence_lines, vec![1]);
+    }
+}
@@ -0,0 +1,223 @@
+//! High-level access to a locker for Rust programs.
+//!
+//! `Vault` pairs an unlocked `Locker` with its `SecretsStore`, so callers can
+//! add, read and remove secrets without handling the key or the
+//! load-modify-save cycle themselves. Every write goes through the store's
+//! locked cycle, so a vault can be used next to the TUI or the CLI. Nothing
+//! here prints; errors are returned.
+
+use anyhow::{Context, Result};
+use std::path::Path;
+use zeroize::Zeroizing;
+
+use crate::core::init::Locker;
+use crate::core::store::{PlainSecret, SecretsStore, check_env_name};
+
+/// An unlocked locker and its secrets
+pub struct Vault {
+    locker: Locker,
+    store: SecretsStore,
+}
+
+/// What `Vault::set` stores besides the value
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SetOptions {
+    /// Expiration as Unix timestamp (None = permanent)
+    pub expires_at: Option<i64>,
+    pub tags: Vec<String>,
+    pub description: Option<String>,
+}
+
+/// A secret's metadata, without its value
+#[derive(Debug, Clone, PartialEq)]
+pub struct SecretMeta {
+    pub name: String,
+    /// Expiration as Unix timestamp (None = permanent)
+    pub expires_at: Option<i64>,
+    pub expired: bool,
+    pub tags: Vec<String>,
+    pub description: Option<String>,
+    /// Names of the per-environment variants, sorted
+    pub variants: Vec<String>,
+    /// When the secret was first added, as Unix timestamp (0 = unknown)
+    pub created_at: i64,
+    /// When the secret was last written, as Unix timestamp (0 = unknown)
+    pub updated_at: i64,
+}
+
+impl Vault {
+    /// Unlocks the active profile's locker, creating it on first use like the CLI
+    pub fn unlock(passphrase: &str) -> Result<Self> {
+        Self::from_locker(Locker::init_or_load_with_passphrase(passphrase)?)
+    }
+
+    /// Unlocks the locker in `locker_dir`, creating it on first use
+    pub fn unlock_at(locker_dir: &Path, passphrase: &str) -> Result<Self> {
+        Self::from_locker(Locker::init_or_load_at(locker_dir, passphrase)?)
+    }
+
+    fn from_locker(locker: Locker) -> Result<Self> {
+        let store = SecretsStore::load(locker.base_dir(), locker_key(&locker)?)?;
+        Ok(Self { locker, store })
+    }
+
+    /// Adds a secret, or replaces the value and metadata of an existing one
+    pub fn set(&mut self, name: &str, value: &str, options: &SetOptions) -> Result<()> {
+        check_env_name(name)?;
+        let key = locker_key(&self.locker)?;
+        let plain = PlainSecret {
+            name: name.to_string(),
+            value: value.to_string(),
+            expires_at: options.expires_at,
+            tags: options.tags.clone(),
+            description: options.description.clone(),
+        };
+        self.store.add_many(&[plain], self.locker.base_dir(), key)?;
+        Ok(())
+    }
+
+    /// Accepts values above `store.max_secret_bytes` from now on
+    pub fn allow_large_values(&mut self) {
+        self.store.allow_large_values();
+    }
+
+    /// Rereads the secrets from disk, picking up changes made by other processes.
+    /// Reads otherwise see the secrets as of unlocking and this vault's own writes.
+    pub fn reload(&mut self) -> Result<()> {
+        let key = locker_key(&self.locker)?;
+        let mut store = SecretsStore::load(self.locker.base_dir(), key)?;
+        std::mem::swap(&mut self.store.secrets, &mut store.secrets);
+        Ok(())
+    }
+
+    /// The default value of a secret. Fails if it is missing or expired.
+    pub fn get(&self, name: &str) -> Result<Zeroizing<String>> {
+        let key = locker_key(&self.locker)?;
+        let secret = self
+            .store
+            .get_secret(name)
+            .ok_or_else(|| anyhow::anyhow!("Token '{}' not found", name))?;
+        if secret.is_expired() {
+            anyhow::bail!("Token '{}' has expired", name);
+        }
+        Ok(Zeroizing::new(secret.decrypt_for(None, key)?))
+    }
+
+    /// Removes a secret. Returns whether it existed.
+    pub fn remove(&mut self, name: &str) -> Result<bool> {
+        if !self.store.contains(name) {
+            return Ok(false);
+        }
+        let key = locker_key(&self.locker)?;
+        self.store
+            .delete_secret(name, self.locker.base_dir(), key)?;
+        Ok(true)
+    }
+
+    /// Metadata of every secret, sorted by name
+    pub fn list(&self) -> Vec<SecretMeta> {
+        self.store
+            .list_secrets()
+            .into_iter()
+            .map(|secret| SecretMeta {
+                name: secret.name.clone(),
+                expires_at: secret.expires_at,
+                expired: secret.is_expired(),
+                tags: secret.tags.clone(),
+                description: secret.description.clone(),
+                variants: secret
+                    .variant_names()
+                    .into_iter()
+                    .map(String::from)
+                    .collect(),
+                created_at: secret.created_at,
+                updated_at: secret.updated_at,
+            })
+            .collect()
+    }
+
+    /// The locker directory this vault reads and writes
+    pub fn locker_dir(&self) -> &Path {
+        self.locker.base_dir()
+    }
+}
+
+fn locker_key(locker: &Locker) -> Result<&[u8]> {
+    locker.get_key().context("Failed to get encryption key")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_vault_set_get_remove_lifecycle() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let mut vault = Vault::unlock_at(temp_dir.path(), "vault-pass").unwrap();
+        assert!(vault.list().is_empty());
+
+        let options = SetOptions {
+            tags: vec!["ci".to_string()],
+            description: Some("Deploy key".to_string()),
+            ..Default::default()
+        };
+        vault.set("API_KEY", "sk-123", &options).unwrap();
+        vault
+            .set("DB_URL", "postgres://db", &SetOptions::default())
+            .unwrap();
+        assert_eq!(*vault.get("API_KEY").unwrap(), "sk-123");
+
+        let list = vault.list();
+        let names: Vec<&str> = list.iter().map(|meta| meta.name.as_str()).collect();
+        assert_eq!(names, vec!["API_KEY", "DB_URL"]);
+        assert_eq!(list[0].tags, vec!["ci"]);
+        assert_eq!(list[0].description.as_deref(), Some("Deploy key"));
+        assert!(!list[0].expired && list[0].created_at > 0);
+
+        // Replacing a value keeps its creation time
+        vault
+            .set("API_KEY", "sk-456", &SetOptions::default())
+            .unwrap();
+        assert_eq!(*vault.get("API_KEY").unwrap(), "sk-456");
+        assert_eq!(vault.list()[0].created_at, list[0].created_at);
+
+        // Writes reach the disk: a second vault sees them, the first one after reload
+        let mut other = Vault::unlock_at(temp_dir.path(), "vault-pass").unwrap();
+        assert_eq!(*other.get("DB_URL").unwrap(), "postgres://db");
+        other
+            .set("FROM_OTHER", "v", &SetOptions::default())
+            .unwrap();
+        assert!(vault.get("FROM_OTHER").is_err());
+        vault.reload().unwrap();
+        assert_eq!(*vault.get("FROM_OTHER").unwrap(), "v");
+
+        assert!(vault.remove("API_KEY").unwrap());
+        assert!(!vault.remove("API_KEY").unwrap());
+        assert!(vault.get("API_KEY").is_err());
+        let reopened = Vault::unlock_at(temp_dir.path(), "vault-pass").unwrap();
+        assert_eq!(reopened.list().len(), 2);
+    }
+
+    #[test]
+    fn test_vault_rejects_wrong_passphrase_bad_names_and_expired_reads() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let mut vault = Vault::unlock_at(temp_dir.path(), "vault-pass").unwrap();
+        assert!(Vault::unlock_at(temp_dir.path(), "wrong").is_err());
+
+        assert!(
+            vault
+                .set("not a name", "v", &SetOptions::default())
+                .is_err()
+        );
+
+        let expired = SetOptions {
+            expires_at: Some(1),
+            ..Default::default()
+        };
+        vault.set("OLD", "v", &expired).unwrap();
+        assert!(vault.list()[0].expired);
+        let err = vault.get("OLD").unwrap_err().to_string();
+        assert!(err.contains("expired"));
+    }
+}
@@ -0,0 +1,465 @@
+//! Minimal YAML reader for `import --format yaml`.
+//!
+//! Covers what secret files are written with: block mappings and sequences,
+//! plain, quoted and literal (`|`) scalars, flow sequences of scalars, and
+//! comments. Anchors, aliases, tags, flow mappings, folded scalars and multiple
+//! documents are rejected rather than misread. Scalars are kept as text, so
+//! `PORT: 5432` reads as the string `"5432"`.
+
+use anyhow::Result;
+
+/// A parsed YAML value
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    /// `~`, `null` or nothing at all
+    Null,
+    Scalar(String),
+    List(Vec<Node>),
+    /// Entries in file order
+    Map(Vec<(String, Node)>),
+}
+
+/// Parses a single YAML document
+pub fn parse(content: &str) -> Result<Node> {
+    let mut parser = Parser {
+        lines: content.lines().map(String::from).collect(),
+        pos: 0,
+    };
+    if let Some((0, text)) = parser.peek()
+        && text.starts_with("---")
+        && text[3..].trim().is_empty()
+    {
+        parser.pos += 1;
+    }
+
+    let node = match parser.peek() {
+        None => Node::Null,
+        Some((indent, _)) => parser.block(indent)?,
+    };
+    if parser.peek().is_some() {
+        anyhow::bail!("line {}: unexpected content", parser.pos + 1);
+    }
+    Ok(node)
+}
+
+struct Parser {
+    lines: Vec<String>,
+    pos: usize,
+}
+
+impl Parser {
+    /// Moves past blank and comment lines, returning the next line's
+    /// indentation and trimmed text
+    fn peek(&mut self) -> Option<(usize, String)> {
+        while let Some(line) = self.lines.get(self.pos) {
+            let text = line.trim();
+            if !text.is_empty() && !text.starts_with('#') {
+                return Some((indentation(line), text.to_string()));
+            }
+            self.pos += 1;
+        }
+        None
+    }
+
+    /// Parses the mapping or sequence starting at the current line
+    fn block(&mut self, indent: usize) -> Result<Node> {
+        self.check_indentation()?;
+        match self.peek() {
+            Some((_, text)) if is_list_item(&text) => self.list(indent),
+            _ => self.map(indent),
+        }
+    }
+
+    fn map(&mut self, indent: usize) -> Result<Node> {
+        let mut entries: Vec<(String, Node)> = Vec::new();
+        while let Some((line_indent, text)) = self.peek() {
+            if line_indent < indent {
+                break;
+            }
+            let line_no = self.pos + 1;
+            if line_indent > indent {
+                anyhow::bail!("line {}: unexpected indentation", line_no);
+            }
+            if text == "---" || text == "..." {
+                anyhow::bail!("line {}: multiple documents are not supported", line_no);
+            }
+            let Some((key, rest)) = split_key(&text, line_no)? else {
+                anyhow::bail!("line {}: expected `key: value`", line_no);
+            };
+            if entries.iter().any(|(k, _)| *k == key) {
+                anyhow::bail!("line {}: duplicate key {}", line_no, key);
+            }
+            self.pos += 1;
+            let value = self.value(&rest, indent, line_no, true)?;
+            entries.push((key, value));
+        }
+        Ok(Node::Map(entries))
+    }
+
+    fn list(&mut self, indent: usize) -> Result<Node> {
+        let mut items = Vec::new();
+        while let Some((line_indent, text)) = self.peek() {
+            if line_indent < indent || !is_list_item(&text) {
+                break;
+            }
+            let line_no = self.pos + 1;
+            if line_indent > indent {
+                anyhow::bail!("line {}: unexpected indentation", line_no);
+            }
+            let rest = &text[1..];
+            let item_indent = indent + 1 + indentation(rest);
+            let rest = rest.trim();
+            if !rest.is_empty() && (is_list_item(rest) || split_key(rest, line_no)?.is_some()) {
+                // `- key: value` opens a mapping whose keys line up after the dash
+                let line = &mut self.lines[self.pos];
+                *line = line.replacen('-', " ", 1);
+                items.push(self.block(item_indent)?);
+            } else {
+                self.pos += 1;
+                items.push(self.value(rest, indent, line_no, false)?);
+            }
+        }
+        Ok(Node::List(items))
+    }
+
+    /// Parses what follows `key:` or `-`; a nested block starts on the next
+    /// line when nothing follows on this one
+    fn value(
+        &mut self,
+        rest: &str,
+        parent_indent: usize,
+        line_no: usize,
+        in_map: bool,
+    ) -> Result<Node> {
+        let rest = rest.trim();
+        if rest.starts_with('|') || rest.starts_with('>') {
+            return self.block_scalar(rest, parent_indent, line_no);
+        }
+        if !rest.is_empty() && !rest.starts_with('#') {
+            return inline_value(rest, line_no);
+        }
+        match self.peek() {
+            Some((indent, _)) if indent > parent_indent => self.block(indent),
+            // A mapping value may be a sequence at the key's own indentation
+            Some((indent, text)) if in_map && indent == parent_indent && is_list_item(&text) => {
+                self.list(indent)
+            }
+            _ => Ok(Node::Null),
+        }
+    }
+
+    /// Reads a `|` literal block; `-` and `+` chomping are supported
+    fn block_scalar(&mut self, header: &str, parent_indent: usize, line_no: usize) -> Result<Node> {
+        let header = strip_comment(header);
+        let chomp = match header {
+            "|" => None,
+            "|-" => Some(false),
+            "|+" => Some(true),
+            _ if header.starts_with('>') => anyhow::bail!(
+                "line {}: folded block scalars (>) are not supported, use |",
+                line_no
+            ),
+            _ => anyhow::bail!(
+                "line {}: unsupported block scalar header {}",
+                line_no,
+                header
+            ),
+        };
+
+        let mut body: Vec<&str> = Vec::new();
+        let mut content_indent = None;
+        while let Some(line) = self.lines.get(self.pos) {
+            if line.trim().is_empty() {
+                body.push("");
+                self.pos += 1;
+                continue;
+            }
+            let indent = indentation(line);
+            if indent <= parent_indent {
+                break;
+            }
+            let content_indent = *content_indent.get_or_insert(indent);
+            if indent < content_indent {
+                anyhow::bail!(
+                    "line {}: block scalar is not indented consistently",
+                    self.pos + 1
+                );
+            }
+            body.push(&line[content_indent..]);
+            self.pos += 1;
+        }
+
+        let trailing_blank = body.iter().rev().take_while(|l| l.is_empty()).count();
+        let mut text = body[..body.len() - trailing_blank].join("\n");
+        match chomp {
+            Some(false) => {}
+            None if !text.is_empty() => text.push('\n'),
+            None => {}
+            Some(true) => text.push_str(&"\n".repeat(trailing_blank + 1)),
+        }
+        Ok(Node::Scalar(text))
+    }
+
+    fn check_indentation(&self) -> Result<()> {
+        if let Some(line) = self.lines.get(self.pos)
+            && line[..indentation(line)].contains('\t')
+        {
+            anyhow::bail!(
+                "line {}: tabs are not allowed for indentation",
+                self.pos + 1
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Leading whitespace width; tabs count so that `check_indentation` can reject them
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start_matches([' ', '\t']).len()
+}
+
+fn is_list_item(text: &str) -> bool {
+    text == "-" || text.starts_with("- ")
+}
+
+/// Splits `key: rest`. Returns None when the text is not a mapping entry,
+/// such as `http://host` or a quoted scalar.
+fn split_key(text: &str, line_no: usize) -> Result<Option<(String, String)>> {
+    if text.starts_with('"') || text.starts_with('\'') {
+        let (key, len) = quoted_scalar(text, line_no)?;
+        return Ok(text[len..]
+            .trim_start()
+            .strip_prefix(':')
+            .filter(|rest| rest.is_empty() || rest.starts_with([' ', '\t']))
+            .map(|rest| (key, rest.to_string())));
+    }
+    if text.starts_with(['[', '{', '#']) {
+        return Ok(None);
+    }
+
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let next = chars.peek().map(|(_, n)| *n);
+        if c == '#' && i > 0 && text[..i].ends_with([' ', '\t']) {
+            return Ok(None);
+        }
+        if c == ':' && next.is_none_or(|n| n == ' ' || n == '\t') {
+            let key = text[..i].trim_end();
+            if key.is_empty() {
+                return Ok(None);
+            }
+            return Ok(Some((key.to_string(), text[i + 1..].to_string())));
+        }
+    }
+    Ok(None)
+}
+
+/// Parses a value written on the same line as its key or dash
+fn inline_value(text: &str, line_no: usize) -> Result<Node> {
+    match text.chars().next() {
+        Some('"' | '\'') => {
+            let (value, len) = quoted_scalar(text, line_no)?;
+            expect_end(&text[len..], line_no)?;
+            Ok(Node::Scalar(value))
+        }
+        Some('[') => flow_list(text, line_no),
+        Some('{') => anyhow::bail!(
+            "line {}: flow mappings ({{...}}) are not supported",
+            line_no
+        ),
+        Some('&' | '*' | '!') => anyhow::bail!(
+            "line {}: anchors, aliases and tags are not supported",
+            line_no
+        ),
+        _ => Ok(plain_scalar(strip_comment(text))),
+    }
+}
+
+fn plain_scalar(text: &str) -> Node {
+    match text {
+        "" | "~" | "null" | "Null" | "NULL" => Node::Null,
+        _ => Node::Scalar(text.to_string()),
+    }
+}
+
+/// Parses `[a, "b", 'c']`, whose items must be scalars
+fn flow_list(text: &str, line_no: usize) -> Result<Node> {
+    let mut items = Vec::new();
+    let mut rest = text[1..].trim_start();
+    loop {
+        if let Some(after) = rest.strip_prefix(']') {
+            expect_end(after, line_no)?;
+            return Ok(Node::List(items));
+        }
+        let (item, len) = match rest.chars().next() {
+            Some('"' | '\'') => quoted_scalar(rest, line_no)?,
+            Some('[' | '{') => {
+                anyhow::bail!(
+                    "line {}: nested flow collections are not supported",
+                    line_no
+                )
+            }
+            Some(_) => {
+                let len = rest.find([',', ']']).unwrap_or(rest.len());
+                (rest[..len].trim_end().to_string(), len)
+            }
+            None => anyhow::bail!("line {}: unterminated [ list", line_no),
+        };
+        items.push(Node::Scalar(item));
+        rest = rest[len..].trim_start();
+        if let Some(after) = rest.strip_prefix(',') {
+            rest = after.trim_start();
+        } else if !rest.starts_with(']') {
+            anyhow::bail!("line {}: unterminated [ list", line_no);
+        }
+    }
+}
+
+/// Parses a quoted scalar at the start of `text`, returning its value and
+/// length. Double-quoted escapes follow JSON, which is what `format::to_yaml`
+/// writes.
+fn quoted_scalar(text: &str, line_no: usize) -> Result<(String, usize)> {
+    if text.starts_with('\'') {
+        let mut value = String::new();
+        let mut chars = text.char_indices().skip(1).peekable();
+        while let Some((i, c)) = chars.next() {
+            if c != '\'' {
+                value.push(c);
+            } else if chars.peek().is_some_and(|(_, n)| *n == '\'') {
+                value.push('\'');
+                chars.next();
+            } else {
+                return Ok((value, i + 1));
+            }
+        }
+        anyhow::bail!("line {}: unterminated quoted value", line_no);
+    }
+
+    let mut escaped = false;
+    for (i, c) in text.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => {
+                let value = serde_json::from_str(&text[..=i]).map_err(|_| {
+                    anyhow::anyhow!("line {}: unsupported escape in quoted value", line_no)
+                })?;
+                return Ok((value, i + 1));
+            }
+            _ => {}
+        }
+    }
+    anyhow::bail!("line {}: unterminated quoted value", line_no)
+}
+
+/// Drops a trailing ` # comment` from a plain value
+fn strip_comment(text: &str) -> &str {
+    let end = text
+        .char_indices()
+        .find(|&(i, c)| c == '#' && i > 0 && text[..i].ends_with([' ', '\t']))
+        .map_or(text.len(), |(i, _)| i);
+    text[..end].trim_end()
+}
+
+/// Only a comment may follow a quoted value or flow list
+fn expect_end(rest: &str, line_no: usize) -> Result<()> {
+    let rest = rest.trim_start();
+    if rest.is_empty() || rest.starts_with('#') {
+        Ok(())
+    } else {
+        anyhow::bail!("line {}: unexpected text after value: {}", line_no, rest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scalar(value: &str) -> Node {
+        Node::Scalar(value.to_string())
+    }
+
+    #[test]
+    fn test_parse_flat_mapping() {
+        let content = "---\n# secrets\nAPI_KEY: sk-123  # live\nQUOTED: \"a \\\"b\\\" #c\\n\"\nSINGLE: 'it''s'\nPORT: 5432\nURL: http://host:80/x\n";
+
+        assert_eq!(
+            parse(content).unwrap(),
+            Node::Map(vec![
+                ("API_KEY".to_string(), scalar("sk-123")),
+                ("QUOTED".to_string(), scalar("a \"b\" #c\n")),
+                ("SINGLE".to_string(), scalar("it's")),
+                ("PORT".to_string(), scalar("5432")),
+                ("URL".to_string(), scalar("http://host:80/x")),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_list_of_mappings() {
+        let content =
+            "- name: A\n  tags: [ci, \"b, c\"]\n-   name: B\n    tags:\n    - x\n    value: ~\n";
+
+        assert_eq!(
+            parse(content).unwrap(),
+            Node::List(vec![
+                Node::Map(vec![
+                    ("name".to_string(), scalar("A")),
+                    (
+                        "tags".to_string(),
+                        Node::List(vec![scalar("ci"), scalar("b, c")])
+                    ),
+                ]),
+                Node::Map(vec![
+                    ("name".to_string(), scalar("B")),
+                    ("tags".to_string(), Node::List(vec![scalar("x")])),
+                    ("value".to_string(), Node::Null),
+                ]),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_literal_block_scalars() {
+        let content = "CERT: |\n  line 1\n\n    line 2\n\nSTRIP: |-\n  x\nNEXT: y\n";
+
+        assert_eq!(
+            parse(content).unwrap(),
+            Node::Map(vec![
+                ("CERT".to_string(), scalar("line 1\n\n  line 2\n")),
+                ("STRIP".to_string(), scalar("x")),
+                ("NEXT".to_string(), scalar("y")),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_nested_mapping() {
+        let node = parse("db:\n  user: admin\n").unwrap();
+
+        assert_eq!(
+            node,
+            Node::Map(vec![(
+                "db".to_string(),
+                Node::Map(vec![("user".to_string(), scalar("admin"))])
+            )])
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_unsupported_syntax() {
+        for content in [
+            "A: &x 1\n",
+            "A: {b: 1}\n",
+            "A: >\n  folded\n",
+            "A: 1\n  B: 2\n",
+            "A: 1\nA: 2\n",
+            "A: \"unterminated\n",
+            "A: 1\n---\nB: 2\n",
+            "A:\n\tB: 1\n",
+            "just text\n",
+        ] {
+            assert!(parse(content).is_err(), "{:?} should be rejected", content);
+        }
+    }
+}
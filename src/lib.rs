@@ -3,5 +3,41 @@
 //! This crate provides both a TUI application and a library for managing
 //! secrets securely. It includes a token security analyzer for detecting
 //! potential security risks in codebases.
+//!
+//! The types most programs need are re-exported at the crate root; the
+//! modules under [`core`] hold the rest. [`Vault`] is the simplest way in.
+//!
+//! ```
+//! use lazy_locker::{Locker, SecretsStore};
+//!
+//! let dir = tempfile::tempdir()?;
+//! let locker = Locker::init_or_load_at(dir.path(), "correct horse battery staple")?;
+//! let key = locker.get_key().expect("an unlocked locker has a key");
+//!
+//! let mut store = SecretsStore::load(locker.base_dir(), key)?;
+//! store.add_secret("API_KEY".into(), "sk-123".into(), None, locker.base_dir(), key)?;
+//!
+//! for secret in store.list_secrets() {
+//!     println!("{} ({})", secret.name, secret.expiration_display());
+//! }
+//! assert_eq!(store.get_secret("API_KEY").unwrap().decrypt_for(None, key)?, "sk-123");
+//! # Ok::<(), anyhow::Error>(())
+//! ```
 
 pub mod core;
+
+/// Talks to a running agent, which serves decrypted secrets without the passphrase
+pub use crate::core::agent::AgentClient;
+/// Output format of the `token get`, `token list` and `export` commands
+pub use crate::core::cli::OutputFormat;
+/// Settings read from a locker's `config.toml`
+pub use crate::core::config::Config;
+/// Encrypts with AES-256-GCM into a versioned blob, and decrypts blobs of either cipher
+pub use crate::core::crypto::{decrypt, encrypt};
+/// A locker directory unlocked with its passphrase, holding the derived key
+pub use crate::core::init::Locker;
+/// Encrypted secrets of a locker, one of them, and the error of a store
+/// written under another key
+pub use crate::core::store::{Secret, SecretsStore, StoreKeyMismatch};
+/// An unlocked locker that adds, reads and removes secrets, saving as it goes
+pub use crate::core::vault::{SecretMeta, SetOptions, Vault};
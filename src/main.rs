@@ -5,29 +5,87 @@ mod tui;
 mod ui;
 
 use anyhow::Result;
-use app::{App, Field, Modal, Mode};
+use app::{App, Field, Modal, Mode, UiState};
 use core::agent::{self, AgentClient};
-use core::cli;
+use core::cli::{self, CliError, Expiration};
+use core::config::{self, KeyAction};
 use core::executor;
-use core::init::Locker;
-use core::store::SecretsStore;
+use core::generator;
+use core::hooks;
+use core::init::{KdfParams, Locker};
+use core::store::{ConflictStrategy, SecretKind, SecretsStore};
+use core::transport::{AgentTransport, Transport};
 use crossterm::event::{Event, KeyCode};
 use zeroize::Zeroize;
 
-fn main() -> Result<()> {
-    let args: Vec<String> = std::env::args().collect();
+fn main() {
+    if let Err(e) = dispatch() {
+        // With --json (before any `--` handing arguments to a command), scripts
+        // get the error as JSON on stdout, like the output they asked for
+        let json = std::env::args()
+            .skip(1)
+            .take_while(|arg| arg != "--")
+            .any(|arg| arg == "--json");
+        if json {
+            println!("{}", cli::error_json(&e));
+        } else {
+            eprintln!("Error: {:?}", e);
+        }
+        std::process::exit(cli::exit_code(&e));
+    }
+}
+
+fn dispatch() -> Result<()> {
+    let mut args: Vec<String> = std::env::args().collect();
+
+    // Global `--home <DIR>` and `--profile <NAME>`, in any order before the command
+    loop {
+        match args.get(1).map(String::as_str) {
+            Some("--home") => {
+                let dir = args.get(2).ok_or_else(|| {
+                    CliError::Usage("Usage: lazy-locker --home <DIR> [COMMAND]".into())
+                })?;
+                config::set_home(std::path::Path::new(dir))
+                    .map_err(|e| CliError::Usage(e.to_string()))?;
+            }
+            Some("--profile") => {
+                let name = args.get(2).ok_or_else(|| {
+                    CliError::Usage("Usage: lazy-locker --profile <NAME> [COMMAND]".into())
+                })?;
+                config::set_profile(name).map_err(|e| CliError::Usage(e.to_string()))?;
+            }
+            _ => break,
+        }
+        args.drain(1..3);
+    }
 
     // Mode CLI
     if args.len() >= 2 {
         match args[1].as_str() {
             "run" if args.len() >= 3 => return run_with_secrets(&args[2..]),
-            "agent" => return run_agent_mode(&args[2..]),
-            "status" => return show_status(),
+            "agent" => {
+                return match args.get(2).map(String::as_str) {
+                    Some("start") => start_agent(&args[3..]),
+                    Some("restart") => stop_agent().and_then(|_| start_agent(&args[3..])),
+                    _ => run_agent_mode(&args[2..]),
+                };
+            }
+            "clipboard-clear" => return run_clipboard_clear_mode(&args[2..]),
+            "status" => return show_status(&args[2..]),
             "stop" => return stop_agent(),
             "init" => return run_init_command(&args[2..]),
+            "rotate" => return run_rotate_command(&args[2..]),
+            "passphrase" => return run_passphrase_command(&args[2..]),
+            "recovery" => return run_recovery_command(&args[2..]),
+            "check" => return run_check_command(&args[2..]),
             "token" => return run_token_command(&args[2..]),
             "import" => return run_import_command(&args[2..]),
             "export" => return run_export_command(&args[2..]),
+            "backup" => return run_backup_command(&args[2..]),
+            "restore" => return run_restore_command(&args[2..]),
+            "restore-backup" => return cli::cmd_restore_backup(args.get(2).map(String::as_str)),
+            "profile" => return run_profile_command(&args[2..]),
+            "shell-env" => return run_shell_env_command(&args[2..]),
             "help" | "--help" | "-h" => {
                 print_help();
                 return Ok(());
@@ -53,57 +111,310 @@ fn print_help() {
     println!("USAGE:");
     println!("  lazy-locker                    Opens the TUI interface");
     println!("  lazy-locker run <cmd>          Executes a command with injected secrets");
-    println!("  lazy-locker status             Shows agent status");
+    println!("  lazy-locker run --env <NAME> -- <cmd>");
+    println!("                                 Same, resolving <NAME> environment variants");
+    println!("  lazy-locker run --only <A,B> | --except <C,D> | --tag <TAG> -- <cmd>");
+    println!("                                 Same, injecting only the selected tokens");
+    println!("  lazy-locker run --shell -- '<cmd line>'");
+    println!("                                 Same, interpreting the line with the shell");
+    println!("  lazy-locker run --dry-run [--json] -- <cmd>");
+    println!(
+        "                                 Lists the variables <cmd> would receive, then exits"
+    );
+    println!("  lazy-locker status [--json]    Shows agent status (exits 1 when not running)");
+    println!("                                 The agent's session lasts LAZY_LOCKER_TTL_HOURS");
+    println!("                                 hours (default 8, 0 = never expires); with");
+    println!("                                 LAZY_LOCKER_IDLE_TTL=1 it counts from the last use");
     println!("  lazy-locker stop               Stops the agent");
+    println!("  lazy-locker agent start [--ttl <H>]");
+    println!("                                 Starts the agent without opening the TUI, for SDKs");
+    println!("  lazy-locker agent restart [--ttl <H>]");
+    println!("                                 Stops the agent, then starts it again");
+    println!("  lazy-locker profile list       Lists the profiles (* marks the active one)");
     println!("  lazy-locker --version          Shows version");
     println!();
+    println!("  lazy-locker --profile <NAME> <COMMAND>");
+    println!("                                 Uses the separate locker of profile <NAME>, with");
+    println!("                                 its own agent (or set LAZY_LOCKER_PROFILE)");
+    println!("  lazy-locker --home <DIR> <COMMAND>");
+    println!("                                 Keeps the locker in <DIR> instead of");
+    println!("                                 ~/.config/.lazy-locker (or set LAZY_LOCKER_HOME)");
+    println!();
     println!("HEADLESS COMMANDS (for CI/CD):");
+    println!("  Without --passphrase or LAZY_LOCKER_PASSPHRASE, the passphrase is prompted");
+    println!("  for on a terminal. Every command also accepts, instead of --passphrase:");
+    println!("      --passphrase-stdin         Read it from the first line of stdin");
+    println!("      --passphrase-fd <N>        Read it from file descriptor N (Unix)");
+    println!("  Neither shows in the process list or reaches child environments.");
+    println!("  token, import and restore also accept --no-hooks, skipping the on_change");
+    println!("  hook set in config.toml.");
+    println!();
     println!("  lazy-locker init [OPTIONS]");
-    println!("      --passphrase <PASS>        Passphrase (or set LAZY_LOCKER_PASSPHRASE)");
-    println!("      --force                    Overwrite existing locker");
+    println!("      --passphrase <PASS>        Passphrase (or set LAZY_LOCKER_PASSPHRASE;");
+    println!("                                 prompted twice on a terminal otherwise)");
+    println!("      --force                    Replace an existing locker, moving its files to");
+    println!(
+        "                                 backup/<TIMESTAMP>/ (asks to confirm on a terminal)"
+    );
+    println!("      --yes                      Skip the --force confirmation");
+    println!("      --kdf-mem <MiB>            Argon2id memory cost (default 19, minimum 19)");
+    println!("      --kdf-time <PASSES>        Argon2id passes (default 2, minimum 2)");
+    println!("      --allow-weak               Accept a weak passphrase without confirmation");
+    println!("      --recovery                 Also create a recovery key (printed once)");
+    println!();
+    println!("  lazy-locker passphrase change [OPTIONS]");
+    println!("      --old <PASS>               Current passphrase (or set LAZY_LOCKER_PASSPHRASE)");
+    println!("      --new <PASS>               New passphrase (prompted twice on a terminal)");
+    println!("      --dry-run                  Verify and report without writing anything");
+    println!();
+    println!("  lazy-locker rotate --new <PASS> [OPTIONS]   Same as passphrase change");
+    println!("      --old <PASS>               Current passphrase (or set LAZY_LOCKER_PASSPHRASE)");
+    println!("      --dry-run                  Verify and report without writing anything");
+    println!();
+    println!("  lazy-locker check [--passphrase <PASS>]");
+    println!("      Decrypt every secret without printing it, and check the key files,");
+    println!("      metadata and file permissions. Fails if any secret does not decrypt.");
+    println!();
+    println!("  lazy-locker recovery <enable|disable|status>");
+    println!(
+        "      enable                     Create a recovery key (printed once), replacing any"
+    );
+    println!("      disable                    Remove the recovery key");
+    println!("      status                     Show whether recovery is enabled");
+    println!(
+        "  lazy-locker recovery unlock [OPTIONS]   Set a new passphrase with the recovery key"
+    );
+    println!("      --key <RECOVERY>           Recovery key (prompted on a terminal otherwise)");
+    println!("      --new <PASS>               New passphrase (prompted twice on a terminal)");
     println!();
     println!("  lazy-locker token add <NAME> [VALUE] [OPTIONS]");
     println!("      --stdin                    Read value from stdin");
-    println!("      --expires <DAYS>           Expiration in days");
+    println!(
+        "      --stdin-json               Add many tokens from a JSON object or array on stdin"
+    );
+    println!(
+        "      --json-stdin               Add one token from a JSON object: name, value, expires, note, tags"
+    );
+    println!("      --expires <DAYS|never>     Expiration in days (never or 0: permanent)");
+    println!("      --expires-at <DATE>        Expiration date: YYYY-MM-DD or RFC 3339");
+    println!(
+        "      --permanent                Never expires, despite store.default_expiration_days"
+    );
+    println!("      --env <NAME>               Store as the <NAME> variant of an existing token");
+    println!("      --totp                     Value is a base32 TOTP seed (see token code)");
+    println!("      --note <TEXT>              Note describing what the token is for");
+    println!("      --tag <TAG>                Tag the token, e.g. by service (repeatable)");
+    println!(
+        "      --allow-any-name           Accept a name that is not a valid env variable name"
+    );
+    println!("      --no-analyze               Do not warn about placeholder or mismatched values");
+    println!("      --allow-large              Accept values above store.max_secret_bytes (1 MiB)");
+    println!(
+        "      --protected                Copy only: not printed by token get, list or export"
+    );
+    println!("      --passphrase <PASS>        Passphrase");
+    println!();
+    println!("  lazy-locker token gen <NAME> [OPTIONS]");
+    println!("      --length <N>               Length in characters (default: 32, 8 to 1024)");
+    println!("      --charset <SET>            alnum (default), hex, base64 (url-safe) or symbols");
+    println!("      --expires <DAYS|never>     Expiration in days (never or 0: permanent)");
+    println!("      --expires-at <DATE>        Expiration date: YYYY-MM-DD or RFC 3339");
+    println!(
+        "      --permanent                Never expires, despite store.default_expiration_days"
+    );
+    println!(
+        "      --copy                     Copy the value to the clipboard instead of printing"
+    );
     println!("      --passphrase <PASS>        Passphrase");
     println!();
     println!("  lazy-locker token get <NAME> [OPTIONS]");
     println!("      --json                     Output as JSON");
     println!("      --env                      Output as KEY=VALUE");
+    println!("      --env-select <NAME>        Resolve the <NAME> variant (falls back to default)");
+    println!(
+        "      --copy                     Copy the value to the clipboard instead of printing"
+    );
+    println!("      --force                    Print the value of a protected token");
+    println!("      --passphrase <PASS>        Passphrase");
+    println!();
+    println!("  lazy-locker token code <NAME> [OPTIONS]");
+    println!("      --json                     Output as JSON");
     println!("      --passphrase <PASS>        Passphrase");
     println!();
     println!("  lazy-locker token list [OPTIONS]");
     println!("      --json                     Output as JSON");
     println!("      --env                      Output all as KEY=VALUE");
+    println!("      --env-select <NAME>        Resolve the <NAME> variant (falls back to default)");
+    println!(
+        "      --tag <TAG>                Only tokens with this tag (repeatable: all of them)"
+    );
+    println!("      --quiet                    Do not warn about tokens expiring soon");
+    println!("      --no-color                 Plain rows, also when NO_COLOR is set or piped");
+    println!("      --names-only               Only the names, one per line, sorted");
+    println!("      --count                    Only the number of tokens");
+    println!("      --force                    With --env: include protected tokens");
     println!("      --passphrase <PASS>        Passphrase");
     println!();
-    println!("  lazy-locker token remove <NAME> [OPTIONS]");
+    println!("  lazy-locker token which <NAME> [OPTIONS]");
+    println!("      --json                     Output as JSON");
+    println!("      --env-select <NAME>        Show which variant <NAME> resolves to");
+    println!("      --passphrase <PASS>        Passphrase");
+    println!();
+    println!("  lazy-locker token exists <NAME> [OPTIONS]");
+    println!("      --verbose                  Say why, instead of only setting the exit code");
+    println!("      --passphrase <PASS>        Passphrase");
+    println!();
+    println!("  lazy-locker token update <NAME> [VALUE] [OPTIONS]");
+    println!("      --stdin                    Read the new value from stdin");
+    println!(
+        "      --note <TEXT>              Set the note (\"\" clears it); VALUE is then optional"
+    );
+    println!("      --tag <TAG>                Replace the tags (repeatable, \"\" clears them)");
+    println!("      --protected, --unprotected Make the token copy only, or lift that");
+    println!("      --passphrase <PASS>        Passphrase");
+    println!();
+    println!("  lazy-locker token edit <NAME> [OPTIONS]");
+    println!("      Opens the value in $EDITOR (or $VISUAL, then vi)");
+    println!("      --passphrase <PASS>        Passphrase");
+    println!();
+    println!("  lazy-locker token rename <OLD> <NEW> [OPTIONS]");
+    println!(
+        "      --allow-any-name           Accept a name that is not a valid env variable name"
+    );
+    println!("      --passphrase <PASS>        Passphrase");
+    println!();
+    println!("  lazy-locker token remove <NAME>... [OPTIONS]");
+    println!("      --glob <PATTERN>           Also remove tokens matching PATTERN (* and ?)");
+    println!("      --yes                      Remove several tokens without asking");
+    println!("      --strict                   Fail, removing nothing, if a NAME is missing");
+    println!("      --passphrase <PASS>        Passphrase");
+    println!();
+    println!("  lazy-locker token prune [OPTIONS]");
+    println!("      Remove expired tokens (permanent tokens are never removed)");
+    println!("      --older-than <DAYS>        Only tokens expired more than DAYS days ago");
+    println!("      --dry-run                  Show what would be removed without writing");
     println!("      --passphrase <PASS>        Passphrase");
     println!();
     println!("  lazy-locker import [FILE] [OPTIONS]");
     println!("      --stdin                    Read from stdin");
-    println!("      --format <env|json>        Input format (default: env)");
-    println!("      --expires <DAYS>           Expiration for all imported tokens");
+    println!("      --format <env|json|yaml>   Input format (default: env)");
+    println!("      --expires <DAYS|never>     Expiration for all imported tokens");
+    println!("      --expires-at <DATE>        Expiration date for all imported tokens");
+    println!("      --permanent                Import all tokens without expiration");
+    println!(
+        "      --on-conflict <MODE>       Existing tokens: skip (default), overwrite or error"
+    );
+    println!("      --dry-run                  Show what would be imported without writing");
+    println!("      --no-analyze               Do not warn about placeholder or mismatched values");
+    println!("      --allow-large              Accept values above store.max_secret_bytes (1 MiB)");
+    println!(
+        "      --allow-any-name           Accept a name that is not a valid env variable name"
+    );
+    println!(
+        "      --prefix <STR>             Prepend <STR> to every imported name (e.g. STRIPE_)"
+    );
     println!("      --passphrase <PASS>        Passphrase");
     println!();
     println!("  lazy-locker export [OPTIONS]");
-    println!("      --json                     Output as JSON");
+    println!("      --json                     Output as JSON object (NAME: value)");
+    println!("      --with-metadata            With --json: include expiration, tags, description");
     println!("      --env                      Output as .env format (default)");
+    println!("      --yaml                     Output as a YAML mapping (NAME: \"value\")");
+    println!("      --format <FORMAT>          env, json, yaml, or csv/table: metadata, no values");
+    println!(
+        "      --shell                    With --env: emit export NAME='value' lines for eval"
+    );
+    println!("      --env-select <NAME>        Resolve the <NAME> variant (falls back to default)");
+    println!("      --only <NAME,NAME2>        Export only these tokens");
+    println!("      --except <NAME,NAME2>      Leave these tokens out");
+    println!("      --tag <TAG>                Export only tokens with this tag (repeatable: all)");
+    println!("      --mask                     Write ******** instead of every value");
+    println!("      --strip-prefix <STR>       Remove <STR> from the start of exported names");
+    println!("      --force                    Include the values of protected tokens");
     println!("      --passphrase <PASS>        Passphrase");
     println!();
+    println!("  lazy-locker backup --out <FILE> [OPTIONS]");
+    println!("      Encrypted backup of every token, restorable on another machine");
+    println!(
+        "      --backup-passphrase <PASS> Seal the backup with this instead of the passphrase"
+    );
+    println!("      --passphrase <PASS>        Passphrase");
+    println!();
+    println!("  lazy-locker restore --in <FILE> [OPTIONS]");
+    println!("      --backup-passphrase <PASS> Passphrase the backup was sealed with");
+    println!(
+        "      --on-conflict <MODE>       Existing tokens: skip (default), overwrite or error"
+    );
+    println!(
+        "      --allow-any-name           Accept a name that is not a valid env variable name"
+    );
+    println!("      --passphrase <PASS>        Passphrase");
+    println!();
+    println!("  lazy-locker restore-backup [TIMESTAMP]");
+    println!("      Put back the locker files that init --force moved to backup/<TIMESTAMP>/;");
+    println!("      the current locker is backed up first. Lists the backups without TIMESTAMP.");
+    println!();
+    println!("  lazy-locker shell-env [OPTIONS]   For eval \"$(lazy-locker shell-env)\"");
+    println!("      --only <NAME,NAME2>        Only these tokens");
+    println!("      --except <NAME,NAME2>      Leave these tokens out");
+    println!("      --tag <TAG>                Only tokens with this tag (repeatable: all; needs");
+    println!("                                 the passphrase)");
+    println!("      --env-select <NAME>        Resolve the <NAME> variant (falls back to default)");
+    println!("      --passphrase <PASS>        Passphrase, used when the agent is not running");
+    println!();
+    println!("EXIT CODES:");
+    println!("  0 success, 1 token not found (or other failure), 2 token expired,");
+    println!("  3 missing or incorrect passphrase, 4 invalid command line");
+    println!("  With --json, errors are printed to stdout as");
+    println!("  {{\"error\": {{\"code\": \"not_found\", \"message\": \"...\"}}}}");
+    println!();
     println!("EXAMPLES:");
     println!("  lazy-locker run python script.py");
     println!("  lazy-locker init --passphrase \"mypass\"");
     println!("  lazy-locker token add API_KEY \"sk-123\" --expires 30");
+    println!("  lazy-locker token add CERT_PASS --stdin --expires-at 2025-12-31");
     println!("  echo \"secret\" | lazy-locker token add DB_PASS --stdin");
+    println!("  echo '{{\"A\": \"1\", \"B\": \"2\"}}' | lazy-locker token add --stdin-json");
     println!("  lazy-locker import .env --passphrase \"mypass\"");
+    println!("  lazy-locker import secrets.yaml --format yaml");
+    println!("  lazy-locker token add DB_URL --env prod \"postgres://prod\"");
+    println!("  lazy-locker token update SVC_TOKEN --note \"GitHub Actions deploy key\"");
+    println!("  lazy-locker run --env prod -- ./deploy.sh");
+    println!("  lazy-locker run --only DB_URL -- ./migrate.sh");
     println!("  lazy-locker token list --json");
+    println!("  lazy-locker token list --tag backend --tag db");
+    println!("  lazy-locker token exists API_KEY || lazy-locker token gen API_KEY");
+    println!("  lazy-locker token remove --glob 'PROJ_*' --yes");
+    println!("  lazy-locker --profile work token list");
+    println!("  lazy-locker backup --out locker.bak");
+    println!("  lazy-locker export --only API_KEY,DB_URL > handoff.env");
+    println!("  eval \"$(lazy-locker export --env --shell)\"");
+    println!("  lazy-locker export --format csv > tokens.csv");
+}
+
+/// Delayed clipboard clear (spawned by `--copy` commands)
+fn run_clipboard_clear_mode(args: &[String]) -> Result<()> {
+    let (_, flags) = parse_cli_args(args);
+    let secs = flags
+        .get("after")
+        .and_then(|v| v.as_deref())
+        .and_then(|v| v.parse().ok())
+        .ok_or_else(|| {
+            CliError::Usage("Usage: lazy-locker clipboard-clear --after <SECS>".into())
+        })?;
+
+    executor::run_clipboard_clear(std::time::Duration::from_secs(secs))
 }
 
 /// Agent mode (called by the daemon)
 fn run_agent_mode(args: &[String]) -> Result<()> {
     let mut key_hex = String::new();
     let mut store_path = String::new();
+    let mut ttl_hours = None;
+    let mut idle_ttl = false;
+    let mut audit = false;
+    let mut readonly = false;
+    let mut allowed_pids = Vec::new();
 
     let mut i = 0;
     while i < args.len() {
@@ -116,17 +427,50 @@ fn run_agent_mode(args: &[String]) -> Result<()> {
                 store_path = args[i + 1].clone();
                 i += 2;
             }
+            "--ttl" if i + 1 < args.len() => {
+                ttl_hours = Some(args[i + 1].parse::<u64>().map_err(|_| {
+                    anyhow::anyhow!(
+                        "Invalid --ttl '{}': expected a number of hours",
+                        args[i + 1]
+                    )
+                })?);
+                i += 2;
+            }
+            "--idle-ttl" => {
+                idle_ttl = true;
+                i += 1;
+            }
+            "--audit" => {
+                audit = true;
+                i += 1;
+            }
+            "--readonly" | "--no-shutdown" => {
+                readonly = true;
+                i += 1;
+            }
+            "--allow-pid" if i + 1 < args.len() => {
+                allowed_pids.extend(agent::parse_pid_list(&args[i + 1])?);
+                i += 2;
+            }
             _ => i += 1,
         }
     }
 
     if key_hex.is_empty() || store_path.is_empty() {
         return Err(anyhow::anyhow!(
-            "Usage: lazy-locker agent --key <key_hex> --store <path>"
+            "Usage: lazy-locker agent --key <key_hex> --store <path> [--ttl <hours>] [--idle-ttl] [--audit] [--readonly] [--allow-pid <PID,PID2>]"
         ));
     }
 
-    agent::run_agent(&key_hex, &store_path)
+    agent::run_agent(
+        &key_hex,
+        &store_path,
+        ttl_hours,
+        idle_ttl,
+        audit,
+        readonly,
+        allowed_pids,
+    )
 }
 
 // ============================================================================
@@ -166,71 +510,415 @@ fn parse_cli_args(
     (positional, flags)
 }
 
+/// Every value of a repeatable flag such as `--tag a --tag b`; each value may
+/// also list several, comma-separated (`--tag a,b`)
+fn flag_values(args: &[String], name: &str) -> Vec<String> {
+    let flag = format!("--{}", name);
+    args.windows(2)
+        .filter(|pair| pair[0] == flag && !pair[1].starts_with("--"))
+        .flat_map(|pair| {
+            pair[1]
+                .split(',')
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Reads `--passphrase-stdin` or `--passphrase-fd <N>`. Standard input can only
+/// be read once, so `--passphrase-stdin` is refused next to a flag reading a value from it.
+fn passphrase_input(
+    flags: &std::collections::HashMap<String, Option<String>>,
+) -> Result<cli::PassphraseInput> {
+    if flags.contains_key("passphrase-stdin") {
+        if let Some(other) = ["stdin", "json-stdin", "stdin-json"]
+            .into_iter()
+            .find(|name| flags.contains_key(*name))
+        {
+            anyhow::bail!(CliError::Usage(format!(
+                "--passphrase-stdin and --{} both read standard input; \
+                 pass the passphrase with --passphrase-fd or the environment instead",
+                other
+            )));
+        }
+        return Ok(cli::PassphraseInput::Stdin);
+    }
+    match flags.get("passphrase-fd") {
+        None => Ok(cli::PassphraseInput::None),
+        Some(fd) => fd
+            .as_deref()
+            .and_then(|fd| fd.parse().ok())
+            .map(cli::PassphraseInput::Fd)
+            .ok_or_else(|| {
+                CliError::Usage("--passphrase-fd expects a file descriptor number".into()).into()
+            }),
+    }
+}
+
+/// The passphrase from `--passphrase`, `--passphrase-stdin`, `--passphrase-fd`,
+/// the environment variable or a prompt, in that order
+fn passphrase_flag(flags: &std::collections::HashMap<String, Option<String>>) -> Result<String> {
+    cli::get_passphrase(
+        flags.get("passphrase").and_then(|v| v.as_deref()),
+        passphrase_input(flags)?,
+    )
+}
+
+/// Reads `--expires` or `--expires-at`, failing loudly on a missing or malformed value
+fn expires_flag(
+    flags: &std::collections::HashMap<String, Option<String>>,
+) -> Result<Option<Expiration>> {
+    let value = |name: &str| {
+        flags
+            .get(name)
+            .map(|value| value.as_deref().unwrap_or_default())
+    };
+    if flags.contains_key("permanent") {
+        if flags.contains_key("expires") || flags.contains_key("expires-at") {
+            anyhow::bail!(CliError::Usage(
+                "--permanent cannot be combined with --expires or --expires-at".into()
+            ));
+        }
+        return Ok(Some(Expiration::Never));
+    }
+    Expiration::from_flags(value("expires"), value("expires-at"))
+}
+
 /// init command
 fn run_init_command(args: &[String]) -> Result<()> {
     let (_, flags) = parse_cli_args(args);
 
-    let passphrase = cli::get_passphrase(flags.get("passphrase").and_then(|v| v.as_deref()))?;
+    let mut kdf = KdfParams::default();
+    if let Some(mem) = flags.get("kdf-mem") {
+        let mib: u32 = mem
+            .as_deref()
+            .unwrap_or_default()
+            .parse()
+            .map_err(|_| CliError::Usage("--kdf-mem expects a memory size in MiB".into()))?;
+        kdf.m_cost = mib
+            .checked_mul(1024)
+            .ok_or_else(|| CliError::Usage("--kdf-mem is too large".into()))?;
+    }
+    if let Some(time) = flags.get("kdf-time") {
+        kdf.t_cost = time
+            .as_deref()
+            .unwrap_or_default()
+            .parse()
+            .map_err(|_| CliError::Usage("--kdf-time expects a number of passes".into()))?;
+    }
+    let passphrase = cli::get_new_passphrase(
+        flags.get("passphrase").and_then(|v| v.as_deref()),
+        passphrase_input(&flags)?,
+    )?;
     let force = flags.contains_key("force");
+    let yes = flags.contains_key("yes");
+    let allow_weak = flags.contains_key("allow-weak");
+    let recovery = flags.contains_key("recovery");
+
+    cli::cmd_init(&passphrase, force, yes, kdf, allow_weak, recovery)
+}
+
+/// rotate command
+fn run_rotate_command(args: &[String]) -> Result<()> {
+    let (_, flags) = parse_cli_args(args);
+
+    let old = cli::get_passphrase(
+        flags
+            .get("old")
+            .or_else(|| flags.get("passphrase"))
+            .and_then(|v| v.as_deref()),
+        passphrase_input(&flags)?,
+    )?;
+    let new = flags.get("new").and_then(|v| v.as_deref()).ok_or_else(|| {
+        CliError::Usage("Usage: lazy-locker rotate --old <PASS> --new <PASS>".into())
+    })?;
+    let dry_run = flags.contains_key("dry-run");
+
+    cli::cmd_rotate(&old, new, dry_run)
+}
 
-    cli::cmd_init(&passphrase, force)
+/// passphrase subcommands
+fn run_passphrase_command(args: &[String]) -> Result<()> {
+    match args.first().map(|s| s.as_str()) {
+        Some("change") => {
+            let (_, flags) = parse_cli_args(&args[1..]);
+
+            let old = cli::get_passphrase(
+                flags
+                    .get("old")
+                    .or_else(|| flags.get("passphrase"))
+                    .and_then(|v| v.as_deref()),
+                passphrase_input(&flags)?,
+            )?;
+            let new = cli::get_changed_passphrase(flags.get("new").and_then(|v| v.as_deref()))?;
+            let dry_run = flags.contains_key("dry-run");
+
+            cli::cmd_rotate(&old, &new, dry_run)
+        }
+        _ => anyhow::bail!(CliError::Usage(
+            "Usage: lazy-locker passphrase change [--old <PASS>] [--new <PASS>]".into()
+        )),
+    }
+}
+
+/// check command
+fn run_check_command(args: &[String]) -> Result<()> {
+    let (_, flags) = parse_cli_args(args);
+    let passphrase = passphrase_flag(&flags)?;
+
+    cli::cmd_check(&passphrase)
+}
+
+/// recovery subcommands
+fn run_recovery_command(args: &[String]) -> Result<()> {
+    let (_, flags) = parse_cli_args(args.get(1..).unwrap_or_default());
+    let passphrase = || passphrase_flag(&flags);
+
+    match args.first().map(|s| s.as_str()) {
+        Some("enable") => cli::cmd_recovery_enable(&passphrase()?),
+        Some("disable") => cli::cmd_recovery_disable(&passphrase()?),
+        Some("status") => cli::cmd_recovery_status(),
+        Some("unlock") => {
+            let key = cli::get_recovery_key(flags.get("key").and_then(|v| v.as_deref()))?;
+            let new = cli::get_changed_passphrase(flags.get("new").and_then(|v| v.as_deref()))?;
+            cli::cmd_recovery_unlock(&key, &new)
+        }
+        _ => anyhow::bail!(CliError::Usage(
+            "Usage: lazy-locker recovery <enable|disable|status|unlock> [OPTIONS]".into()
+        )),
+    }
 }
 
 /// token subcommands
 fn run_token_command(args: &[String]) -> Result<()> {
     if args.is_empty() {
-        anyhow::bail!("Usage: lazy-locker token <add|get|list|remove> [OPTIONS]");
+        anyhow::bail!(CliError::Usage(
+            "Usage: lazy-locker token <add|gen|get|code|list|which|exists|update|edit|rename|remove> [OPTIONS]"
+                .into()
+        ));
     }
 
     let subcommand = &args[0];
     let sub_args = &args[1..];
     let (positional, flags) = parse_cli_args(sub_args);
+    if flags.contains_key("no-hooks") {
+        hooks::disable();
+    }
 
-    let passphrase = cli::get_passphrase(flags.get("passphrase").and_then(|v| v.as_deref()))?;
+    let passphrase = passphrase_flag(&flags)?;
     let format =
         cli::OutputFormat::from_args(flags.contains_key("json"), flags.contains_key("env"));
+    let env_select = flags.get("env-select").and_then(|v| v.as_deref());
 
     match subcommand.as_str() {
         "add" => {
-            let name = positional
-                .first()
-                .ok_or_else(|| anyhow::anyhow!("Usage: lazy-locker token add <NAME> [VALUE]"))?;
+            let expires = expires_flag(&flags)?;
+
+            // On add, `--env <NAME>` selects the environment variant to store
+            let env = flags.get("env").and_then(|v| v.as_deref());
+
+            let tags = flag_values(sub_args, "tag");
+            let options = cli::TokenAddOptions {
+                expires,
+                env,
+                totp: flags.contains_key("totp"),
+                note: flags.get("note").and_then(|v| v.as_deref()),
+                tags: &tags,
+                allow_any_name: flags.contains_key("allow-any-name"),
+                no_analyze: flags.contains_key("no-analyze"),
+                allow_large: flags.contains_key("allow-large"),
+                protected: flags.contains_key("protected"),
+            };
+
+            if flags.contains_key("stdin-json") {
+                if !positional.is_empty() {
+                    anyhow::bail!(CliError::Usage(
+                        "With --stdin-json, the names and values come from the JSON input".into()
+                    ));
+                }
+                return cli::cmd_token_add_json(options, &passphrase);
+            }
+            if flags.contains_key("json-stdin") {
+                if !positional.is_empty() {
+                    anyhow::bail!(CliError::Usage(
+                        "With --json-stdin, the name and value come from the JSON object".into()
+                    ));
+                }
+                return cli::cmd_token_add_from_json(options, &passphrase);
+            }
+
+            let name = positional.first().ok_or_else(|| {
+                CliError::Usage("Usage: lazy-locker token add <NAME> [VALUE]".into())
+            })?;
             let value = positional.get(1).map(|s| s.as_str());
             let stdin = flags.contains_key("stdin");
-            let expires = flags
-                .get("expires")
-                .and_then(|v| v.as_ref())
-                .and_then(|v| v.parse::<u32>().ok());
 
-            cli::cmd_token_add(name, value, stdin, expires, &passphrase)
+            cli::cmd_token_add(name, value, stdin, options, &passphrase)
         }
         "get" => {
             let name = positional
                 .first()
-                .ok_or_else(|| anyhow::anyhow!("Usage: lazy-locker token get <NAME>"))?;
+                .ok_or_else(|| CliError::Usage("Usage: lazy-locker token get <NAME>".into()))?;
+            let copy = flags.contains_key("copy");
+            let force = flags.contains_key("force");
 
-            cli::cmd_token_get(name, format, &passphrase)
+            cli::cmd_token_get(name, format, env_select, copy, force, &passphrase)
         }
-        "list" => cli::cmd_token_list(format, &passphrase),
-        "remove" | "rm" | "delete" => {
+        "list" => {
+            let filter = cli::SecretFilter::from_args(None, None, &flag_values(sub_args, "tag"));
+
+            let options = cli::TokenListOptions {
+                env: env_select,
+                quiet: flags.contains_key("quiet"),
+                no_color: flags.contains_key("no-color"),
+                names_only: flags.contains_key("names-only"),
+                count: flags.contains_key("count"),
+                force: flags.contains_key("force"),
+            };
+
+            cli::cmd_token_list(format, &filter, options, &passphrase)
+        }
+        "which" => {
             let name = positional
                 .first()
-                .ok_or_else(|| anyhow::anyhow!("Usage: lazy-locker token remove <NAME>"))?;
+                .ok_or_else(|| CliError::Usage("Usage: lazy-locker token which <NAME>".into()))?;
 
-            cli::cmd_token_remove(name, &passphrase)
+            cli::cmd_token_which(name, format, env_select, &passphrase)
         }
-        _ => anyhow::bail!(
-            "Unknown token subcommand: {}. Use add, get, list, or remove.",
+        "exists" => {
+            let name = positional
+                .first()
+                .ok_or_else(|| CliError::Usage("Usage: lazy-locker token exists <NAME>".into()))?;
+
+            let code = cli::cmd_token_exists(name, flags.contains_key("verbose"), &passphrase)?;
+            std::process::exit(code)
+        }
+        "gen" | "generate" => {
+            let name = positional.first().ok_or_else(|| {
+                CliError::Usage("Usage: lazy-locker token gen <NAME> [--length N]".into())
+            })?;
+            let length = match flags.get("length").and_then(|v| v.as_deref()) {
+                Some(len) => len
+                    .parse()
+                    .map_err(|_| CliError::Usage(format!("Invalid --length value '{}'", len)))?,
+                None => generator::DEFAULT_LENGTH,
+            };
+            let charset = match flags.get("charset").and_then(|v| v.as_deref()) {
+                Some(charset) => generator::Charset::parse(charset)?,
+                None => generator::Charset::default(),
+            };
+            let expires = expires_flag(&flags)?;
+            let copy = flags.contains_key("copy");
+
+            cli::cmd_token_gen(name, length, charset, expires, copy, &passphrase)
+        }
+        "code" => {
+            let name = positional
+                .first()
+                .ok_or_else(|| CliError::Usage("Usage: lazy-locker token code <NAME>".into()))?;
+
+            cli::cmd_token_code(name, format, &passphrase)
+        }
+        "update" => {
+            let name = positional.first().ok_or_else(|| {
+                CliError::Usage("Usage: lazy-locker token update <NAME> [VALUE]".into())
+            })?;
+            let value = positional.get(1).map(|s| s.as_str());
+            let stdin = flags.contains_key("stdin");
+            let note = flags.get("note").and_then(|v| v.as_deref());
+            let tags = flags
+                .contains_key("tag")
+                .then(|| flag_values(sub_args, "tag"));
+            let protected = match (
+                flags.contains_key("protected"),
+                flags.contains_key("unprotected"),
+            ) {
+                (true, true) => anyhow::bail!(CliError::Usage(
+                    "--protected and --unprotected cannot be combined".into()
+                )),
+                (true, false) => Some(true),
+                (false, true) => Some(false),
+                (false, false) => None,
+            };
+
+            cli::cmd_token_update(
+                name,
+                value,
+                stdin,
+                note,
+                tags.as_deref(),
+                protected,
+                &passphrase,
+            )
+        }
+        "edit" => {
+            let name = positional
+                .first()
+                .ok_or_else(|| CliError::Usage("Usage: lazy-locker token edit <NAME>".into()))?;
+
+            cli::cmd_token_edit(name, &passphrase)
+        }
+        "rename" | "mv" => {
+            let (old, new) = match positional.as_slice() {
+                [old, new] => (old, new),
+                _ => anyhow::bail!(CliError::Usage(
+                    "Usage: lazy-locker token rename <OLD> <NEW>".into()
+                )),
+            };
+
+            let allow_any_name = flags.contains_key("allow-any-name");
+
+            cli::cmd_token_rename(old, new, allow_any_name, &passphrase)
+        }
+        "remove" | "rm" | "delete" => {
+            let glob = flags.get("glob").cloned().flatten();
+            if positional.is_empty() && glob.is_none() {
+                anyhow::bail!(CliError::Usage(
+                    "Usage: lazy-locker token remove <NAME>... [--glob <PATTERN>]".into()
+                ));
+            }
+            let yes = flags.contains_key("yes");
+            let strict = flags.contains_key("strict");
+
+            cli::cmd_token_remove(&positional, glob.as_deref(), yes, strict, &passphrase)
+        }
+        "prune" => {
+            let older_than = match flags.get("older-than").and_then(|v| v.as_deref()) {
+                Some(days) => days.parse::<u32>().map_err(|_| {
+                    CliError::Usage(format!("Invalid --older-than value '{}'", days))
+                })?,
+                None => 0,
+            };
+            let dry_run = flags.contains_key("dry-run");
+
+            cli::cmd_token_prune(older_than.into(), dry_run, &passphrase)
+        }
+        _ => anyhow::bail!(CliError::Usage(format!(
+            "Unknown token subcommand: {}. Use add, gen, get, code, list, which, exists, update, edit, rename, remove, or prune.",
             subcommand
-        ),
+        ))),
+    }
+}
+
+/// Reads `--on-conflict`, skipping existing tokens by default
+fn conflict_flag(
+    flags: &std::collections::HashMap<String, Option<String>>,
+) -> Result<ConflictStrategy> {
+    match flags.get("on-conflict") {
+        Some(value) => ConflictStrategy::parse(value.as_deref().unwrap_or_default())
+            .map_err(|e| CliError::Usage(e.to_string()).into()),
+        None => Ok(ConflictStrategy::default()),
     }
 }
 
 /// import command
 fn run_import_command(args: &[String]) -> Result<()> {
     let (positional, flags) = parse_cli_args(args);
+    if flags.contains_key("no-hooks") {
+        hooks::disable();
+    }
 
-    let passphrase = cli::get_passphrase(flags.get("passphrase").and_then(|v| v.as_deref()))?;
+    let passphrase = passphrase_flag(&flags)?;
     let file = positional.first().map(|s| s.as_str());
     let stdin = flags.contains_key("stdin");
     let format = flags
@@ -238,31 +926,146 @@ fn run_import_command(args: &[String]) -> Result<()> {
         .and_then(|v| v.as_ref())
         .map(|s| s.as_str())
         .unwrap_or("env");
-    let expires = flags
-        .get("expires")
-        .and_then(|v| v.as_ref())
-        .and_then(|v| v.parse::<u32>().ok());
+    let options = cli::ImportOptions {
+        expires: expires_flag(&flags)?,
+        on_conflict: conflict_flag(&flags)?,
+        dry_run: flags.contains_key("dry-run"),
+        no_analyze: flags.contains_key("no-analyze"),
+        allow_large: flags.contains_key("allow-large"),
+        allow_any_name: flags.contains_key("allow-any-name"),
+        prefix: flags.get("prefix").and_then(|v| v.as_deref()),
+    };
+
+    cli::cmd_import(file, stdin, format, options, &passphrase)
+}
+
+/// profile command
+fn run_profile_command(args: &[String]) -> Result<()> {
+    match args.first().map(String::as_str) {
+        None | Some("list") => cli::cmd_profile_list(),
+        Some(other) => anyhow::bail!(CliError::Usage(format!(
+            "Unknown profile subcommand: {}. Use list.",
+            other
+        ))),
+    }
+}
+
+/// backup command
+fn run_backup_command(args: &[String]) -> Result<()> {
+    let (_, flags) = parse_cli_args(args);
+
+    let out = flags
+        .get("out")
+        .and_then(|v| v.as_deref())
+        .ok_or_else(|| CliError::Usage("Usage: lazy-locker backup --out <FILE>".into()))?;
+    let passphrase = passphrase_flag(&flags)?;
+    let backup_passphrase = flags.get("backup-passphrase").and_then(|v| v.as_deref());
+
+    cli::cmd_backup(out, backup_passphrase, &passphrase)
+}
+
+/// restore command
+fn run_restore_command(args: &[String]) -> Result<()> {
+    let (_, flags) = parse_cli_args(args);
+    if flags.contains_key("no-hooks") {
+        hooks::disable();
+    }
 
-    cli::cmd_import(file, stdin, format, expires, &passphrase)
+    let input = flags
+        .get("in")
+        .and_then(|v| v.as_deref())
+        .ok_or_else(|| CliError::Usage("Usage: lazy-locker restore --in <FILE>".into()))?;
+    let passphrase = passphrase_flag(&flags)?;
+    let backup_passphrase = flags.get("backup-passphrase").and_then(|v| v.as_deref());
+    let on_conflict = conflict_flag(&flags)?;
+
+    let allow_any_name = flags.contains_key("allow-any-name");
+
+    cli::cmd_restore(
+        input,
+        backup_passphrase,
+        on_conflict,
+        allow_any_name,
+        &passphrase,
+    )
 }
 
 /// export command
 fn run_export_command(args: &[String]) -> Result<()> {
     let (_, flags) = parse_cli_args(args);
 
-    let passphrase = cli::get_passphrase(flags.get("passphrase").and_then(|v| v.as_deref()))?;
-    let format = if flags.contains_key("json") {
-        cli::OutputFormat::Json
-    } else {
-        cli::OutputFormat::Env
+    let passphrase = passphrase_flag(&flags)?;
+    let format_flag = flags.get("format").and_then(|v| v.as_deref());
+    let format = match format_flag {
+        Some(name) => cli::OutputFormat::parse_export(name)?,
+        None if flags.contains_key("json") => cli::OutputFormat::Json,
+        None => cli::OutputFormat::Env,
+    };
+    let with_metadata = flags.contains_key("with-metadata");
+    let env_select = flags.get("env-select").and_then(|v| v.as_deref());
+    let filter = cli::SecretFilter::from_args(
+        flags.get("only").and_then(|v| v.as_deref()),
+        flags.get("except").and_then(|v| v.as_deref()),
+        &flag_values(args, "tag"),
+    );
+    let options = cli::ExportOptions {
+        format,
+        with_metadata,
+        env: env_select,
+        shell: flags.contains_key("shell"),
+        yaml: flags.contains_key("yaml") || format_flag == Some("yaml"),
+        template: flags.get("template").and_then(|v| v.as_deref()),
+        mask: flags.contains_key("mask"),
+        strip_prefix: flags.get("strip-prefix").and_then(|v| v.as_deref()),
+        force: flags.contains_key("force"),
     };
 
-    cli::cmd_export(format, &passphrase)
+    cli::cmd_export(&options, &filter, &passphrase)
+}
+
+/// shell-env command
+fn run_shell_env_command(args: &[String]) -> Result<()> {
+    let (_, flags) = parse_cli_args(args);
+
+    let filter = cli::SecretFilter::from_args(
+        flags.get("only").and_then(|v| v.as_deref()),
+        flags.get("except").and_then(|v| v.as_deref()),
+        &flag_values(args, "tag"),
+    );
+    let env_select = flags.get("env-select").and_then(|v| v.as_deref());
+
+    cli::cmd_shell_env(
+        &filter,
+        env_select,
+        flags.get("passphrase").and_then(|v| v.as_deref()),
+        passphrase_input(&flags)?,
+    )
 }
 
 /// Shows agent status
-fn show_status() -> Result<()> {
-    match AgentClient::status() {
+/// Exits with 1 when the agent is not running
+fn show_status(args: &[String]) -> Result<()> {
+    let (_, flags) = parse_cli_args(args);
+    let status = AgentClient::status();
+    if flags.contains_key("json") {
+        let json = match &status {
+            Ok(data) => serde_json::json!({
+                "running": true,
+                "uptime_secs": data.get("uptime_secs"),
+                "ttl_remaining_secs": data.get("ttl_remaining_secs"),
+                "idle_ttl": data.get("idle_ttl").and_then(|v| v.as_bool()).unwrap_or(false),
+                "pid": agent::read_agent_pid(),
+            }),
+            Err(_) => serde_json::json!({ "running": false }),
+        };
+        println!("{}", json);
+        if status.is_err() {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    match status {
         Ok(data) => {
             println!("✅ Agent active");
             if let Some(uptime) = data.get("uptime_secs").and_then(|v| v.as_u64()) {
@@ -270,49 +1073,98 @@ fn show_status() -> Result<()> {
                 let mins = (uptime % 3600) / 60;
                 println!("   Uptime: {}h {:02}m", hours, mins);
             }
-            if let Some(remaining) = data.get("ttl_remaining_secs").and_then(|v| v.as_u64()) {
-                let hours = remaining / 3600;
-                let mins = (remaining % 3600) / 60;
-                println!("   TTL remaining: {}h {:02}m", hours, mins);
+            match data.get("ttl_remaining_secs") {
+                Some(serde_json::Value::Null) => println!("   TTL: none (never expires)"),
+                Some(remaining) => {
+                    if let Some(remaining) = remaining.as_u64() {
+                        let hours = remaining / 3600;
+                        let mins = (remaining % 3600) / 60;
+                        let idle = data.get("idle_ttl").and_then(|v| v.as_bool()) == Some(true);
+                        let label = if idle {
+                            "Idle time remaining"
+                        } else {
+                            "TTL remaining"
+                        };
+                        println!("   {}: {}h {:02}m", label, hours, mins);
+                    }
+                }
+                None => {}
             }
         }
         Err(_) => {
             println!("❌ Agent not started");
             println!("   Run lazy-locker to start the agent");
+            std::process::exit(1);
         }
     }
     Ok(())
 }
 
+/// Starts the agent headlessly, like closing the TUI does
+fn start_agent(args: &[String]) -> Result<()> {
+    let (_, flags) = parse_cli_args(args);
+    let ttl_flag = match flags.get("ttl").and_then(|v| v.as_deref()) {
+        Some(value) => Some(value.parse::<u64>().map_err(|_| {
+            CliError::Usage(format!(
+                "Invalid --ttl '{}': expected a number of hours",
+                value
+            ))
+        })?),
+        None => None,
+    };
+    if agent::is_agent_running() {
+        println!("ℹ️  Agent already running (use lazy-locker agent restart)");
+        return Ok(());
+    }
+
+    let passphrase = passphrase_flag(&flags)?;
+    let locker = Locker::init_or_load_with_passphrase(&passphrase)?;
+    let key = locker
+        .get_key()
+        .ok_or_else(|| anyhow::anyhow!("Error loading key"))?;
+    let store = SecretsStore::load(locker.base_dir(), key)?;
+
+    let ttl = agent::resolve_ttl_hours(ttl_flag)?;
+    agent::start_daemon(key.to_vec(), store, Some(ttl))?;
+    print_agent_started(ttl);
+    Ok(())
+}
+
+fn print_agent_started(ttl_hours: u64) {
+    if ttl_hours == 0 {
+        println!("✅ Agent started (no expiry)");
+    } else {
+        println!("✅ Agent started ({}h TTL)", ttl_hours);
+    }
+}
+
 /// Stops the agent
 fn stop_agent() -> Result<()> {
     let socket_path = agent::get_socket_path()?;
-    if socket_path.exists() {
-        use std::io::{BufRead, BufReader, Write};
-        use std::os::unix::net::UnixStream;
-
-        if let Ok(mut stream) = UnixStream::connect(&socket_path) {
-            writeln!(stream, r#"{{"action":"shutdown"}}"#)?;
-            stream.flush()?;
-
-            let mut reader = BufReader::new(&stream);
-            let mut response = String::new();
-            reader.read_line(&mut response)?;
-
-            // Wait for agent to fully stop (socket removed)
-            for _ in 0..50 {
-                if !socket_path.exists() && !agent::is_agent_running() {
-                    break;
+    if Transport::exists(&socket_path) {
+        match AgentClient::shutdown() {
+            Ok(()) => {
+                // Wait for agent to fully stop (socket removed)
+                for _ in 0..50 {
+                    if !Transport::exists(&socket_path) && !agent::is_agent_running() {
+                        break;
+                    }
+                    std::thread::sleep(std::time::Duration::from_millis(100));
                 }
-                std::thread::sleep(std::time::Duration::from_millis(100));
-            }
 
-            // Force remove socket if still exists
-            if socket_path.exists() {
-                std::fs::remove_file(&socket_path).ok();
-            }
+                // Force remove socket if still exists
+                if Transport::exists(&socket_path) {
+                    Transport::cleanup(&socket_path);
+                }
 
-            println!("✅ Agent stopped");
+                println!("✅ Agent stopped");
+            }
+            // A read-only agent answers but refuses to stop
+            Err(e) if agent::is_agent_running() => match agent::read_agent_pid() {
+                Some(pid) => anyhow::bail!("{} (PID {})", e, pid),
+                None => return Err(e),
+            },
+            Err(_) => {}
         }
     } else {
         println!("ℹ️  Agent not started");
@@ -322,36 +1174,114 @@ fn stop_agent() -> Result<()> {
 
 /// Executes a command with secrets injected as environment variables
 fn run_with_secrets(command_args: &[String]) -> Result<()> {
-    // First, try via the agent (no passphrase needed)
-    if agent::is_agent_running() {
-        let secrets = AgentClient::get_secrets()?;
+    // Leading options come before the command, e.g. `run --env prod --dry-run -- cmd`
+    let mut env = None;
+    let mut only = None;
+    let mut except = None;
+    let mut tags = Vec::new();
+    let mut dry_run = false;
+    let mut json = false;
+    let mut shell = false;
+    let mut passphrase_input = cli::PassphraseInput::None;
+    let mut command_args = command_args;
+    loop {
+        match command_args {
+            [flag, name, rest @ ..] if flag == "--env" => {
+                env = Some(name.as_str());
+                command_args = rest;
+            }
+            [flag, names, rest @ ..] if flag == "--only" => {
+                only = Some(names.as_str());
+                command_args = rest;
+            }
+            [flag, names, rest @ ..] if flag == "--except" => {
+                except = Some(names.as_str());
+                command_args = rest;
+            }
+            [flag, tag, rest @ ..] if flag == "--tag" => {
+                tags.extend(tag.split(',').map(str::to_string));
+                command_args = rest;
+            }
+            [flag, rest @ ..] if flag == "--dry-run" => {
+                dry_run = true;
+                command_args = rest;
+            }
+            [flag, rest @ ..] if flag == "--json" => {
+                json = true;
+                command_args = rest;
+            }
+            [flag, rest @ ..] if flag == "--shell" => {
+                shell = true;
+                command_args = rest;
+            }
+            [flag, rest @ ..] if flag == "--passphrase-stdin" => {
+                passphrase_input = cli::PassphraseInput::Stdin;
+                command_args = rest;
+            }
+            [flag, fd, rest @ ..] if flag == "--passphrase-fd" => {
+                let fd = fd.parse().map_err(|_| {
+                    CliError::Usage("--passphrase-fd expects a file descriptor number".into())
+                })?;
+                passphrase_input = cli::PassphraseInput::Fd(fd);
+                command_args = rest;
+            }
+            [sep, rest @ ..] if sep == "--" => {
+                command_args = rest;
+                break;
+            }
+            _ => break,
+        }
+    }
+    if command_args.is_empty() {
+        anyhow::bail!(CliError::Usage(
+            "Usage: lazy-locker run [--env <NAME>] [--only A,B] [--except C,D] [--tag T] \
+             [--shell] [--dry-run [--json]] [--passphrase-stdin | --passphrase-fd N] \
+             [--] <cmd> [args...]"
+                .into()
+        ));
+    }
+    let filter = cli::SecretFilter::from_args(only, except, &tags);
+    let command = command_args.join(" ");
 
-        // Exécuter la commande avec les secrets
-        use std::process::{Command, Stdio};
-        let command = command_args.join(" ");
+    // First, try via the agent (no passphrase needed). The socket may exist before
+    // the agent accepts connections, so retry briefly before falling back.
+    // The agent does not serve tags, so a tag filter needs the store.
+    let agent_secrets = if filter.tags.is_empty() {
+        AgentClient::try_get_secrets(env)
+    } else {
+        None
+    };
+    if let Some(mut secrets) = agent_secrets {
+        if let Err(e) = filter.retain_values(&mut secrets) {
+            for value in secrets.values_mut() {
+                value.zeroize();
+            }
+            return Err(e);
+        }
+        if dry_run {
+            let names: Vec<String> = secrets.keys().cloned().collect();
+            for value in secrets.values_mut() {
+                value.zeroize();
+            }
+            return print_run_manifest(names, "agent", env, &command, json);
+        }
 
-        let output = Command::new("sh")
-            .arg("-c")
-            .arg(&command)
-            .envs(&secrets)
-            .stdout(Stdio::inherit())
-            .stderr(Stdio::inherit())
-            .status()?;
+        // Exécuter la commande avec les secrets
+        let status = executor::run_with_env(command_args, shell, &secrets);
+        for value in secrets.values_mut() {
+            value.zeroize();
+        }
+        let status = status?;
 
-        if !output.success() {
-            std::process::exit(output.code().unwrap_or(1));
+        if !status.success() {
+            std::process::exit(status.code().unwrap_or(1));
         }
 
         return Ok(());
     }
 
     // Fallback: ask for passphrase
-    use std::io::Write;
-
-    print!("Passphrase: ");
-    std::io::stdout().flush()?;
-
-    let passphrase = rpassword::read_password()?;
+    let passphrase = cli::get_passphrase(None, passphrase_input)?;
 
     let locker = Locker::init_or_load_with_passphrase(&passphrase)?;
     let key = locker
@@ -359,15 +1289,53 @@ fn run_with_secrets(command_args: &[String]) -> Result<()> {
         .ok_or_else(|| anyhow::anyhow!("Error loading key"))?;
 
     let store = SecretsStore::load(locker.base_dir(), key)?;
+    let selected = filter.select(&store)?;
 
-    let command = command_args.join(" ");
-    let output = executor::execute_with_secrets(&command, &store, key)?;
+    if dry_run {
+        let names = selected.iter().map(|s| s.name.clone()).collect();
+        return print_run_manifest(names, "passphrase", env, &command, json);
+    }
 
-    std::io::stdout().write_all(&output.stdout)?;
-    std::io::stderr().write_all(&output.stderr)?;
+    let status = executor::execute_with_secrets(command_args, shell, &selected, key, env)?;
 
-    if !output.status.success() {
-        std::process::exit(output.status.code().unwrap_or(1));
+    if !status.success() {
+        std::process::exit(status.code().unwrap_or(1));
+    }
+
+    Ok(())
+}
+
+/// Prints the variable names `run` would inject, without values
+fn print_run_manifest(
+    mut names: Vec<String>,
+    source: &str,
+    env: Option<&str>,
+    command: &str,
+    json: bool,
+) -> Result<()> {
+    names.sort();
+
+    if json {
+        let manifest = serde_json::json!({
+            "command": command,
+            "source": source,
+            "env": env,
+            "variables": names,
+        });
+        println!("{}", serde_json::to_string_pretty(&manifest)?);
+    } else {
+        println!(
+            "Dry run: `{}` would receive {} variables",
+            command,
+            names.len()
+        );
+        println!("   Source: {}", source);
+        if let Some(env) = env {
+            println!("   Environment: {}", env);
+        }
+        for name in &names {
+            println!("   {}", name);
+        }
     }
 
     Ok(())
@@ -384,15 +1352,20 @@ fn run_tui() -> Result<()> {
     let mut terminal = tui::init()?;
     let mut app = App::new();
     let mut locker: Option<Locker> = None;
+    // Copied secret waiting to be cleared from the clipboard
+    let mut clipboard_clear: Option<executor::ClipboardClear> = None;
     let work_dir = std::env::current_dir()?;
 
     // Always require passphrase to enable full functionality (add/delete secrets)
     app.enter_init_mode();
+    app.creating_locker = !config::Config::get_locker_dir()?.join("salt").exists();
 
     // Update usages at startup
     app.update_token_usages(&work_dir);
 
     loop {
+        app.expire_jump(std::time::Instant::now());
+        app.expire_reveal(std::time::Instant::now());
         terminal.draw(|frame| ui::render(&app, frame))?;
 
         // Use 100ms poll timeout for better compatibility with various terminals (e.g., Ghostty)
@@ -402,265 +1375,448 @@ fn run_tui() -> Result<()> {
             // Clear status message on any key press
             app.clear_status();
 
-            let prev_selected = app.selected_index;
+            let prev_selected = app.get_selected_secret_name();
 
             // Handle special actions before general key handling
-            let handled = match (&app.mode, &app.modal, key.code) {
-                // Passphrase validation
-                (Mode::InitPassphrase, _, KeyCode::Enter) => {
-                    let passphrase_str = String::from_utf8_lossy(&app.passphrase);
-                    match Locker::init_or_load_with_passphrase(&passphrase_str) {
-                        Ok(l) => {
-                            locker = Some(l);
-                            app.initialized = true;
-                            app.mode = Mode::Normal;
-                            if let Some(ref l) = locker
-                                && let Some(key) = l.get_key()
-                            {
-                                let store = SecretsStore::load(l.base_dir(), key)?;
-
-                                // Don't start agent during TUI session - will be started on exit
-                                // This ensures TUI has exclusive write access to the store
-                                app.set_status("✅ Locker unlocked".to_string());
+            // (skipped while jumping, so typed letters don't trigger actions)
+            let handled = !app.is_jumping()
+                && match (&app.mode, &app.modal, key.code) {
+                    // Passphrase validation
+                    (Mode::InitPassphrase, _, KeyCode::Enter) => {
+                        let passphrase_str = String::from_utf8_lossy(&app.passphrase);
+                        match Locker::init_or_load_with_passphrase(&passphrase_str) {
+                            Ok(l) => {
+                                locker = Some(l);
+                                app.initialized = true;
+                                app.mode = Mode::Normal;
+                                if let Some(ref l) = locker
+                                    && let Some(key) = l.get_key()
+                                {
+                                    let store = SecretsStore::load(l.base_dir(), key)?;
+
+                                    // Don't start agent during TUI session - will be started on exit
+                                    // so it serves the final state; writes themselves are serialized by secrets.lock
+                                    let days = app.config.store.warn_expiring_days;
+                                    match store.expiring_summary(days) {
+                                        Some(summary) => app.set_status(format!("⚠️  {}", summary)),
+                                        None => app.set_status("✅ Locker unlocked".to_string()),
+                                    }
 
-                                app.secrets_store = Some(store);
+                                    app.secrets_store = Some(store);
+                                    app.restore_ui_state(UiState::load(l.base_dir()));
+                                }
+                                app.passphrase.zeroize();
+                                app.update_token_usages(&work_dir);
                             }
-                            app.passphrase.zeroize();
-                            app.update_token_usages(&work_dir);
+                            Err(e) => app.set_error(e.to_string()),
                         }
-                        Err(e) => app.set_error(e.to_string()),
+                        true
                     }
-                    true
-                }
-                // Add secret - validate with Enter on Expiration field
-                (Mode::Normal, Modal::AddSecret, KeyCode::Enter)
-                    if app.current_field == Field::Expiration =>
-                {
-                    if !app.new_secret_name.is_empty() && !app.new_secret_value.is_empty() {
-                        let expiration_days = app.get_expiration_days();
-                        let name = app.new_secret_name.clone();
-                        let value = app.new_secret_value.clone();
-
-                        if let Some(ref mut store) = app.secrets_store {
-                            if let Some(ref l) = locker {
-                                if let Some(key) = l.get_key() {
-                                    match store.add_secret(
-                                        name,
-                                        value,
-                                        expiration_days,
-                                        l.base_dir(),
-                                        key,
-                                    ) {
-                                        Ok(_) => {
-                                            app.new_secret_name.clear();
-                                            app.new_secret_value.zeroize();
-                                            app.new_secret_expiration.clear();
-                                            app.close_modal();
-                                            app.set_status(
-                                                "✓ Secret added successfully".to_string(),
-                                            );
-                                            app.update_token_usages(&work_dir);
+                    // Add secret - validate with Enter on Expiration field, or confirm
+                    // replacing an existing secret
+                    (Mode::Normal, Modal::AddSecret, KeyCode::Enter)
+                    | (
+                        Mode::Normal,
+                        Modal::OverwriteConfirm,
+                        KeyCode::Char('y') | KeyCode::Enter,
+                    ) if app.current_field == Field::Expiration
+                        || app.modal == Modal::OverwriteConfirm =>
+                    {
+                        if !app.new_secret_name.is_empty() && !app.new_secret_value.is_empty() {
+                            match app.get_expiration() {
+                                Err(e) => app.set_error(e),
+                                // Nothing is written until the overwrite is confirmed
+                                Ok(_) if app.hold_for_overwrite() => {}
+                                Ok(expires_at) => {
+                                    let name = app.new_secret_name.clone();
+                                    let value = app.new_secret_value.clone();
+
+                                    if let Some(ref mut store) = app.secrets_store {
+                                        if let Some(ref l) = locker {
+                                            if let Some(key) = l.get_key() {
+                                                match store.add_secret_at(
+                                                    name,
+                                                    value,
+                                                    expires_at,
+                                                    l.base_dir(),
+                                                    key,
+                                                ) {
+                                                    Ok(_) => {
+                                                        app.new_secret_name.clear();
+                                                        app.new_secret_value.zeroize();
+                                                        app.new_secret_expiration.clear();
+                                                        app.close_modal();
+                                                        app.set_status(
+                                                            "✓ Secret added successfully"
+                                                                .to_string(),
+                                                        );
+                                                        app.update_token_usages(&work_dir);
+                                                    }
+                                                    Err(e) => app.set_error(e.to_string()),
+                                                }
+                                            } else {
+                                                app.set_error(
+                                                    "Encryption key not available".to_string(),
+                                                );
+                                            }
+                                        } else {
+                                            app.set_error("Locker not initialized".to_string());
+                                        }
+                                    } else {
+                                        app.set_error("Secrets store not loaded".to_string());
+                                    }
+                                }
+                            }
+                        } else if app.new_secret_name.is_empty() {
+                            app.set_error("Name is required".to_string());
+                        } else {
+                            app.set_error("Value is required".to_string());
+                        }
+                        true
+                    }
+                    // Delete confirmation
+                    (Mode::Normal, Modal::DeleteConfirm, KeyCode::Char('y'))
+                    | (Mode::Normal, Modal::DeleteConfirm, KeyCode::Enter) => {
+                        if let Some(secret_name) = app.get_selected_secret_name() {
+                            if let Some(ref mut store) = app.secrets_store {
+                                if let Some(ref l) = locker {
+                                    if let Some(key) = l.get_key() {
+                                        match store.delete_secret(&secret_name, l.base_dir(), key) {
+                                            Ok(_) => {
+                                                let count = app.secrets_count();
+                                                if count > 0 && app.selected_index >= count {
+                                                    app.selected_index = count - 1;
+                                                }
+                                                app.close_modal();
+                                                app.set_status("✓ Secret deleted".to_string());
+                                                app.update_token_usages(&work_dir);
+                                            }
+                                            Err(e) => app.set_error(e.to_string()),
                                         }
-                                        Err(e) => app.set_error(e.to_string()),
+                                    } else {
+                                        app.set_error("Encryption key not available".to_string());
                                     }
                                 } else {
-                                    app.set_error("Encryption key not available".to_string());
+                                    app.set_error("Locker not initialized".to_string());
                                 }
                             } else {
-                                app.set_error("Locker not initialized".to_string());
+                                app.set_error("Secrets store not loaded".to_string());
                             }
-                        } else {
-                            app.set_error("Secrets store not loaded".to_string());
                         }
-                    } else if app.new_secret_name.is_empty() {
-                        app.set_error("Name is required".to_string());
-                    } else {
-                        app.set_error("Value is required".to_string());
+                        true
                     }
-                    true
-                }
-                // Delete confirmation
-                (Mode::Normal, Modal::DeleteConfirm, KeyCode::Char('y'))
-                | (Mode::Normal, Modal::DeleteConfirm, KeyCode::Enter) => {
-                    if let Some(secret_name) = app.get_selected_secret_name() {
-                        if let Some(ref mut store) = app.secrets_store {
-                            if let Some(ref l) = locker {
-                                if let Some(key) = l.get_key() {
-                                    match store.delete_secret(&secret_name, l.base_dir(), key) {
-                                        Ok(_) => {
-                                            let count = app.secrets_count();
-                                            if count > 0 && app.selected_index >= count {
-                                                app.selected_index = count - 1;
+                    // Rename confirmation
+                    (Mode::Normal, Modal::Rename, KeyCode::Enter) => {
+                        if let Some(old_name) = app.get_selected_secret_name() {
+                            let new_name = app.rename_input.trim().to_string();
+                            if new_name == old_name {
+                                app.close_modal();
+                            } else if let Some(ref mut store) = app.secrets_store {
+                                if let Some(ref l) = locker {
+                                    if let Some(key) = l.get_key() {
+                                        match store.rename_secret(
+                                            &old_name,
+                                            &new_name,
+                                            l.base_dir(),
+                                            key,
+                                        ) {
+                                            Ok(_) => {
+                                                app.select_secret(&new_name);
+                                                app.close_modal();
+                                                app.set_status(format!(
+                                                    "✓ Renamed to '{}'",
+                                                    new_name
+                                                ));
+                                                app.update_token_usages(&work_dir);
                                             }
-                                            app.close_modal();
-                                            app.set_status("✓ Secret deleted".to_string());
-                                            app.update_token_usages(&work_dir);
+                                            Err(e) => app.set_error(e.to_string()),
                                         }
-                                        Err(e) => app.set_error(e.to_string()),
+                                    } else {
+                                        app.set_error("Encryption key not available".to_string());
                                     }
                                 } else {
-                                    app.set_error("Encryption key not available".to_string());
+                                    app.set_error("Locker not initialized".to_string());
                                 }
                             } else {
-                                app.set_error("Locker not initialized".to_string());
+                                app.set_error("Secrets store not loaded".to_string());
                             }
-                        } else {
-                            app.set_error("Secrets store not loaded".to_string());
                         }
+                        true
                     }
-                    true
-                }
-                // Reveal secret with 'e'
-                (Mode::Normal, Modal::None, KeyCode::Char('e')) => {
-                    if let Some(secret_name) = app.get_selected_secret_name() {
-                        if app.revealed_secret.is_some() {
-                            if let Some(ref mut revealed) = app.revealed_secret {
-                                revealed.zeroize();
+                    // Reveal secret with 'e'
+                    (Mode::Normal, Modal::None | Modal::Detail, code)
+                        if app.config.keybindings.action(code) == Some(KeyAction::Reveal) =>
+                    {
+                        if let Some(secret_name) = app.get_selected_secret_name() {
+                            if app.revealed_secret.is_some() {
+                                app.hide_revealed();
+                            } else if let Some(refusal) = app.reveal_refusal() {
+                                app.set_error(refusal);
+                            } else if let Some(ref store) = app.secrets_store
+                                && let Some(ref l) = locker
+                                && let Some(key) = l.get_key()
+                            {
+                                match store.decrypt_secret(&secret_name, key) {
+                                    Ok(decrypted) => app.reveal(decrypted),
+                                    Err(e) => app.set_error(e.to_string()),
+                                }
                             }
-                            app.revealed_secret = None;
-                        } else if let Some(ref store) = app.secrets_store
+                        }
+                        true
+                    }
+                    // Show or hide the live code of a TOTP secret with 'o'
+                    (Mode::Normal, Modal::None, KeyCode::Char('o')) => {
+                        if app.totp_seed.is_some() {
+                            app.hide_totp();
+                        } else if let Some(secret_name) = app.get_selected_secret_name()
+                            && let Some(ref store) = app.secrets_store
                             && let Some(ref l) = locker
                             && let Some(key) = l.get_key()
+                            && let Some(secret) = store.get_secret(&secret_name)
                         {
-                            match store.decrypt_secret(&secret_name, key) {
-                                Ok(decrypted) => {
-                                    app.revealed_secret = Some(decrypted);
+                            if secret.kind == SecretKind::Totp {
+                                match secret.decrypt_for(None, key) {
+                                    Ok(seed) => app.totp_seed = Some(seed),
+                                    Err(e) => app.set_error(e.to_string()),
                                 }
-                                Err(e) => app.set_error(e.to_string()),
+                            } else {
+                                app.set_status(format!("'{}' is not a TOTP seed", secret_name));
                             }
                         }
+                        true
                     }
-                    true
-                }
-                // Copy to clipboard with 'y'
-                (Mode::Normal, Modal::None, KeyCode::Char('y')) => {
-                    if let Some(secret_name) = app.get_selected_secret_name()
-                        && let Some(ref store) = app.secrets_store
-                        && let Some(ref l) = locker
-                        && let Some(key) = l.get_key()
+                    // Copy to clipboard with 'y'
+                    (Mode::Normal, Modal::None, code)
+                        if app.config.keybindings.action(code) == Some(KeyAction::Copy) =>
                     {
-                        match store.decrypt_secret(&secret_name, key) {
-                            Ok(mut decrypted) => {
-                                match executor::copy_to_clipboard(&decrypted) {
-                                    Ok(_) => {
-                                        app.set_status(format!(
-                                            "✓ '{}' copied to clipboard",
-                                            secret_name
-                                        ));
+                        if let Some(secret_name) = app.get_selected_secret_name()
+                            && let Some(ref store) = app.secrets_store
+                            && let Some(ref l) = locker
+                            && let Some(key) = l.get_key()
+                        {
+                            match store.decrypt_secret(&secret_name, key) {
+                                Ok(mut decrypted) => {
+                                    match executor::copy_to_clipboard(&decrypted) {
+                                        Ok(_) => {
+                                            if let Some(previous) = clipboard_clear.take() {
+                                                previous.cancel();
+                                            }
+                                            let secs = app.config.clipboard.clear_after_secs;
+                                            if secs > 0 {
+                                                clipboard_clear =
+                                                    Some(executor::ClipboardClear::schedule(
+                                                        &decrypted,
+                                                        std::time::Duration::from_secs(secs),
+                                                    ));
+                                                app.set_status(format!(
+                                                    "✓ '{}' copied (clears in {}s)",
+                                                    secret_name, secs
+                                                ));
+                                            } else {
+                                                app.set_status(format!(
+                                                    "✓ '{}' copied to clipboard",
+                                                    secret_name
+                                                ));
+                                            }
+                                        }
+                                        Err(e) => app.set_error(format!("Clipboard error: {}", e)),
                                     }
-                                    Err(e) => app.set_error(format!("Clipboard error: {}", e)),
+                                    decrypted.zeroize();
                                 }
-                                decrypted.zeroize();
+                                Err(e) => app.set_error(e.to_string()),
                             }
-                            Err(e) => app.set_error(e.to_string()),
                         }
+                        true
                     }
-                    true
-                }
-                // Command modal - execute command with Enter
-                (Mode::Normal, Modal::Command, KeyCode::Enter) => {
-                    if let Some(cmd) = app.get_selected_command() {
-                        match cmd {
-                            "env" => {
-                                if let (Some(store), Some(l)) = (&app.secrets_store, &locker) {
-                                    if let Some(key) = l.get_key() {
-                                        let env_path = work_dir.join(".env");
-                                        match executor::generate_env_file(store, key, &env_path) {
-                                            Ok(_) => {
-                                                app.set_status(format!(
-                                                    "✓ .env generated: {}",
-                                                    env_path.display()
-                                                ));
+                    // Command modal - execute command with Enter
+                    (Mode::Normal, Modal::Command, KeyCode::Enter) => {
+                        if let Some(cmd) = app.get_selected_command() {
+                            // `:env mask` and `:json mask` write placeholders instead of values
+                            let mask = app.get_command_argument() == Some("mask");
+                            match cmd {
+                                "env" => {
+                                    if let (Some(store), Some(l)) = (&app.secrets_store, &locker) {
+                                        if let Some(key) = l.get_key() {
+                                            let env_path = work_dir.join(".env");
+                                            match executor::generate_env_file(
+                                                store, key, &env_path, mask,
+                                            ) {
+                                                Ok(_) => {
+                                                    app.set_status(format!(
+                                                        "✓ .env generated: {}",
+                                                        env_path.display()
+                                                    ));
+                                                }
+                                                Err(e) => app.set_error(format!("Error: {}", e)),
                                             }
+                                        } else {
+                                            app.set_error(
+                                                "Encryption key not available".to_string(),
+                                            );
+                                        }
+                                    } else {
+                                        app.set_error("Locker not initialized".to_string());
+                                    }
+                                }
+                                "env-example" => {
+                                    if let Some(ref store) = app.secrets_store {
+                                        let path = work_dir.join(".env.example");
+                                        match executor::generate_env_template(store, &path) {
+                                            Ok(_) => app.set_status(format!(
+                                                "✓ .env.example generated: {}",
+                                                path.display()
+                                            )),
                                             Err(e) => app.set_error(format!("Error: {}", e)),
                                         }
                                     } else {
-                                        app.set_error("Encryption key not available".to_string());
+                                        app.set_error("Locker not initialized".to_string());
                                     }
-                                } else {
-                                    app.set_error("Locker not initialized".to_string());
                                 }
-                            }
-                            "bash" | "zsh" | "fish" => {
-                                if let (Some(store), Some(l)) = (&app.secrets_store, &locker) {
-                                    if let Some(key) = l.get_key() {
-                                        match executor::export_to_shell_profile(store, key, cmd) {
-                                            Ok(path) => {
-                                                app.set_status(format!(
-                                                    "✓ Exported to {}",
-                                                    path.display()
-                                                ));
+                                "bash" | "zsh" | "fish" => {
+                                    if let (Some(store), Some(l)) = (&app.secrets_store, &locker) {
+                                        if let Some(key) = l.get_key() {
+                                            match executor::export_to_shell_profile(store, key, cmd)
+                                            {
+                                                Ok(path) => {
+                                                    app.set_status(format!(
+                                                        "✓ Exported to {}",
+                                                        path.display()
+                                                    ));
+                                                }
+                                                Err(e) => app.set_error(format!("Error: {}", e)),
                                             }
-                                            Err(e) => app.set_error(format!("Error: {}", e)),
+                                        } else {
+                                            app.set_error(
+                                                "Encryption key not available".to_string(),
+                                            );
                                         }
                                     } else {
-                                        app.set_error("Encryption key not available".to_string());
+                                        app.set_error("Locker not initialized".to_string());
                                     }
-                                } else {
-                                    app.set_error("Locker not initialized".to_string());
                                 }
-                            }
-                            "json" => {
-                                if let (Some(store), Some(l)) = (&app.secrets_store, &locker) {
-                                    if let Some(key) = l.get_key() {
-                                        let json_path = work_dir.join("secrets.json");
-                                        match executor::export_to_json(store, key, &json_path) {
-                                            Ok(_) => {
+                                "json" => {
+                                    if let (Some(store), Some(l)) = (&app.secrets_store, &locker) {
+                                        if let Some(key) = l.get_key() {
+                                            let json_path = work_dir.join("secrets.json");
+                                            match executor::export_to_json(
+                                                store, key, &json_path, mask,
+                                            ) {
+                                                Ok(_) => {
+                                                    app.set_status(format!(
+                                                        "✓ JSON exported: {}",
+                                                        json_path.display()
+                                                    ));
+                                                }
+                                                Err(e) => app.set_error(format!("Error: {}", e)),
+                                            }
+                                        } else {
+                                            app.set_error(
+                                                "Encryption key not available".to_string(),
+                                            );
+                                        }
+                                    } else {
+                                        app.set_error("Locker not initialized".to_string());
+                                    }
+                                }
+                                "get-file" => {
+                                    if let Some(secret_name) = app.get_selected_secret_name()
+                                        && let Some(ref store) = app.secrets_store
+                                        && let Some(ref l) = locker
+                                        && let Some(key) = l.get_key()
+                                    {
+                                        let file_path = match app.get_command_argument() {
+                                            Some(path) => work_dir.join(path),
+                                            None => work_dir.join(&secret_name),
+                                        };
+                                        match store.decrypt_secret(&secret_name, key) {
+                                            Ok(mut decrypted) => {
+                                                match executor::write_secret_file(
+                                                    &decrypted, &file_path,
+                                                ) {
+                                                    Ok(_) => app.set_status(format!(
+                                                        "✓ '{}' written to {}",
+                                                        secret_name,
+                                                        file_path.display()
+                                                    )),
+                                                    Err(e) => {
+                                                        app.set_error(format!("Error: {}", e))
+                                                    }
+                                                }
+                                                decrypted.zeroize();
+                                            }
+                                            Err(e) => app.set_error(e.to_string()),
+                                        }
+                                    } else {
+                                        app.set_error("No secret selected".to_string());
+                                    }
+                                }
+                                "prune" => {
+                                    if let (Some(store), Some(l)) =
+                                        (&mut app.secrets_store, &locker)
+                                        && let Some(key) = l.get_key()
+                                    {
+                                        match store.prune_expired(0, l.base_dir(), key) {
+                                            Ok(removed) if removed.is_empty() => app.set_status(
+                                                "ℹ No expired secrets to prune".to_string(),
+                                            ),
+                                            Ok(removed) => {
+                                                app.selected_index = app
+                                                    .selected_index
+                                                    .min(app.secrets_count().saturating_sub(1));
                                                 app.set_status(format!(
-                                                    "✓ JSON exported: {}",
-                                                    json_path.display()
+                                                    "✓ Pruned {} expired secret(s): {}",
+                                                    removed.len(),
+                                                    removed.join(", ")
                                                 ));
                                             }
                                             Err(e) => app.set_error(format!("Error: {}", e)),
                                         }
                                     } else {
-                                        app.set_error("Encryption key not available".to_string());
+                                        app.set_error("Locker not initialized".to_string());
                                     }
-                                } else {
-                                    app.set_error("Locker not initialized".to_string());
                                 }
-                            }
-                            "clear" => match executor::clear_shell_exports() {
-                                Ok(cleared) if !cleared.is_empty() => {
-                                    let paths: Vec<_> = cleared
-                                        .iter()
-                                        .map(|p| {
-                                            p.file_name()
-                                                .unwrap_or_default()
-                                                .to_string_lossy()
-                                                .to_string()
-                                        })
-                                        .collect();
-                                    app.set_status(format!(
-                                        "✓ Cleared exports from: {}",
-                                        paths.join(", ")
-                                    ));
-                                }
-                                Ok(_) => {
-                                    app.set_status("ℹ No exports found to clear".to_string());
+                                "clear" => match executor::clear_shell_exports() {
+                                    Ok(cleared) if !cleared.is_empty() => {
+                                        let paths: Vec<_> = cleared
+                                            .iter()
+                                            .map(|p| {
+                                                p.file_name()
+                                                    .unwrap_or_default()
+                                                    .to_string_lossy()
+                                                    .to_string()
+                                            })
+                                            .collect();
+                                        app.set_status(format!(
+                                            "✓ Cleared exports from: {}",
+                                            paths.join(", ")
+                                        ));
+                                    }
+                                    Ok(_) => {
+                                        app.set_status("ℹ No exports found to clear".to_string());
+                                    }
+                                    Err(e) => app.set_error(format!("Error: {}", e)),
+                                },
+                                _ => {
+                                    app.set_error(format!("Unknown command: {}", cmd));
                                 }
-                                Err(e) => app.set_error(format!("Error: {}", e)),
-                            },
-                            _ => {
-                                app.set_error(format!("Unknown command: {}", cmd));
                             }
+                            app.close_modal();
+                        } else if !app.command_input.is_empty() {
+                            app.set_error(format!("Unknown command: {}", app.command_input));
+                            app.close_modal();
                         }
-                        app.close_modal();
-                    } else if !app.command_input.is_empty() {
-                        app.set_error(format!("Unknown command: {}", app.command_input));
-                        app.close_modal();
+                        true
                     }
-                    true
-                }
-                _ => false,
-            };
+                    _ => false,
+                };
 
             if !handled {
                 app.handle_key(key.code);
             }
 
             // Update usages if selection has changed
-            if app.selected_index != prev_selected {
+            if app.get_selected_secret_name() != prev_selected {
+                app.hide_totp();
                 app.update_token_usages(&work_dir);
             }
         }
@@ -672,14 +1828,29 @@ fn run_tui() -> Result<()> {
 
     tui::restore()?;
 
+    // Reopen on the same secret and view next time
+    if let Some(ref l) = locker
+        && app.secrets_store.is_some()
+        && let Err(e) = app.ui_state().save(l.base_dir())
+    {
+        println!("⚠️ Could not save the UI state: {}", e);
+    }
+
+    // Clipboard helpers outlive the process, so clear a pending copy now
+    if let Some(pending) = clipboard_clear {
+        pending.flush();
+    }
+
     // Start agent on exit if locker was initialized (for SDKs to use)
     if let Some(ref l) = locker
         && let Some(key) = l.get_key()
         && let Some(ref store) = app.secrets_store
         && !agent::is_agent_running()
     {
-        match agent::start_daemon(key.to_vec(), store.clone()) {
-            Ok(_) => println!("✅ Agent started (8h TTL)"),
+        match agent::resolve_ttl_hours(None).and_then(|ttl| {
+            agent::start_daemon(key.to_vec(), store.clone(), Some(ttl)).map(|_| ttl)
+        }) {
+            Ok(ttl) => print_agent_started(ttl),
             Err(e) => println!("⚠️ Could not start agent: {}", e),
         }
     }
@@ -1,4 +1,8 @@
-use crate::app::{App, Field, Modal, Mode};
+use crate::app::{App, Field, Modal, Mode, SortMode};
+use crate::core::format;
+use crate::core::init::{Strength, estimate_strength};
+use crate::core::store::format_timestamp;
+use crate::core::totp;
 use ratatui::{
     Frame,
     layout::{Alignment, Constraint, Direction, Layout, Rect},
@@ -56,9 +60,15 @@ pub fn render(app: &App, frame: &mut Frame) {
     match app.modal {
         Modal::AddSecret => render_add_secret_modal(app, frame),
         Modal::DeleteConfirm => render_delete_confirm_modal(app, frame),
+        Modal::OverwriteConfirm => {
+            render_add_secret_modal(app, frame);
+            render_overwrite_confirm_modal(app, frame);
+        }
         Modal::Help => render_help_modal(frame),
         Modal::Command => render_command_modal(app, frame),
-        Modal::None => {}
+        Modal::Rename => render_rename_modal(app, frame),
+        Modal::Detail => render_detail_modal(app, frame),
+        Modal::Search | Modal::None => {}
     }
 
     // Render persistent footer
@@ -87,11 +97,24 @@ fn render_passphrase_input(app: &App, area: Rect, frame: &mut Frame) {
 
     let passphrase_str = String::from_utf8_lossy(&app.passphrase);
     let masked_passphrase = "*".repeat(passphrase_str.len());
-    let mut input_text = format!("Passphrase: {}", masked_passphrase);
+    let mut lines = vec![Line::from(format!("Passphrase: {}", masked_passphrase))];
+    if app.creating_locker && !passphrase_str.is_empty() {
+        let strength = estimate_strength(&passphrase_str);
+        let color = match strength {
+            Strength::Weak => theme::RED,
+            Strength::Fair => theme::YELLOW,
+            Strength::Strong => theme::GREEN,
+        };
+        lines.push(Line::from(vec![
+            Span::raw("Strength:   "),
+            Span::styled(strength.label(), Style::default().fg(color).bold()),
+        ]));
+    }
     if let Some(ref error) = app.error_message {
-        input_text.push_str(&format!("\n\n❌ Error: {}", error));
+        lines.push(Line::from(""));
+        lines.push(Line::from(format!("❌ Error: {}", error)));
     }
-    let input = Paragraph::new(input_text)
+    let input = Paragraph::new(lines)
         .style(Style::default().fg(if app.error_message.is_some() {
             theme::RED
         } else {
@@ -146,9 +169,28 @@ fn render_main(app: &App, area: Rect, frame: &mut Frame) {
 
 fn render_secrets_list(app: &App, area: Rect, frame: &mut Frame) {
     let count = app.secrets_count();
+    let mut title = if app.search_query.is_empty() && app.modal != Modal::Search {
+        " Secrets (↑↓ navigate) ".to_string()
+    } else {
+        let cursor = if app.modal == Modal::Search { "_" } else { "" };
+        format!(" Secrets /{}{} ", app.search_query, cursor)
+    };
+    if let Some(ref tag) = app.tag_filter {
+        title.push_str(&format!("· #{} ", tag));
+    }
+    if app.sort_mode != SortMode::Name {
+        title.push_str(&format!("· by {} ", app.sort_mode.label()));
+    }
 
     if count == 0 {
-        let empty_msg = Paragraph::new("No secrets. Press 'a' to add one.")
+        let message = if !app.search_query.is_empty() {
+            format!("No secrets match '{}'", app.search_query)
+        } else if let Some(ref tag) = app.tag_filter {
+            format!("No secrets tagged #{}", tag)
+        } else {
+            "No secrets. Press 'a' to add one.".to_string()
+        };
+        let empty_msg = Paragraph::new(message)
             .style(Style::default().fg(theme::COMMENT))
             .alignment(Alignment::Center)
             .block(
@@ -156,18 +198,16 @@ fn render_secrets_list(app: &App, area: Rect, frame: &mut Frame) {
                     .borders(Borders::ALL)
                     .border_style(Style::default().fg(theme::BLUE))
                     .style(Style::default().bg(theme::BG_DARK))
-                    .title(" Secrets "),
+                    .title(title),
             );
         frame.render_widget(empty_msg, area);
         return;
     }
 
     // Build items from agent_secrets or store
-    let items: Vec<ListItem> = if let Some(ref secrets) = app.agent_secrets {
+    let names = app.filtered_secret_names();
+    let items: Vec<ListItem> = if app.agent_secrets.is_some() {
         // Agent mode: display from agent_secrets
-        let mut names: Vec<_> = secrets.keys().collect();
-        names.sort();
-
         names
             .iter()
             .enumerate()
@@ -176,7 +216,15 @@ fn render_secrets_list(app: &App, area: Rect, frame: &mut Frame) {
                 let prefix = if is_selected { "▶ " } else { "  " };
 
                 let value_display = if is_selected {
-                    if let Some(ref revealed) = app.revealed_secret {
+                    if let Some(ref seed) = app.totp_seed {
+                        let now = std::time::SystemTime::now();
+                        match totp::generate_totp(seed, now) {
+                            Ok(code) => {
+                                format!("⏱ {} ({}s)", code, totp::seconds_remaining(now))
+                            }
+                            Err(_) => "⏱ invalid seed".to_string(),
+                        }
+                    } else if let Some(ref revealed) = app.revealed_secret {
                         revealed.clone()
                     } else {
                         "********".to_string()
@@ -200,9 +248,9 @@ fn render_secrets_list(app: &App, area: Rect, frame: &mut Frame) {
             .collect()
     } else if let Some(ref store) = app.secrets_store {
         // Normal mode: display from store
-        store
-            .list_secrets()
+        names
             .iter()
+            .filter_map(|name| store.get_secret(name))
             .enumerate()
             .map(|(i, s)| {
                 let is_selected = i == app.selected_index;
@@ -243,14 +291,49 @@ fn render_secrets_list(app: &App, area: Rect, frame: &mut Frame) {
             .borders(Borders::ALL)
             .border_style(Style::default().fg(theme::PURPLE))
             .style(Style::default().bg(theme::BG_DARK))
-            .title(" Secrets (↑↓ navigate) "),
+            .title(title),
     );
     frame.render_widget(list, area);
 }
 
 fn render_token_usages(app: &App, area: Rect, frame: &mut Frame) {
+    let selected = app.get_selected_secret_name().and_then(|name| {
+        app.secrets_store
+            .as_ref()
+            .and_then(|store| store.get_secret(&name))
+    });
+
+    // The selected secret's note, in full, above its usages
+    let area = match selected.and_then(|secret| secret.description.as_deref()) {
+        Some(note) => {
+            let width = area.width.saturating_sub(2).max(1) as usize;
+            let lines = note.chars().count().div_ceil(width).clamp(1, 4) as u16;
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(lines + 2), Constraint::Min(1)])
+                .split(area);
+            let paragraph = Paragraph::new(note)
+                .style(Style::default().fg(theme::FG))
+                .wrap(Wrap { trim: true })
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .border_style(Style::default().fg(theme::TEAL))
+                        .style(Style::default().bg(theme::BG_DARK))
+                        .title(" Note "),
+                );
+            frame.render_widget(paragraph, chunks[0]);
+            chunks[1]
+        }
+        None => area,
+    };
+
     let title = if let Some(name) = app.get_selected_secret_name() {
-        format!(" Usage of '{}' ", name)
+        let added = selected.and_then(|secret| secret.added_display());
+        match added {
+            Some(added) => format!(" Usage of '{}' · {} ", name, added),
+            None => format!(" Usage of '{}' ", name),
+        }
     } else {
         " Usage ".to_string()
     };
@@ -282,11 +365,17 @@ fn render_token_usages(app: &App, area: Rect, frame: &mut Frame) {
         }
     };
 
+    // Window into the files that fits the panel, from `usages_scroll`
+    let files = report.files_sorted();
+    let rows = (area.height.saturating_sub(2) as usize).max(1);
+    app.usages_rows.set(rows);
+    let start = app.usages_scroll.min(files.len().saturating_sub(rows));
+    let end = (start + rows).min(files.len());
+    let width = area.width.saturating_sub(2) as usize;
+
     // Build list items from analysis report
-    let items: Vec<ListItem> = report
-        .files_sorted()
+    let items: Vec<ListItem> = files[start..end]
         .iter()
-        .take(20) // Limit to 20 results
         .map(|file| {
             let filename = file
                 .path
@@ -303,6 +392,7 @@ fn render_token_usages(app: &App, area: Rect, frame: &mut Frame) {
                 let text = format!("✓  {} ({} calls)", filename, file.call_count);
                 (text, Style::default().fg(theme::GREEN))
             };
+            let display = format::truncate_chars(&display, width);
 
             ListItem::new(display).style(style)
         })
@@ -326,17 +416,28 @@ fn render_token_usages(app: &App, area: Rect, frame: &mut Frame) {
         )
     };
 
-    let list = List::new(items).block(
-        Block::default()
-            .borders(Borders::ALL)
-            .border_style(Style::default().fg(if report.exposure_count > 0 {
-                theme::YELLOW
-            } else {
-                theme::TEAL
-            }))
-            .style(Style::default().bg(theme::BG_DARK))
-            .title(title_with_count),
-    );
+    let mut block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(if report.exposure_count > 0 {
+            theme::YELLOW
+        } else {
+            theme::TEAL
+        }))
+        .style(Style::default().bg(theme::BG_DARK))
+        .title(title_with_count);
+    if files.len() > rows {
+        block = block.title_bottom(
+            Line::from(format!(
+                " showing {}–{} of {} · PgUp/PgDn ",
+                start + 1,
+                end,
+                files.len()
+            ))
+            .right_aligned(),
+        );
+    }
+
+    let list = List::new(items).block(block);
     frame.render_widget(list, area);
 }
 
@@ -424,22 +525,35 @@ fn render_add_secret_modal(app: &App, frame: &mut Frame) {
                 .title(" Name (Enter: next) "),
         );
 
-    // Display token in plain text (not masked)
-    let value_input = Paragraph::new(app.new_secret_value.as_str())
-        .style(value_style)
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .border_style(Style::default().fg(if app.current_field == Field::Value {
-                    theme::CYAN
-                } else {
-                    theme::FG_DARK
-                }))
-                .title(" Plain text token (Enter: next) "),
-        );
+    // Plain text unless masked with F3, e.g. while sharing the screen
+    let masked;
+    let (value_text, value_title) = if app.mask_new_value {
+        masked = masked_input(&app.new_secret_value);
+        (
+            masked.as_str(),
+            " Token, masked (Enter: next, F2: generate, F3: show) ",
+        )
+    } else {
+        (
+            app.new_secret_value.as_str(),
+            " Plain text token (Enter: next, F2: generate, F3: mask) ",
+        )
+    };
+    let value_input = Paragraph::new(value_text).style(value_style).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(if app.current_field == Field::Value {
+                theme::CYAN
+            } else {
+                theme::FG_DARK
+            }))
+            .title(value_title),
+    );
 
     let expiration_display = if app.new_secret_expiration.is_empty() {
         "Permanent (empty = no expiration)".to_string()
+    } else if app.new_secret_expiration.contains('-') {
+        format!("Until {}", app.new_secret_expiration)
     } else {
         format!("{} days", app.new_secret_expiration)
     };
@@ -455,12 +569,20 @@ fn render_add_secret_modal(app: &App, frame: &mut Frame) {
                         theme::FG_DARK
                     }),
                 )
-                .title(" Expiration in days (Enter: confirm) "),
+                .title(" Expiration in days or YYYY-MM-DD (Enter: confirm) "),
         );
 
-    let instructions = Paragraph::new("Tab: switch field | Enter: next/confirm | Esc: cancel")
+    let instructions = if let Some(ref error) = app.error_message {
+        Paragraph::new(format!("❌ {}", error))
+            .style(Style::default().fg(theme::RED))
+            .alignment(Alignment::Center)
+    } else {
+        Paragraph::new(
+            "Tab: switch field | Enter: next/confirm | F2: generate | F3: mask | Esc: cancel",
+        )
         .style(Style::default().fg(theme::COMMENT))
-        .alignment(Alignment::Center);
+        .alignment(Alignment::Center)
+    };
 
     frame.render_widget(name_input, chunks[0]);
     frame.render_widget(value_input, chunks[1]);
@@ -468,6 +590,11 @@ fn render_add_secret_modal(app: &App, frame: &mut Frame) {
     frame.render_widget(instructions, chunks[3]);
 }
 
+/// One `*` per character, so the length stays visible while typing
+fn masked_input(value: &str) -> String {
+    "*".repeat(value.chars().count())
+}
+
 fn render_delete_confirm_modal(app: &App, frame: &mut Frame) {
     let area = centered_rect(50, 30, frame.area());
 
@@ -498,6 +625,172 @@ fn render_delete_confirm_modal(app: &App, frame: &mut Frame) {
     frame.render_widget(paragraph, inner);
 }
 
+fn render_overwrite_confirm_modal(app: &App, frame: &mut Frame) {
+    let area = centered_rect(50, 30, frame.area());
+
+    frame.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" ⚠️ Confirm overwrite ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme::ORANGE))
+        .style(Style::default().bg(theme::BG_HIGHLIGHT));
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let text = format!(
+        "'{}' exists — overwrite its value?\n\n[Y] Yes  |  [N] No / Esc: back to the form",
+        app.new_secret_name
+    );
+
+    let paragraph = Paragraph::new(text)
+        .style(Style::default().fg(theme::FG))
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true });
+
+    frame.render_widget(paragraph, inner);
+}
+
+fn render_rename_modal(app: &App, frame: &mut Frame) {
+    let area = centered_rect(50, 30, frame.area());
+
+    frame.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" ✏️ Rename secret ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme::CYAN))
+        .style(Style::default().bg(theme::BG_HIGHLIGHT));
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let secret_name = app
+        .get_selected_secret_name()
+        .unwrap_or_else(|| "?".to_string());
+    let mut text = format!(
+        "New name for '{}':\n\n{}_\n\n[Enter] Rename  |  [Esc] Cancel",
+        secret_name, app.rename_input
+    );
+    if let Some(ref error) = app.error_message {
+        text.push_str(&format!("\n\n❌ {}", error));
+    }
+
+    let paragraph = Paragraph::new(text)
+        .style(Style::default().fg(theme::FG))
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true });
+
+    frame.render_widget(paragraph, inner);
+}
+
+fn render_detail_modal(app: &App, frame: &mut Frame) {
+    let area = centered_rect(60, 60, frame.area());
+
+    frame.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" 🔎 Secret details ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme::CYAN))
+        .style(Style::default().bg(theme::BG_HIGHLIGHT));
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let name = app
+        .get_selected_secret_name()
+        .unwrap_or_else(|| "?".to_string());
+    let value = app.revealed_secret.as_deref().unwrap_or("********");
+    let field = |label: &str, text: String| {
+        Line::from(vec![
+            Span::styled(format!("{:<9}", label), Style::default().fg(theme::COMMENT)),
+            Span::styled(text, Style::default().fg(theme::FG)),
+        ])
+    };
+
+    let mut lines = vec![
+        field("Name", name.clone()),
+        field("Value", value.to_string()),
+    ];
+    match app
+        .secrets_store
+        .as_ref()
+        .and_then(|store| store.get_secret(&name))
+    {
+        Some(secret) => {
+            let when = |timestamp: i64| {
+                if timestamp == 0 {
+                    "unknown".to_string()
+                } else {
+                    format_timestamp(timestamp)
+                }
+            };
+            let expires = match secret.expires_at {
+                Some(at) => format!("{} ({})", secret.expiration_display(), format_timestamp(at)),
+                None => secret.expiration_display(),
+            };
+            let tags = if secret.tags.is_empty() {
+                "none".to_string()
+            } else {
+                secret
+                    .tags
+                    .iter()
+                    .map(|tag| format!("#{}", tag))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            };
+            let usages = match &app.token_analysis {
+                Some(report) if !report.files.is_empty() => format!(
+                    "{} calls in {} files",
+                    report.total_calls,
+                    report.files.len()
+                ),
+                _ => match &app.analysis_skipped_reason {
+                    Some(reason) => reason.replace('\n', " "),
+                    None => "none in the current directory".to_string(),
+                },
+            };
+            lines.extend([
+                field("Expires", expires),
+                field("Created", when(secret.created_at)),
+                field("Updated", when(secret.updated_at)),
+                field("Tags", tags),
+                field(
+                    "Note",
+                    secret
+                        .description
+                        .clone()
+                        .unwrap_or_else(|| "none".to_string()),
+                ),
+                field(
+                    "Access",
+                    if secret.protected {
+                        "copy only (protected)".to_string()
+                    } else {
+                        "reveal and copy".to_string()
+                    },
+                ),
+                field("Usages", usages),
+            ]);
+        }
+        None => lines.push(Line::styled(
+            "Metadata is only shown when unlocked with the passphrase.",
+            Style::default().fg(theme::COMMENT),
+        )),
+    }
+    lines.push(Line::default());
+    lines.push(Line::styled(
+        "[e] Reveal/hide value  |  [Esc] Close",
+        Style::default().fg(theme::COMMENT),
+    ));
+
+    let paragraph = Paragraph::new(lines).wrap(Wrap { trim: false });
+
+    frame.render_widget(paragraph, inner);
+}
+
 fn render_help_modal(frame: &mut Frame) {
     let area = centered_rect(60, 70, frame.area());
 
@@ -515,19 +808,28 @@ fn render_help_modal(frame: &mut Frame) {
     let help_text = vec![
         "Navigation:",
         "  ↑/↓     Navigate between secrets",
+        "  g<abc>  Jump to the first secret starting with <abc>",
+        "  /       Filter secrets by name (Esc clears the filter)",
+        "  s       Sort by name / expiration / last update",
+        "  t       Filter by tag, cycling through the tags in use",
+        "  PgDn/PgUp  Scroll the usages panel",
         "",
         "Actions on secrets:",
         "  a       Add a new secret",
+        "  Enter   Show the details of the selected secret",
         "  e       Reveal/hide the selected token",
         "  y       Copy decrypted token to clipboard",
+        "  o       Show/hide the live code of a TOTP secret",
+        "  r       Rename the selected secret",
         "  d       Delete the selected secret",
         "",
         "Commands (press : to open):",
-        "  :env    Generate .env file (plain text)",
+        "  :env    Generate .env file (plain text, :env mask hides values)",
+        "  :env-example  Generate .env.example (names only)",
         "  :bash   Export to ~/.bashrc",
         "  :zsh    Export to ~/.zshrc",
         "  :fish   Export to fish config",
-        "  :json   Export as JSON file",
+        "  :json   Export as JSON file (:json mask hides values)",
         "  :clear  Remove exports from shell profiles",
         "",
         "General:",
@@ -537,6 +839,8 @@ fn render_help_modal(frame: &mut Frame) {
         "",
         "In the add form:",
         "  Tab     Switch field",
+        "  F2      Generate a random value",
+        "  F3      Mask / show the value",
         "  Enter   Go to next field / Confirm",
         "",
         "Press Esc or h to close",
@@ -622,17 +926,32 @@ fn render_command_modal(app: &App, frame: &mut Frame) {
 
 fn render_footer(app: &App, area: Rect, frame: &mut Frame) {
     // Display status message if it exists
+    let jump_text;
     let helper_text = if let Some(ref status) = app.status_message {
         status.as_str()
+    } else if let Some(ref query) = app.jump_query {
+        jump_text = format!("Jump to: {}_  (Esc: cancel)", query);
+        jump_text.as_str()
     } else {
         match (&app.mode, &app.modal) {
             (Mode::InitPassphrase, _) => "Type passphrase and Enter. Esc to quit.",
-            (_, Modal::AddSecret) => "Tab: field | Enter: next/confirm | Esc: cancel",
+            (_, Modal::AddSecret) => {
+                "Tab: field | Enter: next/confirm | F2: generate value | F3: mask | Esc: cancel"
+            }
             (_, Modal::DeleteConfirm) => "Y: confirm | N/Esc: cancel",
+            (_, Modal::OverwriteConfirm) => "Y: overwrite | N/Esc: back to the form",
             (_, Modal::Help) => "Esc/h: close help",
             (_, Modal::Command) => "↑/↓: select | Enter: execute | Esc: cancel",
+            (_, Modal::Rename) => "Enter: rename | Esc: cancel",
+            (_, Modal::Detail) => "e: reveal/hide value | Esc: close",
+            (_, Modal::Search) => "Type to filter | Enter: keep filter | Esc: clear",
+            (Mode::Normal, Modal::None)
+                if !app.search_query.is_empty() || app.tag_filter.is_some() =>
+            {
+                "/: edit filter | Esc: clear filter | e: reveal | y: copy | d: delete | q: quit"
+            }
             (Mode::Normal, Modal::None) => {
-                "a: add | e: reveal | y: copy | d: delete | :: cmd | h: help | q: quit"
+                "a: add | e: reveal | y: copy | r: rename | d: delete | /: search | :: cmd | h: help | q: quit"
             }
         }
     };
@@ -656,3 +975,41 @@ fn render_footer(app: &App, area: Rect, frame: &mut Frame) {
 
     frame.render_widget(helper, area);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::{Terminal, backend::TestBackend};
+
+    fn rendered_rows(app: &App) -> Vec<String> {
+        let mut terminal = Terminal::new(TestBackend::new(100, 40)).unwrap();
+        terminal.draw(|frame| render(app, frame)).unwrap();
+        let buffer = terminal.backend().buffer();
+        (0..buffer.area.height)
+            .map(|y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer[(x, y)].symbol())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_add_modal_masks_value_with_one_asterisk_per_char() {
+        let mut app = App::new();
+        app.mode = Mode::Normal;
+        app.open_add_modal();
+        app.current_field = Field::Value;
+        app.new_secret_value = "s3cré7".to_string();
+
+        app.mask_new_value = false;
+        assert!(rendered_rows(&app).iter().any(|row| row.contains("s3cré7")));
+
+        app.mask_new_value = true;
+        let rows = rendered_rows(&app);
+        assert!(rows.iter().all(|row| !row.contains("s3cré7")));
+        let masked = rows.iter().find(|row| row.contains('*')).unwrap();
+        assert_eq!(masked.matches('*').count(), 6);
+        assert!(rows.iter().any(|row| row.contains("masked")));
+    }
+}